@@ -0,0 +1,566 @@
+//! Localization of user-facing strings.
+//!
+//! Messages are looked up gettext-style: the English text is
+//! the message id, and untranslated messages fall back to it.
+
+use std::fmt::Display;
+
+use crate::Error;
+
+/// Language of user-facing strings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum Lang {
+    #[default]
+    En,
+    /// Simplified Chinese.
+    Zh,
+}
+
+impl Lang {
+    /// Detects the language from `LC_ALL`, `LC_MESSAGES` and `LANG`.
+    ///
+    /// Falls back to English if none of them is recognized.
+    pub fn from_env() -> Self {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .into_iter()
+            .filter_map(|key| std::env::var(key).ok())
+            .find(|value| !value.is_empty())
+            .and_then(|value| value.split(['_', '.', '-']).next()?.parse().ok())
+            .unwrap_or_default()
+    }
+
+    /// Translates the given message.
+    pub fn tr(self, msgid: &'static str) -> &'static str {
+        let catalog = match self {
+            Lang::En => return msgid,
+            Lang::Zh => ZH,
+        };
+        catalog
+            .iter()
+            .find(|(id, _)| *id == msgid)
+            .map_or(msgid, |&(_, msg)| msg)
+    }
+
+    /// Translates the given message and replaces `{0}`, `{1}`, ...
+    /// with the given arguments.
+    pub fn trf(self, msgid: &'static str, args: &[&dyn Display]) -> String {
+        let mut msg = self.tr(msgid).to_owned();
+        for (i, arg) in args.iter().enumerate() {
+            msg = msg.replace(&format!("{{{i}}}"), &arg.to_string());
+        }
+        msg
+    }
+
+    /// Code of the language, as parsed.
+    pub(crate) fn code(self) -> &'static str {
+        match self {
+            Lang::En => "en",
+            Lang::Zh => "zh",
+        }
+    }
+
+    /// Gets the help message in this language.
+    pub fn help_msg(self) -> &'static str {
+        match self {
+            Lang::En => crate::HELP_MSG,
+            Lang::Zh => HELP_MSG_ZH,
+        }
+    }
+}
+
+impl std::str::FromStr for Lang {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "en" => Self::En,
+            "zh" => Self::Zh,
+            _ => {
+                return Err(Error::UnknownVariant {
+                    ty: "language",
+                    variants: &["en", "zh"],
+                    value: s.to_owned(),
+                })
+            }
+        })
+    }
+}
+
+/// Simplified Chinese catalog.
+const ZH: &[(&str, &str)] = &[
+    // cli-parser
+    (
+        "missing value for argument '{0}', expected type '{1}'",
+        "参数 '{0}' 缺少值，应为 '{1}' 类型",
+    ),
+    ("invalid integer formatting: {0}", "整数格式无效：{0}"),
+    ("invalid IP-address formatting: {0}", "IP 地址格式无效：{0}"),
+    ("non-unicode value: {0}", "非 Unicode 值：{0}"),
+    ("unknown flag: {0}", "未知参数：{0}"),
+    (
+        "unknown variant '{0}' for type '{1}', expected one of: {2}",
+        "'{1}' 类型没有 '{0}' 这一取值，应为以下之一：{2}",
+    ),
+    ("cannot read balance file: {0}", "无法读取平衡参数文件：{0}"),
+    ("invalid balance file: {0}", "平衡参数文件无效：{0}"),
+    (
+        "plunder {0} is out of range, expected 0 to 1",
+        "掠夺比例 {0} 超出范围，应为 0 到 1",
+    ),
+    ("invalid duration: {0}", "时长无效：{0}"),
+    (
+        "cannot place {0} countries, expected 2 to {1} for this shape; pass -l {1}",
+        "无法放置 {0} 个国家，此形状应为 2 到 {1} 个；请传入 -l {1}",
+    ),
+    (", or -S hex for up to 6", "，或传入 -S hex 以放置至多 6 个"),
+    (
+        "cannot control {0} countries, expected 1 to {1}; pass --players {1}",
+        "无法控制 {0} 个国家，应为 1 到 {1} 个；请传入 --players {1}",
+    ),
+    (
+        "location quality {0} is out of range, expected 1 to {1}; pass -q {1} for the worst",
+        "位置质量 {0} 超出范围，应为 1 到 {1}；传入 -q {1} 以选择最差的位置",
+    ),
+    (
+        "client port {0} is the port of the local server; pass -c {1}",
+        "客户端端口 {0} 与本机服务端端口相同；请传入 -c {1}",
+    ),
+    (
+        "map width {0} is clamped to the maximum of {1}",
+        "地图宽度 {0} 被限制为最大值 {1}",
+    ),
+    (
+        ", as rectangular maps are 10 wider than -W; pass -W {0} or less",
+        "，因为矩形地图比 -W 宽 10；请传入不超过 {0} 的 -W",
+    ),
+    ("warning: {0}", "警告：{0}"),
+    (
+        "invalid environment variable {0}: {1}",
+        "环境变量 {0} 无效：{1}",
+    ),
+    (
+        "map height {0} is clamped to the maximum of {1}",
+        "地图高度 {0} 被限制为最大值 {1}",
+    ),
+    // console
+    ("use dedicated server", "请使用独立服务端"),
+    ("client feature not enabled", "未启用客户端功能"),
+    (
+        "given protocol is not supported in this build",
+        "此版本不支持该协议",
+    ),
+    ("pinging socket {0} using {1}", "正在通过 {1} 连接 {0}"),
+    ("bot clients only play over udp", "AI 客户端仅支持 udp"),
+    ("playing for an AI on {0}", "正在 {0} 上代 AI 游玩"),
+    ("the AI was eliminated", "AI 已被消灭"),
+    ("no games on {0}", "{0} 上没有游戏"),
+    ("games on {0}:", "{0} 上的游戏："),
+    (
+        "game to join [1-{0}, empty to quit]: ",
+        "要加入的游戏 [1-{0}，留空退出]：",
+    ),
+    ("Width", "宽度"),
+    ("Height", "高度"),
+    ("Shape", "形状"),
+    ("Difficulty", "难度"),
+    ("Speed", "速度"),
+    ("Role", "模式"),
+    ("Server", "服务端"),
+    ("rectangle", "矩形"),
+    ("rhombus", "菱形"),
+    ("hexagon", "六边形"),
+    ("easiest", "最简单"),
+    ("easy", "简单"),
+    ("normal", "普通"),
+    ("hard", "困难"),
+    ("hardest", "最困难"),
+    ("slowest", "最慢"),
+    ("slower", "较慢"),
+    ("slow", "慢"),
+    ("fast", "快"),
+    ("faster", "较快"),
+    ("fastest", "最快"),
+    ("singleplayer", "单人游戏"),
+    ("client", "客户端"),
+    ("invalid server address: {0}", "服务端地址无效：{0}"),
+    ("server address", "服务端地址"),
+    ("server port", "服务端端口"),
+    (
+        "protocol (tcp, udp, ws, http)",
+        "协议（tcp、udp、ws、http）",
+    ),
+    ("your name", "你的名字"),
+    ("invalid value: {0}", "无效的值：{0}"),
+    (
+        "[Up]/[Down] select, [Left]/[Right] change, [Enter] start, [Esc] quit",
+        "[上]/[下] 选择，[左]/[右] 更改，[Enter] 开始，[Esc] 退出",
+    ),
+    (
+        "[1]/[2]/[3] start a small/medium/large skirmish",
+        "[1]/[2]/[3] 开始小/中/大型遭遇战",
+    ),
+    ("error fetching state: {0}", "获取状态失败：{0}"),
+    ("the server stopped responding", "服务端已无响应"),
+    (
+        "the host was lost, electing a new one",
+        "主机已失联，正在选举新主机",
+    ),
+    (
+        "hosting the game in place of the lost host",
+        "正在代替失联的主机托管游戏",
+    ),
+    ("failed to host the game: {0}", "托管游戏失败：{0}"),
+    (
+        "the password is longer than {0} bytes",
+        "密码超过了 {0} 字节",
+    ),
+    ("the server rejected the password", "服务端拒绝了密码"),
+    (
+        "the server does not let this address join",
+        "服务端不允许此地址加入",
+    ),
+    ("the relay is full", "中继已满"),
+    ("the server rejected the connection", "服务端拒绝了连接"),
+    (
+        "invalid address: {0}, expected such as 192.168.1.2:19140 or [fe80::1%eth0]:19140",
+        "无效的地址：{0}，应形如 192.168.1.2:19140 或 [fe80::1%eth0]:19140",
+    ),
+    (
+        "not a WebSocket URL: {0}, expected ws:// or wss://",
+        "不是 WebSocket URL：{0}，应为 ws:// 或 wss://",
+    ),
+    ("connecting to {0}", "正在连接 {0}"),
+    ("you", "你"),
+    ("player {0}", "玩家 {0}"),
+    ("grassland", "草地"),
+    ("forest", "森林"),
+    ("hill", "丘陵"),
+    ("road", "道路"),
+    ("upgrade queued", "已排队升级"),
+    ("village", "村庄"),
+    ("town", "城镇"),
+    ("fortress", "要塞"),
+    ("unknown land", "未知土地"),
+    ("nothing", "无"),
+    ("mountain", "山脉"),
+    ("mine", "矿山"),
+    ("unknown", "未知"),
+    ("{0} built {1} at {2}", "{0}在 {2} 建造了{1}"),
+    ("{0} at {1} burned down", "{1} 的{0}被烧毁了"),
+    ("captured {0} at {1}", "占领了 {1} 的{0}"),
+    ("lost {0} at {1} to {2}", "{1} 的{0}被{2}夺走了"),
+    ("under attack at {0}", "{0} 遭到进攻"),
+    ("{0} of {1}", "{1}的{0}"),
+    ("flagged", "已插旗"),
+    ("{0} flags", "{0} 面旗"),
+    ("rally point", "集结点"),
+    ("UNDER ATTACK", "遭到进攻"),
+    ("ASSIST", "辅助"),
+    ("prices: {0} {1} {2}", "价格：{0} {1} {2}"),
+    ("CLASSIC", "经典"),
+    ("BLITZ", "闪电战"),
+    ("MARATHON", "马拉松"),
+    ("{0} captured {1} at {2}", "{0}占领了 {2} 的{1}"),
+    ("{0} lost the mine at {1}", "{0}失去了 {1} 的矿山"),
+    ("eliminated: {0}", "已被消灭：{0}"),
+    (
+        "{0} plundered {1} gold from {2} at {3}",
+        "{0}在 {3} 从{2}处掠夺了 {1} 金币",
+    ),
+    (
+        "build {0} at {1} for {2} gold",
+        "在 {1} 建造{0}，花费 {2} 金币",
+    ),
+    ("nothing to build", "没有可建造的地方"),
+    ("fortress at {0} is under siege", "{0} 的要塞正遭到围攻"),
+    (
+        "Event log: [E] close, [J]/[K] scroll",
+        "事件日志：[E] 关闭，[J]/[K] 滚动",
+    ),
+    ("fingerprint: {0}", "指纹：{0}"),
+    ("Population: [T] close", "人口：[T] 关闭"),
+    ("Map: [Y] close", "地图：[Y] 关闭"),
+    ("no population recorded yet", "尚未记录人口"),
+    ("player", "玩家"),
+    ("pop", "人口"),
+    ("vil", "村庄"),
+    ("fort", "要塞"),
+    ("mines", "矿"),
+    ("gold", "金币"),
+    ("{0} went idle, the AI took over", "{0}已离开，由 AI 接管"),
+    ("{0} came back", "{0}回来了"),
+    ("{0} joined", "{0}加入了游戏"),
+    ("game paused", "游戏已暂停"),
+    ("game resumed", "游戏继续"),
+    ("resuming in {0}", "{0}秒后继续"),
+    ("PAUSED", "已暂停"),
+    ("{0} left, the AI took over", "{0}已退出，由 AI 接管"),
+    ("server: {0}", "服务器：{0}"),
+    ("{0} surrendered", "{0}投降了"),
+    ("{0} won", "{0}获胜了"),
+    ("{0}/{1} players ended the turn", "{0}/{1} 名玩家结束了回合"),
+    (
+        "{0}/{1} players voted to restart",
+        "{0}/{1} 名玩家投票重新开始",
+    ),
+    // gui-cocoa
+    ("About Curse of Rust", "关于 Curse of Rust"),
+    ("Preferences", "偏好设置"),
+    ("Display Preferences", "显示偏好设置"),
+    ("Copy Preferences", "拷贝偏好设置"),
+    ("Restore Default Preferences", "恢复默认偏好设置"),
+    ("New Game", "新游戏"),
+    ("Game", "游戏"),
+    ("Pause", "暂停"),
+    ("Faster", "加速"),
+    ("Slower", "减速"),
+    ("Restart Same Seed", "以相同种子重新开始"),
+    ("Restart New Seed", "以新种子重新开始"),
+    ("Curse of Rust Help", "Curse of Rust 帮助"),
+    ("File", "文件"),
+    ("Help", "帮助"),
+    ("About", "关于"),
+    ("GUI Preferences", "图形偏好设置"),
+    ("Large tiles", "大图块"),
+    ("High contrast", "高对比度"),
+    ("Reduce motion", "减弱动态效果"),
+    (
+        "Preference parsing error will be emitted here.",
+        "偏好设置的解析错误将显示在这里。",
+    ),
+    (
+        "Integrated server is currently not implemented, please use dedicated server.",
+        "暂未实现内置服务端，请使用独立服务端。",
+    ),
+    (
+        "UDP multiplayer client is not usable, please use the console version.",
+        "UDP 多人客户端不可用，请使用控制台版本。",
+    ),
+    ("Singleplayer - seed: {0}", "单人游戏 - 种子：{0}"),
+    (
+        "daily challenge {0} won in {1} steps, a new best",
+        "{0} 每日挑战获胜，用时 {1} 步，创下新纪录",
+    ),
+    (
+        "daily challenge {0} won in {1} steps, the best is {2}",
+        "{0} 每日挑战获胜，用时 {1} 步，最佳纪录为 {2} 步",
+    ),
+    (
+        "seed: {0}, AI personality seed: {1}",
+        "种子：{0}，AI 个性种子：{1}",
+    ),
+];
+
+/// The help message in Simplified Chinese.
+const HELP_MSG_ZH: &str = r#"                                __
+   ____                        /  ]  ________             __
+  / __ \_ _ ___ ___ ___    __ _| |_  |  ___  \__  __ ___ _| |__
+_/ /  \/ | |X _/ __/ __\  /   \   /  | |___| | | |  / __/_  __/
+\ X    | | | | |__ | __X  | X || |   | X_  __/ |_|  X__ | | X
+ \ \__/\ __X_| \___/___/  \___/| |   | | \ \_ X__ /___ /  \__/
+  \____/                       |/    |_\  \__/
+
+  DM Earth 制作于 2024 年。
+
+  命令行参数：
+
+-W 宽度
+  地图宽度（默认为 21）
+
+-H 高度
+  地图高度（默认为 21）
+
+-S [rhombus|rect|hex]
+  地图形状（默认为矩形）。菱形与矩形最多 N=4 个国家，六边形最多 N=6 个。
+
+-l [2|3| ... N]
+  设置国家数量 L（默认为 N）。
+
+-i [0|1|2|3|4]
+  国家之间的不平等程度（0 最低，4 最高）。
+
+-q [1|2| ... L]
+  按质量选择玩家的位置（1 = 地图上最好的，L = 最差的）。仅限单人模式。
+
+-r
+  完全随机的初始条件，覆盖 -l、-i 与 -q 选项。
+
+-d [ee|e|n|h|hh]
+  难度（AI），从最简单到最困难（默认为普通）。
+
+--assist
+  在简单难度（ee 与 e）下以额外的金币和人口开局。
+
+--adaptive-ai
+  让 AI 根据人类玩家的进攻性作出反应：每 500 步，若人类夺取的地块多于被 AI 夺走的，AI 变得更具进攻性；若 AI 夺取的更多，则变得更保守。
+
+--rotate-ai
+  按随地图种子变化的轮换分配 AI 策略（包括 turtle），而非在每张地图上使用相同的阵容。
+
+--landscapes
+  将部分草地生成为森林或丘陵，在战斗中为其所有者提供掩护。
+
+--teams 队伍数
+  将玩家依次分入给定数量的队伍：玩家 1 在第一队，玩家 2 在第二队，依此类推。盟友之间不会交战，同队的 AI 共享对地图的评估，并且不会在队友已插旗的地块上插旗，最后存活的队伍获胜。
+
+--personality-seed 种子
+  由给定种子（无符号整数）为每个 AI 生成略有不同的插旗阈值与进攻性，使相同阵容的玩法各不相同。游戏结束时会打印该种子。
+
+-s [p|sss|ss|s|n|f|ff|fff]
+  游戏速度，从最慢到最快（默认为普通）。
+
+-R 种子
+  指定生成地图所用的随机种子（无符号整数）。
+
+-T
+  开局时显示人口图表，可按 T 切换。
+
+-E [1|2| ... L]
+  启动最多容纳 L 个客户端的服务端。
+
+-e 端口
+  服务端端口（默认为 19140）。
+
+-Y IP[:端口]
+  通过给定 IP 地址（默认端口 19140）的中继托管服务端，而不是直接接受连接。
+
+-y 端口
+  启动中继，在主机与其客户端之间转发游戏数据。
+
+-a 秒数
+  将空闲达到该时长的客户端的国家交由 AI 控制，直到其回来（默认为 30，0 表示禁用）。
+
+-C IP[:端口]
+  启动客户端并连接到给定 IP 地址的服务端。后跟端口的 IPv6 地址需加方括号，并可限定网络接口，例如 [fe80::1%eth0]:19140。省略端口时使用服务端端口 19140。省略地址时，将在连接前询问地址和端口，同 --connect-ui。
+
+-c 端口
+  客户端端口（默认为 19150）。
+
+-m [keyboard|termux|hybrid]
+  控制方式。
+
+--menu
+  开始游戏前在表单中配置：地图大小与形状、难度、速度，以及是否加入服务端。其他参数将填入表单。
+
+--connect-ui
+  启动客户端，连接前询问服务端地址与端口、协议以及你的名字。其他参数作为默认值。
+
+--narrate
+  以文字为读屏软件讲述游戏，而不绘制地图。按 T 读出光标所在的图块。
+
+--balance 文件
+  从给定的 TOML 文件加载平衡参数，未给出的参数保持默认值。
+
+--preset [classic|blitz|marathon]
+  设定速度与平衡参数的规则预设：blitz 为城市便宜、增长快的短局，marathon 为城市昂贵、增长慢的长局。-s 与 --balance 会覆盖预设。
+
+--skirmish [small|medium|large]
+  快速开始的遭遇战，覆盖地图选项：小型矩形上的单挑、矩形上的四国混战，或最大六边形上的六国混战。在游戏菜单中，按 1、2、3 键即可直接开始。
+
+--daily
+  游玩当日地图，同一天（按 UTC 计）全世界所有玩家的地图相同，使用经典平衡参数，速度与难度均为普通。它覆盖地图选项、--skirmish、--preset、--balance 与 -s，每天最快的胜利所用游戏步数会保存在档案中。
+
+--headless
+  不绘制画面，模拟仅由 AI 参与的游戏，并以 CSV 格式输出各策略的胜率。
+
+--batch N
+  --headless 模式下模拟的游戏局数（默认为 1）。
+
+--bench-render N
+  将仅由 AI 参与的游戏渲染 N 帧并丢弃输出，以 CSV 格式输出每帧的字节数与耗时。
+
+--alert [bell|notify]
+  失去城市或要塞被围攻时提醒你的方式：终端响铃（默认）或桌面通知。
+
+--quiet
+  关闭提醒。
+
+--palette [auto|truecolor|ansi|patterns]
+  控制台中玩家的配色：在支持 24 位色的终端上使用对色盲友好的颜色、终端的 16 色，或不使用颜色而在各玩家的地块上标注各自的符号。默认使用档案中设置的配色，否则使用终端支持的配色。
+
+--shade
+  在支持 24 位色的终端上，以所属玩家的颜色为地块背景着色，单位越少颜色越深。
+
+--braille
+  开始时以盲文字符绘制地图概览（每个地块一个点）而非地块，以便在小终端上容纳大地图。按 Y 在两者之间切换。
+
+--time-limit 时长
+  在给定时长后结束游戏，如 90s、20m 或 1h，暂停的时间不计入。得分（人口加金币）最高的玩家获胜。
+
+--tick-ms 毫秒数
+  模拟一刻的实际时长（默认为 10）。各档速度每隔若干刻前进一步，因此更长的刻会同比放慢所有速度并节省电量。
+
+--turns 步数
+  回合制模式：所有人类玩家按 Enter 结束回合后，游戏前进给定的步数。
+
+--players 国家数
+  从此客户端控制多个国家，例如在同一键盘上合作游戏，按 Tab 在它们之间切换。多人游戏中，这些国家向服务器申领。
+
+--observe 端口
+  在给定端口上通过 HTTP 以 JSON 格式向观众提供游戏状态。仅限服务端，需要启用 observer 功能。
+
+--rpc 端口
+  不托管游戏，而是让本地工具在给定端口上通过 JSON-RPC 驱动游戏引擎。仅限服务端，需要启用 rpc 功能。
+
+--maps 文件
+  依次进行给定文件中列出的各轮游戏，每行一轮，写作 -s 42 -S hex 等选项，覆盖其他给定的选项。每轮玩家每胜过一名玩家得一分，轮间公布积分榜，结束时写入该文件旁。仅限服务端。
+
+--resume 文件
+  从给定文件中保存的状态继续游戏，等待 -E 指定数量的客户端重新加入。接管失去主机的中继游戏的客户端会启动这样的服务端。仅限服务端。
+
+--bind IP
+  服务端或中继绑定的地址，例如容器中的 0.0.0.0（默认为检测到的本机地址）。
+
+--no-lobby-print
+  不打印连接到服务端大厅的客户端。
+
+--healthz 端口
+  在给定端口上通过 HTTP 的 GET /healthz 响应存活探针，并通过 GET /metrics 提供每刻耗时。仅限服务端。
+
+--announce 主机:端口
+  等待客户端时向给定地址的主服务器公布游戏，以便客户端通过 --list 找到它。仅限服务端。
+
+--name 名称
+  通过 --announce 公布的游戏名称（默认为个人资料中的名称）。
+
+--password 密码
+  在服务端上，只允许提供相同密码的客户端加入。在客户端上，为要提供的密码，最长 32 字节。
+
+--allow 地址,...
+  只允许来自给定 IP 地址或主机名的客户端加入，例如 192.168.1.20,alice.lan。仅限服务端，不可通过中继。
+
+--list 主机:端口
+  列出向给定地址的主服务器公布的游戏，并加入所选的游戏。仅限客户端。
+
+--url ws[s]://主机[:端口][/路径]
+  通过给定的 WebSocket URL 加入服务端，例如反向代理后的 wss://example.com/curseofrust，代替 -C。仅限客户端，需要启用 ws 功能。
+
+--export 文件
+  每一步都将地图镜像到给定文件中，供外部可视化工具以内存映射方式读取。仅限单人游戏。
+
+--bot [aggr_greedy|one_greedy|persistent_greedy|opportunist|noble|midas|turtle|swarm]
+  由给定策略的 AI 代替你游玩，且不进行渲染，用于向服务端提供 AI 玩家。仅限客户端，使用 udp。
+
+--trace-wire
+  打印每条收发的消息及其时间、方向和字段。服务端打印到标准输出，客户端打印到标准错误。
+
+--lang [en|zh]
+  界面语言（默认取自 LANG 环境变量）。
+
+-v
+  显示版本号
+
+-h
+  显示此帮助
+
+  环境变量：
+
+COR_SEED, COR_SPEED, COR_PROTOCOL
+  与 -R、-s 和 -p 相同。
+
+COR_SERVER
+  为端口时与 -e 相同，为地址时与 -C 相同。
+
+  命令行参数优先于环境变量。
+"#;
@@ -1,13 +1,69 @@
-use std::{cmp::max, ffi::OsStr, net::SocketAddr};
+use std::{
+    cmp::max,
+    ffi::OsStr,
+    net::{IpAddr, SocketAddr},
+    path::PathBuf,
+    time::Duration,
+};
 
-use curseofrust::state::{BasicOpts, MultiplayerOpts};
+use curseofrust::{state::BasicOpts, Strategy};
+use serde::{Deserialize, Serialize};
 
-use wrapper::{DifficultyWrapper as Difficulty, SpeedWrapper as Speed, StencilWrapper as Stencil};
+use wrapper::{
+    AddrWrapper, DifficultyWrapper as Difficulty, PresetWrapper as Preset, SpeedWrapper as Speed,
+    StencilWrapper as Stencil, StrategyWrapper,
+};
 
+pub use lang::Lang;
+pub use profile::Profile;
+pub use quick::{Daily, Skirmish};
+pub use rules::Rules;
+pub use validate::Warning;
+
+mod lang;
+mod profile;
+mod quick;
+mod rules;
+mod validate;
 mod wrapper;
 
-const DEFAULT_SERVER_PORT: u16 = 19140;
-const DEFAULT_CLIENT_PORT: u16 = 19150;
+/// Parses a socket address as given to `-C`: IPv4 or IPv6, the
+/// latter bracketed if followed by a port, and scoped by the name or
+/// index of an interface, such as `[fe80::1%en0]:19140`.
+///
+/// Returns the address, on [`DEFAULT_SERVER_PORT`] if the port is
+/// left out, and whether the port was given.
+pub fn parse_addr(s: &str) -> Result<(SocketAddr, bool), Error> {
+    s.parse::<AddrWrapper>()
+        .map(|addr| (addr.addr, addr.port_given))
+}
+
+/// Role of the program in a multiplayer game.
+#[derive(Default, Debug)]
+pub enum MultiplayerOpts {
+    Server {
+        port: u16,
+        /// Relay to host the game through, instead of
+        /// accepting connections directly.
+        relay: Option<SocketAddr>,
+    },
+    Client {
+        server: SocketAddr,
+        port: u16,
+    },
+    /// Forwards game traffic between a host and its clients.
+    Relay {
+        port: u16,
+    },
+    #[default]
+    None,
+}
+
+/// Port servers listen on by default.
+pub const DEFAULT_SERVER_PORT: u16 = 19140;
+/// Port clients bind by default.
+pub const DEFAULT_CLIENT_PORT: u16 = 19150;
+const DEFAULT_AFK_TIMEOUT: u64 = 30;
 
 /// Parses the command line arguments.
 #[deprecated(note = "use `parse_to_options` instead")]
@@ -29,6 +85,8 @@ pub enum Protocol {
     #[default]
     Udp,
     WebSocket,
+    /// HTTP long-polling, for networks where only HTTP passes.
+    Http,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
@@ -44,6 +102,19 @@ pub enum ControlMode {
     Hybrid,
 }
 
+#[cfg(feature = "net-proto")]
+impl Protocol {
+    /// Name of the protocol, as parsed.
+    pub fn name(self) -> &'static str {
+        match self {
+            Protocol::Tcp => "tcp",
+            Protocol::Udp => "udp",
+            Protocol::WebSocket => "ws",
+            Protocol::Http => "http",
+        }
+    }
+}
+
 #[cfg(feature = "net-proto")]
 impl std::str::FromStr for Protocol {
     type Err = Error;
@@ -53,10 +124,11 @@ impl std::str::FromStr for Protocol {
             "tcp" => Protocol::Tcp,
             "udp" => Protocol::Udp,
             "ws" | "websocket" => Protocol::WebSocket,
+            "http" => Protocol::Http,
             _ => {
                 return Err(Error::UnknownVariant {
                     ty: "protocol",
-                    variants: &["tcp", "udp", "ws or websocket"],
+                    variants: &["tcp", "udp", "ws or websocket", "http"],
                     value: s.to_owned(),
                 })
             }
@@ -64,6 +136,106 @@ impl std::str::FromStr for Protocol {
     }
 }
 
+/// How the player is alerted when losing a city
+/// or when a fortress comes under siege.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum Alert {
+    /// Rings the terminal bell, or beeps.
+    #[default]
+    Bell,
+    /// Shows a notification of the operating system.
+    Notify,
+}
+
+impl Alert {
+    /// Name of the alert, as parsed.
+    fn name(self) -> &'static str {
+        match self {
+            Alert::Bell => "bell",
+            Alert::Notify => "notify",
+        }
+    }
+}
+
+impl std::str::FromStr for Alert {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "bell" => Self::Bell,
+            "notify" => Self::Notify,
+            _ => {
+                return Err(Error::UnknownVariant {
+                    ty: "alert",
+                    variants: &["bell", "notify"],
+                    value: s.to_owned(),
+                })
+            }
+        })
+    }
+}
+
+/// Colors players are told apart by in the console.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[non_exhaustive]
+pub enum Palette {
+    /// Picks one of the others by what the terminal supports.
+    #[default]
+    Auto,
+    /// Colors safe for color vision deficiencies, on terminals
+    /// with 24-bit colors.
+    Truecolor,
+    /// The 16 colors of any terminal.
+    Ansi,
+    /// No colors, with a symbol of its own on the tiles of each player.
+    Patterns,
+}
+
+impl Palette {
+    /// Name of the palette, as parsed.
+    pub fn name(self) -> &'static str {
+        match self {
+            Palette::Auto => "auto",
+            Palette::Truecolor => "truecolor",
+            Palette::Ansi => "ansi",
+            Palette::Patterns => "patterns",
+        }
+    }
+}
+
+impl std::str::FromStr for Palette {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "auto" => Self::Auto,
+            "truecolor" => Self::Truecolor,
+            "ansi" => Self::Ansi,
+            "patterns" => Self::Patterns,
+            _ => {
+                return Err(Error::UnknownVariant {
+                    ty: "palette",
+                    variants: &["auto", "truecolor", "ansi", "patterns"],
+                    value: s.to_owned(),
+                })
+            }
+        })
+    }
+}
+
+impl ControlMode {
+    /// Name of the control mode, as parsed.
+    fn name(self) -> &'static str {
+        match self {
+            ControlMode::Termux => "termux",
+            ControlMode::Keyboard => "keyboard",
+            ControlMode::Hybrid => "hybrid",
+        }
+    }
+}
+
 impl std::str::FromStr for ControlMode {
     type Err = Error;
 
@@ -92,61 +264,491 @@ where
     let mut multiplayer_opts = MultiplayerOpts::default();
     let mut exit = false;
     let mut cm = ControlMode::default();
+    let mut narrate = false;
+    let mut lang = None;
+    let mut help = false;
+    let mut afk_timeout = DEFAULT_AFK_TIMEOUT;
+    let mut headless = false;
+    let mut batch = 1;
+    let mut bench_render = None;
+    let mut alert = Some(Alert::default());
+    let mut palette = Palette::default();
+    let mut shade = false;
+    let mut braille = false;
+    let mut time_limit = Duration::ZERO;
+    let mut turns = 0;
+    let mut players = 1;
+    let mut observe = None;
+    let mut rpc = None;
+    let mut export = None;
+    let mut maps = None;
+    let mut resume = None;
+    let mut bot = None;
+    let mut trace_wire = false;
+    let mut menu = false;
+    let mut connect_ui = false;
+    let mut bind = None;
+    let mut lobby_print = true;
+    let mut healthz = None;
+    let mut announce = None;
+    let mut game_name = None;
+    let mut password = None;
+    let mut allow = Vec::new();
+    let mut list = None;
+    let mut url = None;
+    let mut preset = None;
+    let mut skirmish = None;
+    let mut daily = None;
+    let mut speed_given = false;
+    let mut balance_given = false;
 
     #[cfg(feature = "net-proto")]
     let mut protocol = Protocol::default();
 
+    // Variables of the environment sit between defaults and flags.
+    if let Some(seed) = env_var("COR_SEED")? {
+        basic_opts.seed = seed;
+    }
+    if let Some(Speed(speed)) = env_var("COR_SPEED")? {
+        basic_opts.speed = speed;
+        speed_given = true;
+    }
+    #[cfg(feature = "net-proto")]
+    if let Some(p) = env_var("COR_PROTOCOL")? {
+        protocol = p;
+    }
+    // An address joins a server, as `-C`. A port is left to the
+    // server to host on, see `env_server_port`.
+    if let Some(server) = env_var::<String>("COR_SERVER")?.filter(|s| s.parse::<u16>().is_err()) {
+        multiplayer_opts = MultiplayerOpts::Client {
+            server: parse_addr(&server)
+                .map_err(|err| Error::InvalidEnv {
+                    var: "COR_SERVER",
+                    source: Box::new(err),
+                })?
+                .0,
+            port: DEFAULT_CLIENT_PORT,
+        };
+    }
+
     let args = clap_lex::RawArgs::new(args);
     let mut cursor = args.cursor();
     args.next(&mut cursor); // skip bin
     while let Some(arg) = args.next(&mut cursor) {
-        if let Some(mut s) = arg.to_short() {
-            while let Some(Ok(flag)) = s.next() {
-                macro_rules! parse {
-                    ($a:expr, $t:expr, $vt:ty) => {{
-                        let v: Result<$vt, _> = args
+        macro_rules! parse {
+            ($a:expr, $t:expr, $vt:ty) => {{
+                let v: Result<$vt, _> = args
+                    .next(&mut cursor)
+                    .ok_or_else(|| Error::MissingValue { arg: $a, ty: $t })
+                    .and_then(|a| {
+                        a.to_value_os()
+                            .to_string_lossy()
+                            .parse()
+                            .map_err(From::from)
+                    });
+                v
+            }};
+            ($a:expr, $t:expr) => {
+                parse!($a, $t, _)
+            };
+        }
+
+        if let Some((name, value)) = arg.to_long() {
+            match name? {
+                "narrate" => narrate = true,
+                "assist" => basic_opts.assist = true,
+                "adaptive-ai" => basic_opts.adaptive_ai = true,
+                "rotate-ai" => basic_opts.rotate_ai = true,
+                "landscapes" => basic_opts.landscapes = true,
+                "headless" => headless = true,
+                "trace-wire" => trace_wire = true,
+                "menu" => menu = true,
+                "connect-ui" => connect_ui = true,
+                "no-lobby-print" => lobby_print = false,
+                "bind" => {
+                    bind = Some(match value {
+                        Some(value) => value.to_string_lossy().parse()?,
+                        None => parse!("--bind", "IP")?,
+                    })
+                }
+                "announce" => {
+                    announce = Some(match value {
+                        Some(value) => value.to_string_lossy().into_owned(),
+                        None => args
+                            .next(&mut cursor)
+                            .ok_or(Error::MissingValue {
+                                arg: "--announce",
+                                ty: "address",
+                            })?
+                            .to_value_os()
+                            .to_string_lossy()
+                            .into_owned(),
+                    })
+                }
+                "name" => {
+                    game_name = Some(match value {
+                        Some(value) => value.to_string_lossy().into_owned(),
+                        None => args
                             .next(&mut cursor)
-                            .ok_or_else(|| Error::MissingValue { arg: $a, ty: $t })
-                            .and_then(|a| {
-                                a.to_value_os()
-                                    .to_string_lossy()
-                                    .parse()
-                                    .map_err(From::from)
-                            });
-                        v
-                    }};
-                    ($a:expr, $t:expr) => {
-                        parse!($a, $t, _)
+                            .ok_or(Error::MissingValue {
+                                arg: "--name",
+                                ty: "string",
+                            })?
+                            .to_value_os()
+                            .to_string_lossy()
+                            .into_owned(),
+                    })
+                }
+                "password" => {
+                    password = Some(match value {
+                        Some(value) => value.to_string_lossy().into_owned(),
+                        None => args
+                            .next(&mut cursor)
+                            .ok_or(Error::MissingValue {
+                                arg: "--password",
+                                ty: "string",
+                            })?
+                            .to_value_os()
+                            .to_string_lossy()
+                            .into_owned(),
+                    })
+                }
+                "allow" => {
+                    let list = match value {
+                        Some(value) => value.to_string_lossy().into_owned(),
+                        None => args
+                            .next(&mut cursor)
+                            .ok_or(Error::MissingValue {
+                                arg: "--allow",
+                                ty: "list",
+                            })?
+                            .to_value_os()
+                            .to_string_lossy()
+                            .into_owned(),
+                    };
+                    allow.extend(
+                        list.split(',')
+                            .map(str::trim)
+                            .filter(|entry| !entry.is_empty())
+                            .map(str::to_owned),
+                    );
+                }
+                "list" => {
+                    list = Some(match value {
+                        Some(value) => value.to_string_lossy().into_owned(),
+                        None => args
+                            .next(&mut cursor)
+                            .ok_or(Error::MissingValue {
+                                arg: "--list",
+                                ty: "address",
+                            })?
+                            .to_value_os()
+                            .to_string_lossy()
+                            .into_owned(),
+                    })
+                }
+                "url" => {
+                    let value = match value {
+                        Some(value) => value.to_string_lossy().into_owned(),
+                        None => args
+                            .next(&mut cursor)
+                            .ok_or(Error::MissingValue {
+                                arg: "--url",
+                                ty: "URL",
+                            })?
+                            .to_value_os()
+                            .to_string_lossy()
+                            .into_owned(),
                     };
+                    let scheme = value.split_once("://").map(|(scheme, _)| scheme);
+                    if !scheme.is_some_and(|s| {
+                        s.eq_ignore_ascii_case("ws") || s.eq_ignore_ascii_case("wss")
+                    }) {
+                        return Err(Error::InvalidUrl(value));
+                    }
+                    url = Some(value);
+                }
+                "healthz" => {
+                    healthz = Some(match value {
+                        Some(value) => value.to_string_lossy().parse()?,
+                        None => parse!("--healthz", "integer")?,
+                    })
+                }
+                "quiet" => alert = None,
+                "alert" => {
+                    alert = Some(match value {
+                        Some(value) => value.to_string_lossy().parse()?,
+                        None => parse!("--alert", "alert", Alert)?,
+                    })
+                }
+                "palette" => {
+                    palette = match value {
+                        Some(value) => value.to_string_lossy().parse()?,
+                        None => parse!("--palette", "palette", Palette)?,
+                    }
+                }
+                "shade" => shade = true,
+                "braille" => braille = true,
+                "balance" => {
+                    let path = match value {
+                        Some(value) => value.to_owned(),
+                        None => args
+                            .next(&mut cursor)
+                            .ok_or(Error::MissingValue {
+                                arg: "--balance",
+                                ty: "path",
+                            })?
+                            .to_value_os()
+                            .to_owned(),
+                    };
+                    let file = std::fs::read_to_string(path).map_err(Error::ReadBalance)?;
+                    basic_opts.balance = toml::from_str(&file).map_err(Error::InvalidBalance)?;
+                    let plunder = basic_opts.balance.plunder;
+                    if !(0.0..=1.0).contains(&plunder) {
+                        return Err(Error::PlunderOutOfRange(plunder));
+                    }
+                    balance_given = true;
+                }
+                "preset" => {
+                    preset = Some(
+                        match value {
+                            Some(value) => value.to_string_lossy().parse()?,
+                            None => parse!("--preset", "preset", Preset)?,
+                        }
+                        .0,
+                    )
+                }
+                "skirmish" => {
+                    skirmish = Some(match value {
+                        Some(value) => value.to_string_lossy().parse()?,
+                        None => parse!("--skirmish", "size", Skirmish)?,
+                    })
+                }
+                "daily" => daily = Some(Daily::today()),
+                "export" => {
+                    export = Some(PathBuf::from(match value {
+                        Some(value) => value.to_owned(),
+                        None => args
+                            .next(&mut cursor)
+                            .ok_or(Error::MissingValue {
+                                arg: "--export",
+                                ty: "path",
+                            })?
+                            .to_value_os()
+                            .to_owned(),
+                    }))
+                }
+                "maps" => {
+                    maps = Some(PathBuf::from(match value {
+                        Some(value) => value.to_owned(),
+                        None => args
+                            .next(&mut cursor)
+                            .ok_or(Error::MissingValue {
+                                arg: "--maps",
+                                ty: "path",
+                            })?
+                            .to_value_os()
+                            .to_owned(),
+                    }))
+                }
+                "resume" => {
+                    resume = Some(PathBuf::from(match value {
+                        Some(value) => value.to_owned(),
+                        None => args
+                            .next(&mut cursor)
+                            .ok_or(Error::MissingValue {
+                                arg: "--resume",
+                                ty: "path",
+                            })?
+                            .to_value_os()
+                            .to_owned(),
+                    }))
+                }
+                "bot" => {
+                    bot = Some(
+                        match value {
+                            Some(value) => value.to_string_lossy().parse()?,
+                            None => parse!("--bot", "strategy", StrategyWrapper)?,
+                        }
+                        .0,
+                    )
+                }
+                "time-limit" => {
+                    time_limit = parse_duration(&match value {
+                        Some(value) => value.to_string_lossy().into_owned(),
+                        None => args
+                            .next(&mut cursor)
+                            .ok_or(Error::MissingValue {
+                                arg: "--time-limit",
+                                ty: "duration",
+                            })?
+                            .to_value_os()
+                            .to_string_lossy()
+                            .into_owned(),
+                    })?
+                }
+                "observe" => {
+                    observe = Some(match value {
+                        Some(value) => value.to_string_lossy().parse()?,
+                        None => parse!("--observe", "integer")?,
+                    })
+                }
+                "rpc" => {
+                    rpc = Some(match value {
+                        Some(value) => value.to_string_lossy().parse()?,
+                        None => parse!("--rpc", "integer")?,
+                    })
+                }
+                "teams" => {
+                    basic_opts.teams = Some(match value {
+                        Some(value) => value.to_string_lossy().parse()?,
+                        None => parse!("--teams", "integer")?,
+                    })
+                }
+                "personality-seed" => {
+                    basic_opts.ai_personality_seed = Some(match value {
+                        Some(value) => value.to_string_lossy().parse()?,
+                        None => parse!("--personality-seed", "integer")?,
+                    })
+                }
+                "tick-ms" => {
+                    // Minimum tick.
+                    basic_opts.tick_ms = max(
+                        match value {
+                            Some(value) => value.to_string_lossy().parse()?,
+                            None => parse!("--tick-ms", "integer")?,
+                        },
+                        1,
+                    )
+                }
+                "turns" => {
+                    turns = match value {
+                        Some(value) => value.to_string_lossy().parse()?,
+                        None => parse!("--turns", "integer")?,
+                    }
                 }
+                "players" => {
+                    players = match value {
+                        Some(value) => value.to_string_lossy().parse()?,
+                        None => parse!("--players", "integer")?,
+                    }
+                }
+                "bench-render" => {
+                    bench_render = Some(match value {
+                        Some(value) => value.to_string_lossy().parse()?,
+                        None => parse!("--bench-render", "integer")?,
+                    })
+                }
+                "batch" => {
+                    batch = match value {
+                        Some(value) => value.to_string_lossy().parse()?,
+                        None => parse!("--batch", "integer")?,
+                    }
+                }
+                "lang" => {
+                    lang = Some(match value {
+                        Some(value) => value.to_string_lossy().parse()?,
+                        None => parse!("--lang", "language", Lang)?,
+                    })
+                }
+                f => return Err(Error::UnknownLongFlag { flag: f.to_owned() }),
+            }
+        } else if let Some(mut s) = arg.to_short() {
+            // Values may be attached to their flags, as in `-W30` and `-dhh`,
+            // taking the rest of the argument.
+            macro_rules! short {
+                ($a:expr, $t:expr, $vt:ty) => {{
+                    match s.next_value_os() {
+                        Some(value) => {
+                            let value = value.to_string_lossy();
+                            let v: Result<$vt, _> =
+                                value.strip_prefix('=').unwrap_or(&value).parse();
+                            v.map_err(Error::from)
+                        }
+                        None => parse!($a, $t, $vt),
+                    }
+                }};
+                ($a:expr, $t:expr) => {
+                    short!($a, $t, _)
+                };
+            }
+
+            while let Some(Ok(flag)) = s.next() {
                 match flag {
-                    'W' => basic_opts.width = parse!("-W", "integer")?,
+                    'W' => basic_opts.width = short!("-W", "integer")?,
                     // Minimum height.
-                    'H' => basic_opts.height = max(parse!("-H", "integer")?, 5),
-                    'S' => basic_opts.shape = parse!("-S", "shape", Stencil)?.0,
-                    'l' => basic_opts.locations = parse!("-l", "integer")?,
-                    'i' => basic_opts.inequality = Some(parse!("-i", "integer")?),
-                    'q' => basic_opts.conditions = Some(parse!("-q", "integer")?),
+                    'H' => basic_opts.height = max(short!("-H", "integer")?, 5),
+                    'S' => basic_opts.shape = short!("-S", "shape", Stencil)?.0,
+                    'l' => basic_opts.locations = short!("-l", "integer")?,
+                    'i' => basic_opts.inequality = Some(short!("-i", "integer")?),
+                    'q' => basic_opts.conditions = Some(short!("-q", "integer")?),
                     'r' => basic_opts.keep_random = true,
-                    'd' => basic_opts.difficulty = parse!("-d", "difficulty", Difficulty)?.0,
-                    's' => basic_opts.speed = parse!("-s", "speed", Speed)?.0,
-                    'R' => basic_opts.seed = parse!("-R", "integer")?,
+                    'd' => basic_opts.difficulty = short!("-d", "difficulty", Difficulty)?.0,
+                    's' => {
+                        basic_opts.speed = short!("-s", "speed", Speed)?.0;
+                        speed_given = true;
+                    }
+                    'R' => basic_opts.seed = short!("-R", "integer")?,
                     'T' => basic_opts.timeline = true,
                     'E' => {
-                        basic_opts.clients = parse!("-E", "integer")?;
+                        basic_opts.clients = short!("-E", "integer")?;
                         if matches!(multiplayer_opts, MultiplayerOpts::None) {
                             multiplayer_opts = MultiplayerOpts::Server {
                                 port: DEFAULT_SERVER_PORT,
+                                relay: None,
                             };
                         }
                     }
                     'e' => {
-                        multiplayer_opts = MultiplayerOpts::Server {
-                            port: parse!("-e", "integer")?,
+                        let parsed = short!("-e", "integer")?;
+                        if let MultiplayerOpts::Server { ref mut port, .. } = multiplayer_opts {
+                            *port = parsed
+                        } else {
+                            multiplayer_opts = MultiplayerOpts::Server {
+                                port: parsed,
+                                relay: None,
+                            };
+                        }
+                    }
+                    'Y' => {
+                        let parsed = short!("-Y", "address", AddrWrapper)?.addr;
+                        if let MultiplayerOpts::Server { ref mut relay, .. } = multiplayer_opts {
+                            *relay = Some(parsed);
+                        } else {
+                            multiplayer_opts = MultiplayerOpts::Server {
+                                port: DEFAULT_SERVER_PORT,
+                                relay: Some(parsed),
+                            }
+                        }
+                    }
+                    'y' => {
+                        multiplayer_opts = MultiplayerOpts::Relay {
+                            port: short!("-y", "integer")?,
                         };
                     }
+                    'a' => afk_timeout = short!("-a", "integer")?,
                     'C' => {
-                        let parsed = parse!("-C", "SocketAddr")?;
+                        // Addresses without a port, or no address at all,
+                        // are completed at runtime.
+                        let value = match s.next_value_os() {
+                            Some(value) => Some(value.to_string_lossy().into_owned()),
+                            None => args
+                                .peek_os(&cursor)
+                                .filter(|next| !next.to_string_lossy().starts_with('-'))
+                                .and_then(|_| args.next_os(&mut cursor))
+                                .map(|next| next.to_string_lossy().into_owned()),
+                        };
+                        let parsed =
+                            match value.as_deref().map(|v| v.strip_prefix('=').unwrap_or(v)) {
+                                Some(value) => parse_addr(value)?.0,
+                                None => {
+                                    connect_ui = true;
+                                    SocketAddr::from((
+                                        std::net::Ipv4Addr::LOCALHOST,
+                                        DEFAULT_SERVER_PORT,
+                                    ))
+                                }
+                            };
                         if let MultiplayerOpts::Client { ref mut server, .. } = multiplayer_opts {
                             *server = parsed;
                         } else {
@@ -157,7 +759,7 @@ where
                         }
                     }
                     'c' => {
-                        let parsed = parse!("-c", "integer")?;
+                        let parsed = short!("-c", "integer")?;
                         if let MultiplayerOpts::Client { ref mut port, .. } = multiplayer_opts {
                             *port = parsed
                         } else {
@@ -175,14 +777,14 @@ where
                         exit = true
                     }
                     'h' => {
-                        println!("{HELP_MSG}");
+                        help = true;
                         exit = true
                     }
 
                     #[cfg(feature = "net-proto")]
-                    'p' => protocol = parse!("-p", "protocol", Protocol)?,
+                    'p' => protocol = short!("-p", "protocol", Protocol)?,
 
-                    'm' => cm = parse!("-m", "control mode", ControlMode)?,
+                    'm' => cm = short!("-m", "control mode", ControlMode)?,
 
                     f => return Err(Error::UnknownFlag { flag: f }),
                 }
@@ -190,12 +792,59 @@ where
         }
     }
 
+    let lang = lang.unwrap_or_else(Lang::from_env);
+    // Printed here so that `--lang` takes effect regardless of order.
+    if help {
+        println!("{}", lang.help_msg());
+    }
+
+    // The speed and balance given override the preset, regardless of order.
+    if let Some(preset) = preset {
+        basic_opts.preset = Some(preset);
+        if !speed_given {
+            basic_opts.speed = preset.speed();
+        }
+        if !balance_given {
+            basic_opts.balance = preset.balance();
+        }
+    }
+
+    // Quick starts override the map given, and the daily challenge
+    // overrides quick starts, regardless of order.
+    if let Some(skirmish) = skirmish {
+        skirmish.apply(&mut basic_opts);
+    }
+    if let Some(daily) = daily {
+        daily.apply(&mut basic_opts);
+    }
+
     // Fix a weird bug.
     if basic_opts.shape == curseofrust::grid::Stencil::Rect {
         basic_opts.width += 10;
     }
 
-    Ok(Options {
+    // A URL joins a server over WebSocket, regardless of order.
+    if url.is_some() {
+        #[cfg(feature = "net-proto")]
+        {
+            protocol = Protocol::WebSocket;
+        }
+        if !matches!(multiplayer_opts, MultiplayerOpts::Client { .. }) {
+            multiplayer_opts = MultiplayerOpts::Client {
+                server: SocketAddr::from((std::net::Ipv4Addr::LOCALHOST, DEFAULT_SERVER_PORT)),
+                port: DEFAULT_CLIENT_PORT,
+            };
+        }
+    }
+
+    if connect_ui && !matches!(multiplayer_opts, MultiplayerOpts::Client { .. }) {
+        multiplayer_opts = MultiplayerOpts::Client {
+            server: SocketAddr::from((std::net::Ipv4Addr::LOCALHOST, DEFAULT_SERVER_PORT)),
+            port: DEFAULT_CLIENT_PORT,
+        };
+    }
+
+    let mut options = Options {
         basic: basic_opts,
         multiplayer: multiplayer_opts,
         exit,
@@ -203,7 +852,83 @@ where
         #[cfg(feature = "net-proto")]
         protocol,
         control_mode: cm,
-    })
+        narrate,
+        lang,
+        afk_timeout: (afk_timeout > 0).then(|| Duration::from_secs(afk_timeout)),
+        headless,
+        batch,
+        bench_render,
+        alert,
+        palette,
+        shade,
+        braille,
+        time_limit: (!time_limit.is_zero()).then_some(time_limit),
+        turns: (turns > 0).then_some(turns),
+        players,
+        observe,
+        rpc,
+        export,
+        maps,
+        resume,
+        daily,
+        bot,
+        trace_wire,
+        menu,
+        connect_ui,
+        bind,
+        lobby_print,
+        healthz,
+        announce,
+        name: game_name,
+        password,
+        allow,
+        list,
+        url,
+        warnings: Vec::new(),
+    };
+    validate::validate(&mut options)?;
+    Ok(options)
+}
+
+/// Gets the port `COR_SERVER` gives the server to host on, as `-e`,
+/// if it is a port rather than an address to join.
+///
+/// Only the server reads it, so that clients run in the same
+/// environment keep playing alone.
+pub fn env_server_port() -> Option<u16> {
+    std::env::var("COR_SERVER").ok()?.parse().ok()
+}
+
+/// Reads a non-empty variable of the environment.
+fn env_var<T>(var: &'static str) -> Result<Option<T>, Error>
+where
+    T: std::str::FromStr,
+    Error: From<T::Err>,
+{
+    std::env::var(var)
+        .ok()
+        .filter(|value| !value.is_empty())
+        .map(|value| {
+            value.parse().map_err(|err| Error::InvalidEnv {
+                var,
+                source: Box::new(Error::from(err)),
+            })
+        })
+        .transpose()
+}
+
+/// Parses a duration in seconds, or with an `s`, `m` or `h` suffix.
+fn parse_duration(s: &str) -> Result<Duration, Error> {
+    let (value, unit) = s.split_at(s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len()));
+    let secs = match unit {
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        _ => return Err(Error::InvalidDuration(s.to_owned())),
+    };
+    Ok(Duration::from_secs(
+        value.parse::<u64>()?.saturating_mul(secs),
+    ))
 }
 
 /// The options for the program.
@@ -214,11 +939,291 @@ pub struct Options {
     pub multiplayer: MultiplayerOpts,
     pub exit: bool,
     pub control_mode: ControlMode,
+    /// Narrate the game as text instead of drawing the grid.
+    pub narrate: bool,
+    /// Language of user-facing strings.
+    pub lang: Lang,
+    /// Time after which the server hands idle clients over to AI.
+    /// `None` if disabled.
+    pub afk_timeout: Option<Duration>,
+    /// Simulate AI-only games without rendering.
+    pub headless: bool,
+    /// Number of games to simulate in headless mode.
+    pub batch: usize,
+    /// Number of frames to render in the rendering benchmark,
+    /// if it is run instead of the game.
+    pub bench_render: Option<usize>,
+    /// How to alert the player of losses. `None` if quiet.
+    pub alert: Option<Alert>,
+    /// Colors of players in the console. [`Palette::Auto`] defers
+    /// to the profile, then to the terminal.
+    pub palette: Palette,
+    /// Shade the background of tiles by their population, on
+    /// terminals with 24-bit colors.
+    pub shade: bool,
+    /// Start the console drawing the ownership of the map in braille
+    /// instead of the tiles.
+    pub braille: bool,
+    /// Time after which the player with the highest score wins.
+    /// `None` if unlimited.
+    pub time_limit: Option<Duration>,
+    /// Steps the game advances per turn in turn-based mode.
+    /// `None` if real-time.
+    pub turns: Option<u32>,
+    /// Countries this client controls, switched between with Tab.
+    pub players: usize,
+    /// Port of the read-only HTTP endpoint the server serves
+    /// the game state to spectators on, if any.
+    pub observe: Option<u16>,
+    /// Port of the local JSON-RPC endpoint the server drives
+    /// the engine through instead of hosting a game, if any.
+    pub rpc: Option<u16>,
+    /// File the packed state is mirrored into on every step,
+    /// for external visualizers to map. `None` if disabled.
+    pub export: Option<PathBuf>,
+    /// File listing the rounds the server plays back to back,
+    /// scoring them together. `None` for a single game.
+    pub maps: Option<PathBuf>,
+    /// File holding the state the server continues the game from,
+    /// as saved by a client taking over a relayed game. `None` for
+    /// a new game.
+    pub resume: Option<PathBuf>,
+    /// Daily challenge whose map is played, if any.
+    pub daily: Option<Daily>,
+    /// Strategy of the king the client plays for instead of the user,
+    /// without rendering. `None` if the user plays.
+    pub bot: Option<Strategy>,
+    /// Print every frame sent or received over the network.
+    pub trace_wire: bool,
+    /// Configure the game in a form before starting it,
+    /// starting from the other options.
+    pub menu: bool,
+    /// Ask for the server, protocol and name to join with before
+    /// connecting, starting from the other options.
+    pub connect_ui: bool,
+    /// Address the server binds, instead of the detected local one.
+    pub bind: Option<IpAddr>,
+    /// Print clients connecting to the lobby of the server.
+    pub lobby_print: bool,
+    /// Port of the HTTP endpoint the server answers liveness
+    /// probes on, if any.
+    pub healthz: Option<u16>,
+    /// Address of the master server the server announces
+    /// its game to while in the lobby, if any.
+    pub announce: Option<String>,
+    /// Name of the game announced to the master server.
+    /// `None` for the name of the profile.
+    pub name: Option<String>,
+    /// Password the server asks joining clients for, and the client
+    /// gives. `None` if none.
+    pub password: Option<String>,
+    /// Addresses and host names of the clients the server lets join.
+    /// Empty if any.
+    pub allow: Vec<String>,
+    /// Address of the master server the client lists games of,
+    /// to pick the server to join.
+    pub list: Option<String>,
+    /// WebSocket URL the client joins the server at instead of
+    /// its address, such as `wss://example.com/curseofrust` behind
+    /// a reverse proxy.
+    pub url: Option<String>,
+    /// Options the game accepts, but not as given.
+    pub warnings: Vec<Warning>,
 
     #[cfg(feature = "net-proto")]
     pub protocol: Protocol,
 }
 
+impl Options {
+    /// Serializes the options back into canonical arguments,
+    /// without the program name.
+    ///
+    /// Parsing the arguments gives the same options, apart from
+    /// [`exit`](Self::exit), the [`daily`](Self::daily) challenge, whose
+    /// map is given instead, and the balance, which is read from a file
+    /// and not included. Options of the game are always included, and
+    /// other options only if they are not the default.
+    pub fn to_args(&self) -> Vec<String> {
+        let mut args = self.rules_args();
+        macro_rules! push {
+            ($flag:expr, $value:expr) => {{
+                args.push($flag.to_owned());
+                args.push($value.to_string());
+            }};
+        }
+
+        match self.multiplayer {
+            MultiplayerOpts::None => {}
+            MultiplayerOpts::Server { port, relay } => {
+                push!("-e", port);
+                if let Some(relay) = relay {
+                    push!("-Y", relay);
+                }
+                push!("-E", self.basic.clients);
+            }
+            MultiplayerOpts::Relay { port } => push!("-y", port),
+            MultiplayerOpts::Client { server, port } => {
+                push!("-C", server);
+                push!("-c", port);
+            }
+        }
+        #[cfg(feature = "net-proto")]
+        if self.protocol != Protocol::default() {
+            push!("-p", self.protocol.name());
+        }
+        if self.control_mode != ControlMode::default() {
+            push!("-m", self.control_mode.name());
+        }
+        push!("--lang", self.lang.code());
+        match self.alert {
+            None => args.push("--quiet".to_owned()),
+            Some(Alert::Bell) => {}
+            Some(alert) => push!("--alert", alert.name()),
+        }
+        if self.palette != Palette::default() {
+            push!("--palette", self.palette.name());
+        }
+        if self.players > 1 {
+            push!("--players", self.players);
+        }
+        if let Some(port) = self.observe {
+            push!("--observe", port);
+        }
+        if let Some(port) = self.rpc {
+            push!("--rpc", port);
+        }
+        if let Some(ip) = self.bind {
+            push!("--bind", ip);
+        }
+        if let Some(port) = self.healthz {
+            push!("--healthz", port);
+        }
+        if let Some(ref master) = self.announce {
+            push!("--announce", master);
+        }
+        if let Some(ref name) = self.name {
+            push!("--name", name);
+        }
+        // Passwords are kept out of the arguments, which are logged.
+        if !self.allow.is_empty() {
+            push!("--allow", self.allow.join(","));
+        }
+        if let Some(ref master) = self.list {
+            push!("--list", master);
+        }
+        if let Some(ref url) = self.url {
+            push!("--url", url);
+        }
+        if let Some(ref path) = self.export {
+            push!("--export", path.display());
+        }
+        if let Some(ref path) = self.maps {
+            push!("--maps", path.display());
+        }
+        if let Some(ref path) = self.resume {
+            push!("--resume", path.display());
+        }
+        if let Some(name) = self.bot.and_then(|bot| StrategyWrapper(bot).name()) {
+            push!("--bot", name);
+        }
+        if self.batch != 1 {
+            push!("--batch", self.batch);
+        }
+        if let Some(frames) = self.bench_render {
+            push!("--bench-render", frames);
+        }
+
+        for (flag, on) in [
+            ("--narrate", self.narrate),
+            ("--shade", self.shade),
+            ("--braille", self.braille),
+            ("--headless", self.headless),
+            ("--trace-wire", self.trace_wire),
+            ("--menu", self.menu),
+            ("--connect-ui", self.connect_ui),
+            ("--no-lobby-print", !self.lobby_print),
+        ] {
+            if on {
+                args.push(flag.to_owned());
+            }
+        }
+        args
+    }
+
+    /// Serializes the options deciding how the game is played into
+    /// canonical arguments, as [`to_args`](Self::to_args) does.
+    ///
+    /// These are the options a game resumed after a host migration
+    /// keeps, along with the balance, see [`Rules`].
+    pub fn rules_args(&self) -> Vec<String> {
+        let basic = &self.basic;
+        let mut args = Vec::new();
+        macro_rules! push {
+            ($flag:expr, $value:expr) => {{
+                args.push($flag.to_owned());
+                args.push($value.to_string());
+            }};
+        }
+
+        // Undo the fix of the weird bug.
+        let width = if basic.shape == curseofrust::grid::Stencil::Rect {
+            basic.width.saturating_sub(10)
+        } else {
+            basic.width
+        };
+        push!("-W", width);
+        push!("-H", basic.height);
+        push!("-S", Stencil(basic.shape).name());
+        if !basic.keep_random {
+            push!("-l", basic.locations);
+        }
+        if let Some(inequality) = basic.inequality {
+            push!("-i", inequality);
+        }
+        if let Some(conditions) = basic.conditions {
+            push!("-q", conditions);
+        }
+        push!("-d", Difficulty(basic.difficulty).name());
+        push!("-s", Speed(basic.speed).name());
+        push!("-R", basic.seed);
+        if let Some(name) = basic.preset.and_then(|p| Preset(p).name()) {
+            push!("--preset", name);
+        }
+        if let Some(seed) = basic.ai_personality_seed {
+            push!("--personality-seed", seed);
+        }
+        if let Some(teams) = basic.teams {
+            push!("--teams", teams);
+        }
+        if basic.tick_ms != BasicOpts::default().tick_ms {
+            push!("--tick-ms", basic.tick_ms);
+        }
+
+        if self.afk_timeout != Some(Duration::from_secs(DEFAULT_AFK_TIMEOUT)) {
+            push!("-a", self.afk_timeout.map_or(0, |t| t.as_secs()));
+        }
+        if let Some(time_limit) = self.time_limit {
+            push!("--time-limit", time_limit.as_secs());
+        }
+        if let Some(turns) = self.turns {
+            push!("--turns", turns);
+        }
+        for (flag, on) in [
+            ("-r", basic.keep_random),
+            ("-T", basic.timeline),
+            ("--assist", basic.assist),
+            ("--adaptive-ai", basic.adaptive_ai),
+            ("--rotate-ai", basic.rotate_ai),
+            ("--landscapes", basic.landscapes),
+        ] {
+            if on {
+                args.push(flag.to_owned());
+            }
+        }
+        args
+    }
+}
+
 #[derive(Debug)]
 pub enum Error {
     MissingValue {
@@ -233,40 +1238,144 @@ pub enum Error {
     UnknownFlag {
         flag: char,
     },
+    UnknownLongFlag {
+        flag: String,
+    },
     UnknownVariant {
         ty: &'static str,
         variants: &'static [&'static str],
         value: String,
     },
+    /// The balance file could not be read.
+    ReadBalance(std::io::Error),
+    /// The balance file is not valid.
+    InvalidBalance(toml::de::Error),
+    /// The share of gold plundered is not between 0 and 1.
+    PlunderOutOfRange(f32),
+    /// The duration is not a number with an optional unit.
+    InvalidDuration(String),
+    /// The number of locations is more than the shape has,
+    /// or less than two.
+    LocationsOutOfRange {
+        locations: usize,
+        max: usize,
+        /// Whether the hexagon has enough locations.
+        hex: bool,
+    },
+    /// The quality of the player's location is not one of the locations.
+    ConditionsOutOfRange {
+        conditions: u32,
+        locations: usize,
+    },
+    /// The number of countries to control is zero, or more
+    /// than there are.
+    PlayersOutOfRange {
+        players: usize,
+        max: usize,
+    },
+    /// The client binds the port the server on this machine listens on.
+    PortConflict {
+        port: u16,
+        suggested: u16,
+    },
+    /// The URL is not a `ws` or `wss` URL.
+    InvalidUrl(String),
+    /// The socket address is not an IP address with an optional port,
+    /// or its interface is not known.
+    InvalidAddr(String),
+    /// A variable of the environment is not valid.
+    InvalidEnv {
+        var: &'static str,
+        source: Box<Error>,
+    },
 }
 
-impl std::fmt::Display for Error {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl Error {
+    /// Formats this error in the given language.
+    pub fn localized(&self, lang: Lang) -> String {
         match self {
-            Error::MissingValue { arg, ty } => {
-                write!(
-                    f,
-                    "missing value for argument '{arg}', expected type '{ty}'"
-                )
+            Error::MissingValue { arg, ty } => lang.trf(
+                "missing value for argument '{0}', expected type '{1}'",
+                &[arg, ty],
+            ),
+            Error::InvalidIntValueFmt(err) => lang.trf("invalid integer formatting: {0}", &[err]),
+            Error::InvalidIpAddrValueFmt(err) => {
+                lang.trf("invalid IP-address formatting: {0}", &[err])
             }
-            Error::InvalidIntValueFmt(err) => write!(f, "invalid integer formatting: {err}"),
-            Error::InvalidIpAddrValueFmt(err) => write!(f, "invalid IP-address formatting: {err}"),
             Error::NonUnicodeValue { content } => {
-                write!(f, "non-unicode value: {content:?}")
+                lang.trf("non-unicode value: {0}", &[&format!("{content:?}")])
+            }
+            Error::UnknownFlag { flag } => lang.trf("unknown flag: {0}", &[flag]),
+            Error::UnknownLongFlag { flag } => {
+                lang.trf("unknown flag: {0}", &[&format!("--{flag}")])
             }
-            Error::UnknownFlag { flag } => write!(f, "unknown flag: {flag}"),
             Error::UnknownVariant {
                 ty,
                 variants,
                 value,
-            } => write!(
-                f,
-                "unknown variant '{value}' for type '{ty}', expected one of: {variants:?}",
+            } => lang.trf(
+                "unknown variant '{0}' for type '{1}', expected one of: {2}",
+                &[value, ty, &format!("{variants:?}")],
+            ),
+            Error::ReadBalance(err) => lang.trf("cannot read balance file: {0}", &[err]),
+            Error::InvalidBalance(err) => lang.trf("invalid balance file: {0}", &[err]),
+            Error::PlunderOutOfRange(plunder) => lang.trf(
+                "plunder {0} is out of range, expected 0 to 1",
+                &[plunder],
+            ),
+            Error::InvalidDuration(value) => lang.trf("invalid duration: {0}", &[value]),
+            Error::LocationsOutOfRange {
+                locations,
+                max,
+                hex,
+            } => {
+                let mut msg = lang.trf(
+                    "cannot place {0} countries, expected 2 to {1} for this shape; pass -l {1}",
+                    &[locations, max],
+                );
+                if *hex {
+                    msg += lang.tr(", or -S hex for up to 6");
+                }
+                msg
+            }
+            Error::ConditionsOutOfRange {
+                conditions,
+                locations,
+            } => lang.trf(
+                "location quality {0} is out of range, expected 1 to {1}; pass -q {1} for the worst",
+                &[conditions, locations],
+            ),
+            Error::PlayersOutOfRange { players, max } => lang.trf(
+                "cannot control {0} countries, expected 1 to {1}; pass --players {1}",
+                &[players, max],
+            ),
+            Error::PortConflict { port, suggested } => lang.trf(
+                "client port {0} is the port of the local server; pass -c {1}",
+                &[port, suggested],
+            ),
+            Error::InvalidUrl(url) => lang.trf(
+                "not a WebSocket URL: {0}, expected ws:// or wss://",
+                &[url],
+            ),
+            Error::InvalidAddr(addr) => lang.trf(
+                "invalid address: {0}, expected such as 192.168.1.2:19140 or [fe80::1%eth0]:19140",
+                &[addr],
+            ),
+            Error::InvalidEnv { var, source } => lang.trf(
+                "invalid environment variable {0}: {1}",
+                &[var, &source.localized(lang)],
             ),
         }
     }
 }
 
+impl std::fmt::Display for Error {
+    #[inline]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.localized(Lang::En))
+    }
+}
+
 impl<'a> From<&'a OsStr> for Error {
     #[inline]
     fn from(value: &'a OsStr) -> Self {
@@ -283,6 +1392,13 @@ impl From<std::num::ParseIntError> for Error {
     }
 }
 
+impl From<std::convert::Infallible> for Error {
+    #[inline]
+    fn from(value: std::convert::Infallible) -> Self {
+        match value {}
+    }
+}
+
 impl From<std::net::AddrParseError> for Error {
     #[inline]
     fn from(value: std::net::AddrParseError) -> Self {
@@ -329,6 +1445,24 @@ _/ /  \/ | |X _/ __/ __\  /   \   /  | |___| | | |  / __/_  __/
 -d [ee|e|n|h|hh]
   Difficulty level (AI) from the easiest to the hardest (default is normal).
 
+--assist
+  Start with extra gold and population on the easy difficulties (ee and e).
+
+--adaptive-ai
+  Let the AI react to how aggressive humans play: every 500 steps, the AI turns more aggressive if humans took more tiles than they lost to it, and calmer if it took more.
+
+--rotate-ai
+  Deal the AI strategies in a rotation shifting with the map seed, including turtle, instead of the same lineup on every map.
+
+--landscapes
+  Generate some grassland as forest or hill, which shelter their owners in combat.
+
+--teams teams
+  Deal the players into the given number of teams in turn, player 1 in the first team, player 2 in the second, and so on. Allies do not fight each other, allied AIs share their view of the map and leave the tiles flagged by each other alone, and the last team standing wins.
+
+--personality-seed seed
+  Give every AI slightly different flag thresholds and aggression, rolled from the given seed (unsigned integer), so that the same lineup plays differently. The seed is printed when the game ends.
+
 -s [p|sss|ss|s|n|f|ff|fff]
   Game speed from the slowest to the fastest (default is normal).
 
@@ -336,7 +1470,7 @@ _/ /  \/ | |X _/ __/ __\  /   \   /  | |___| | | |  / __/_  __/
   Specify a random seed (unsigned integer) for map generation.
 
 -T
-  Show the timeline.
+  Show the population graph at start, toggled with T.
 
 -E [1|2| ... L]
   Start a server for not more than L clients.
@@ -344,8 +1478,17 @@ _/ /  \/ | |X _/ __/ __\  /   \   /  | |___| | | |  / __/_  __/
 -e port
   Server's port (19140 is default).
 
--C IP
-  Start a client and connect to the provided server's IP-address.
+-Y IP[:port]
+  Host the server through the relay at the provided IP-address (port 19140 by default), instead of accepting connections directly.
+
+-y port
+  Start a relay forwarding game traffic between a host and its clients.
+
+-a seconds
+  Hand countries of clients idle for this long over to AI until they come back (30 is default, 0 disables).
+
+-C IP[:port]
+  Start a client and connect to the provided server's IP-address. IPv6 addresses followed by a port are bracketed, and may be scoped to an interface, such as [fe80::1%eth0]:19140. Without the port, the server's port 19140 is used. Without the address, both are asked for before connecting, as with --connect-ui.
 
 -c port
   Clients's port (19150 is default).
@@ -353,9 +1496,189 @@ _/ /  \/ | |X _/ __/ __\  /   \   /  | |___| | | |  / __/_  __/
 -m [keyboard|termux|hybrid]
   Control method.
 
+--menu
+  Configure the game in a form before starting it: map size and shape, difficulty, speed, and whether to join a server. Other arguments fill the form.
+
+--connect-ui
+  Start a client, asking for the server address and port, the protocol and your name before connecting. Other arguments are the defaults.
+
+--narrate
+  Narrate the game as text for screen readers, instead of drawing the map. Press T to read the tile under the cursor.
+
+--balance file
+  Loads balance parameters from the given TOML file. Missing parameters keep their default.
+
+--preset [classic|blitz|marathon]
+  Rule preset setting the speed and balance: blitz for short games with cheap, fast-growing cities, marathon for long games with expensive, slow-growing ones. -s and --balance override it.
+
+--skirmish [small|medium|large]
+  Quick-start skirmish overriding the map: a duel on a small rectangle, four countries on a rectangle, or six on the largest hexagon. In the game menu, keys 1, 2 and 3 start them at once.
+
+--daily
+  Play the map of the day, which is the same for everyone worldwide on a given day (in UTC), with the classic balance at normal speed and difficulty. It overrides the map options, --skirmish, --preset, --balance and -s, and the quickest win of each day, in steps of the game, is kept in the profile.
+
+--headless
+  Simulate AI-only games without rendering, and print win rates of each strategy as CSV.
+
+--batch N
+  Number of games to simulate with --headless (1 by default).
+
+--bench-render N
+  Render N frames of an AI-only game to nowhere, and print bytes emitted and time per frame as CSV.
+
+--alert [bell|notify]
+  How to alert you when you lose a city or a fortress of yours comes under siege: the terminal bell (default), or a desktop notification.
+
+--quiet
+  Disable alerts.
+
+--palette [auto|truecolor|ansi|patterns]
+  Colors of players in the console: colors safe for color blindness on terminals with 24-bit colors, the 16 terminal colors, or no colors with a symbol per player on their tiles. By default, the palette of the profile is used if set, otherwise one the terminal supports.
+
+--shade
+  Shade the background of tiles by the color of their owner, darker for fewer units, on terminals with 24-bit colors.
+
+--braille
+  Start drawing an overview of the map in braille, a dot per tile, instead of the tiles, to fit large maps on small terminals. Press Y to switch between them.
+
+--time-limit duration
+  End the game after the given time, such as 90s, 20m or 1h, not counting pauses. The player with the highest score, population plus gold, wins.
+
+--tick-ms milliseconds
+  Real time of a simulation tick (10 by default). Speeds step the game every few ticks, so larger ticks slow every speed down and save power.
+
+--turns steps
+  Turn-based mode: the game advances the given number of steps once every human player ends the turn with Enter.
+
+--players countries
+  Control several countries from this client, such as to play co-op on one keyboard, switching between them with Tab. In multiplayer, the countries are claimed from the server.
+
+--observe port
+  Serve the game state as JSON over HTTP on the given port, for spectators. Server only, requires the observer feature.
+
+--rpc port
+  Instead of hosting a game, let local tools drive the engine over JSON-RPC on the given port. Server only, requires the rpc feature.
+
+--maps file
+  Play the rounds listed in the given file back to back, one per line as options such as -s 42 -S hex, over the others given. Players score a point per player they outrank each round, and the standings are told between rounds and written next to the file at the end. Server only.
+
+--resume file
+  Continue the game from the state saved in the given file, until as many clients as given by -E joined again. Clients taking over a relayed game whose host was lost start such servers. Server only.
+
+--bind IP
+  Address the server or relay binds, such as 0.0.0.0 in containers (the detected local address by default).
+
+--no-lobby-print
+  Do not print clients connecting to the lobby of the server.
+
+--healthz port
+  Answer liveness probes on GET /healthz over HTTP on the given port, and GET /metrics with the time spent per tick. Server only.
+
+--announce host:port
+  Announce the game to the master server at the given address while waiting for clients, so that clients can find it with --list. Server only.
+
+--name name
+  Name of the game announced with --announce (the name of the profile by default).
+
+--password password
+  On the server, let only clients giving the same password join. On the client, the password to give, of up to 32 bytes.
+
+--allow address,...
+  Let only clients from the given IP addresses or host names join, such as 192.168.1.20,alice.lan. Server only, not through a relay.
+
+--list host:port
+  List the games announced to the master server at the given address, and join the one picked. Client only.
+
+--url ws[s]://host[:port][/path]
+  Join the server at the given WebSocket URL, such as wss://example.com/curseofrust behind a reverse proxy, instead of -C. Client only, requires the ws feature.
+
+--export file
+  Mirror the map into the given file on every step, for external visualizers to memory-map. Singleplayer only.
+
+--bot [aggr_greedy|one_greedy|persistent_greedy|opportunist|noble|midas|turtle|swarm]
+  Play for an AI with the given strategy instead of you, without rendering, to supply AI players to a server. Client only, over udp.
+
+--trace-wire
+  Print every message sent or received with its time, direction and fields. The server prints to the standard output, and the client to the standard error.
+
+--lang [en|zh]
+  Language of the interface (taken from the LANG environment variable by default).
+
 -v
   Display the version number
 
 -h
   Display this help
+
+  Environment variables:
+
+COR_SEED, COR_SPEED, COR_PROTOCOL
+  Same as -R, -s and -p.
+
+COR_SERVER
+  Same as -e if a port, or -C if an address.
+
+  Arguments override environment variables.
 "#;
+
+#[cfg(test)]
+mod tests {
+    use curseofrust::Speed;
+
+    use super::parse_to_options;
+
+    #[test]
+    fn env_sits_between_defaults_and_flags() {
+        let parse =
+            |args: &[&str]| parse_to_options(std::iter::once(&"curseofrust").chain(args)).unwrap();
+        // Empty variables are ignored. Tests running meanwhile give
+        // the seed and speed as flags, if at all.
+        std::env::set_var("COR_SEED", "");
+        std::env::set_var("COR_SPEED", "");
+        let defaults = parse(&[]);
+        std::env::set_var("COR_SEED", "42");
+        std::env::set_var("COR_SPEED", "f");
+        let env = parse(&[]);
+        let flags = parse(&["-R", "7", "-s", "s"]);
+        std::env::remove_var("COR_SEED");
+        std::env::remove_var("COR_SPEED");
+
+        assert_eq!(defaults.basic.speed, Speed::Normal);
+        assert_eq!((env.basic.seed, env.basic.speed), (42, Speed::Fast));
+        assert_eq!((flags.basic.seed, flags.basic.speed), (7, Speed::Slow));
+    }
+
+    #[test]
+    fn args_round_trip() {
+        let parse = |args: Vec<String>| {
+            parse_to_options(std::iter::once("curseofrust".to_owned()).chain(args)).unwrap()
+        };
+        let args = [
+            "--preset",
+            "marathon",
+            "--resume",
+            "game.state",
+            "--time-limit",
+            "5m",
+            "--tick-ms",
+            "25",
+            "-e",
+            "19140",
+            "-E",
+            "3",
+            "-d",
+            "hh",
+            "--teams",
+            "2",
+            "--landscapes",
+        ];
+        let options = parse(args.map(str::to_owned).to_vec());
+        let args = options.to_args();
+        let parsed = parse(args.clone());
+        assert_eq!(format!("{parsed:?}"), format!("{options:?}"));
+        assert_eq!(parsed.to_args(), args);
+        for flag in ["--preset", "--resume", "--time-limit", "--tick-ms"] {
+            assert!(args.iter().any(|arg| arg == flag), "{flag} is lost");
+        }
+    }
+}
@@ -0,0 +1,104 @@
+//! Local player profile.
+//!
+//! The profile is kept as a TOML file in the platform config
+//! directory, and the frontends update it whenever a game ends.
+
+use std::{collections::BTreeMap, path::PathBuf, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+use crate::Palette;
+
+const FILE_NAME: &str = "profile.toml";
+
+/// Player profile and lifetime statistics.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Profile {
+    /// Name of the player, the login name by default.
+    pub name: String,
+    /// Preferred color, given as the player whose color it is.
+    pub color: Option<u32>,
+    /// Preferred palette of the console, used unless one is given.
+    pub palette: Option<Palette>,
+    pub wins: u32,
+    pub losses: u32,
+    /// Total time played, in seconds.
+    pub playtime: u64,
+    /// Quickest win of each daily challenge played, in steps of
+    /// the game, by the date of the challenge.
+    pub daily_steps: BTreeMap<String, u64>,
+}
+
+impl Profile {
+    /// Path of the profile file, if the config directory is known.
+    ///
+    /// The directory is `%APPDATA%` on Windows,
+    /// `~/Library/Application Support` on macOS,
+    /// and `$XDG_CONFIG_HOME` or `~/.config` elsewhere.
+    pub fn path() -> Option<PathBuf> {
+        let env = |key| {
+            std::env::var_os(key)
+                .filter(|v| !v.is_empty())
+                .map(PathBuf::from)
+        };
+        let dir = if cfg!(windows) {
+            env("APPDATA")?
+        } else if cfg!(target_os = "macos") {
+            env("HOME")?.join("Library/Application Support")
+        } else {
+            env("XDG_CONFIG_HOME").or_else(|| Some(env("HOME")?.join(".config")))?
+        };
+        Some(dir.join("curseofrust").join(FILE_NAME))
+    }
+
+    /// Loads the profile.
+    ///
+    /// A missing or invalid file gives a new profile.
+    pub fn load() -> Self {
+        let mut profile: Self = Self::path()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .and_then(|file| toml::from_str(&file).ok())
+            .unwrap_or_default();
+        if profile.name.is_empty() {
+            profile.name = ["USER", "USERNAME"]
+                .into_iter()
+                .find_map(|key| std::env::var(key).ok().filter(|v| !v.is_empty()))
+                .unwrap_or_default();
+        }
+        profile
+    }
+
+    /// Saves the profile, creating the config directory if needed.
+    pub fn save(&self) -> std::io::Result<()> {
+        let path = Self::path().ok_or(std::io::ErrorKind::NotFound)?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let file = toml::to_string(self).map_err(std::io::Error::other)?;
+        std::fs::write(path, file)
+    }
+
+    /// Records a game played for the given time.
+    ///
+    /// `won` is `None` if the game was left undecided.
+    pub fn record(&mut self, won: Option<bool>, played: Duration) {
+        match won {
+            Some(true) => self.wins += 1,
+            Some(false) => self.losses += 1,
+            None => {}
+        }
+        self.playtime += played.as_secs();
+    }
+
+    /// Records a win of the daily challenge of the given date, taking
+    /// the given steps of the game.
+    ///
+    /// Returns whether the win is the quickest of the day.
+    pub fn record_daily(&mut self, date: String, steps: u64) -> bool {
+        let best = self.daily_steps.entry(date).or_insert(steps);
+        let new = steps <= *best;
+        *best = steps.min(*best);
+        new
+    }
+}
@@ -0,0 +1,167 @@
+//! Games set up at once: skirmishes of a few sizes, and the daily
+//! challenge, on which everyone plays the same map on the same day.
+
+use std::time::SystemTime;
+
+use curseofrust::{grid::Stencil, state::BasicOpts, Difficulty, Preset};
+
+use crate::Error;
+
+/// Size of a quick-start skirmish.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Skirmish {
+    /// A duel on a small map.
+    Small,
+    /// Four countries on a rectangle.
+    Medium,
+    /// Six countries on the largest hexagon.
+    Large,
+}
+
+impl Skirmish {
+    pub const ALL: [Self; 3] = [Self::Small, Self::Medium, Self::Large];
+
+    /// Name of the size, as parsed.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Small => "small",
+            Self::Medium => "medium",
+            Self::Large => "large",
+        }
+    }
+
+    /// Shape, width as given by `-W`, height and number of countries
+    /// of the map.
+    pub fn map(self) -> (Stencil, u32, u32, usize) {
+        match self {
+            Self::Small => (Stencil::Rect, 11, 15, 2),
+            Self::Medium => (Stencil::Rect, 21, 21, 4),
+            Self::Large => (Stencil::Hex, 40, 29, 6),
+        }
+    }
+
+    /// Writes the map into the options, with the width as given
+    /// by `-W`.
+    pub(crate) fn apply(self, b_opt: &mut BasicOpts) {
+        let (shape, width, height, locations) = self.map();
+        b_opt.shape = shape;
+        b_opt.width = width;
+        b_opt.height = height;
+        b_opt.locations = locations;
+    }
+}
+
+impl std::str::FromStr for Skirmish {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|size| size.name() == s)
+            .ok_or_else(|| Error::UnknownVariant {
+                ty: "skirmish",
+                variants: &["small", "medium", "large"],
+                value: s.to_owned(),
+            })
+    }
+}
+
+/// The daily challenge of a day.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Daily {
+    /// Days since 1970-01-01, in UTC.
+    pub day: u64,
+}
+
+impl Daily {
+    /// Gets the challenge of the current day, in UTC so that it is
+    /// the same all over the world.
+    pub fn today() -> Self {
+        let secs = SystemTime::UNIX_EPOCH
+            .elapsed()
+            .unwrap_or_default()
+            .as_secs();
+        Self {
+            day: secs / (24 * 60 * 60),
+        }
+    }
+
+    /// Gets the date of the day, as `YYYY-MM-DD`.
+    pub fn date(self) -> String {
+        // Days to the civil calendar, after Howard Hinnant.
+        let z = self.day as i64 + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z.rem_euclid(146_097);
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + (month <= 2) as i64;
+        format!("{year:04}-{month:02}-{day:02}")
+    }
+
+    /// Gets the seed of the map of the day.
+    pub fn seed(self) -> u64 {
+        // SplitMix64, so that the seeds of adjacent days differ.
+        let mut z = self.day.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Writes the game of the day into the options, with the width
+    /// as given by `-W`: the map, the classic balance at normal speed,
+    /// and AI personalities.
+    pub(crate) fn apply(self, b_opt: &mut BasicOpts) {
+        let seed = self.seed();
+        Skirmish::ALL[(seed >> 8) as usize % Skirmish::ALL.len()].apply(b_opt);
+        b_opt.seed = seed;
+        b_opt.keep_random = false;
+        b_opt.inequality = Some((seed >> 16) as u32 % 5);
+        b_opt.conditions = None;
+        b_opt.difficulty = Difficulty::Normal;
+        b_opt.ai_personality_seed = Some(seed);
+        b_opt.balance = Preset::Classic.balance();
+        b_opt.preset = None;
+        b_opt.speed = Preset::Classic.speed();
+        b_opt.assist = false;
+        b_opt.adaptive_ai = false;
+        b_opt.rotate_ai = false;
+        b_opt.landscapes = false;
+        b_opt.teams = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use curseofrust::{state::BasicOpts, Preset, Speed};
+
+    use super::Daily;
+
+    #[test]
+    fn date() {
+        let date = |day| Daily { day }.date();
+        assert_eq!(date(0), "1970-01-01");
+        assert_eq!(date(59), "1970-03-01");
+        assert_eq!(date(10_956), "1999-12-31");
+        assert_eq!(date(11_016), "2000-02-29");
+        assert_eq!(date(11_017), "2000-03-01");
+        assert_eq!(date(19_782), "2024-02-29");
+        assert_eq!(date(20_743), "2026-10-17");
+    }
+
+    #[test]
+    fn same_game() {
+        let daily = Daily { day: 20_743 };
+        let mut fast = BasicOpts::default();
+        fast.speed = Speed::Fastest;
+        fast.balance = Preset::Blitz.balance();
+        fast.preset = Some(Preset::Blitz);
+        fast.adaptive_ai = true;
+        let mut normal = BasicOpts::default();
+        daily.apply(&mut fast);
+        daily.apply(&mut normal);
+        assert_eq!(format!("{fast:?}"), format!("{normal:?}"));
+    }
+}
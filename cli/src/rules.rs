@@ -0,0 +1,125 @@
+//! Rules of a game, carried over to the server resuming it after
+//! a host migration.
+
+use std::path::Path;
+
+use curseofrust::GameBalance;
+use serde::{Deserialize, Serialize};
+
+use crate::Options;
+
+/// How a game is played, as a server tells the clients that may
+/// host the game in its place.
+///
+/// Encoded as a TOML document, see the `RULES` message of
+/// `curseofrust-msg`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Rules {
+    /// Canonical arguments of the options of the game,
+    /// see [`Options::rules_args`].
+    pub args: Vec<String>,
+    /// The balance, which arguments only give as a file.
+    pub balance: GameBalance,
+}
+
+impl Rules {
+    /// Gets the rules of the game the options describe.
+    pub fn of(options: &Options) -> Self {
+        Self {
+            args: options.rules_args(),
+            balance: options.basic.balance,
+        }
+    }
+
+    /// Encodes the rules as a TOML document.
+    pub fn encode(&self) -> Result<String, toml::ser::Error> {
+        toml::to_string(self)
+    }
+
+    /// Decodes rules encoded with [`encode`](Self::encode).
+    pub fn decode(rules: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(rules)
+    }
+
+    /// Writes the balance to the given file, to be read back
+    /// with `--balance`.
+    pub fn save_balance(&self, path: &Path) -> std::io::Result<()> {
+        let balance = toml::to_string(&self.balance).map_err(std::io::Error::other)?;
+        std::fs::write(path, balance)
+    }
+
+    /// Gets the arguments playing by these rules, with the balance
+    /// saved to `balance` by [`save_balance`](Self::save_balance).
+    pub fn to_args(&self, balance: &Path) -> Vec<String> {
+        let mut args = self.args.clone();
+        args.push("--balance".to_owned());
+        args.push(balance.display().to_string());
+        args
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use curseofrust::{Difficulty, Preset, Speed};
+
+    use super::Rules;
+    use crate::parse_to_options;
+
+    #[test]
+    fn resumed_games_keep_rules() {
+        let dir = std::env::temp_dir().join(format!("curseofrust-rules-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let custom = dir.join("custom.toml");
+        std::fs::write(
+            &custom,
+            "plunder = 0.5\ngrowth = 2.0\nflag_cooldown = [1, 1, 1, 1, 1]\n",
+        )
+        .unwrap();
+
+        let options = parse_to_options([
+            "curseofrust",
+            "-d",
+            "h",
+            "--preset",
+            "blitz",
+            "-s",
+            "s",
+            "--teams",
+            "2",
+            "--time-limit",
+            "300",
+            "--turns",
+            "5",
+            "--landscapes",
+            "-a",
+            "0",
+            "--balance",
+            custom.to_str().unwrap(),
+        ])
+        .unwrap();
+        let rules = Rules::decode(&Rules::of(&options).encode().unwrap()).unwrap();
+        assert_eq!(rules, Rules::of(&options));
+
+        let balance = dir.join("balance.toml");
+        rules.save_balance(&balance).unwrap();
+        let mut args = vec!["curseofrust-server".to_owned()];
+        args.extend(rules.to_args(&balance));
+        let resumed = parse_to_options(args).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let basic = &resumed.basic;
+        assert_eq!(basic.balance, options.basic.balance);
+        assert_eq!(basic.balance.plunder, 0.5);
+        assert_eq!(basic.balance.growth, 2.0);
+        assert_eq!(basic.preset, Some(Preset::Blitz));
+        assert_eq!(basic.speed, Speed::Slow);
+        assert_eq!(basic.difficulty, Difficulty::Hard);
+        assert_eq!(basic.teams, Some(2));
+        assert!(basic.landscapes);
+        assert_eq!(resumed.time_limit, Some(Duration::from_secs(300)));
+        assert_eq!(resumed.turns, Some(5));
+        assert_eq!(resumed.afk_timeout, None);
+    }
+}
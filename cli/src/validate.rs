@@ -0,0 +1,177 @@
+//! Checks of options against each other, after parsing.
+
+use curseofrust::{grid::Stencil, MAX_HEIGHT, MAX_WIDTH};
+
+use crate::{Error, Lang, MultiplayerOpts, Options, DEFAULT_CLIENT_PORT};
+
+/// An option the game accepts, but not as given.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Warning {
+    /// The map is wider than the maximum, and is narrowed.
+    WidthClamped {
+        /// Width of the map, including the 10 columns
+        /// rectangular maps have on top of `-W`.
+        width: u32,
+        rect: bool,
+    },
+    /// The map is higher than the maximum, and is lowered.
+    HeightClamped { height: u32 },
+}
+
+impl Warning {
+    /// Formats this warning in the given language.
+    pub fn localized(&self, lang: Lang) -> String {
+        match *self {
+            Warning::WidthClamped { width, rect } => {
+                let mut msg = lang.trf(
+                    "map width {0} is clamped to the maximum of {1}",
+                    &[&width, &MAX_WIDTH],
+                );
+                if rect {
+                    msg += &lang.trf(
+                        ", as rectangular maps are 10 wider than -W; pass -W {0} or less",
+                        &[&(MAX_WIDTH - 10)],
+                    );
+                }
+                msg
+            }
+            Warning::HeightClamped { height } => lang.trf(
+                "map height {0} is clamped to the maximum of {1}",
+                &[&height, &MAX_HEIGHT],
+            ),
+        }
+    }
+}
+
+impl std::fmt::Display for Warning {
+    #[inline]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.localized(Lang::En))
+    }
+}
+
+/// Checks the options, failing on conflicts the game cannot start
+/// with, and recording warnings of options it would clamp.
+pub(crate) fn validate(options: &mut Options) -> Result<(), Error> {
+    let basic = &options.basic;
+
+    // `-r` overrides `-l` and `-q`.
+    if !basic.keep_random {
+        let max = basic.shape.max_locs();
+        if !(2..=max).contains(&basic.locations) {
+            return Err(Error::LocationsOutOfRange {
+                locations: basic.locations,
+                max,
+                hex: basic.locations > max && basic.locations <= Stencil::Hex.max_locs(),
+            });
+        }
+        if let Some(conditions) = basic.conditions {
+            if conditions == 0 || conditions as usize > basic.locations {
+                return Err(Error::ConditionsOutOfRange {
+                    conditions,
+                    locations: basic.locations,
+                });
+            }
+        }
+    }
+
+    // Singleplayer games have room for as many humans as locations.
+    let max = match options.multiplayer {
+        MultiplayerOpts::None if basic.keep_random => basic.shape.max_locs(),
+        MultiplayerOpts::None => basic.locations,
+        _ => Stencil::Hex.max_locs(),
+    };
+    if !(1..=max).contains(&options.players) {
+        return Err(Error::PlayersOutOfRange {
+            players: options.players,
+            max,
+        });
+    }
+
+    if let MultiplayerOpts::Client { server, port } = options.multiplayer {
+        // Both ends cannot bind the same port of one machine.
+        if server.ip().is_loopback() && server.port() == port {
+            return Err(Error::PortConflict {
+                port,
+                suggested: if port == DEFAULT_CLIENT_PORT {
+                    port.wrapping_add(1)
+                } else {
+                    DEFAULT_CLIENT_PORT
+                },
+            });
+        }
+    }
+
+    if basic.width > MAX_WIDTH {
+        options.warnings.push(Warning::WidthClamped {
+            width: basic.width,
+            rect: basic.shape == Stencil::Rect,
+        });
+    }
+    if basic.height > MAX_HEIGHT {
+        options.warnings.push(Warning::HeightClamped {
+            height: basic.height,
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Warning;
+    use crate::{parse_to_options, Error};
+
+    fn parse(args: &[&str]) -> Result<crate::Options, Error> {
+        parse_to_options(std::iter::once(&"curseofrust").chain(args))
+    }
+
+    #[test]
+    fn conflicts_are_rejected() {
+        assert!(matches!(
+            parse(&["-l", "1"]),
+            Err(Error::LocationsOutOfRange { locations: 1, .. })
+        ));
+        // Too many for rectangles, but not for hexagons.
+        assert!(matches!(
+            parse(&["-S", "rect", "-l", "5"]),
+            Err(Error::LocationsOutOfRange { hex: true, .. })
+        ));
+        assert!(matches!(
+            parse(&["-l", "3", "-q", "4"]),
+            Err(Error::ConditionsOutOfRange {
+                conditions: 4,
+                locations: 3
+            })
+        ));
+        assert!(matches!(
+            parse(&["-l", "3", "--players", "4"]),
+            Err(Error::PlayersOutOfRange { players: 4, max: 3 })
+        ));
+        assert!(matches!(
+            parse(&["-C", "127.0.0.1:19150"]),
+            Err(Error::PortConflict {
+                port: 19150,
+                suggested: 19151
+            })
+        ));
+
+        // `-r` leaves the locations to the map.
+        assert!(parse(&["-r", "-l", "1"]).is_ok());
+        assert!(parse(&["-l", "3", "-q", "3", "--players", "3"]).is_ok());
+        assert!(parse(&["-C", "127.0.0.1:19140"]).is_ok());
+    }
+
+    #[test]
+    fn clamped_maps_warn() {
+        let options = parse(&["-W", "1000", "-H", "1000"]).unwrap();
+        assert!(matches!(
+            options.warnings[..],
+            [
+                Warning::WidthClamped { rect: true, .. },
+                Warning::HeightClamped { height: 1000 }
+            ]
+        ));
+        assert!(parse(&[]).unwrap().warnings.is_empty());
+    }
+}
@@ -1,9 +1,22 @@
-use curseofrust::{grid::Stencil, Difficulty, Speed};
+use std::net::{IpAddr, SocketAddr, SocketAddrV6};
 
-use crate::Error;
+use curseofrust::{grid::Stencil, Difficulty, Preset, Speed, Strategy};
+
+use crate::{Error, DEFAULT_SERVER_PORT};
 
 pub struct StencilWrapper(pub Stencil);
 
+impl StencilWrapper {
+    /// Name of the shape, as parsed.
+    pub fn name(&self) -> &'static str {
+        match self.0 {
+            Stencil::Rhombus => "rhombus",
+            Stencil::Rect => "rect",
+            Stencil::Hex => "hex",
+        }
+    }
+}
+
 impl std::str::FromStr for StencilWrapper {
     type Err = Error;
 
@@ -25,6 +38,19 @@ impl std::str::FromStr for StencilWrapper {
 
 pub struct DifficultyWrapper(pub Difficulty);
 
+impl DifficultyWrapper {
+    /// Name of the difficulty, as parsed.
+    pub fn name(&self) -> &'static str {
+        match self.0 {
+            Difficulty::Easiest => "ee",
+            Difficulty::Easy => "e",
+            Difficulty::Normal => "n",
+            Difficulty::Hard => "h",
+            Difficulty::Hardest => "hh",
+        }
+    }
+}
+
 impl std::str::FromStr for DifficultyWrapper {
     type Err = Error;
 
@@ -48,6 +74,22 @@ impl std::str::FromStr for DifficultyWrapper {
 
 pub struct SpeedWrapper(pub Speed);
 
+impl SpeedWrapper {
+    /// Name of the speed, as parsed.
+    pub fn name(&self) -> &'static str {
+        match self.0 {
+            Speed::Pause => "p",
+            Speed::Slowest => "sss",
+            Speed::Slower => "ss",
+            Speed::Slow => "s",
+            Speed::Normal => "n",
+            Speed::Fast => "f",
+            Speed::Faster => "ff",
+            Speed::Fastest => "fff",
+        }
+    }
+}
+
 impl std::str::FromStr for SpeedWrapper {
     type Err = Error;
 
@@ -71,3 +113,239 @@ impl std::str::FromStr for SpeedWrapper {
         }))
     }
 }
+
+pub struct StrategyWrapper(pub Strategy);
+
+impl StrategyWrapper {
+    /// Name of the strategy, as parsed.
+    ///
+    /// `None` for strategies that cannot be parsed.
+    pub fn name(&self) -> Option<&'static str> {
+        Some(match self.0 {
+            Strategy::AggrGreedy => "aggr_greedy",
+            Strategy::OneGreedy => "one_greedy",
+            Strategy::PersistentGreedy => "persistent_greedy",
+            Strategy::Opportunist => "opportunist",
+            Strategy::Noble => "noble",
+            Strategy::Midas => "midas",
+            Strategy::Turtle => "turtle",
+            Strategy::Swarm => "swarm",
+            _ => return None,
+        })
+    }
+}
+
+impl std::str::FromStr for StrategyWrapper {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self(match s {
+            "aggr_greedy" => Strategy::AggrGreedy,
+            "one_greedy" => Strategy::OneGreedy,
+            "persistent_greedy" => Strategy::PersistentGreedy,
+            "opportunist" => Strategy::Opportunist,
+            "noble" => Strategy::Noble,
+            "midas" => Strategy::Midas,
+            "turtle" => Strategy::Turtle,
+            "swarm" => Strategy::Swarm,
+            _ => {
+                return Err(Error::UnknownVariant {
+                    ty: "strategy",
+                    variants: &[
+                        "aggr_greedy",
+                        "one_greedy",
+                        "persistent_greedy",
+                        "opportunist",
+                        "noble",
+                        "midas",
+                        "turtle",
+                        "swarm",
+                    ],
+                    value: s.to_owned(),
+                })
+            }
+        }))
+    }
+}
+
+pub struct PresetWrapper(pub Preset);
+
+impl PresetWrapper {
+    /// Name of the preset, as parsed.
+    ///
+    /// `None` for presets that cannot be parsed.
+    pub fn name(&self) -> Option<&'static str> {
+        Some(match self.0 {
+            Preset::Classic => "classic",
+            Preset::Blitz => "blitz",
+            Preset::Marathon => "marathon",
+            _ => return None,
+        })
+    }
+}
+
+impl std::str::FromStr for PresetWrapper {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self(match s {
+            "classic" => Preset::Classic,
+            "blitz" => Preset::Blitz,
+            "marathon" => Preset::Marathon,
+            _ => {
+                return Err(Error::UnknownVariant {
+                    ty: "preset",
+                    variants: &["classic", "blitz", "marathon"],
+                    value: s.to_owned(),
+                })
+            }
+        }))
+    }
+}
+
+/// A socket address whose port may be left out, and whose IPv6
+/// address may be bracketed and scoped by the name or index of an
+/// interface, such as `[fe80::1%en0]:19140`.
+pub struct AddrWrapper {
+    /// The address, on [`DEFAULT_SERVER_PORT`] if the port is left out.
+    pub addr: SocketAddr,
+    pub port_given: bool,
+}
+
+impl std::str::FromStr for AddrWrapper {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || Error::InvalidAddr(s.to_owned());
+        let (host, port) = if let Some(rest) = s.strip_prefix('[') {
+            let (host, rest) = rest.split_once(']').ok_or_else(invalid)?;
+            match rest {
+                "" => (host, None),
+                _ => (host, Some(rest.strip_prefix(':').ok_or_else(invalid)?)),
+            }
+        } else {
+            // More colons make an IPv6 address without a port.
+            match s.split_once(':') {
+                Some((host, port)) if !port.contains(':') => (host, Some(port)),
+                _ => (s, None),
+            }
+        };
+        let port = port
+            .map(|port| port.parse::<u16>().map_err(|_| invalid()))
+            .transpose()?;
+        let (ip, scope) = match host.split_once('%') {
+            Some((ip, scope)) => (ip, Some(scope)),
+            None => (host, None),
+        };
+        let ip: IpAddr = ip.parse().map_err(|_| invalid())?;
+        let addr = match (ip, scope) {
+            (IpAddr::V6(ip), Some(scope)) => {
+                let scope = scope
+                    .parse()
+                    .ok()
+                    .or_else(|| interface_index(scope))
+                    .ok_or_else(invalid)?;
+                SocketAddr::V6(SocketAddrV6::new(
+                    ip,
+                    port.unwrap_or(DEFAULT_SERVER_PORT),
+                    0,
+                    scope,
+                ))
+            }
+            (IpAddr::V4(_), Some(_)) => return Err(invalid()),
+            (ip, None) => SocketAddr::new(ip, port.unwrap_or(DEFAULT_SERVER_PORT)),
+        };
+        Ok(Self {
+            addr,
+            port_given: port.is_some(),
+        })
+    }
+}
+
+/// Gets the index of the network interface of the given name.
+#[cfg(unix)]
+fn interface_index(name: &str) -> Option<u32> {
+    let name = std::ffi::CString::new(name).ok()?;
+    // SAFETY: the name is a valid C string, which outlives the call.
+    let index = unsafe { libc::if_nametoindex(name.as_ptr()) };
+    (index != 0).then_some(index)
+}
+
+/// Interfaces are given by index only on other platforms.
+#[cfg(not(unix))]
+fn interface_index(_name: &str) -> Option<u32> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{Ipv6Addr, SocketAddr};
+
+    use crate::{parse_to_options, MultiplayerOpts, DEFAULT_SERVER_PORT};
+
+    use super::AddrWrapper;
+
+    fn parse(s: &str) -> Option<(SocketAddr, bool)> {
+        s.parse::<AddrWrapper>()
+            .ok()
+            .map(|addr| (addr.addr, addr.port_given))
+    }
+
+    #[test]
+    fn ipv4() {
+        assert_eq!(
+            parse("192.168.1.2:19150"),
+            Some(("192.168.1.2:19150".parse().unwrap(), true))
+        );
+        assert_eq!(
+            parse("192.168.1.2"),
+            Some((([192, 168, 1, 2], DEFAULT_SERVER_PORT).into(), false))
+        );
+        assert_eq!(parse("192.168.1.2:"), None);
+        assert_eq!(parse("192.168.1.2:65536"), None);
+        assert_eq!(parse("192.168.1.2%1"), None);
+    }
+
+    #[test]
+    fn ipv6() {
+        assert_eq!(
+            parse("[::1]:19150"),
+            Some(("[::1]:19150".parse().unwrap(), true))
+        );
+        let default = Some(((Ipv6Addr::LOCALHOST, DEFAULT_SERVER_PORT).into(), false));
+        assert_eq!(parse("[::1]"), default);
+        assert_eq!(parse("::1"), default);
+        assert_eq!(parse("[::1]19150"), None);
+        assert_eq!(parse("[::1"), None);
+    }
+
+    #[test]
+    fn ipv6_scope() {
+        let (addr, port_given) = parse("[fe80::1%3]:19150").unwrap();
+        assert!(port_given);
+        let SocketAddr::V6(addr) = addr else {
+            panic!("{addr} should be IPv6");
+        };
+        assert_eq!(addr.ip(), &"fe80::1".parse::<Ipv6Addr>().unwrap());
+        assert_eq!((addr.port(), addr.scope_id()), (19150, 3));
+
+        let (addr, port_given) = parse("fe80::1%3").unwrap();
+        assert!(!port_given);
+        assert_eq!(addr.port(), DEFAULT_SERVER_PORT);
+        assert_eq!(parse("[fe80::1%no-such-interface]:19150"), None);
+    }
+
+    #[test]
+    fn client_without_port() {
+        let options = parse_to_options(["curseofrust", "-C", "192.168.1.2"]).unwrap();
+        assert!(!options.connect_ui);
+        assert!(matches!(
+            options.multiplayer,
+            MultiplayerOpts::Client { server, .. }
+                if server == ([192, 168, 1, 2], DEFAULT_SERVER_PORT).into()
+        ));
+
+        let options = parse_to_options(["curseofrust", "-C"]).unwrap();
+        assert!(options.connect_ui);
+    }
+}
@@ -0,0 +1,42 @@
+//! Watches a map exported with `--export`, printing the number
+//! of tiles held by each player whenever the game advances.
+//!
+//! ```sh
+//! cargo run --example export_reader -- map.bin
+//! ```
+
+use std::{fs::File, time::Duration};
+
+use memmap2::Mmap;
+
+const HEADER_SIZE: usize = 20;
+const TILE_SIZE: usize = 4;
+
+fn main() -> std::io::Result<()> {
+    let path = std::env::args_os()
+        .nth(1)
+        .expect("usage: export_reader <file>");
+    let file = File::open(path)?;
+    // SAFETY: the game only rewrites the file in place.
+    let map = unsafe { Mmap::map(&file)? };
+    assert_eq!(&map[..4], b"CORS", "not an exported map");
+
+    let time = || u64::from_le_bytes(map[12..20].try_into().unwrap());
+    let mut last = None;
+    loop {
+        let before = time();
+        let mut tiles = [0usize; 8];
+        for tile in map[HEADER_SIZE..].chunks_exact(TILE_SIZE) {
+            // Kinds from grassland up are habitable.
+            if tile[0] >= 3 {
+                tiles[tile[1] as usize % tiles.len()] += 1;
+            }
+        }
+        // Skip reads torn by an update.
+        if before == time() && last != Some(before) {
+            last = Some(before);
+            println!("{before}: {:?}", &tiles[1..]);
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+}
@@ -0,0 +1,53 @@
+//! Alerts on losses of the controlled player.
+
+use std::io::Write;
+
+use crossterm::{queue, style};
+use curseofrust::{snapshot::GameEvent, Pos};
+use curseofrust_cli_parser::Alert;
+
+use crate::{
+    narrate::{land_name, player_name},
+    State,
+};
+
+/// Alerts the player if one of the given events lost a city of theirs,
+/// or if a fortress of theirs newly came under siege.
+pub(crate) fn check<W: Write>(st: &mut State<W>, events: &[GameEvent]) -> std::io::Result<()> {
+    let controlled = st.s.controlled;
+    let besieged: Vec<Pos> = st.s.besieged_fortresses(controlled).collect();
+
+    let lost = events.iter().find_map(|event| match *event {
+        GameEvent::CityCaptured {
+            pos,
+            land,
+            from,
+            to,
+        } if from == controlled => {
+            let at = format!("{},{}", pos.0, pos.1);
+            Some(st.lang.trf(
+                "lost {0} at {1} to {2}",
+                &[&land_name(st, land), &at, &player_name(st, to)],
+            ))
+        }
+        _ => None,
+    });
+    let sieged = besieged
+        .iter()
+        .find(|pos| !st.besieged.contains(pos))
+        .map(|&Pos(x, y)| {
+            st.lang
+                .trf("fortress at {0} is under siege", &[&format!("{x},{y}")])
+        });
+    st.besieged = besieged;
+
+    let Some(message) = lost.or(sieged) else {
+        return Ok(());
+    };
+    match st.alert {
+        Some(Alert::Bell) => queue!(st.out, style::Print('\x07')),
+        // OSC 9, shown as a desktop notification by many terminal emulators.
+        Some(Alert::Notify) => queue!(st.out, style::Print(format!("\x1b]9;{message}\x07"))),
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,119 @@
+//! Offline benchmark of the rendering path.
+
+use std::{
+    io::Write,
+    time::{Duration, Instant},
+};
+
+use curseofrust::{
+    state::{BasicOpts, UI},
+    Pos, Strategy,
+};
+use curseofrust_cli_parser::{ControlMode, Lang};
+
+use crate::{output, DirectBoxedError, State};
+
+/// Writer discarding everything, counting the bytes.
+#[derive(Debug, Default)]
+struct Sink {
+    bytes: u64,
+}
+
+impl Write for Sink {
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.bytes += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    #[inline]
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct Record {
+    frames: u64,
+    bytes: u64,
+    time: Duration,
+}
+
+impl Record {
+    fn write_csv(&self, out: &mut impl Write, mode: &str) -> std::io::Result<()> {
+        let frames = self.frames.max(1) as f64;
+        writeln!(
+            out,
+            "{},{},{:.0},{:.1}",
+            mode,
+            self.frames,
+            self.bytes as f64 / frames,
+            self.time.as_secs_f64() * 1e6 / frames
+        )
+    }
+}
+
+/// Renders the given number of frames of an AI-only game to a sink,
+/// both redrawing everything and only the changed tiles as the game
+/// loop does, and prints the bytes emitted and time per frame as CSV.
+pub(crate) fn run(
+    b_opt: BasicOpts,
+    frames: usize,
+    control: ControlMode,
+    lang: Lang,
+    out: &mut impl Write,
+) -> Result<(), DirectBoxedError> {
+    let mut s = curseofrust::state::State::new(b_opt)?;
+    let controlled = s.controlled;
+    s.hand_to_king(controlled, Strategy::Opportunist);
+    let mut st = State {
+        ui: UI::new(&s),
+        s,
+        control,
+        narrate: false,
+        call_overlay: false,
+        braille: false,
+        stats: false,
+        log: Default::default(),
+        alert: None,
+        besieged: vec![],
+        time_left: None,
+        turns: None,
+        turn_ended: false,
+        winner: None,
+        countdown: None,
+        lang,
+        theme: Default::default(),
+        swipe: None,
+        last_flag: None,
+        out: Sink::default(),
+    };
+
+    let mut full = Record::default();
+    let mut diff = Record::default();
+    for _ in 0..frames {
+        let prev = st.s.snapshot();
+        st.s.step_n(1);
+
+        let bytes = st.out.bytes;
+        let start = Instant::now();
+        output::draw_all_grid(&mut st)?;
+        full.time += start.elapsed();
+        full.bytes += st.out.bytes - bytes;
+        full.frames += 1;
+
+        let bytes = st.out.bytes;
+        let start = Instant::now();
+        let changes = prev.diff(&st.s.snapshot());
+        let cities: Vec<Pos> = st.s.grid.cities(controlled).map(|(p, _)| p).collect();
+        output::draw_grid(&mut st, Some(changes.iter().map(|c| c.pos).chain(cities)))?;
+        diff.time += start.elapsed();
+        diff.bytes += st.out.bytes - bytes;
+        diff.frames += 1;
+    }
+
+    writeln!(out, "mode,frames,bytes_per_frame,us_per_frame")?;
+    full.write_csv(out, "full")?;
+    diff.write_csv(out, "diff")?;
+    Ok(())
+}
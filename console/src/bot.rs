@@ -0,0 +1,36 @@
+//! Headless client playing for an AI, to supply AI players to servers.
+
+#![cfg(feature = "multiplayer")]
+
+use std::net::SocketAddr;
+
+use curseofrust::Strategy;
+use curseofrust_cli_parser::{Lang, Protocol};
+use curseofrust_msg::bot::{BotRunner, KingBot, Outcome};
+
+use crate::DirectBoxedError;
+
+/// Plays for a king of the given strategy until the game ends.
+pub(crate) fn run(
+    server: SocketAddr,
+    port: u16,
+    protocol: Protocol,
+    strategy: Strategy,
+    lang: Lang,
+) -> Result<(), DirectBoxedError> {
+    if protocol != Protocol::Udp {
+        return Err(DirectBoxedError {
+            inner: lang.tr("bot clients only play over udp").into(),
+        });
+    }
+    let mut runner = BotRunner::connect(crate::client::local_addr(server, port)?, server)?;
+    println!("{}", lang.trf("playing for an AI on {0}", &[&server]));
+    match runner.run(&mut KingBot::new(strategy))? {
+        Outcome::Winner(player) => {
+            let name = lang.trf("player {0}", &[&player.0]);
+            println!("{}", lang.trf("{0} won", &[&name]));
+        }
+        Outcome::Eliminated => println!("{}", lang.tr("the AI was eliminated")),
+    }
+    Ok(())
+}
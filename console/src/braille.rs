@@ -0,0 +1,139 @@
+//! Overview of the ownership of the map in braille, a tile per dot,
+//! for maps too large for the terminal.
+//!
+//! A cell of the terminal holds 2x4 tiles, drawn in the color of the
+//! player holding most of them, with a dot for each tile of theirs.
+//! Rows are shifted by half a tile each, as in the tile view.
+
+use std::io::Write;
+
+use crossterm::{
+    cursor, queue,
+    style::{self, Attribute, ContentStyle, StyledContent},
+    terminal,
+};
+use curseofrust::{grid::Tile, Player, Pos, MAX_PLAYERS};
+
+use crate::{graph::Canvas, narrate::player_name, output, State};
+
+/// Column of the first cell.
+const LEFT: u16 = 2;
+
+/// Dot of the given tile, counted from the top left.
+#[inline]
+fn dot(Pos(x, y): Pos) -> (usize, usize) {
+    (x as usize + y as usize / 2, y as usize)
+}
+
+/// Cells taken by the map, across and down.
+fn size<W>(st: &State<W>) -> (usize, usize) {
+    let (w, h) = (st.s.grid.width() as usize, st.s.grid.height() as usize);
+    ((w + h / 2).div_ceil(2), h.div_ceil(4))
+}
+
+/// Gets the tile in the middle of the cell at the given
+/// terminal position, if it is on the map.
+pub(crate) fn rev_pos<W>(st: &State<W>, column: u16, row: u16) -> Option<Pos> {
+    let (width, height) = size(st);
+    let cx = (column.checked_sub(LEFT)?) as usize;
+    let cy = (row.checked_sub(1)?) as usize;
+    if cx >= width || cy >= height {
+        return None;
+    }
+    let y = (cy * 4 + 2).min(st.s.grid.height() as usize - 1);
+    let x = (cx * 2 + 1).saturating_sub(y / 2);
+    Some(Pos(x as i32, y as i32))
+}
+
+/// Draws the overview over the whole screen, with the tiles held by
+/// each player under it.
+pub(crate) fn draw_view<W: Write>(st: &mut State<W>) -> Result<(), std::io::Error> {
+    let lang = st.lang;
+    queue!(
+        st.out,
+        cursor::MoveTo(0, 0),
+        style::PrintStyledContent(StyledContent::new(
+            ContentStyle {
+                attributes: Attribute::Reverse.into(),
+                ..Default::default()
+            },
+            lang.tr("Map: [Y] close"),
+        )),
+        terminal::Clear(terminal::ClearType::UntilNewLine),
+    )?;
+
+    let (width, height) = size(st);
+    let mut held = vec![[0u8; MAX_PLAYERS]; width * height];
+    let mut totals = [0u32; MAX_PLAYERS];
+    let tiles = || {
+        st.s.grid
+            .tiles()
+            .filter(|(_, t)| t.is_habitable() || matches!(t, Tile::Mine(_)))
+    };
+    for (pos, tile) in tiles() {
+        let (x, y) = dot(pos);
+        let owner = tile.owner().0 as usize;
+        if let Some(n) = held[y / 4 * width + x / 2].get_mut(owner) {
+            *n += 1;
+            totals[owner] += 1;
+        }
+    }
+    let owners: Vec<Player> = held
+        .iter()
+        .map(|n| {
+            // The last of the largest is taken, so that players win
+            // ties over neutral tiles.
+            let p = (0..MAX_PLAYERS).max_by_key(|&p| n[p]).unwrap_or(0);
+            Player(p as u32)
+        })
+        .collect();
+    let mut canvas = Canvas::new(width, height);
+    for (pos, tile) in tiles() {
+        let (x, y) = dot(pos);
+        if tile.owner() == owners[y / 4 * width + x / 2] {
+            canvas.set(x, y, st.theme.color(tile.owner()));
+        }
+    }
+
+    let (cx, cy) = dot(st.ui.cursor);
+    let cursor = (cx / 2, cy / 4);
+    for row in 0..height {
+        queue!(
+            st.out,
+            cursor::MoveTo(LEFT, row as u16 + 1),
+            terminal::Clear(terminal::ClearType::UntilNewLine),
+        )?;
+        let cells: Vec<_> = canvas.row(row).collect();
+        for (column, (c, color)) in cells.into_iter().enumerate() {
+            let owner = owners[row * width + column];
+            let mut style = st.theme.style(owner);
+            style.foreground_color = Some(color);
+            if (column, row) == cursor {
+                style.attributes.set(Attribute::Reverse);
+            }
+            // Dots of any player look alike without colors.
+            let c = match st.theme.mark(owner) {
+                Some(mark) if c != '\u{2800}' => mark.chars().next().unwrap_or(c),
+                _ => c,
+            };
+            output::print_styled(&mut st.out, style, c)?;
+        }
+    }
+
+    queue!(
+        st.out,
+        cursor::MoveTo(LEFT, height as u16 + 2),
+        terminal::Clear(terminal::ClearType::CurrentLine),
+    )?;
+    for (p, &total) in totals.iter().enumerate().skip(1) {
+        if total == 0 {
+            continue;
+        }
+        let player = Player(p as u32);
+        let mark = st.theme.mark(player).unwrap_or("■");
+        output::print_styled(&mut st.out, st.theme.style(player), format!("{mark} "))?;
+        let name = player_name(st, player);
+        queue!(st.out, style::Print(format!("{name} {total}  ")))?;
+    }
+    Ok(())
+}
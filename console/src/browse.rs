@@ -0,0 +1,67 @@
+//! Picking a public game announced to a master server.
+
+use std::{
+    io::{self, BufRead as _, Write as _},
+    net::SocketAddr,
+};
+
+use curseofrust_cli_parser::Lang;
+use curseofrust_msg::master;
+
+/// Lists the games announced to the master server, and asks
+/// which one to join.
+///
+/// Returns `None` if there is no game, or none was picked.
+pub(crate) fn pick(master: &str, lang: Lang) -> io::Result<Option<SocketAddr>> {
+    let games = master::list(master)?;
+    let mut out = io::stdout().lock();
+    if games.is_empty() {
+        writeln!(out, "{}", lang.trf("no games on {0}", &[&master]))?;
+        return Ok(None);
+    }
+
+    writeln!(out, "{}", lang.trf("games on {0}:", &[&master]))?;
+    let name_width = games
+        .iter()
+        .map(|g| g.name.chars().count())
+        .max()
+        .unwrap_or(0);
+    for (i, game) in games.iter().enumerate() {
+        writeln!(
+            out,
+            "{:>3}. {:name_width$}  {}/{}  {}  {}",
+            i + 1,
+            game.name,
+            game.players,
+            game.capacity,
+            game.map,
+            game.addr,
+        )?;
+    }
+
+    let mut line = String::new();
+    loop {
+        write!(
+            out,
+            "{}",
+            lang.trf("game to join [1-{0}, empty to quit]: ", &[&games.len()])
+        )?;
+        out.flush()?;
+        line.clear();
+        if io::stdin().lock().read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        match line.trim() {
+            "" => return Ok(None),
+            n => {
+                if let Some(game) = n
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|n| games.get(n.wrapping_sub(1)))
+                {
+                    return Ok(Some(game.addr));
+                }
+            }
+        }
+    }
+}
@@ -1,30 +1,96 @@
 #![cfg(feature = "multiplayer")]
 
 use std::{
-    cell::{RefCell, UnsafeCell},
+    cell::{Cell, RefCell},
     convert::Infallible,
     io::Write,
     net::SocketAddr,
     ops::{ControlFlow, Deref, DerefMut},
+    sync::OnceLock,
+    time::{Duration, Instant},
 };
 
-use async_executor::LocalExecutor;
-use crossterm::{cursor, execute, terminal};
-use curseofrust::Pos;
-use curseofrust_cli_parser::ControlMode;
-use curseofrust_msg::{bytemuck, client_msg::*, C2SData, S2CData, C2S_SIZE, S2C_SIZE};
-use curseofrust_net_foundation::{Connection, Handle, Protocol};
+use async_channel::{Receiver, Sender};
+use crossterm::{execute, terminal};
+use curseofrust::{
+    grid::Tile, snapshot::GameEvent, Player, Pos, Speed, FLAG_POWER, MAX_FLAG_POWER,
+};
+use curseofrust_cli_parser::{ControlMode, Rules};
+use curseofrust_msg::{
+    bytemuck, capability,
+    client_msg::*,
+    event::Event,
+    fragment::Reassembler,
+    migration, reject, server_msg,
+    trace::{Flow, Tracer},
+    C2SData, S2CData, StateOrder, C2S_SIZE, MAX_MISSED_PINGS, MAX_PASSWORD, PING_INTERVAL,
+    S2C_SIZE,
+};
+use curseofrust_net_foundation::{
+    clock::{Clock, SystemClock},
+    reliable::{self, Received, Reliability},
+    Connection, Handle, Protocol, RecvOr,
+};
 use local_ip_address::{local_ip, local_ipv6};
 
-use crate::{control, DirectBoxedError, State};
+use crate::{control, narrate, DirectBoxedError, State};
+
+/// Tracer of frames exchanged with the server, if `--trace-wire` is given.
+static TRACER: OnceLock<Tracer> = OnceLock::new();
+
+/// Prints the frame to the standard error if tracing,
+/// as the standard output is taken by the game.
+fn trace(flow: Flow, frame: &[u8]) {
+    if let Some(tracer) = TRACER.get() {
+        eprintln!("{}", tracer.line("server", flow, frame));
+    }
+}
+
+/// Frames queued either way before newer ones are dropped, enough
+/// for flagging a long frontline at once.
+const QUEUE: usize = 256;
+
+/// Runs the connection to the server, sending the packets queued and
+/// passing on the frames received, until either side closes it.
+async fn connect(
+    mut socket: Connection<'_>,
+    outgoing: Receiver<Vec<u8>>,
+    incoming: Sender<Result<Vec<u8>, std::io::Error>>,
+) {
+    let mut buf = [0u8; S2C_SIZE];
+    loop {
+        match socket.recv_or(&mut buf, outgoing.recv()).await {
+            RecvOr::Other(Ok(packet)) => {
+                let _ = socket.send(&packet).await;
+            }
+            RecvOr::Other(Err(_)) => break,
+            RecvOr::Recv(received) => {
+                // Failures of datagram sockets are transient.
+                let closed =
+                    matches!(received, Ok(0) | Err(_)) && socket.protocol() != Protocol::Udp;
+                let _ = incoming.try_send(received.map(|nread| buf[..nread].to_vec()));
+                if closed {
+                    break;
+                }
+            }
+        }
+    }
+}
 
 #[derive(Copy, Clone)]
 struct MultiplayerClient<'env> {
-    executor: *const LocalExecutor<'env>,
-    socket: *const UnsafeCell<Connection<'env>>,
+    outgoing: &'env Sender<Vec<u8>>,
+    reliability: &'env RefCell<Option<Reliability>>,
 }
 
 impl MultiplayerClient<'_> {
+    /// Queues the packet for the server, dropping it if the queue
+    /// is full, as a congested network would.
+    fn send_packet(&self, packet: Vec<u8>) {
+        trace(Flow::Sent, &packet);
+        let _ = self.outgoing.try_send(packet);
+    }
+
     fn send_with_info(&self, cursor: Pos, msg: u8, info: u8) {
         let data: C2SData = (cursor, info).into();
         let mut buf = [0u8; C2S_SIZE];
@@ -33,14 +99,11 @@ impl MultiplayerClient<'_> {
             .expect("the buffer should longer than one byte");
         *m = msg;
         d.copy_from_slice(bytemuck::bytes_of(&data));
-        unsafe {
-            let socket = &mut (*UnsafeCell::raw_get(self.socket));
-            (*self.executor)
-                .spawn(async move {
-                    let _ = socket.send(&buf).await;
-                })
-                .detach();
-        }
+        let packet = match self.reliability.borrow_mut().as_mut() {
+            Some(reliability) => reliability.wrap(&buf),
+            None => buf.to_vec(),
+        };
+        self.send_packet(packet);
     }
 
     #[inline]
@@ -58,18 +121,40 @@ impl<'env> control::Client for MultiplayerClient<'env> {
     }
 
     fn toggle_flag<W>(&mut self, st: &mut State<W>, pos: Pos) -> Result<(), Self::Error> {
+        let Ok(i) = st.s.controlled.index() else {
+            return Ok(());
+        };
         if st
             .s
             .grid
             .tile(st.ui.cursor)
             .is_some_and(|t| t.is_habitable())
         {
-            let fg = &st.s.fgs[st.s.controlled.0 as usize];
-            if fg.is_flagged(st.ui.cursor) {
+            // Flag powers are not sent back by the server,
+            // so they are tracked locally.
+            let fg = &mut st.s.fgs[i];
+            if fg.power(st.ui.cursor) >= MAX_FLAG_POWER {
+                fg.remove(&st.s.grid, st.ui.cursor, MAX_FLAG_POWER);
                 self.send(pos, FLAG_OFF);
             } else {
-                self.send(pos, FLAG_ON);
+                fg.raise(&st.s.grid, st.ui.cursor, FLAG_POWER);
+                self.send_with_info(pos, FLAG_ON, 1);
+            }
+        }
+        Ok(())
+    }
+
+    fn raise_flag<W>(
+        &mut self,
+        st: &mut State<W>,
+        pos: Pos,
+        stacks: u8,
+    ) -> Result<(), Self::Error> {
+        if st.s.grid.tile(pos).is_some_and(|t| t.is_habitable()) {
+            if let Ok(i) = st.s.controlled.index() {
+                st.s.fgs[i].raise(&st.s.grid, pos, stacks as i32 * FLAG_POWER);
             }
+            self.send_with_info(pos, FLAG_ON, stacks);
         }
         Ok(())
     }
@@ -86,12 +171,58 @@ impl<'env> control::Client for MultiplayerClient<'env> {
         Ok(())
     }
 
+    #[inline]
+    fn flag_cities<W>(&mut self, _st: &mut State<W>) -> Result<(), Self::Error> {
+        self.send(Pos::default(), FLAG_CITIES);
+        Ok(())
+    }
+
+    #[inline]
+    fn flag_frontline<W>(&mut self, _st: &mut State<W>) -> Result<(), Self::Error> {
+        self.send(Pos::default(), FLAG_FRONTLINE);
+        Ok(())
+    }
+
     #[inline]
     fn build<W>(&mut self, _st: &mut State<W>, pos: Pos) -> Result<(), Self::Error> {
         self.send(pos, BUILD);
         Ok(())
     }
 
+    #[inline]
+    fn build_road<W>(&mut self, _st: &mut State<W>, pos: Pos) -> Result<(), Self::Error> {
+        self.send(pos, BUILD_ROAD);
+        Ok(())
+    }
+
+    fn toggle_queued_build<W>(&mut self, st: &mut State<W>, pos: Pos) -> Result<(), Self::Error> {
+        // Queued upgrades are not sent back by the server,
+        // so they are tracked locally too.
+        if st.s.is_build_queued(st.s.controlled, pos) {
+            st.s.unqueue_build(st.s.controlled, pos);
+            self.send_with_info(pos, QUEUE_BUILD, 0);
+        } else if st.s.queue_build(st.s.controlled, pos).is_ok() {
+            self.send_with_info(pos, QUEUE_BUILD, 1);
+        }
+        Ok(())
+    }
+
+    fn set_rally<W>(
+        &mut self,
+        st: &mut State<W>,
+        city: Pos,
+        target: Option<Pos>,
+    ) -> Result<(), Self::Error> {
+        // Rally points are not sent back by the server,
+        // so they are tracked locally.
+        if let Some(data) = C2SData::rally(city, target) {
+            if st.s.grid.set_rally(st.s.controlled, city, target).is_ok() {
+                self.send_with_info(city, RALLY, data.msg);
+            }
+        }
+        Ok(())
+    }
+
     #[inline(always)]
     fn faster<W>(&mut self, _st: &mut State<W>) -> Result<(), Self::Error> {
         Ok(())
@@ -102,50 +233,169 @@ impl<'env> control::Client for MultiplayerClient<'env> {
         Ok(())
     }
 
+    /// Speeds are told by the server, so resuming takes effect
+    /// after its countdown.
+    #[inline]
+    fn toggle_pause<W>(&mut self, st: &mut State<W>) -> Result<(), Self::Error> {
+        if st.s.speed == Speed::Pause && st.countdown.is_none() {
+            self.send(Pos::default(), UNPAUSE);
+        } else {
+            self.send(Pos::default(), PAUSE);
+        }
+        Ok(())
+    }
+
+    #[inline]
+    fn surrender<W>(&mut self, _st: &mut State<W>) -> Result<(), Self::Error> {
+        self.send(Pos::default(), SURRENDER);
+        Ok(())
+    }
+
+    #[inline]
+    fn vote_restart<W>(&mut self, _st: &mut State<W>) -> Result<(), Self::Error> {
+        self.send(Pos::default(), VOTE_RESTART);
+        Ok(())
+    }
+
+    #[inline]
+    fn end_turn<W>(&mut self, _st: &mut State<W>) -> Result<(), Self::Error> {
+        self.send(Pos::default(), END_TURN);
+        Ok(())
+    }
+
+    /// The server switches the player, and tells it in the next state.
+    #[inline]
+    fn switch_player<W>(&mut self, _st: &mut State<W>) -> Result<(), Self::Error> {
+        self.send(Pos::default(), SWITCH_PLAYER);
+        Ok(())
+    }
+
     #[inline]
-    fn toggle_pause<W>(&mut self, _st: &mut State<W>) -> Result<(), Self::Error> {
-        self.send(Pos::default(), PAUSE);
+    fn resync<W>(&mut self, _st: &mut State<W>) -> Result<(), Self::Error> {
+        self.send(Pos::default(), FULL_STATE_REQUEST);
         Ok(())
     }
 }
 
+/// Gets the local address to reach the given server from.
+pub(crate) fn local_addr(
+    server: SocketAddr,
+    port: u16,
+) -> Result<SocketAddr, local_ip_address::Error> {
+    // Windows refuses to reach loopback from a LAN address.
+    let ip = if server.ip().is_loopback() {
+        server.ip()
+    } else {
+        match server {
+            SocketAddr::V4(_) => local_ip(),
+            SocketAddr::V6(_) => local_ipv6(),
+        }?
+    };
+    Ok((ip, port).into())
+}
+
+/// What the client joins the lobby of the server with.
+pub(crate) struct Join<'a> {
+    /// Countries the client claims.
+    pub(crate) players: usize,
+    /// Password the server asks for, if any.
+    pub(crate) password: Option<&'a str>,
+}
+
 pub(crate) fn run<W: Write>(
     st: &mut State<W>,
     server: SocketAddr,
     port: u16,
+    url: Option<&str>,
     protocol: curseofrust_cli_parser::Protocol,
+    trace_wire: bool,
+    join: Join<'_>,
 ) -> Result<(), DirectBoxedError> {
-    let local: SocketAddr = (
-        match server {
-            SocketAddr::V4(_) => local_ip(),
-            SocketAddr::V6(_) => local_ipv6(),
-        }?,
-        port,
-    )
-        .into();
+    let Join { players, password } = join;
+    if trace_wire {
+        let _ = TRACER.set(Tracer::new());
+    }
+    if password.is_some_and(|password| password.len() > MAX_PASSWORD) {
+        return Err(DirectBoxedError {
+            inner: st
+                .lang
+                .trf("the password is longer than {0} bytes", &[&MAX_PASSWORD])
+                .into(),
+        });
+    }
+    let local = local_addr(server, port)?;
 
+    let cli_protocol = protocol;
     let protocol = match protocol {
         curseofrust_cli_parser::Protocol::Tcp => Protocol::Tcp,
         curseofrust_cli_parser::Protocol::Udp => Protocol::Udp,
         #[cfg(feature = "ws")]
         curseofrust_cli_parser::Protocol::WebSocket => Protocol::WebSocket,
+        #[cfg(feature = "http")]
+        curseofrust_cli_parser::Protocol::Http => Protocol::Http,
         _ => {
             return Err(DirectBoxedError {
-                inner: "given protocol is not supported in this build".into(),
+                inner: st
+                    .lang
+                    .tr("given protocol is not supported in this build")
+                    .into(),
             })
         }
     };
 
-    let handle = Handle::bind(local, protocol)?;
-    let socket = UnsafeCell::new(futures_lite::future::block_on(handle.connect(server))?);
+    let handle;
+    let socket = match url {
+        // Without the `ws` feature, the protocol of URLs is not supported.
+        #[cfg(feature = "ws")]
+        Some(url) => {
+            let url: curseofrust_net_foundation::url::WsUrl = url.parse()?;
+            println!("{}", st.lang.trf("connecting to {0}", &[&url]));
+            futures_lite::future::block_on(Connection::connect_url(&url))?
+        }
+        _ => {
+            handle = Handle::bind(local, protocol)?;
+            futures_lite::future::block_on(handle.connect(server))?
+        }
+    };
+    // Enabled once the server accepts it.
+    let reliability = RefCell::<Option<Reliability>>::new(None);
+    let order = Cell::new(StateOrder::default());
+    let reassembler = RefCell::new(Reassembler::new());
+    let clock = SystemClock;
+    // Time of the last ping of the server, if it pings at all.
+    let pinged_at = Cell::new(None::<Instant>);
+    // Whether the server sends events, which then need not be told
+    // from the states. Servers that do tell about joining players.
+    let events_sent = Cell::new(false);
+    // Reason the server gave for rejecting the client, if it did.
+    let rejected = Cell::new(None::<u8>);
+    // Last state applied, to host from if the host of a relay is lost.
+    let last_state = RefCell::new(None::<Box<S2CData>>);
+    // Rules of the game the server sent, kept by the game once hosted
+    // in place of a lost host.
+    let rules = RefCell::new(None::<Rules>);
+    // First player the client controlled, claimed again from the next
+    // host once the host was lost, `0` until then.
+    let first_player = Cell::new(0u8);
+    let claim = Cell::new(0u8);
+
+    let (outgoing, outgoing_rx) = async_channel::bounded(QUEUE);
+    let (incoming_tx, incoming) = async_channel::bounded(QUEUE);
+    let client = MultiplayerClient {
+        outgoing: &outgoing,
+        reliability: &reliability,
+    };
 
     let executor = async_executor::LocalExecutor::new();
+    executor
+        .spawn(connect(socket, outgoing_rx, incoming_tx))
+        .detach();
     let mut time = 0i32;
     st.s.time = 0;
 
-    let mut s2c_buf = [0u8; S2C_SIZE];
-
     let mut init = false;
+    let lang = st.lang;
+    let tick = st.s.tick;
 
     {
         #[repr(transparent)]
@@ -172,54 +422,343 @@ pub(crate) fn run<W: Write>(
 
         futures_lite::future::block_on(executor.run(async {
             'game: loop {
-                let timer = async_io::Timer::after(crate::DURATION);
+                let timer = clock.timer(tick);
 
                 if time >= 1600 {
                     time = 0;
                 }
 
                 if time % 50 == 0 {
-                    const ALIVE_PACKET: [u8; C2S_SIZE] =
-                        [curseofrust_msg::client_msg::IS_ALIVE, 0, 0, 0];
-
-                    unsafe {
-                        executor.spawn((*socket.get()).send(&ALIVE_PACKET)).detach();
+                    const CAPABILITIES: u8 = capability::RLE
+                        | capability::PING
+                        | capability::TILE_FLAGS
+                        | capability::EVENTS
+                        | capability::MIGRATE;
+
+                    // Only datagrams get lost.
+                    let capabilities = if protocol == Protocol::Udp {
+                        CAPABILITIES | capability::RELIABLE | capability::FRAGMENT
+                    } else {
+                        CAPABILITIES
+                    };
+                    let mut packet = vec![
+                        curseofrust_msg::client_msg::IS_ALIVE,
+                        players as u8,
+                        claim.get(),
+                        capabilities,
+                    ];
+                    // Servers check the password of the packet the client joins with.
+                    if let Some(password) = password.filter(|_| !init) {
+                        packet[0] = CONNECT;
+                        packet.extend_from_slice(password.as_bytes());
                     }
-                    if !init {
-                        println!("pinging socket {} using {}", server, local)
+                    client.send_packet(packet);
+                    if !init && url.is_none() {
+                        println!(
+                            "{}",
+                            lang.trf("pinging socket {0} using {1}", &[&server, &local])
+                        )
                     }
                 }
 
                 time += 1;
 
-                let fetch_st = async {
-                    let nread = unsafe { (*socket.get()).recv(&mut s2c_buf).await? };
-                    if nread < S2C_SIZE {
-                        return Err(std::io::Error::new(
-                            std::io::ErrorKind::UnexpectedEof,
-                            format!("short read: {} < {}", nread, S2C_SIZE),
-                        )
-                        .into());
-                    }
+                if let Some(reason) = rejected.get() {
+                    let msg = match reason {
+                        reject::PASSWORD => "the server rejected the password",
+                        reject::NOT_ALLOWED => "the server does not let this address join",
+                        reject::FULL => "the relay is full",
+                        _ => "the server rejected the connection",
+                    };
+                    return Err(DirectBoxedError {
+                        inner: lang.tr(msg).into(),
+                    });
+                }
 
-                    let (&msg, data) = s2c_buf
-                        .split_first()
-                        .expect("the buffer should longer than one byte");
-                    let data: S2CData = *bytemuck::from_bytes(data);
-                    if msg == curseofrust_msg::server_msg::STATE {
-                        let mut st_guard = st.borrow_mut();
-                        let st = &mut **st_guard;
-                        curseofrust_msg::apply_s2c_msg(&mut st.s, data)?;
-                        crate::output::draw_all_grid(st)?;
-                        Ok(true)
-                    } else {
-                        Result::<bool, DirectBoxedError>::Ok(false)
+                if pinged_at
+                    .get()
+                    .is_some_and(|at| clock.elapsed(at) >= PING_INTERVAL * MAX_MISSED_PINGS)
+                {
+                    return Err(DirectBoxedError {
+                        inner: lang.tr("the server stopped responding").into(),
+                    });
+                }
+
+                if let Some(reliability) = reliability.borrow_mut().as_mut() {
+                    for packet in reliability.due() {
+                        client.send_packet(packet);
                     }
-                };
+                }
 
-                let client = MultiplayerClient {
-                    executor: &executor,
-                    socket: &socket,
+                let fetch_st = async {
+                    let frame = incoming.recv().await.map_err(|_| {
+                        std::io::Error::from(std::io::ErrorKind::ConnectionAborted)
+                    })??;
+                    trace(Flow::Received, &frame);
+                    // Events come reliably once the server accepts the layer.
+                    let frame = match reliability.borrow_mut().as_mut() {
+                        Some(reliability) if frame.first() == Some(&reliable::RELIABLE) => {
+                            match reliability.unwrap(&frame) {
+                                Received::Reliable { payload, ack } => {
+                                    client.send_packet(ack.to_vec());
+                                    match payload {
+                                        Some(payload) => payload.to_vec(),
+                                        None => return Ok(false),
+                                    }
+                                }
+                                _ => return Ok(false),
+                            }
+                        }
+                        _ => frame,
+                    };
+                    let nread = frame.len();
+                    let Some((&msg, data)) = frame.split_first() else {
+                        return Ok(false);
+                    };
+                    let data: S2CData = match msg {
+                        server_msg::STATE if nread < S2C_SIZE => {
+                            return Err(std::io::Error::new(
+                                std::io::ErrorKind::UnexpectedEof,
+                                format!("short read: {} < {}", nread, S2C_SIZE),
+                            )
+                            .into());
+                        }
+                        server_msg::STATE => *bytemuck::from_bytes(data),
+                        server_msg::STATE_RLE => match S2CData::decode_rle(data) {
+                            Some(data) => data,
+                            // Garbled on the way, so the next one may be late.
+                            None => {
+                                client.send(Pos::default(), FULL_STATE_REQUEST);
+                                return Ok(false);
+                            }
+                        },
+                        server_msg::STATE_FRAGMENT => {
+                            let Some(frame) = reassembler.borrow_mut().push(data) else {
+                                return Ok(false);
+                            };
+                            let data = match frame.split_first() {
+                                Some((&server_msg::STATE, data)) if frame.len() == S2C_SIZE => {
+                                    Some(*bytemuck::from_bytes(data))
+                                }
+                                Some((&server_msg::STATE_RLE, data)) => S2CData::decode_rle(data),
+                                _ => None,
+                            };
+                            match data {
+                                Some(data) => data,
+                                None => {
+                                    client.send(Pos::default(), FULL_STATE_REQUEST);
+                                    return Ok(false);
+                                }
+                            }
+                        }
+                        reliable::ACK => {
+                            if let Some(reliability) = reliability.borrow_mut().as_mut() {
+                                reliability.unwrap(&frame);
+                            }
+                            return Ok(false);
+                        }
+                        server_msg::RELIABLE => {
+                            reliability
+                                .borrow_mut()
+                                .get_or_insert_with(Reliability::new);
+                            return Ok(false);
+                        }
+                        server_msg::CONN_REJECTED => {
+                            rejected.set(Some(data.first().copied().unwrap_or_default()));
+                            return Ok(false);
+                        }
+                        server_msg::HOST_LOST => {
+                            // The next host pings once it resumes the game.
+                            pinged_at.set(None);
+                            order.set(StateOrder::default());
+                            claim.set(first_player.get());
+                            if let Some(ref data) = *last_state.borrow() {
+                                if crate::host::server_exe().is_some() {
+                                    client.send_packet(migration::candidate_frame(data).to_vec());
+                                }
+                            }
+                            let mut st_guard = st.borrow_mut();
+                            let st = &mut **st_guard;
+                            let notice = st.lang.tr("the host was lost, electing a new one");
+                            st.log.push(notice.to_owned());
+                            crate::output::draw_notice(st, notice)?;
+                            return Ok(false);
+                        }
+                        server_msg::RULES => {
+                            let text = std::str::from_utf8(data).ok();
+                            if let Some(decoded) = text.and_then(|text| Rules::decode(text).ok()) {
+                                *rules.borrow_mut() = Some(decoded);
+                            }
+                            return Ok(false);
+                        }
+                        server_msg::ELECTED => {
+                            if let (Some(ref state), [clients, ..]) = (&*last_state.borrow(), data)
+                            {
+                                let mut st_guard = st.borrow_mut();
+                                let st = &mut **st_guard;
+                                let notice = match crate::host::resume(
+                                    state,
+                                    server,
+                                    *clients,
+                                    cli_protocol,
+                                    tick,
+                                    rules.borrow().as_ref(),
+                                ) {
+                                    Ok(()) => st
+                                        .lang
+                                        .tr("hosting the game in place of the lost host")
+                                        .to_owned(),
+                                    Err(e) => st.lang.trf("failed to host the game: {0}", &[&e]),
+                                };
+                                st.log.push(notice.clone());
+                                crate::output::draw_notice(st, &notice)?;
+                            }
+                            return Ok(false);
+                        }
+                        server_msg::PING => {
+                            pinged_at.set(Some(clock.now()));
+                            client.send_packet(vec![PONG, 0, 0, 0]);
+                            return Ok(false);
+                        }
+                        server_msg::EVENT => {
+                            events_sent.set(true);
+                            if let Ok(event) = Event::decode(data) {
+                                let mut st_guard = st.borrow_mut();
+                                show_event(&mut st_guard, event)?;
+                            }
+                            return Ok(false);
+                        }
+                        server_msg::AFK => {
+                            if let [player, idle, ..] = *data {
+                                let mut st_guard = st.borrow_mut();
+                                let st = &mut **st_guard;
+                                let name = narrate::player_name(st, Player(player as u32));
+                                let notice = if idle != 0 {
+                                    st.lang.trf("{0} went idle, the AI took over", &[&name])
+                                } else {
+                                    st.lang.trf("{0} came back", &[&name])
+                                };
+                                crate::output::draw_notice(st, &notice)?;
+                            }
+                            return Ok(false);
+                        }
+                        server_msg::SURRENDER => {
+                            if let [player, ..] = *data {
+                                let mut st_guard = st.borrow_mut();
+                                let st = &mut **st_guard;
+                                let name = narrate::player_name(st, Player(player as u32));
+                                let notice = st.lang.trf("{0} surrendered", &[&name]);
+                                crate::output::draw_notice(st, &notice)?;
+                            }
+                            return Ok(false);
+                        }
+                        server_msg::ELIMINATED => {
+                            if let [player, ..] = *data {
+                                let mut st_guard = st.borrow_mut();
+                                let st = &mut **st_guard;
+                                if let Ok(c) = st.s.country_mut(Player(player as u32)) {
+                                    c.eliminated = true;
+                                }
+                                let name = narrate::player_name(st, Player(player as u32));
+                                let notice = st.lang.trf("eliminated: {0}", &[&name]);
+                                crate::output::draw_notice(st, &notice)?;
+                            }
+                            return Ok(false);
+                        }
+                        server_msg::PLUNDER => {
+                            if let [x, y, from, to, g0, g1, g2, g3, ..] = *data {
+                                let mut st_guard = st.borrow_mut();
+                                let st = &mut **st_guard;
+                                let event = GameEvent::CityPlundered {
+                                    pos: Pos(x as i32, y as i32),
+                                    from: Player(from as u32),
+                                    to: Player(to as u32),
+                                    gold: u32::from_be_bytes([g0, g1, g2, g3]) as u64,
+                                };
+                                crate::log::log_events(st, &[event]);
+                            }
+                            return Ok(false);
+                        }
+                        server_msg::GAME_OVER => {
+                            if let [winner, ..] = *data {
+                                let mut st_guard = st.borrow_mut();
+                                let st = &mut **st_guard;
+                                st.time_left = Some(Duration::ZERO);
+                                st.winner = Some(Player(winner as u32));
+                                let name = narrate::player_name(st, Player(winner as u32));
+                                let notice = st.lang.trf("{0} won", &[&name]);
+                                crate::output::draw_all_grid(st)?;
+                                crate::output::draw_notice(st, &notice)?;
+                            }
+                            return Ok(false);
+                        }
+                        server_msg::END_TURN => {
+                            if let [ended, players, ..] = *data {
+                                let mut st_guard = st.borrow_mut();
+                                let st = &mut **st_guard;
+                                let notice = st
+                                    .lang
+                                    .trf("{0}/{1} players ended the turn", &[&ended, &players]);
+                                crate::output::draw_notice(st, &notice)?;
+                            }
+                            return Ok(false);
+                        }
+                        server_msg::VOTE_RESTART => {
+                            if let [votes, voters, ..] = *data {
+                                let mut st_guard = st.borrow_mut();
+                                let st = &mut **st_guard;
+                                let notice = st
+                                    .lang
+                                    .trf("{0}/{1} players voted to restart", &[&votes, &voters]);
+                                crate::output::draw_notice(st, &notice)?;
+                            }
+                            return Ok(false);
+                        }
+                        _ => return Result::<bool, DirectBoxedError>::Ok(false),
+                    };
+                    let mut st_guard = st.borrow_mut();
+                    let st = &mut **st_guard;
+                    let mut state_order = order.get();
+                    if !state_order.accept(&st.s, &data) {
+                        return Ok(false);
+                    }
+                    order.set(state_order);
+                    let prev = st.s.snapshot();
+                    let prev_time = st.s.time;
+                    curseofrust_msg::apply_s2c_msg(&mut st.s, data)?;
+                    if first_player.get() == 0 {
+                        first_player.set(data.player);
+                    }
+                    **last_state
+                        .borrow_mut()
+                        .get_or_insert_with(|| Box::new(data)) = data;
+                    // Whether a multiple of 10 was passed.
+                    if st.s.time / 10 != prev_time / 10 {
+                        crate::mark_timeline(&mut st.s);
+                    }
+                    if data.seq() != 0 {
+                        let mut ack = [STATE_ACK; C2S_SIZE];
+                        ack[1..]
+                            .copy_from_slice(bytemuck::bytes_of(&C2SData::state_ack(data.seq())));
+                        client.send_packet(ack.to_vec());
+                    }
+                    st.time_left = data.time_left();
+                    let mut events = prev.events(&st.s.snapshot());
+                    // Events sent at best effort may be lost.
+                    if events_sent.get()
+                        && (protocol != Protocol::Udp || reliability.borrow().is_some())
+                    {
+                        events.retain(|event| {
+                            !matches!(
+                                event,
+                                GameEvent::CityCaptured { .. } | GameEvent::PlayerEliminated(_)
+                            )
+                        });
+                    }
+                    crate::log::log_events(st, &events);
+                    crate::alert::check(st, &events)?;
+                    crate::output::draw_all_grid(st)?;
+                    Ok(true)
                 };
 
                 let recv_input = async {
@@ -248,21 +787,18 @@ pub(crate) fn run<W: Write>(
                         Ok(true) => {
                             let mut st = st.borrow_mut();
                             init = true;
-                            crossterm::terminal::enable_raw_mode()?;
-                            execute!(st.out, terminal::EnterAlternateScreen)?;
-                            if matches!(st.control, ControlMode::Termux | ControlMode::Hybrid) {
-                                execute!(st.out, crossterm::event::EnableMouseCapture)?;
-                            }
-                            execute!(
-                                st.out,
-                                terminal::Clear(terminal::ClearType::All),
-                                cursor::Hide
+                            crate::term::enter(
+                                !st.narrate,
+                                matches!(st.control, ControlMode::Termux | ControlMode::Hybrid),
                             )?;
+                            if !st.narrate {
+                                execute!(st.out, terminal::Clear(terminal::ClearType::All))?;
+                            }
                         }
                         Ok(_) => {}
 
                         Err(e) => {
-                            eprintln!("error fetching state: {}", e.inner);
+                            eprintln!("{}", lang.trf("error fetching state: {0}", &[&e.inner]));
                         }
                     }
 
@@ -277,3 +813,66 @@ pub(crate) fn run<W: Write>(
 
     Ok(())
 }
+
+/// Logs the event and tells about it.
+fn show_event<W: Write>(st: &mut State<W>, event: Event) -> Result<(), std::io::Error> {
+    let game_event = match event {
+        Event::CityCaptured { pos, from, to } => {
+            let Some(&Tile::Habitable { land, .. }) = st.s.grid.tile(pos) else {
+                return Ok(());
+            };
+            GameEvent::CityCaptured {
+                pos,
+                land,
+                from,
+                to,
+            }
+        }
+        // Told by `ELIMINATED` as well.
+        Event::Eliminated(player) => GameEvent::PlayerEliminated(player),
+        Event::Joined(player) => {
+            let notice = st
+                .lang
+                .trf("{0} joined", &[&narrate::player_name(st, player)]);
+            st.log.push(notice.clone());
+            return crate::output::draw_notice(st, &notice);
+        }
+        Event::Left(player) => {
+            let notice = st.lang.trf(
+                "{0} left, the AI took over",
+                &[&narrate::player_name(st, player)],
+            );
+            st.log.push(notice.clone());
+            return crate::output::draw_notice(st, &notice);
+        }
+        Event::Speed(speed) => {
+            st.s.speed = speed;
+            st.countdown = None;
+            let notice = if speed == Speed::Pause {
+                st.lang.tr("game paused")
+            } else {
+                st.lang.tr("game resumed")
+            };
+            st.log.push(notice.to_owned());
+            crate::output::draw_all_grid(st)?;
+            return crate::output::draw_notice(st, notice);
+        }
+        Event::Countdown(secs) => {
+            st.countdown = Some(secs);
+            let notice = st.lang.trf("resuming in {0}", &[&secs]);
+            crate::output::draw_all_grid(st)?;
+            return crate::output::draw_notice(st, &notice);
+        }
+        Event::Chat { from, text } => {
+            let notice = if from.is_neutral() {
+                st.lang.trf("server: {0}", &[&text])
+            } else {
+                format!("{}: {}", narrate::player_name(st, from), text)
+            };
+            st.log.push(notice.clone());
+            return crate::output::draw_notice(st, &notice);
+        }
+    };
+    crate::log::log_events(st, &[game_event]);
+    crate::alert::check(st, &[game_event])
+}
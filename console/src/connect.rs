@@ -0,0 +1,100 @@
+//! Asking how to join a server, for what is not given on the
+//! command line.
+
+use std::{
+    fmt::Display,
+    io::{self, BufRead as _},
+};
+
+use curseofrust_cli_parser::{parse_addr, Lang, MultiplayerOpts, Profile, Protocol};
+
+/// Asks for the address and port of the server, the protocol and the
+/// name of the player, with the options given as defaults, and writes
+/// the server into the options. The name is saved to the profile.
+///
+/// Returns the protocol, or `None` if the input ended instead.
+pub(crate) fn prompt(
+    m_opt: &mut MultiplayerOpts,
+    protocol: Protocol,
+    lang: Lang,
+) -> io::Result<Option<Protocol>> {
+    let MultiplayerOpts::Client { server, port } = *m_opt else {
+        return Ok(Some(protocol));
+    };
+    let mut out = io::stdout().lock();
+
+    // The port may be given with the address, as to `-C`.
+    let Some(mut server) = ask(&mut out, lang, "server address", server, |s| {
+        let (mut addr, port_given) = parse_addr(s).ok()?;
+        if !port_given {
+            addr.set_port(server.port());
+        }
+        Some(addr)
+    })?
+    else {
+        return Ok(None);
+    };
+    let Some(server_port) = ask(&mut out, lang, "server port", server.port(), |s| {
+        s.parse::<u16>().ok()
+    })?
+    else {
+        return Ok(None);
+    };
+    server.set_port(server_port);
+    let Some(name) = ask(
+        &mut out,
+        lang,
+        "protocol (tcp, udp, ws, http)",
+        protocol.name(),
+        |s| s.parse::<Protocol>().ok().map(Protocol::name),
+    )?
+    else {
+        return Ok(None);
+    };
+    let mut profile = Profile::load();
+    let Some(player) = ask(&mut out, lang, "your name", profile.name.clone(), |s| {
+        Some(s.to_owned())
+    })?
+    else {
+        return Ok(None);
+    };
+
+    *m_opt = MultiplayerOpts::Client { server, port };
+    if player != profile.name {
+        profile.name = player;
+        // A read-only config directory should not fail the game.
+        let _ = profile.save();
+    }
+    Ok(Some(
+        name.parse().expect("the name of a protocol should parse"),
+    ))
+}
+
+/// Asks for a value until it parses, keeping the default if the
+/// answer is empty.
+///
+/// Returns `None` if the input ended.
+fn ask<T: Display>(
+    out: &mut impl io::Write,
+    lang: Lang,
+    label: &'static str,
+    default: T,
+    parse: impl Fn(&str) -> Option<T>,
+) -> io::Result<Option<T>> {
+    let mut line = String::new();
+    loop {
+        write!(out, "{} [{}]: ", lang.tr(label), default)?;
+        out.flush()?;
+        line.clear();
+        if io::stdin().lock().read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        match line.trim() {
+            "" => return Ok(Some(default)),
+            answer => match parse(answer) {
+                Some(value) => return Ok(Some(value)),
+                None => writeln!(out, "{}", lang.trf("invalid value: {0}", &[&answer]))?,
+            },
+        }
+    }
+}
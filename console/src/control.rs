@@ -1,4 +1,7 @@
-use std::ops::{ControlFlow, DerefMut};
+use std::{
+    ops::{ControlFlow, DerefMut},
+    time::{Duration, Instant},
+};
 
 use crossterm::{
     event::{
@@ -10,7 +13,11 @@ use curseofrust::{grid::Tile, Pos};
 use curseofrust_cli_parser::ControlMode;
 use futures_lite::StreamExt as _;
 
-use crate::{output, DirectBoxedError, State};
+use crate::{
+    braille, narrate,
+    output::{self, SoftButton},
+    DirectBoxedError, State,
+};
 
 pub(crate) trait Client {
     type Error: std::error::Error + Send + Sync + 'static;
@@ -18,14 +25,62 @@ pub(crate) trait Client {
     fn quit<W>(&mut self, st: &mut State<W>) -> Result<(), Self::Error>;
 
     fn toggle_flag<W>(&mut self, st: &mut State<W>, pos: Pos) -> Result<(), Self::Error>;
+    /// Stacks the given number of flags on the tile, up to the
+    /// maximum, without ever removing them.
+    fn raise_flag<W>(&mut self, st: &mut State<W>, pos: Pos, stacks: u8)
+        -> Result<(), Self::Error>;
     fn rm_all_flag<W>(&mut self, st: &mut State<W>) -> Result<(), Self::Error>;
     fn rm_half_flag<W>(&mut self, st: &mut State<W>) -> Result<(), Self::Error>;
+    fn flag_cities<W>(&mut self, st: &mut State<W>) -> Result<(), Self::Error>;
+    fn flag_frontline<W>(&mut self, st: &mut State<W>) -> Result<(), Self::Error>;
 
     fn build<W>(&mut self, st: &mut State<W>, pos: Pos) -> Result<(), Self::Error>;
+    fn build_road<W>(&mut self, st: &mut State<W>, pos: Pos) -> Result<(), Self::Error>;
+    /// Queues an upgrade of the tile, or unqueues it if queued.
+    fn toggle_queued_build<W>(&mut self, st: &mut State<W>, pos: Pos) -> Result<(), Self::Error>;
+    fn set_rally<W>(
+        &mut self,
+        st: &mut State<W>,
+        city: Pos,
+        target: Option<Pos>,
+    ) -> Result<(), Self::Error>;
 
     fn faster<W>(&mut self, st: &mut State<W>) -> Result<(), Self::Error>;
     fn slower<W>(&mut self, st: &mut State<W>) -> Result<(), Self::Error>;
     fn toggle_pause<W>(&mut self, st: &mut State<W>) -> Result<(), Self::Error>;
+
+    fn surrender<W>(&mut self, st: &mut State<W>) -> Result<(), Self::Error>;
+    fn vote_restart<W>(&mut self, st: &mut State<W>) -> Result<(), Self::Error>;
+    fn end_turn<W>(&mut self, st: &mut State<W>) -> Result<(), Self::Error>;
+    /// Switches to the next country the player controls, if
+    /// several.
+    fn switch_player<W>(&mut self, st: &mut State<W>) -> Result<(), Self::Error>;
+    /// Brings the game state up to date after the client may have
+    /// missed some, such as after being suspended.
+    fn resync<W>(&mut self, st: &mut State<W>) -> Result<(), Self::Error>;
+}
+
+/// Longest interval between two presses of Space on a tile for the
+/// second one to stack another flag rather than toggle them.
+const DOUBLE_TAP: Duration = Duration::from_millis(300);
+
+/// A swipe in progress on a touchscreen.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Swipe {
+    /// Column the swipe has scrolled the grid to.
+    anchor: u16,
+    scrolled: bool,
+}
+
+/// Gets the tile drawn at the given terminal position, in the view
+/// shown.
+fn tile_at<W>(st: &State<W>, column: u16, row: u16) -> Option<Pos> {
+    if st.braille {
+        braille::rev_pos(st, column, row)
+    } else {
+        let rows = output::row_height(st);
+        output::rev_pos(column, row, rows, &st.ui, &st.s.grid)
+    }
 }
 
 pub(crate) async fn accept<W, S>(
@@ -64,10 +119,40 @@ where
             };
         }
         match (event, st.control) {
+            // Raw mode swallows the interrupt, and Windows users expect it to quit.
+            (
+                crossterm::event::Event::Key(KeyEvent {
+                    code: KeyCode::Char('c'),
+                    modifiers: KeyModifiers::CONTROL,
+                    kind: KeyEventKind::Press,
+                    state: _,
+                }),
+                _,
+            ) => {
+                pc!(client.quit(st))?;
+                return Ok(ControlFlow::Break(()));
+            }
+            #[cfg(unix)]
+            (
+                crossterm::event::Event::Key(KeyEvent {
+                    code: KeyCode::Char('z'),
+                    modifiers: KeyModifiers::CONTROL,
+                    kind: KeyEventKind::Press,
+                    state: _,
+                }),
+                _,
+            ) => {
+                crate::term::suspend()?;
+                pc!(client.resync(st))?;
+                if !st.narrate {
+                    queue!(st.out, terminal::Clear(terminal::ClearType::All))?;
+                    output::draw_all_grid(st)?;
+                }
+            }
             (
                 crossterm::event::Event::Key(KeyEvent {
                     code,
-                    modifiers: _,
+                    modifiers,
                     kind: KeyEventKind::Press | KeyEventKind::Repeat,
                     state: _,
                 }),
@@ -75,6 +160,32 @@ where
             ) => {
                 let cursor = st.ui.cursor;
                 let cursor_x_shift = if st.ui.cursor.1 % 2 == 0 { 0 } else { 1 };
+                // Windows consoles report upper case letters while Caps Lock is on.
+                let code = match code {
+                    KeyCode::Char(c) => KeyCode::Char(c.to_ascii_lowercase()),
+                    code => code,
+                };
+                if st.log.is_open() {
+                    match code {
+                        KeyCode::Up | KeyCode::Char('k') => st.log.scroll(1),
+                        KeyCode::Down | KeyCode::Char('j') => st.log.scroll(-1),
+                        KeyCode::Esc | KeyCode::Char('e') => {
+                            st.log.toggle();
+                            queue!(st.out, terminal::Clear(terminal::ClearType::All))?;
+                        }
+                        _ => {}
+                    }
+                    output::draw_all_grid(st)?;
+                    return Ok(ControlFlow::Continue(()));
+                }
+                if st.s.show_timeline && !st.narrate {
+                    if let KeyCode::Esc | KeyCode::Char('t') = code {
+                        st.s.show_timeline = false;
+                        queue!(st.out, terminal::Clear(terminal::ClearType::All))?;
+                        output::draw_all_grid(st)?;
+                    }
+                    return Ok(ControlFlow::Continue(()));
+                }
                 match code {
                     KeyCode::Up | KeyCode::Char('k') => {
                         st.ui.cursor.1 -= 1;
@@ -96,7 +207,39 @@ where
                         return Ok(ControlFlow::Break(()));
                     }
 
-                    KeyCode::Char(' ') => pc!(client.toggle_flag(st, cursor))?,
+                    // Flags the frontline shown on the screen.
+                    KeyCode::Char(' ') if modifiers.contains(KeyModifiers::CONTROL) => {
+                        let size = terminal::size().ok().filter(|_| !st.narrate);
+                        let fg = st.s.flag_grid(st.s.controlled).ok();
+                        let frontline: Vec<Pos> = st
+                            .s
+                            .grid
+                            .frontline(st.s.controlled)
+                            .filter(|&pos| fg.is_some_and(|fg| !fg.is_flagged(pos)))
+                            .filter(|&pos| size.is_none_or(|size| output::on_screen(st, pos, size)))
+                            .collect();
+                        for pos in frontline {
+                            pc!(client.raise_flag(st, pos, 1))?;
+                        }
+                        output::draw_all_grid(st)?;
+                    }
+                    KeyCode::Char(' ') if modifiers.contains(KeyModifiers::SHIFT) => {
+                        pc!(client.raise_flag(st, cursor, 2))?
+                    }
+                    // Terminals that do not report Shift with Space get
+                    // a double press instead.
+                    KeyCode::Char(' ') => {
+                        if st
+                            .last_flag
+                            .take()
+                            .is_some_and(|(pos, at)| pos == cursor && at.elapsed() < DOUBLE_TAP)
+                        {
+                            pc!(client.raise_flag(st, cursor, 1))?;
+                        } else {
+                            pc!(client.toggle_flag(st, cursor))?;
+                            st.last_flag = Some((cursor, Instant::now()));
+                        }
+                    }
                     KeyCode::Char('x') => {
                         pc!(client.rm_all_flag(st))?;
                         output::draw_all_grid(st)?;
@@ -105,19 +248,127 @@ where
                         pc!(client.rm_half_flag(st))?;
                         output::draw_all_grid(st)?;
                     }
+                    KeyCode::Char('o') => {
+                        pc!(client.flag_cities(st))?;
+                        output::draw_all_grid(st)?;
+                    }
+                    KeyCode::Char('w') => {
+                        pc!(client.flag_frontline(st))?;
+                        output::draw_all_grid(st)?;
+                    }
                     KeyCode::Char('r') | KeyCode::Char('v') => {
                         pc!(client.build(st, cursor))?;
                     }
+                    KeyCode::Char('d') => {
+                        pc!(client.build_road(st, cursor))?;
+                    }
+                    KeyCode::Char('u') => {
+                        pc!(client.toggle_queued_build(st, cursor))?;
+                    }
+                    // Move to the suggested tile to build on.
+                    KeyCode::Char('b') => {
+                        let suggestion =
+                            st.s.suggest_build(st.s.controlled).and_then(|pos| {
+                                match st.s.grid.tile(pos) {
+                                    Some(&Tile::Habitable { mut land, .. }) => {
+                                        land.upgrade().map(|_| {
+                                            let price = st.s.grid.price(
+                                                st.s.controlled,
+                                                land,
+                                                &st.s.balance,
+                                            );
+                                            (pos, land, price)
+                                        })
+                                    }
+                                    _ => None,
+                                }
+                            });
+                        let notice = match suggestion {
+                            Some((pos, land, price)) => {
+                                st.ui.cursor = pos;
+                                st.lang.trf(
+                                    "build {0} at {1} for {2} gold",
+                                    &[
+                                        &narrate::land_name(st, land),
+                                        &format!("{},{}", pos.0, pos.1),
+                                        &price,
+                                    ],
+                                )
+                            }
+                            None => st.lang.tr("nothing to build").to_owned(),
+                        };
+                        output::draw_notice(st, &notice)?;
+                    }
+                    // Select a city, then its rally point, or the city again to clear it.
+                    KeyCode::Char('m') => match st.ui.rally_from.take() {
+                        Some(city) => {
+                            pc!(client.set_rally(st, city, Some(cursor).filter(|c| *c != city)))?;
+                            output::draw_all_grid(st)?;
+                        }
+                        None => {
+                            if st
+                                .s
+                                .grid
+                                .tile(cursor)
+                                .is_some_and(|t| t.is_city() && t.owner() == st.s.controlled)
+                            {
+                                st.ui.rally_from = Some(cursor);
+                            }
+                        }
+                    },
 
                     KeyCode::Char('f') => pc!(client.faster(st))?,
                     KeyCode::Char('s') => pc!(client.slower(st))?,
                     KeyCode::Char('p') => pc!(client.toggle_pause(st))?,
+                    KeyCode::Char('g') => pc!(client.surrender(st))?,
+                    KeyCode::Char('n') => pc!(client.vote_restart(st))?,
+                    KeyCode::Enter => pc!(client.end_turn(st))?,
+                    KeyCode::Tab => {
+                        pc!(client.switch_player(st))?;
+                        output::draw_all_grid(st)?;
+                    }
+                    KeyCode::F(12) => {
+                        let fingerprint = st.s.fingerprint();
+                        let notice = st.lang.trf("fingerprint: {0}", &[&fingerprint]);
+                        output::draw_notice(st, &notice)?;
+                    }
+                    KeyCode::Char('e') if !st.narrate => {
+                        st.log.toggle();
+                        output::draw_all_grid(st)?;
+                    }
+                    KeyCode::Char('a') => {
+                        st.call_overlay = !st.call_overlay;
+                        output::draw_all_grid(st)?;
+                    }
+                    KeyCode::Char('i') if !st.narrate => {
+                        st.stats = !st.stats;
+                        if !st.stats {
+                            queue!(st.out, terminal::Clear(terminal::ClearType::All))?;
+                        }
+                        output::draw_all_grid(st)?;
+                    }
+
+                    KeyCode::Char('y') if !st.narrate => {
+                        st.braille = !st.braille;
+                        queue!(st.out, terminal::Clear(terminal::ClearType::All))?;
+                        output::draw_all_grid(st)?;
+                    }
+
+                    KeyCode::Char('t') if st.narrate => narrate::describe_cursor(st)?,
+                    KeyCode::Char('t') => {
+                        st.s.show_timeline = true;
+                        queue!(st.out, terminal::Clear(terminal::ClearType::All))?;
+                        output::draw_all_grid(st)?;
+                    }
 
                     _ => {}
                 }
                 if !st.s.grid.tile(st.ui.cursor).is_some_and(Tile::is_visible) {
                     st.ui.cursor = cursor;
                 }
+                if st.narrate && st.ui.cursor != cursor {
+                    narrate::describe_cursor(st)?;
+                }
 
                 cupd!()
             }
@@ -138,6 +389,7 @@ where
                 (KeyCode::PageUp, _) => pc!(client.faster(st))?,
                 (KeyCode::PageDown, _) => pc!(client.slower(st))?,
                 (KeyCode::End, _) => pc!(client.toggle_pause(st))?,
+                (KeyCode::Enter, _) => pc!(client.end_turn(st))?,
 
                 (KeyCode::Home | KeyCode::Up, _) => pc!(client.build(st, cursor))?,
 
@@ -146,6 +398,53 @@ where
 
                 _ => {}
             },
+            (
+                crossterm::event::Event::Mouse(MouseEvent {
+                    kind, column, row, ..
+                }),
+                ControlMode::Termux,
+            ) => match kind {
+                MouseEventKind::Down(MouseButton::Left) => {
+                    st.swipe = Some(Swipe {
+                        anchor: column,
+                        scrolled: false,
+                    })
+                }
+                MouseEventKind::Drag(MouseButton::Left) => {
+                    let Some(swipe) = st.swipe else {
+                        return Ok(ControlFlow::Continue(()));
+                    };
+                    // A tile is four columns wide.
+                    let tiles = (column as i32 - swipe.anchor as i32) / 4;
+                    if tiles != 0 {
+                        let max = st.s.grid.width() + st.s.grid.height() / 2;
+                        st.ui.xskip = (st.ui.xskip as i32 - tiles).clamp(0, max as i32) as u16;
+                        st.swipe = Some(Swipe {
+                            anchor: (swipe.anchor as i32 + tiles * 4) as u16,
+                            scrolled: true,
+                        });
+                        queue!(st.out, terminal::Clear(terminal::ClearType::All))?;
+                        output::draw_all_grid(st)?;
+                    }
+                }
+                // Taps act on release, so that swipes don't.
+                MouseEventKind::Up(MouseButton::Left)
+                    if st.swipe.take().is_some_and(|swipe| !swipe.scrolled) =>
+                {
+                    match output::soft_button_at(st, column, row) {
+                        Some(SoftButton::Build) => pc!(client.build(st, cursor))?,
+                        Some(SoftButton::Flag) => pc!(client.toggle_flag(st, cursor))?,
+                        Some(SoftButton::Pause) => pc!(client.toggle_pause(st))?,
+                        None => match tile_at(st, column, row) {
+                            Some(pos) if pos == cursor => pc!(client.toggle_flag(st, cursor))?,
+                            Some(pos) => st.ui.adjust_cursor(&st.s, pos),
+                            None => {}
+                        },
+                    }
+                    cupd!()
+                }
+                _ => {}
+            },
             (
                 crossterm::event::Event::Mouse(MouseEvent {
                     kind,
@@ -153,9 +452,9 @@ where
                     row,
                     modifiers,
                 }),
-                ControlMode::Termux | ControlMode::Hybrid,
+                ControlMode::Hybrid,
             ) => {
-                let pos = output::rev_pos(column, row, &st.ui, &st.s.grid);
+                let pos = tile_at(st, column, row);
                 if let (MouseEventKind::Down(MouseButton::Left), Some(pos), _, _) =
                     (kind, pos, st.control, modifiers)
                 {
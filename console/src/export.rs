@@ -0,0 +1,36 @@
+//! Mirror of the map in a memory-mapped file, for external visualizers.
+//!
+//! See [`Snapshot::pack_into`] for the layout, and the `export_reader`
+//! example for a reader.
+
+use std::{fs::OpenOptions, io, path::Path};
+
+use curseofrust::snapshot::Snapshot;
+use memmap2::MmapMut;
+
+pub(crate) struct Export {
+    map: MmapMut,
+}
+
+impl Export {
+    /// Creates or truncates the file, sized for the given snapshot.
+    pub(crate) fn create(path: &Path, snapshot: &Snapshot) -> io::Result<Self> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)?;
+        file.set_len(snapshot.packed_len() as u64)?;
+        // SAFETY: readers only read the file, and its size is fixed
+        // for the whole game.
+        let map = unsafe { MmapMut::map_mut(&file)? };
+        let mut export = Self { map };
+        export.update(snapshot);
+        Ok(export)
+    }
+
+    pub(crate) fn update(&mut self, snapshot: &Snapshot) {
+        snapshot.pack_into(&mut self.map);
+    }
+}
@@ -0,0 +1,184 @@
+//! Full screen chart of the population of each player over time,
+//! drawn from the [`Timeline`](curseofrust::state::Timeline).
+
+use std::io::Write;
+
+use crossterm::{
+    cursor, queue,
+    style::{self, Attribute, Color, ContentStyle, StyledContent},
+    terminal,
+};
+use curseofrust::{Player, MAX_PLAYERS};
+
+use crate::{narrate::player_name, output, State};
+
+/// Width of the labels of the vertical axis.
+const LABEL_WIDTH: usize = 7;
+
+/// Braille dots of a cell, by column and row of the dot.
+const DOTS: [[u8; 4]; 2] = [[0x01, 0x02, 0x04, 0x40], [0x08, 0x10, 0x20, 0x80]];
+
+/// Cells of a chart, as braille dots and the color of the
+/// player drawn last on them.
+pub(crate) struct Canvas {
+    cells: Vec<(u8, Color)>,
+    width: usize,
+    height: usize,
+}
+
+impl Canvas {
+    pub(crate) fn new(width: usize, height: usize) -> Self {
+        Self {
+            cells: vec![(0, Color::Reset); width * height],
+            width,
+            height,
+        }
+    }
+
+    /// Sets the dot at the given position, counted from the top left.
+    pub(crate) fn set(&mut self, x: usize, y: usize, color: Color) {
+        if x < self.width * 2 && y < self.height * 4 {
+            let cell = &mut self.cells[y / 4 * self.width + x / 2];
+            cell.0 |= DOTS[x % 2][y % 4];
+            cell.1 = color;
+        }
+    }
+
+    /// Draws a vertical line of dots, to join consecutive points.
+    fn line(&mut self, x: usize, y0: usize, y1: usize, color: Color) {
+        for y in y0.min(y1)..=y0.max(y1) {
+            self.set(x, y, color);
+        }
+    }
+
+    /// Characters of the given row of cells, with their colors.
+    pub(crate) fn row(&self, row: usize) -> impl Iterator<Item = (char, Color)> + '_ {
+        self.cells[row * self.width..(row + 1) * self.width]
+            .iter()
+            .map(|&(dots, color)| (char::from_u32(0x2800 + dots as u32).unwrap_or(' '), color))
+    }
+}
+
+/// Draws the chart over the whole screen.
+pub(crate) fn draw_view<W: Write>(st: &mut State<W>) -> Result<(), std::io::Error> {
+    let (cols, rows) = terminal::size()?;
+    // The title, the horizontal axis, its times and the legend.
+    let height = (rows as usize).saturating_sub(4).max(1);
+    let width = (cols as usize).saturating_sub(LABEL_WIDTH + 1).max(1);
+    let lang = st.lang;
+
+    queue!(
+        st.out,
+        cursor::MoveTo(0, 0),
+        style::PrintStyledContent(StyledContent::new(
+            ContentStyle {
+                attributes: Attribute::Reverse.into(),
+                ..Default::default()
+            },
+            lang.tr("Population: [T] close"),
+        )),
+        terminal::Clear(terminal::ClearType::UntilNewLine),
+    )?;
+
+    let timeline = &st.s.timeline;
+    let times = timeline.times();
+    let players: Vec<Player> = (1..MAX_PLAYERS)
+        .map(|p| Player(p as u32))
+        .filter(|&p| timeline.population(p).iter().any(|&pop| pop > 0.0))
+        .collect();
+    let max = players
+        .iter()
+        .flat_map(|&p| timeline.population(p))
+        .fold(1.0f32, |a, &b| a.max(b));
+
+    let mut canvas = Canvas::new(width, height);
+    let dots_x = width * 2 - 1;
+    let dots_y = height * 4 - 1;
+    let last = times.len().saturating_sub(1).max(1);
+    // Players of lower population last are drawn first, so that
+    // the leaders stay on top.
+    let mut order = players.clone();
+    order.sort_by(|&a, &b| {
+        let last = |p| timeline.population(p).last().copied().unwrap_or_default();
+        last(a).total_cmp(&last(b))
+    });
+    for &player in &order {
+        let color = st.theme.color(player);
+        let mut prev = None;
+        for (i, &pop) in timeline.population(player).iter().enumerate() {
+            let x = i * dots_x / last;
+            let y = dots_y - (pop / max * dots_y as f32).round() as usize;
+            match prev {
+                Some(prev_y) => canvas.line(x, prev_y, y, color),
+                None => canvas.set(x, y, color),
+            }
+            prev = Some(y);
+        }
+    }
+
+    for row in 0..height {
+        // Labels of the top, middle and bottom rows.
+        let label = if row == 0 {
+            format!("{:.0}", max)
+        } else if row == height / 2 {
+            format!("{:.0}", max / 2.0)
+        } else if row == height - 1 {
+            "0".to_owned()
+        } else {
+            String::new()
+        };
+        queue!(
+            st.out,
+            cursor::MoveTo(0, row as u16 + 1),
+            style::Print(format!("{:>LABEL_WIDTH$}│", label)),
+        )?;
+        for (c, color) in canvas.row(row) {
+            output::print_styled(
+                &mut st.out,
+                ContentStyle {
+                    foreground_color: Some(color),
+                    ..Default::default()
+                },
+                c,
+            )?;
+        }
+    }
+
+    // Times of the first and last marks under the axis.
+    let start = times.first().copied().unwrap_or_default().to_string();
+    let end = times.last().copied().unwrap_or_default().to_string();
+    queue!(
+        st.out,
+        cursor::MoveTo(0, height as u16 + 1),
+        style::Print(format!("{:>LABEL_WIDTH$}└{}", "", "─".repeat(width))),
+        cursor::MoveTo(LABEL_WIDTH as u16 + 1, height as u16 + 2),
+        terminal::Clear(terminal::ClearType::CurrentLine),
+        style::Print(&start),
+        cursor::MoveTo(
+            (LABEL_WIDTH + 1 + width.saturating_sub(end.len())) as u16,
+            height as u16 + 2
+        ),
+        style::Print(&end),
+    )?;
+
+    if players.is_empty() {
+        return queue!(
+            st.out,
+            cursor::MoveTo(LABEL_WIDTH as u16 + 2, height as u16 / 2 + 1),
+            style::Print(lang.tr("no population recorded yet")),
+        );
+    }
+    queue!(
+        st.out,
+        cursor::MoveTo(0, height as u16 + 3),
+        terminal::Clear(terminal::ClearType::CurrentLine),
+        style::Print(format!("{:>LABEL_WIDTH$} ", "")),
+    )?;
+    for player in players {
+        let name = player_name(st, player);
+        let mark = st.theme.mark(player).unwrap_or("■");
+        output::print_styled(&mut st.out, st.theme.style(player), format!("{mark} "))?;
+        queue!(st.out, style::Print(name), style::Print("  "))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,74 @@
+//! Taking over a relayed game whose host was lost.
+//!
+//! The elected client saves the last state it applied, and starts
+//! a server next to its own executable resuming that state through
+//! the same relay, by the rules the lost host sent. The client keeps
+//! playing as any other client.
+
+use std::{
+    io,
+    net::SocketAddr,
+    path::PathBuf,
+    process::{Command, Stdio},
+    time::Duration,
+};
+
+use curseofrust_cli_parser::{Protocol, Rules};
+use curseofrust_msg::{bytemuck, S2CData};
+
+/// Path of the server executable shipped next to this one,
+/// if there is one to host with.
+pub(crate) fn server_exe() -> Option<PathBuf> {
+    let exe = std::env::current_exe().ok()?.with_file_name(format!(
+        "curseofrust-server{}",
+        std::env::consts::EXE_SUFFIX
+    ));
+    exe.is_file().then_some(exe)
+}
+
+/// Starts a server continuing from the given state, hosting through
+/// the relay for as many clients as it had.
+///
+/// Without the rules of the game, the server plays by the default ones
+/// at the given tick.
+///
+/// The server outlives the client, so that the others can keep
+/// playing after it quits.
+pub(crate) fn resume(
+    data: &S2CData,
+    relay: SocketAddr,
+    clients: u8,
+    protocol: Protocol,
+    tick: Duration,
+    rules: Option<&Rules>,
+) -> io::Result<()> {
+    let exe = server_exe().ok_or(io::ErrorKind::NotFound)?;
+    let dir = std::env::temp_dir();
+    let path = dir.join(format!("curseofrust-{}.state", std::process::id()));
+    std::fs::write(&path, bytemuck::bytes_of(data))?;
+    let mut command = Command::new(exe);
+    if let Some(rules) = rules {
+        let balance = dir.join(format!("curseofrust-{}.balance.toml", std::process::id()));
+        rules.save_balance(&balance)?;
+        command.args(rules.to_args(&balance));
+    } else {
+        command.arg("--tick-ms").arg(tick.as_millis().to_string());
+    }
+    command
+        .arg("-e")
+        .arg("0")
+        .arg("-Y")
+        .arg(relay.to_string())
+        .arg("-E")
+        .arg(clients.to_string())
+        .arg("-p")
+        .arg(protocol.name())
+        .arg("--resume")
+        .arg(&path)
+        .arg("--no-lobby-print")
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    Ok(())
+}
@@ -0,0 +1,144 @@
+//! Bounded log of game events, with a scrollable full view.
+
+use std::{collections::VecDeque, io::Write};
+
+use crossterm::{
+    cursor, queue,
+    style::{self, Attribute, ContentStyle, StyledContent},
+    terminal::{self, ClearType},
+};
+use curseofrust::snapshot::GameEvent;
+
+use crate::{
+    narrate::{land_name, player_name},
+    State,
+};
+
+/// Oldest entries are dropped beyond this many.
+const MAX_ENTRIES: usize = 256;
+/// Number of entries shown under the map.
+pub(crate) const PANEL_ENTRIES: usize = 3;
+
+#[derive(Debug, Default)]
+pub(crate) struct EventLog {
+    entries: VecDeque<String>,
+    /// Number of entries scrolled back from the newest one,
+    /// if the full log is open.
+    view: Option<usize>,
+}
+
+impl EventLog {
+    pub(crate) fn push(&mut self, entry: String) {
+        if self.entries.len() >= MAX_ENTRIES {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry);
+        // Keep the viewed entries in place.
+        if let Some(offset) = &mut self.view {
+            *offset = (*offset + 1).min(self.entries.len().saturating_sub(1));
+        }
+    }
+
+    /// Iterates over the newest entries, oldest first.
+    pub(crate) fn recent(&self, n: usize) -> impl Iterator<Item = &str> {
+        self.entries
+            .iter()
+            .skip(self.entries.len().saturating_sub(n))
+            .map(String::as_str)
+    }
+
+    #[inline]
+    pub(crate) fn is_open(&self) -> bool {
+        self.view.is_some()
+    }
+
+    #[inline]
+    pub(crate) fn toggle(&mut self) {
+        self.view = match self.view {
+            Some(_) => None,
+            None => Some(0),
+        };
+    }
+
+    /// Scrolls the full log back by `lines`, or forward if negative.
+    pub(crate) fn scroll(&mut self, lines: isize) {
+        if let Some(offset) = &mut self.view {
+            *offset = offset
+                .saturating_add_signed(lines)
+                .min(self.entries.len().saturating_sub(1));
+        }
+    }
+}
+
+/// Describes the given event for the log.
+pub(crate) fn describe<W>(st: &State<W>, event: &GameEvent) -> Option<String> {
+    let lang = st.lang;
+    Some(match *event {
+        GameEvent::CityCaptured { pos, land, to, .. } if !to.is_neutral() => {
+            let at = format!("{},{}", pos.0, pos.1);
+            lang.trf(
+                "{0} captured {1} at {2}",
+                &[&player_name(st, to), &land_name(st, land), &at],
+            )
+        }
+        GameEvent::MineLost { pos, from, .. } => {
+            let at = format!("{},{}", pos.0, pos.1);
+            lang.trf("{0} lost the mine at {1}", &[&player_name(st, from), &at])
+        }
+        GameEvent::PlayerEliminated(player) => {
+            lang.trf("eliminated: {0}", &[&player_name(st, player)])
+        }
+        GameEvent::CityPlundered {
+            pos,
+            from,
+            to,
+            gold,
+        } => {
+            let at = format!("{},{}", pos.0, pos.1);
+            lang.trf(
+                "{0} plundered {1} gold from {2} at {3}",
+                &[&player_name(st, to), &gold, &player_name(st, from), &at],
+            )
+        }
+        _ => return None,
+    })
+}
+
+/// Logs the given events.
+pub(crate) fn log_events<W>(st: &mut State<W>, events: &[GameEvent]) {
+    for event in events {
+        if let Some(entry) = describe(st, event) {
+            st.log.push(entry);
+        }
+    }
+}
+
+/// Draws the full log over the whole screen.
+pub(crate) fn draw_view<W: Write>(st: &mut State<W>) -> Result<(), std::io::Error> {
+    let (_, rows) = terminal::size()?;
+    let lines = rows.saturating_sub(1) as usize;
+    let offset = st.log.view.unwrap_or_default();
+    let end = st.log.entries.len() - offset.min(st.log.entries.len());
+    let start = end.saturating_sub(lines);
+
+    queue!(
+        st.out,
+        terminal::Clear(ClearType::All),
+        cursor::MoveTo(0, 0),
+        style::PrintStyledContent(StyledContent::new(
+            ContentStyle {
+                attributes: Attribute::Reverse.into(),
+                ..Default::default()
+            },
+            st.lang.tr("Event log: [E] close, [J]/[K] scroll"),
+        )),
+    )?;
+    for (row, entry) in st.log.entries.range(start..end).enumerate() {
+        queue!(
+            st.out,
+            cursor::MoveTo(0, row as u16 + 1),
+            style::Print(entry)
+        )?;
+    }
+    Ok(())
+}
@@ -3,18 +3,63 @@ use std::{
     fmt::Debug,
     io::Write,
     ops::ControlFlow,
-    time::{Duration, SystemTime},
+    sync::Mutex,
+    time::{Duration, Instant, SystemTime},
 };
 
-use crossterm::{cursor, execute, terminal};
-use curseofrust::{Pos, Speed, FLAG_POWER};
-use curseofrust_cli_parser::{ControlMode, Options};
+use crossterm::{execute, terminal};
+use curseofrust::{state::Fingerprint, Player, Pos, Speed, FLAG_POWER, MAX_FLAG_POWER};
+use curseofrust_cli_parser::{Alert, ControlMode, Daily, Lang, Options, Palette, Profile};
 
+mod alert;
+mod bench;
+mod bot;
+mod braille;
+#[cfg(feature = "multiplayer")]
+mod browse;
 mod client;
+#[cfg(feature = "multiplayer")]
+mod connect;
 mod control;
+mod export;
+mod graph;
+#[cfg(feature = "multiplayer")]
+mod host;
+mod log;
+mod menu;
+mod narrate;
 mod output;
+mod sim;
+mod stats;
+mod term;
+mod theme;
+
+/// Fingerprint of the game as of the latest timeline mark,
+/// reported if the game panics.
+static FINGERPRINT: Mutex<Option<Fingerprint>> = Mutex::new(None);
+
+/// Records a timeline mark, along with the fingerprint
+/// reported on panics.
+fn mark_timeline(state: &mut curseofrust::state::State) {
+    state.update_timeline();
+    if let Ok(mut fingerprint) = FINGERPRINT.lock() {
+        *fingerprint = Some(state.fingerprint());
+    }
+}
 
-const DURATION: Duration = Duration::from_millis(10);
+/// Restores the terminal on panics, and reports the fingerprint
+/// of the game after the message, for bug reports.
+fn hook_panics() {
+    let hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        // The message would be lost in the alternate screen.
+        term::restore();
+        hook(info);
+        if let Some(fingerprint) = FINGERPRINT.try_lock().ok().and_then(|f| *f) {
+            eprintln!("game fingerprint: {}", fingerprint);
+        }
+    }));
+}
 
 fn main() -> Result<(), DirectBoxedError> {
     fastrand::seed(
@@ -25,48 +70,226 @@ fn main() -> Result<(), DirectBoxedError> {
     );
 
     let Options {
-        basic: b_opt,
-        multiplayer: m_opt,
+        basic: mut b_opt,
+        multiplayer: mut m_opt,
         exit,
+        #[cfg(feature = "multiplayer")]
         protocol,
         control_mode,
+        narrate,
+        lang,
+        headless,
+        batch,
+        bench_render,
+        alert,
+        palette,
+        shade,
+        braille,
+        time_limit,
+        turns,
+        players,
+        export,
+        #[cfg(feature = "multiplayer")]
+        bot,
+        #[cfg(feature = "multiplayer")]
+        trace_wire,
+        menu,
+        connect_ui,
+        list,
+        #[cfg(feature = "multiplayer")]
+        url,
+        #[cfg(feature = "multiplayer")]
+        password,
+        daily,
+        warnings,
         ..
-    } = curseofrust_cli_parser::parse_to_options(std::env::args_os())?;
+    } = curseofrust_cli_parser::parse_to_options(std::env::args_os()).map_err(|err| {
+        DirectBoxedError {
+            inner: err.localized(Lang::from_env()).into(),
+        }
+    })?;
+    for warning in &warnings {
+        eprintln!("{}", lang.trf("warning: {0}", &[&warning.localized(lang)]));
+    }
     if exit {
         return Ok(());
     }
+    #[cfg(feature = "multiplayer")]
+    if let Some(master) = list {
+        let Some(server) = browse::pick(&master, lang)? else {
+            return Ok(());
+        };
+        m_opt = curseofrust_cli_parser::MultiplayerOpts::Client {
+            server,
+            port: match m_opt {
+                curseofrust_cli_parser::MultiplayerOpts::Client { port, .. } => port,
+                _ => curseofrust_cli_parser::DEFAULT_CLIENT_PORT,
+            },
+        };
+    }
+    #[cfg(feature = "multiplayer")]
+    let protocol = match connect_ui {
+        true => match connect::prompt(&mut m_opt, protocol, lang)? {
+            Some(protocol) => protocol,
+            None => return Ok(()),
+        },
+        false => protocol,
+    };
+    #[cfg(not(feature = "multiplayer"))]
+    if list.is_some() || connect_ui {
+        return Err(DirectBoxedError {
+            inner: <Box<dyn std::error::Error>>::from(lang.tr("client feature not enabled")),
+        });
+    }
+    hook_panics();
+    if menu && !menu::run(&mut b_opt, &mut m_opt, lang)? {
+        return Ok(());
+    }
+    if headless {
+        return sim::run(b_opt, batch, &mut std::io::stdout().lock());
+    }
+    #[cfg(feature = "multiplayer")]
+    if let (Some(strategy), curseofrust_cli_parser::MultiplayerOpts::Client { server, port }) =
+        (bot, &m_opt)
+    {
+        return bot::run(*server, *port, protocol, strategy, lang);
+    }
+    if let Some(frames) = bench_render {
+        return bench::run(
+            b_opt,
+            frames,
+            control_mode,
+            lang,
+            &mut std::io::stdout().lock(),
+        );
+    }
 
+    if matches!(m_opt, curseofrust_cli_parser::MultiplayerOpts::None) {
+        // The other countries of the player are left without kings.
+        b_opt.clients = players;
+    }
     let state = curseofrust::state::State::new(b_opt)?;
     let stdout = std::io::stdout();
     let mut st = State {
         ui: curseofrust::state::UI::new(&state),
         s: state,
         control: control_mode,
+        narrate,
+        call_overlay: false,
+        braille,
+        stats: false,
+        log: Default::default(),
+        alert,
+        besieged: vec![],
+        time_left: time_limit,
+        turns,
+        turn_ended: false,
+        winner: None,
+        countdown: None,
+        lang,
+        theme: theme::Theme::new(
+            match palette {
+                Palette::Auto => Profile::load().palette.unwrap_or_default(),
+                palette => palette,
+            },
+            shade,
+        ),
+        swipe: None,
+        last_flag: None,
         out: stdout,
     };
+    let started = Instant::now();
+    let _terminal = term::Guard;
 
     match m_opt {
-        curseofrust::state::MultiplayerOpts::Server { .. } => Err(DirectBoxedError {
-            inner: <Box<dyn std::error::Error>>::from("use dedicated server"),
+        curseofrust_cli_parser::MultiplayerOpts::Server { .. }
+        | curseofrust_cli_parser::MultiplayerOpts::Relay { .. } => Err(DirectBoxedError {
+            inner: <Box<dyn std::error::Error>>::from(lang.tr("use dedicated server")),
         }),
         #[cfg(feature = "multiplayer")]
-        curseofrust::state::MultiplayerOpts::Client { server, port } => {
-            let res = client::run(&mut st, server, port, protocol);
-            execute!(st.out, terminal::Clear(terminal::ClearType::All))?;
-            if matches!(st.control, ControlMode::Termux | ControlMode::Hybrid) {
-                execute!(st.out, crossterm::event::DisableMouseCapture)?;
+        curseofrust_cli_parser::MultiplayerOpts::Client { server, port } => {
+            let res = client::run(
+                &mut st,
+                server,
+                port,
+                url.as_deref(),
+                protocol,
+                trace_wire,
+                client::Join {
+                    players,
+                    password: password.as_deref(),
+                },
+            );
+            if !st.narrate {
+                execute!(st.out, terminal::Clear(terminal::ClearType::All))?;
             }
-            terminal::disable_raw_mode()?;
-            execute!(st.out, terminal::LeaveAlternateScreen, cursor::Show)?;
+            term::restore();
+            record_game(&st, started.elapsed(), daily);
             res
         }
         #[cfg(not(feature = "multiplayer"))]
-        curseofrust::state::MultiplayerOpts::Client { .. } => Err(DirectBoxedError {
-            inner: <Box<dyn std::error::Error>>::from("client feature not enabled"),
+        curseofrust_cli_parser::MultiplayerOpts::Client { .. } => Err(DirectBoxedError {
+            inner: <Box<dyn std::error::Error>>::from(lang.tr("client feature not enabled")),
         }),
 
-        curseofrust::state::MultiplayerOpts::None => run(&mut st),
+        curseofrust_cli_parser::MultiplayerOpts::None => {
+            let export = export
+                .map(|path| export::Export::create(&path, &st.s.snapshot()))
+                .transpose()?;
+            let res = run(&mut st, export);
+            record_game(&st, started.elapsed(), daily);
+            print_seeds(&st);
+            res
+        }
+    }
+}
+
+/// Prints the seeds of a game with AI personalities once it is
+/// over, so that it can be played again.
+fn print_seeds<W>(st: &State<W>) {
+    if let Some(personality) = st.s.ai_personality_seed {
+        println!(
+            "{}",
+            st.lang.trf(
+                "seed: {0}, AI personality seed: {1}",
+                &[&st.s.seed, &personality]
+            )
+        );
+    }
+}
+
+/// Records the game played in the profile, and tells the quickest
+/// win of the daily challenge if it was won.
+fn record_game<W>(st: &State<W>, played: Duration, daily: Option<Daily>) {
+    let won = if st.s.is_eliminated(st.s.controlled) {
+        Some(false)
+    } else {
+        st.winner
+            .or_else(|| st.s.winner())
+            .map(|winner| winner == st.s.controlled)
+    };
+    let mut profile = Profile::load();
+    profile.record(won, played);
+    if let (Some(daily), Some(true)) = (daily, won) {
+        // Timed in steps, which neither pauses nor speeds change.
+        let date = daily.date();
+        let steps = st.s.time;
+        let msg = if profile.record_daily(date.clone(), steps) {
+            st.lang.trf(
+                "daily challenge {0} won in {1} steps, a new best",
+                &[&date, &steps],
+            )
+        } else {
+            let best = profile.daily_steps.get(&date).copied().unwrap_or(steps);
+            st.lang.trf(
+                "daily challenge {0} won in {1} steps, the best is {2}",
+                &[&date, &steps, &best],
+            )
+        };
+        println!("{msg}");
     }
+    // A read-only config directory should not fail the game.
+    let _ = profile.save();
 }
 
 struct DirectBoxedError {
@@ -98,6 +321,35 @@ struct State<W> {
     s: curseofrust::state::State,
     ui: curseofrust::state::UI,
     control: ControlMode,
+    /// Narrate the game as text instead of drawing the grid.
+    narrate: bool,
+    /// Shade tiles by the call of the flags of the controlled player.
+    call_overlay: bool,
+    /// Draw the ownership of the map in braille instead of the tiles.
+    braille: bool,
+    /// Show the statistics table of all players over the map.
+    stats: bool,
+    log: log::EventLog,
+    alert: Option<Alert>,
+    /// Fortresses of the controlled player under siege,
+    /// to alert only when a siege begins.
+    besieged: Vec<Pos>,
+    /// Time left of the match time limit, if any.
+    time_left: Option<Duration>,
+    /// Steps per turn in turn-based mode, `None` if real-time.
+    turns: Option<u32>,
+    /// Whether the player ended the turn, in singleplayer.
+    turn_ended: bool,
+    /// Winner announced by the server, in multiplayer.
+    winner: Option<Player>,
+    /// Seconds until the server resumes the game, while it counts
+    /// down, in multiplayer.
+    countdown: Option<u8>,
+    lang: Lang,
+    theme: theme::Theme,
+    swipe: Option<control::Swipe>,
+    /// Tile flagged by the last press of Space, and when.
+    last_flag: Option<(Pos, Instant)>,
     out: W,
 }
 
@@ -113,34 +365,98 @@ impl control::Client for SingleplayerClient {
 
     #[inline]
     fn toggle_flag<W>(&mut self, st: &mut State<W>, pos: Pos) -> Result<(), Self::Error> {
+        let Ok(i) = st.s.controlled.index() else {
+            return Ok(());
+        };
         if st.s.grid.tile(pos).is_some_and(|t| t.is_habitable()) {
-            let fg = &mut st.s.fgs[st.s.controlled.0 as usize];
-            if fg.is_flagged(st.ui.cursor) {
-                fg.remove(&st.s.grid, st.ui.cursor, FLAG_POWER);
+            let fg = &mut st.s.fgs[i];
+            if fg.power(st.ui.cursor) >= MAX_FLAG_POWER {
+                fg.remove(&st.s.grid, st.ui.cursor, MAX_FLAG_POWER);
             } else {
-                fg.add(&st.s.grid, st.ui.cursor, FLAG_POWER);
+                fg.raise(&st.s.grid, st.ui.cursor, FLAG_POWER);
             }
         }
         Ok(())
     }
 
+    #[inline]
+    fn raise_flag<W>(
+        &mut self,
+        st: &mut State<W>,
+        pos: Pos,
+        stacks: u8,
+    ) -> Result<(), Self::Error> {
+        let Ok(i) = st.s.controlled.index() else {
+            return Ok(());
+        };
+        if st.s.grid.tile(pos).is_some_and(|t| t.is_habitable()) {
+            st.s.fgs[i].raise(&st.s.grid, pos, stacks as i32 * FLAG_POWER);
+        }
+        Ok(())
+    }
+
     #[inline]
     fn rm_all_flag<W>(&mut self, st: &mut State<W>) -> Result<(), Self::Error> {
-        st.s.fgs[st.s.controlled.0 as usize].remove_with_prob(&st.s.grid, 1.0);
+        if let Ok(i) = st.s.controlled.index() {
+            st.s.fgs[i].remove_with_prob(&st.s.grid, 1.0);
+        }
         Ok(())
     }
 
     #[inline]
     fn rm_half_flag<W>(&mut self, st: &mut State<W>) -> Result<(), Self::Error> {
-        st.s.fgs[st.s.controlled.0 as usize].remove_with_prob(&st.s.grid, 0.5);
+        if let Ok(i) = st.s.controlled.index() {
+            st.s.fgs[i].remove_with_prob(&st.s.grid, 0.5);
+        }
+        Ok(())
+    }
+
+    #[inline]
+    fn flag_cities<W>(&mut self, st: &mut State<W>) -> Result<(), Self::Error> {
+        st.s.flag_cities(st.s.controlled);
+        Ok(())
+    }
+
+    #[inline]
+    fn flag_frontline<W>(&mut self, st: &mut State<W>) -> Result<(), Self::Error> {
+        st.s.flag_frontline(st.s.controlled);
         Ok(())
     }
 
     #[inline]
     fn build<W>(&mut self, st: &mut State<W>, pos: Pos) -> Result<(), Self::Error> {
-        let _ =
-            st.s.grid
-                .build(&mut st.s.countries[st.s.controlled.0 as usize], pos);
+        if let Ok(i) = st.s.controlled.index() {
+            let _ = st.s.grid.build(&mut st.s.countries[i], pos, &st.s.balance);
+        }
+        Ok(())
+    }
+
+    #[inline]
+    fn build_road<W>(&mut self, st: &mut State<W>, pos: Pos) -> Result<(), Self::Error> {
+        if let Ok(i) = st.s.controlled.index() {
+            let _ = st.s.grid.build_road(&mut st.s.countries[i], pos);
+        }
+        Ok(())
+    }
+
+    #[inline]
+    fn toggle_queued_build<W>(&mut self, st: &mut State<W>, pos: Pos) -> Result<(), Self::Error> {
+        if st.s.is_build_queued(st.s.controlled, pos) {
+            st.s.unqueue_build(st.s.controlled, pos);
+        } else {
+            let _ = st.s.queue_build(st.s.controlled, pos);
+        }
+        Ok(())
+    }
+
+    #[inline]
+    fn set_rally<W>(
+        &mut self,
+        st: &mut State<W>,
+        city: Pos,
+        target: Option<Pos>,
+    ) -> Result<(), Self::Error> {
+        let _ = st.s.grid.set_rally(st.s.controlled, city, target);
         Ok(())
     }
 
@@ -166,61 +482,138 @@ impl control::Client for SingleplayerClient {
         }
         Ok(())
     }
+
+    #[inline(always)]
+    fn surrender<W>(&mut self, _st: &mut State<W>) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn vote_restart<W>(&mut self, _st: &mut State<W>) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    #[inline]
+    fn end_turn<W>(&mut self, st: &mut State<W>) -> Result<(), Self::Error> {
+        st.turn_ended = st.turns.is_some();
+        Ok(())
+    }
+
+    #[inline]
+    fn switch_player<W>(&mut self, st: &mut State<W>) -> Result<(), Self::Error> {
+        st.s.switch_controlled();
+        st.ui.rally_from = None;
+        Ok(())
+    }
+
+    /// The game is simulated here, so it is never out of date.
+    #[inline]
+    fn resync<W>(&mut self, _st: &mut State<W>) -> Result<(), Self::Error> {
+        Ok(())
+    }
 }
 
-fn run<W: Write>(st: &mut State<W>) -> Result<(), DirectBoxedError> {
-    execute!(st.out, terminal::EnterAlternateScreen)?;
-    crossterm::terminal::enable_raw_mode()?;
-    execute!(
-        st.out,
-        terminal::Clear(terminal::ClearType::All),
-        cursor::Hide
+fn run<W: Write>(
+    st: &mut State<W>,
+    mut export: Option<export::Export>,
+) -> Result<(), DirectBoxedError> {
+    term::enter(
+        !st.narrate,
+        matches!(st.control, ControlMode::Termux | ControlMode::Hybrid),
     )?;
-
-    if matches!(st.control, ControlMode::Termux | ControlMode::Hybrid) {
-        execute!(st.out, crossterm::event::EnableMouseCapture)?;
+    if !st.narrate {
+        execute!(st.out, terminal::Clear(terminal::ClearType::All))?;
     }
 
+    output::draw_all_grid(st)?;
+    let mut snapshot = st.s.snapshot();
+
     let mut time = 0i32;
     let mut events = crossterm::event::EventStream::new();
+    // Key presses end iterations early, so the time limit counts down
+    // by the time passed rather than by ticks.
+    let mut counted = Instant::now();
     loop {
-        let timer = async_io::Timer::after(DURATION);
+        let timer = async_io::Timer::after(st.s.tick);
         time += 1;
         if time >= 1600 {
             time = 0
         }
 
-        if time.checked_rem(slowdown(st.s.speed)) == Some(0) {
-            st.s.kings_move();
-            st.s.simulate();
-            if st.s.show_timeline && st.s.time % 10 == 0 {
-                st.s.update_timeline();
+        let now = Instant::now();
+        let elapsed = now - counted;
+        counted = now;
+        if let Some(left) = st
+            .time_left
+            .as_mut()
+            .filter(|left| st.s.speed != Speed::Pause && !left.is_zero())
+        {
+            *left = left.saturating_sub(elapsed);
+            if left.is_zero() {
+                st.s.speed = Speed::Pause;
+                let winner = st.s.time_up().unwrap_or_default();
+                let notice = st.lang.trf("{0} won", &[&narrate::player_name(st, winner)]);
+                output::draw_all_grid(st)?;
+                output::draw_notice(st, &notice)?;
+            }
+        }
+
+        let steps = match st.turns {
+            Some(ticks) if std::mem::take(&mut st.turn_ended) => ticks,
+            Some(_) => 0,
+            None => (time.checked_rem(slowdown(st.s.speed)) == Some(0)) as u32,
+        };
+        if steps > 0 {
+            st.s.step_n(steps);
+            // Whether a multiple of 10 was passed.
+            if st.s.time % 10 < steps as u64 {
+                mark_timeline(&mut st.s);
             }
 
-            output::draw_all_grid(st)?;
+            // Only redraw tiles changed since the last step,
+            // unless the call overlay may have changed anywhere.
+            let next = st.s.snapshot();
+            let changes = snapshot.diff(&next);
+            let mut events = snapshot.events(&next);
+            events.extend(st.s.take_events());
+            log::log_events(st, &events);
+            alert::check(st, &events)?;
+            if st.narrate {
+                narrate::narrate_changes(st, &snapshot, &changes)?;
+            } else if st.call_overlay {
+                output::draw_all_grid(st)?;
+            } else {
+                // Threats to own cities may change without the cities changing.
+                let cities: Vec<Pos> = st.s.grid.cities(st.s.controlled).map(|(p, _)| p).collect();
+                output::draw_grid(st, Some(changes.iter().map(|c| c.pos).chain(cities)))?;
+            }
+            if let Some(export) = &mut export {
+                export.update(&next);
+            }
+            snapshot = next;
         }
 
         st.out.flush()?;
 
-        let cond = futures_lite::future::block_on(futures_lite::future::or(
-            control::accept(|| &mut *st, &mut events, SingleplayerClient),
-            async {
+        // Nothing changes while paused but by input, so wait for it
+        // without waking up every tick.
+        let paused = st.s.speed == Speed::Pause;
+        let input = control::accept(|| &mut *st, &mut events, SingleplayerClient);
+        let cond = if paused {
+            futures_lite::future::block_on(input)?
+        } else {
+            futures_lite::future::block_on(futures_lite::future::or(input, async {
                 timer.await;
                 Result::<ControlFlow<(), ()>, DirectBoxedError>::Ok(ControlFlow::Continue(()))
-            },
-        ))?;
+            }))?
+        };
 
         if cond.is_break() {
             break;
         }
     }
 
-    if matches!(st.control, ControlMode::Termux | ControlMode::Hybrid) {
-        execute!(st.out, crossterm::event::DisableMouseCapture)?;
-    }
-    terminal::disable_raw_mode()?;
-    execute!(st.out, terminal::LeaveAlternateScreen, cursor::Show)?;
-
+    term::restore();
     Ok(())
 }
 
@@ -0,0 +1,347 @@
+//! Form to configure a new game before starting it.
+
+use std::{
+    io::{self, Write},
+    net::{Ipv4Addr, SocketAddr},
+};
+
+use crossterm::{
+    cursor,
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    queue,
+    style::{self, Attribute},
+    terminal::{self, ClearType},
+};
+use curseofrust::{grid::Stencil, state::BasicOpts, Difficulty, Speed, MAX_HEIGHT, MAX_WIDTH};
+use curseofrust_cli_parser::{
+    parse_addr, Lang, MultiplayerOpts, Skirmish, DEFAULT_CLIENT_PORT, DEFAULT_SERVER_PORT,
+};
+
+use crate::term;
+
+const SHAPES: [(Stencil, &str); 3] = [
+    (Stencil::Rect, "rectangle"),
+    (Stencil::Rhombus, "rhombus"),
+    (Stencil::Hex, "hexagon"),
+];
+const DIFFICULTIES: [(Difficulty, &str); 5] = [
+    (Difficulty::Easiest, "easiest"),
+    (Difficulty::Easy, "easy"),
+    (Difficulty::Normal, "normal"),
+    (Difficulty::Hard, "hard"),
+    (Difficulty::Hardest, "hardest"),
+];
+const SPEEDS: [(Speed, &str); 7] = [
+    (Speed::Slowest, "slowest"),
+    (Speed::Slower, "slower"),
+    (Speed::Slow, "slow"),
+    (Speed::Normal, "normal"),
+    (Speed::Fast, "fast"),
+    (Speed::Faster, "faster"),
+    (Speed::Fastest, "fastest"),
+];
+const MIN_SIZE: u32 = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Width,
+    Height,
+    Shape,
+    Difficulty,
+    Speed,
+    Role,
+    Server,
+}
+
+struct Form {
+    /// Width as given by `-W`, without the 10 columns
+    /// rectangular maps have on top of it.
+    width: u32,
+    height: u32,
+    shape: usize,
+    difficulty: usize,
+    speed: usize,
+    client: bool,
+    server: String,
+    selected: Field,
+    error: Option<String>,
+}
+
+impl Form {
+    fn new(b_opt: &BasicOpts, m_opt: &MultiplayerOpts) -> Self {
+        let server = match m_opt {
+            MultiplayerOpts::Client { server, .. } => *server,
+            _ => SocketAddr::from((Ipv4Addr::LOCALHOST, DEFAULT_SERVER_PORT)),
+        };
+        Self {
+            width: if b_opt.shape == Stencil::Rect {
+                b_opt.width.saturating_sub(10)
+            } else {
+                b_opt.width
+            },
+            height: b_opt.height,
+            shape: SHAPES.iter().position(|s| s.0 == b_opt.shape).unwrap_or(0),
+            difficulty: DIFFICULTIES
+                .iter()
+                .position(|d| d.0 == b_opt.difficulty)
+                .unwrap_or(2),
+            speed: SPEEDS.iter().position(|s| s.0 == b_opt.speed).unwrap_or(3),
+            client: matches!(m_opt, MultiplayerOpts::Client { .. }),
+            server: server.to_string(),
+            selected: Field::Width,
+            error: None,
+        }
+    }
+
+    fn fields(&self) -> &'static [Field] {
+        if self.client {
+            &[
+                Field::Width,
+                Field::Height,
+                Field::Shape,
+                Field::Difficulty,
+                Field::Speed,
+                Field::Role,
+                Field::Server,
+            ]
+        } else {
+            &[
+                Field::Width,
+                Field::Height,
+                Field::Shape,
+                Field::Difficulty,
+                Field::Speed,
+                Field::Role,
+            ]
+        }
+    }
+
+    fn max_width(&self) -> u32 {
+        if SHAPES[self.shape].0 == Stencil::Rect {
+            MAX_WIDTH - 10
+        } else {
+            MAX_WIDTH
+        }
+    }
+
+    /// Moves the selection by the given number of fields.
+    fn select(&mut self, by: isize) {
+        let fields = self.fields();
+        let i = fields.iter().position(|f| *f == self.selected).unwrap_or(0);
+        let i = (i as isize + by).rem_euclid(fields.len() as isize);
+        self.selected = fields[i as usize];
+    }
+
+    /// Changes the value of the selected field, backwards if `by` is negative.
+    fn change(&mut self, by: i32) {
+        fn cycle(i: &mut usize, len: usize, by: i32) {
+            *i = (*i as i32 + by).rem_euclid(len as i32) as usize;
+        }
+        match self.selected {
+            Field::Width => {
+                self.width = self
+                    .width
+                    .saturating_add_signed(by)
+                    .clamp(MIN_SIZE, self.max_width())
+            }
+            Field::Height => {
+                self.height = self
+                    .height
+                    .saturating_add_signed(by)
+                    .clamp(MIN_SIZE, MAX_HEIGHT)
+            }
+            Field::Shape => {
+                cycle(&mut self.shape, SHAPES.len(), by);
+                self.width = self.width.min(self.max_width());
+            }
+            Field::Difficulty => cycle(&mut self.difficulty, DIFFICULTIES.len(), by),
+            Field::Speed => cycle(&mut self.speed, SPEEDS.len(), by),
+            Field::Role => self.client ^= true,
+            Field::Server => {}
+        }
+    }
+
+    fn value(&self, field: Field, lang: Lang) -> String {
+        match field {
+            Field::Width => self.width.to_string(),
+            Field::Height => self.height.to_string(),
+            Field::Shape => lang.tr(SHAPES[self.shape].1).to_owned(),
+            Field::Difficulty => lang.tr(DIFFICULTIES[self.difficulty].1).to_owned(),
+            Field::Speed => lang.tr(SPEEDS[self.speed].1).to_owned(),
+            Field::Role => lang
+                .tr(if self.client {
+                    "client"
+                } else {
+                    "singleplayer"
+                })
+                .to_owned(),
+            Field::Server => self.server.clone(),
+        }
+    }
+
+    /// Sets the map of the given skirmish, for singleplayer.
+    fn quick_start(&mut self, skirmish: Skirmish, b_opt: &mut BasicOpts) {
+        let (shape, width, height, locations) = skirmish.map();
+        self.shape = SHAPES.iter().position(|s| s.0 == shape).unwrap_or(0);
+        self.width = width;
+        self.height = height;
+        self.client = false;
+        b_opt.shape = shape;
+        b_opt.locations = locations;
+    }
+
+    /// Writes the form into the options.
+    fn apply(&self, b_opt: &mut BasicOpts, m_opt: &mut MultiplayerOpts) -> Result<(), String> {
+        if self.client {
+            let (server, _) = parse_addr(&self.server).map_err(|_| self.server.clone())?;
+            *m_opt = match *m_opt {
+                MultiplayerOpts::Client { port, .. } => MultiplayerOpts::Client { server, port },
+                _ => MultiplayerOpts::Client {
+                    server,
+                    port: DEFAULT_CLIENT_PORT,
+                },
+            };
+        } else {
+            *m_opt = MultiplayerOpts::None;
+        }
+
+        let shape = SHAPES[self.shape].0;
+        // Keep the most locations if the shape had them all.
+        if b_opt.locations == b_opt.shape.max_locs() || b_opt.locations > shape.max_locs() {
+            b_opt.locations = shape.max_locs();
+        }
+        b_opt.shape = shape;
+        b_opt.width = if shape == Stencil::Rect {
+            self.width + 10
+        } else {
+            self.width
+        };
+        b_opt.height = self.height;
+        b_opt.difficulty = DIFFICULTIES[self.difficulty].0;
+        b_opt.speed = SPEEDS[self.speed].0;
+        Ok(())
+    }
+
+    fn draw<W: Write>(&self, out: &mut W, lang: Lang) -> io::Result<()> {
+        queue!(
+            out,
+            terminal::Clear(ClearType::All),
+            cursor::MoveTo(2, 1),
+            style::SetAttribute(Attribute::Bold),
+            style::Print(lang.tr("New Game")),
+            style::SetAttribute(Attribute::Reset),
+        )?;
+        let mut line = 3;
+        for &field in self.fields() {
+            let label = lang.tr(match field {
+                Field::Width => "Width",
+                Field::Height => "Height",
+                Field::Shape => "Shape",
+                Field::Difficulty => "Difficulty",
+                Field::Speed => "Speed",
+                Field::Role => "Role",
+                Field::Server => "Server",
+            });
+            let value = match field {
+                Field::Server => format!(" {} ", self.value(field, lang)),
+                _ => format!("< {} >", self.value(field, lang)),
+            };
+            queue!(out, cursor::MoveTo(2, line), style::Print(label))?;
+            queue!(out, cursor::MoveTo(16, line))?;
+            if field == self.selected {
+                queue!(
+                    out,
+                    style::SetAttribute(Attribute::Reverse),
+                    style::Print(value),
+                    style::SetAttribute(Attribute::Reset),
+                )?;
+            } else {
+                queue!(out, style::Print(value))?;
+            }
+            line += 1;
+        }
+        line += 1;
+        if let Some(error) = &self.error {
+            queue!(
+                out,
+                cursor::MoveTo(2, line),
+                style::Print(lang.trf("invalid server address: {0}", &[error])),
+            )?;
+        }
+        queue!(
+            out,
+            cursor::MoveTo(2, line + 2),
+            style::Print(
+                lang.tr("[Up]/[Down] select, [Left]/[Right] change, [Enter] start, [Esc] quit")
+            ),
+            cursor::MoveTo(2, line + 3),
+            style::Print(lang.tr("[1]/[2]/[3] start a small/medium/large skirmish")),
+        )?;
+        out.flush()
+    }
+}
+
+/// Shows the form, filled from the options, and writes the
+/// configured game into them.
+///
+/// Returns `false` if the player quit instead.
+pub(crate) fn run(
+    b_opt: &mut BasicOpts,
+    m_opt: &mut MultiplayerOpts,
+    lang: Lang,
+) -> io::Result<bool> {
+    let mut out = io::stdout();
+    let _terminal = term::Guard;
+    term::enter(true, false)?;
+    edit(&mut Form::new(b_opt, m_opt), b_opt, m_opt, lang, &mut out)
+}
+
+fn edit<W: Write>(
+    form: &mut Form,
+    b_opt: &mut BasicOpts,
+    m_opt: &mut MultiplayerOpts,
+    lang: Lang,
+    out: &mut W,
+) -> io::Result<bool> {
+    loop {
+        form.draw(out, lang)?;
+        let Event::Key(KeyEvent {
+            code,
+            modifiers,
+            kind: KeyEventKind::Press | KeyEventKind::Repeat,
+            ..
+        }) = event::read()?
+        else {
+            continue;
+        };
+        let editing = form.selected == Field::Server;
+        match code {
+            KeyCode::Esc => return Ok(false),
+            KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => return Ok(false),
+            KeyCode::Char('q') if !editing => return Ok(false),
+            KeyCode::Enter => match form.apply(b_opt, m_opt) {
+                Ok(()) => return Ok(true),
+                Err(err) => form.error = Some(err),
+            },
+            KeyCode::Char(c @ '1'..='3') if !editing => {
+                let skirmish = Skirmish::ALL[c as usize - '1' as usize];
+                form.quick_start(skirmish, b_opt);
+                match form.apply(b_opt, m_opt) {
+                    Ok(()) => return Ok(true),
+                    Err(err) => form.error = Some(err),
+                }
+            }
+            KeyCode::Up | KeyCode::BackTab => form.select(-1),
+            KeyCode::Down | KeyCode::Tab => form.select(1),
+            KeyCode::Char('k') if !editing => form.select(-1),
+            KeyCode::Char('j') if !editing => form.select(1),
+            KeyCode::Left | KeyCode::Char('h') if !editing => form.change(-1),
+            KeyCode::Right | KeyCode::Char('l') if !editing => form.change(1),
+            KeyCode::Backspace if editing => {
+                form.server.pop();
+            }
+            KeyCode::Char(c) if editing => form.server.push(c),
+            _ => {}
+        }
+    }
+}
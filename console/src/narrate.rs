@@ -0,0 +1,179 @@
+//! Textual narration of the game, for screen readers.
+
+use std::io::Write;
+
+use crossterm::{queue, style};
+use curseofrust::{
+    grid::{HabitLand, Landscape, Tile},
+    snapshot::{Snapshot, Terrain, TileChange},
+    Player, Pos,
+};
+
+use crate::State;
+
+#[inline]
+pub(crate) fn land_name<W>(st: &State<W>, land: HabitLand) -> &'static str {
+    st.lang.tr(match land {
+        HabitLand::Grassland => "grassland",
+        HabitLand::Village => "village",
+        HabitLand::Town => "town",
+        HabitLand::Fortress => "fortress",
+        _ => "unknown land",
+    })
+}
+
+pub(crate) fn player_name<W>(st: &State<W>, player: Player) -> String {
+    if player == st.s.controlled {
+        st.lang.tr("you").to_owned()
+    } else {
+        st.lang.trf("player {0}", &[&player])
+    }
+}
+
+#[inline]
+pub(crate) fn say<W: Write>(st: &mut State<W>, line: &str) -> Result<(), std::io::Error> {
+    queue!(st.out, style::Print(line), style::Print("\r\n"))
+}
+
+/// Narrates noteworthy changes since the previous snapshot.
+pub(crate) fn narrate_changes<W: Write>(
+    st: &mut State<W>,
+    prev: &Snapshot,
+    changes: &[TileChange],
+) -> Result<(), std::io::Error> {
+    let controlled = st.s.controlled;
+    for &TileChange { pos, tile } in changes {
+        let Some(old) = prev.tile(pos) else {
+            continue;
+        };
+        let Pos(x, y) = pos;
+        let at = format!("{x},{y}");
+
+        let (Terrain::Habitable(old_land), Terrain::Habitable(land)) = (old.terrain, tile.terrain)
+        else {
+            continue;
+        };
+
+        if land > old_land {
+            let line = st.lang.trf(
+                "{0} built {1} at {2}",
+                &[&player_name(st, tile.owner), &land_name(st, land), &at],
+            );
+            say(st, &line)?;
+        } else if land < old_land {
+            let line = st
+                .lang
+                .trf("{0} at {1} burned down", &[&land_name(st, old_land), &at]);
+            say(st, &line)?;
+        }
+
+        if land == HabitLand::Grassland {
+            continue;
+        }
+
+        if old.owner != tile.owner {
+            if tile.owner == controlled {
+                let line = st
+                    .lang
+                    .trf("captured {0} at {1}", &[&land_name(st, land), &at]);
+                say(st, &line)?;
+            } else if old.owner == controlled {
+                let line = st.lang.trf(
+                    "lost {0} at {1} to {2}",
+                    &[&land_name(st, land), &at, &player_name(st, tile.owner)],
+                );
+                say(st, &line)?;
+            }
+        } else if tile.owner == controlled {
+            let enemies = |units: &[u16]| {
+                units
+                    .iter()
+                    .enumerate()
+                    .any(|(p, pop)| p != controlled.0 as usize && *pop > 0)
+            };
+            if enemies(&tile.units) && !enemies(&old.units) {
+                let line = st.lang.trf("under attack at {0}", &[&at]);
+                say(st, &line)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Reads the tile under the cursor.
+pub(crate) fn describe_cursor<W: Write>(st: &mut State<W>) -> Result<(), std::io::Error> {
+    let pos @ Pos(x, y) = st.ui.cursor;
+    let Some(tile) = st.s.grid.tile(pos) else {
+        return Ok(());
+    };
+
+    let lang = st.lang;
+    let mut line = format!("{x},{y}: ");
+    match tile {
+        Tile::Void => line.push_str(lang.tr("nothing")),
+        Tile::Mountain => line.push_str(lang.tr("mountain")),
+        Tile::Mine(owner) if owner.is_neutral() => line.push_str(lang.tr("mine")),
+        Tile::Mine(owner) => {
+            line.push_str(&lang.trf("{0} of {1}", &[&lang.tr("mine"), &player_name(st, *owner)]))
+        }
+        Tile::Habitable {
+            land,
+            units,
+            owner,
+            landscape,
+            road,
+            ..
+        } => {
+            if owner.is_neutral() {
+                line.push_str(land_name(st, *land));
+            } else {
+                line.push_str(&lang.trf(
+                    "{0} of {1}",
+                    &[&land_name(st, *land), &player_name(st, *owner)],
+                ));
+            }
+            match landscape {
+                Landscape::Forest => line.push_str(&format!(", {}", lang.tr("forest"))),
+                Landscape::Hill => line.push_str(&format!(", {}", lang.tr("hill"))),
+                _ => {}
+            }
+            if *road {
+                line.push_str(&format!(", {}", lang.tr("road")));
+            }
+            for (p, pop) in units.iter().enumerate().filter(|(_, pop)| **pop > 0) {
+                line.push_str(&format!(", {} {pop}", player_name(st, Player(p as u32))));
+            }
+        }
+        _ => line.push_str(lang.tr("unknown")),
+    }
+    if st
+        .s
+        .grid
+        .tiles_owned_by(st.s.controlled)
+        .any(|(_, t)| t.rally() == Some(pos))
+    {
+        line.push_str(", ");
+        line.push_str(lang.tr("rally point"));
+    }
+    if st.s.is_build_queued(st.s.controlled, pos) {
+        line.push_str(", ");
+        line.push_str(lang.tr("upgrade queued"));
+    }
+    match st
+        .s
+        .flag_grid(st.s.controlled)
+        .map_or(0, |fg| fg.level(pos))
+    {
+        0 => {}
+        1 => {
+            line.push_str(", ");
+            line.push_str(lang.tr("flagged"));
+        }
+        level => {
+            line.push_str(", ");
+            line.push_str(&lang.trf("{0} flags", &[&level]));
+        }
+    }
+
+    say(st, &line)
+}
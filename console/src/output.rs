@@ -1,17 +1,25 @@
 //! Output functions for the console.
 
-use std::io::Write;
+use std::{fmt::Display, io::Write};
 
 use crossterm::{
     cursor, queue,
     style::{self, Attribute, Color, ContentStyle, StyledContent},
     terminal::{self, ClearType},
 };
-use curseofrust::{state::UI, Grid, Player, Pos};
+use curseofrust::{
+    grid::{HabitLand, Landscape},
+    state::UI,
+    Grid, Player, Pos, Preset, Speed,
+};
+use curseofrust_cli_parser::ControlMode;
 
-use crate::State;
+use crate::{braille, graph, log, narrate, stats, State};
 
 const GRASS: &str = " - ";
+const FOREST: &str = " \" ";
+const HILL: &str = " ~ ";
+const ROAD: &str = "-+-";
 const MOUNTAIN: &str = "/\\^";
 const MINE: &str = "/$\\";
 const VILLAGE: &str = " n ";
@@ -20,34 +28,56 @@ const FORTRESS: &str = "W#W";
 
 const UNKNOWN: &str = "???";
 
-fn player_style(player: Player) -> ContentStyle {
-    ContentStyle {
-        foreground_color: Some(player_color(player)),
-        attributes: if player.is_neutral() {
-            Default::default()
-        } else {
-            style::Attribute::Bold.into()
-        },
-        ..Default::default()
+/// Soft buttons of the Termux layout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SoftButton {
+    Build,
+    Flag,
+    Pause,
+}
+
+const SOFT_BUTTONS: [(SoftButton, &str); 3] = [
+    (SoftButton::Build, "  BUILD  "),
+    (SoftButton::Flag, "  FLAG  "),
+    (SoftButton::Pause, "  PAUSE  "),
+];
+const SOFT_BUTTON_GAP: u16 = 2;
+
+/// Prints styled content.
+///
+/// Legacy Windows consoles without ANSI support can't print
+/// [`StyledContent`], so only the foreground color is applied there.
+pub(crate) fn print_styled<W: Write, D: Display>(
+    out: &mut W,
+    style: ContentStyle,
+    content: D,
+) -> Result<(), std::io::Error> {
+    #[cfg(windows)]
+    if !crossterm::ansi_support::supports_ansi() {
+        if let Some(color) = style.foreground_color {
+            queue!(out, style::SetForegroundColor(color))?;
+        }
+        if let Some(color) = style.background_color {
+            queue!(out, style::SetBackgroundColor(color))?;
+        }
+        return queue!(out, style::Print(content), style::ResetColor);
     }
+    queue!(
+        out,
+        style::PrintStyledContent(StyledContent::new(style, content))
+    )
 }
 
 #[inline]
-fn player_color(player: Player) -> Color {
-    match player {
-        Player::NEUTRAL => Color::Yellow,
-        Player(1) => Color::Green,
-        Player(2) => Color::Blue,
-        Player(3) => Color::Yellow,
-        Player(4) => Color::Red,
-        Player(5) => Color::Magenta,
-        Player(6) => Color::Cyan,
-        Player(7) => Color::DarkGrey,
-        _ => Color::Reset,
+/// Name of the preset shown in the status line.
+fn preset_name(preset: Preset) -> &'static str {
+    match preset {
+        Preset::Blitz => "BLITZ",
+        Preset::Marathon => "MARATHON",
+        _ => "CLASSIC",
     }
 }
 
-#[inline]
 fn pop_to_symbol(pop: u16) -> &'static str {
     match pop {
         0 => GRASS,
@@ -63,14 +93,98 @@ fn pop_to_symbol(pop: u16) -> &'static str {
     }
 }
 
+/// Terminal rows taken by a row of tiles.
+///
+/// The Termux layout doubles them for larger touch targets.
+#[inline]
+pub(crate) fn row_height<W>(st: &State<W>) -> u16 {
+    if st.control == ControlMode::Termux {
+        2
+    } else {
+        1
+    }
+}
+
+/// Whether the tile at the position is drawn whole on a terminal of
+/// the given size.
+pub(crate) fn on_screen<W>(st: &State<W>, Pos(x, y): Pos, (cols, rows): (u16, u16)) -> bool {
+    let column = x * 4 + y * 2 + 1 - st.ui.xskip as i32 * 4;
+    let row = (y + 1) * row_height(st) as i32;
+    column >= 0 && column + 4 <= cols as i32 && row < rows as i32
+}
+
+#[inline]
+fn soft_button_row<W>(st: &State<W>) -> u16 {
+    st.s.grid.height() as u16 * row_height(st) + 3
+}
+
+/// Gets the soft button at the given terminal position.
+pub(crate) fn soft_button_at<W>(st: &State<W>, x: u16, y: u16) -> Option<SoftButton> {
+    let row = soft_button_row(st);
+    if st.control != ControlMode::Termux || !(row..row + 2).contains(&y) {
+        return None;
+    }
+    let mut start = 0;
+    for (button, label) in SOFT_BUTTONS {
+        let end = start + label.len() as u16;
+        if (start..end).contains(&x) {
+            return Some(button);
+        }
+        start = end + SOFT_BUTTON_GAP;
+    }
+    None
+}
+
+fn draw_soft_buttons<W: Write>(st: &mut State<W>) -> Result<(), std::io::Error> {
+    let row = soft_button_row(st);
+    let style = ContentStyle {
+        attributes: Attribute::Reverse.into(),
+        ..Default::default()
+    };
+    for line in row..row + 2 {
+        let mut start = 0;
+        queue!(
+            st.out,
+            cursor::MoveTo(0, line),
+            terminal::Clear(ClearType::CurrentLine)
+        )?;
+        for (_, label) in SOFT_BUTTONS {
+            queue!(st.out, cursor::MoveTo(start, line))?;
+            // Labels sit on the first line, the second one pads the target.
+            if line == row {
+                print_styled(&mut st.out, style, label)?;
+            } else {
+                print_styled(&mut st.out, style, " ".repeat(label.len()))?;
+            }
+            start += label.len() as u16 + SOFT_BUTTON_GAP;
+        }
+    }
+    Ok(())
+}
+
+/// Shows a notice below the status line, or narrates it.
+pub(crate) fn draw_notice<W: Write>(st: &mut State<W>, notice: &str) -> Result<(), std::io::Error> {
+    if st.narrate {
+        return narrate::say(st, notice);
+    }
+    st.log.push(notice.to_owned());
+    let row = st.s.grid.height() as u16 * row_height(st) + 2;
+    queue!(
+        st.out,
+        cursor::MoveTo(0, row),
+        terminal::Clear(ClearType::CurrentLine),
+        style::Print(notice)
+    )
+}
+
 #[inline(always)]
 pub(crate) fn draw_all_grid<W: Write>(st: &mut State<W>) -> Result<(), std::io::Error> {
     draw_grid::<W, [_; 0]>(st, None)
 }
 
-pub(crate) fn rev_pos(x: u16, y: u16, ui: &UI, grid: &Grid) -> Option<Pos> {
+pub(crate) fn rev_pos(x: u16, y: u16, rows: u16, ui: &UI, grid: &Grid) -> Option<Pos> {
     let x = x as i32;
-    let y = y as i32 - 1;
+    let y = (y as i32 - 1).div_euclid(rows as i32);
     let xskip = ui.xskip as i32;
     let x1 = (x + 4 * xskip - 2 * y - 1) / 4;
 
@@ -88,6 +202,20 @@ pub(crate) fn draw_grid<W: Write, I>(
 where
     I: IntoIterator<Item = Pos>,
 {
+    // Narration replaces the grid.
+    if st.narrate {
+        return Ok(());
+    }
+    if st.log.is_open() {
+        return log::draw_view(st);
+    }
+    if st.s.show_timeline {
+        return graph::draw_view(st);
+    }
+    if st.braille {
+        return braille::draw_view(st);
+    }
+
     let h = st.s.grid.height();
     let w = st.s.grid.width();
     let mut tiles = tiles.map(|poss| {
@@ -101,18 +229,22 @@ where
         &mut tiles_all
     };
 
+    let rows = row_height(st);
+    let rally_targets: Vec<Pos> =
+        st.s.grid
+            .tiles_owned_by(st.s.controlled)
+            .filter_map(|(_, t)| t.rally())
+            .collect();
     for Pos(x, y) in iter {
-        queue!(
-            st.out,
-            cursor::MoveTo(
-                ((x * 4 + y * 2 + 1) as i16 - st.ui.xskip as i16 * 4).max(0) as u16,
-                y as u16 + 1
-            )
-        )?;
         let pos = Pos(x, y);
         let Some(tile) = st.s.grid.tile(pos) else {
             break;
         };
+        // Tiles scrolled out to the left.
+        let column = (x * 4 + y * 2 + 1) as i16 - st.ui.xskip as i16 * 4;
+        if column < 0 {
+            continue;
+        }
         macro_rules! cursor {
             () => {
                 let l_sym = if pos == st.ui.cursor {
@@ -122,114 +254,248 @@ where
                 } else {
                     ' '
                 };
-                queue!(
-                    st.out,
-                    style::PrintStyledContent(StyledContent::new(
-                        ContentStyle {
-                            attributes: style::Attribute::Bold.into(),
-                            ..Default::default()
-                        },
-                        l_sym
-                    ))
+                print_styled(
+                    &mut st.out,
+                    ContentStyle {
+                        attributes: style::Attribute::Bold.into(),
+                        ..Default::default()
+                    },
+                    l_sym,
                 )?;
             };
         }
-        match tile {
-            curseofrust::grid::Tile::Void => {
-                cursor!();
-                queue!(st.out, style::Print("   "))?;
-            }
-            curseofrust::grid::Tile::Mountain => {
-                cursor!();
-                queue! {
-                    st.out,
-                    style::PrintStyledContent(StyledContent::new(
+        for line in 0..rows {
+            queue!(
+                st.out,
+                cursor::MoveTo(column as u16, y as u16 * rows + line + 1)
+            )?;
+            match tile {
+                curseofrust::grid::Tile::Void => {
+                    cursor!();
+                    queue!(st.out, style::Print("   "))?;
+                }
+                curseofrust::grid::Tile::Mountain => {
+                    cursor!();
+                    print_styled(
+                        &mut st.out,
                         ContentStyle {
-                            foreground_color: Some(Color::Green),
+                            foreground_color: Some(st.theme.tint(Color::Green)),
                             ..Default::default()
                         },
                         MOUNTAIN,
-                    ))
-                }?;
-            }
-            curseofrust::grid::Tile::Mine(owner) => {
-                cursor!();
-                queue!(
-                    st.out,
-                    style::PrintStyledContent(StyledContent::new(
+                    )?;
+                }
+                curseofrust::grid::Tile::Mine(owner) => {
+                    cursor!();
+                    let green = ContentStyle {
+                        foreground_color: Some(st.theme.tint(Color::Green)),
+                        ..Default::default()
+                    };
+                    print_styled(
+                        &mut st.out,
+                        green,
+                        st.theme.mark(*owner).unwrap_or(&MINE[0..1]),
+                    )?;
+                    print_styled(
+                        &mut st.out,
                         ContentStyle {
-                            foreground_color: Some(Color::Green),
-                            ..Default::default()
-                        },
-                        &MINE[0..1],
-                    )),
-                    style::PrintStyledContent(StyledContent::new(
-                        ContentStyle {
-                            foreground_color: Some(Color::Yellow),
-                            ..player_style(*owner)
+                            foreground_color: Some(st.theme.tint(Color::Yellow)),
+                            ..st.theme.style(*owner)
                         },
                         &MINE[1..2],
-                    )),
-                    style::PrintStyledContent(StyledContent::new(
-                        ContentStyle {
-                            foreground_color: Some(Color::Green),
-                            ..Default::default()
-                        },
-                        &MINE[2..3],
-                    )),
-                )?;
-            }
-            curseofrust::grid::Tile::Habitable { land, units, owner } => {
-                cursor!();
-                let symbol = match land {
-                    curseofrust::grid::HabitLand::Grassland => pop_to_symbol(units.iter().sum()),
-                    curseofrust::grid::HabitLand::Village => VILLAGE,
-                    curseofrust::grid::HabitLand::Town => TOWN,
-                    curseofrust::grid::HabitLand::Fortress => FORTRESS,
-                    _ => UNKNOWN,
-                };
-                let style = player_style(*owner);
-                let l = if let Some(p) =
-                    st.s.fgs
+                    )?;
+                    print_styled(&mut st.out, green, &MINE[2..3])?;
+                }
+                curseofrust::grid::Tile::Habitable {
+                    land,
+                    units,
+                    owner,
+                    landscape,
+                    road,
+                    ..
+                } => {
+                    cursor!();
+                    let symbol = match land {
+                        curseofrust::grid::HabitLand::Grassland => {
+                            match (units.iter().sum(), landscape) {
+                                (0, _) if *road => ROAD,
+                                (0, Landscape::Forest) => FOREST,
+                                (0, Landscape::Hill) => HILL,
+                                (pop, _) => pop_to_symbol(pop),
+                            }
+                        }
+                        curseofrust::grid::HabitLand::Village => VILLAGE,
+                        curseofrust::grid::HabitLand::Town => TOWN,
+                        curseofrust::grid::HabitLand::Fortress => FORTRESS,
+                        _ => UNKNOWN,
+                    };
+                    let mut style = st.theme.style(*owner);
+                    // Grayscale ramp of the 256-color palette, from 232 (black) to 255.
+                    let overlay = st
+                        .call_overlay
+                        .then(|| st.s.flag_grid(st.s.controlled).ok()?.call_intensity(pos))
+                        .flatten()
+                        .filter(|i| *i > 0.0)
+                        .map(|i| Color::AnsiValue(233 + (i * 9.0).round() as u8));
+                    let shade = match overlay {
+                        Some(_) => overlay,
+                        None => st.theme.shade(*owner, units.iter().sum()).map(|(bg, fg)| {
+                            style.foreground_color = Some(fg);
+                            bg
+                        }),
+                    };
+                    style.background_color = shade;
+                    if st.ui.rally_from == Some(pos) {
+                        style.attributes.set(Attribute::Reverse);
+                    }
+                    if rally_targets.contains(&pos) {
+                        style.attributes.set(Attribute::Underlined);
+                    }
+                    if *owner == st.s.controlled && st.s.is_endangered(pos) {
+                        style.attributes.set(Attribute::SlowBlink);
+                    }
+                    let l = if let Some(p) = st
+                        .s
+                        .fgs
                         .iter()
                         .enumerate()
                         .find(|(p, fg)| fg.is_flagged(pos) && Player(*p as u32) != st.s.controlled)
                         .map(|(p, _)| Player(p as u32))
-                {
-                    style::PrintStyledContent(StyledContent::new(player_style(p), "x"))
-                } else {
-                    style::PrintStyledContent(StyledContent::new(style, &symbol[0..1]))
-                };
-                let m = style::PrintStyledContent(StyledContent::new(style, &symbol[1..2]));
-                let r = if st.s.fgs[st.s.controlled.0 as usize].is_flagged(pos) {
-                    style::PrintStyledContent(StyledContent::new(Default::default(), "P"))
-                } else {
-                    style::PrintStyledContent(StyledContent::new(style, &symbol[2..3]))
-                };
+                    {
+                        (
+                            ContentStyle {
+                                background_color: shade,
+                                ..st.theme.style(p)
+                            },
+                            "x",
+                        )
+                    } else if st.s.is_build_queued(st.s.controlled, pos) {
+                        (style, "^")
+                    } else {
+                        let mark = st.theme.mark(*owner);
+                        (style, mark.unwrap_or(&symbol[0..1]))
+                    };
+                    // One `P` per stacked flag, from the right.
+                    let level =
+                        st.s.flag_grid(st.s.controlled)
+                            .map_or(0, |fg| fg.level(pos));
+                    let flag = ContentStyle {
+                        background_color: shade,
+                        ..Default::default()
+                    };
+                    let l = if level >= 3 { (flag, "P") } else { l };
+                    let m = if level >= 2 {
+                        (flag, "P")
+                    } else {
+                        (style, &symbol[1..2])
+                    };
+                    let r = if level >= 1 {
+                        (flag, "P")
+                    } else {
+                        (style, &symbol[2..3])
+                    };
 
-                queue!(st.out, l, m, r)?;
-            }
-            _ => {
-                cursor!();
-                queue!(st.out, style::Print(UNKNOWN))?;
+                    for (style, content) in [l, m, r] {
+                        print_styled(&mut st.out, style, content)?;
+                    }
+                }
+                _ => {
+                    cursor!();
+                    queue!(st.out, style::Print(UNKNOWN))?;
+                }
             }
         }
     }
 
+    if st.stats {
+        stats::draw_table(st)?;
+    }
+
     queue!(
         st.out,
-        cursor::MoveTo(0, st.s.grid.height() as u16 + 1),
+        cursor::MoveTo(0, st.s.grid.height() as u16 * rows + 1),
         terminal::Clear(ClearType::CurrentLine),
-        style::PrintStyledContent(StyledContent::new(
+    )?;
+    print_styled(
+        &mut st.out,
+        ContentStyle {
+            foreground_color: Some(st.theme.color(st.s.controlled)),
+            attributes: Attribute::Reverse.into(),
+            ..Default::default()
+        },
+        format!(
+            "  {}  ",
+            st.s.country(st.s.controlled).map_or(0, |c| c.gold)
+        ),
+    )?;
+    queue!(st.out, style::Print("    "))?;
+
+    let [village, town, fortress] = [HabitLand::Village, HabitLand::Town, HabitLand::Fortress]
+        .map(|land| st.s.grid.price(st.s.controlled, land, &st.s.balance));
+    queue!(
+        st.out,
+        style::Print(
+            st.lang
+                .trf("prices: {0} {1} {2}", &[&village, &town, &fortress])
+        ),
+        style::Print("    ")
+    )?;
+
+    if st.s.assist.is_active() {
+        queue!(
+            st.out,
+            style::Print(st.lang.tr("ASSIST")),
+            style::Print("    ")
+        )?;
+    }
+
+    if let Some(preset) = st.s.preset {
+        queue!(
+            st.out,
+            style::Print(st.lang.tr(preset_name(preset))),
+            style::Print("    ")
+        )?;
+    }
+
+    if let Some(left) = st.time_left {
+        // Rounded up, so that 0:00 means the time is up.
+        let secs = left.as_secs() + (left.subsec_nanos() > 0) as u64;
+        let clock = if secs >= 3600 {
+            format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+        } else {
+            format!("{}:{:02}", secs / 60, secs % 60)
+        };
+        queue!(st.out, style::Print(clock), style::Print("    "))?;
+    }
+
+    if let Some(secs) = st.countdown {
+        let countdown = st.lang.trf("resuming in {0}", &[&secs]);
+        queue!(st.out, style::Print(countdown), style::Print("    "))?;
+    } else if st.s.speed == Speed::Pause {
+        queue!(
+            st.out,
+            style::Print(st.lang.tr("PAUSED")),
+            style::Print("    ")
+        )?;
+    }
+
+    if st
+        .s
+        .grid
+        .cities(st.s.controlled)
+        .any(|(pos, _)| st.s.is_endangered(pos))
+    {
+        print_styled(
+            &mut st.out,
             ContentStyle {
-                foreground_color: Some(player_color(st.s.controlled)),
-                attributes: Attribute::Reverse.into(),
+                foreground_color: Some(Color::Red),
+                attributes: Attribute::Bold.into(),
                 ..Default::default()
             },
-            format!("  {}  ", st.s.countries[st.s.controlled.0 as usize].gold)
-        )),
-        style::Print("    ")
-    )?;
+            st.lang.tr("UNDER ATTACK"),
+        )?;
+        queue!(st.out, style::Print("  "))?;
+    }
 
     if let Some(tile) = st.s.grid.tile(st.ui.cursor) {
         for (pop, coun) in tile
@@ -239,13 +505,354 @@ where
             .zip(&st.s.countries)
             .filter(|(pop, _)| *pop > 0)
         {
-            queue!(
-                st.out,
-                style::Print("  "),
-                style::PrintStyledContent(StyledContent::new(player_style(coun.player), pop))
-            )?;
+            queue!(st.out, style::Print("  "))?;
+            print_styled(&mut st.out, st.theme.style(coun.player), pop)?;
         }
     }
 
+    if st.control == ControlMode::Termux {
+        draw_soft_buttons(st)?;
+    }
+
+    // Recent events, below the soft buttons if any.
+    let row = soft_button_row(st)
+        + if st.control == ControlMode::Termux {
+            2
+        } else {
+            0
+        };
+    for i in 0..log::PANEL_ENTRIES {
+        queue!(
+            st.out,
+            cursor::MoveTo(0, row + i as u16),
+            terminal::Clear(ClearType::CurrentLine)
+        )?;
+    }
+    for (i, entry) in st.log.recent(log::PANEL_ENTRIES).enumerate() {
+        queue!(
+            st.out,
+            cursor::MoveTo(0, row + i as u16),
+            style::Print(entry)
+        )?;
+    }
+
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use curseofrust::{
+        grid::Tile,
+        state::{BasicOpts, State as GameState},
+        FLAG_POWER, MAX_PLAYERS,
+    };
+    use curseofrust_cli_parser::{Lang, Palette};
+
+    use crate::theme::Theme;
+
+    use super::*;
+
+    /// Cell of the screen a frame is drawn on.
+    #[derive(Clone, Copy)]
+    struct Cell {
+        ch: char,
+        fg: Option<u8>,
+        attr: char,
+    }
+
+    const BLANK: Cell = Cell {
+        ch: ' ',
+        fg: None,
+        attr: ' ',
+    };
+
+    /// Plays the escape sequences written by the console onto a
+    /// screen, and prints its text, the foreground colors of its
+    /// cells, and their attributes, one after another.
+    ///
+    /// Colors are indices of the 256-color palette in hex, `*` for
+    /// others. Attributes are `R`everse, `U`nderlined, blin`K` and
+    /// `B`old, the first that applies.
+    fn screen(bytes: &[u8]) -> String {
+        let text = std::str::from_utf8(bytes).expect("the frame should be UTF-8");
+        let mut rows: Vec<Vec<Cell>> = vec![];
+        let (mut row, mut col) = (0, 0);
+        let mut fg = None;
+        let mut attrs: Vec<char> = vec![];
+        let mut chars = text.chars();
+        while let Some(ch) = chars.next() {
+            if ch != '\x1b' {
+                if rows.len() <= row {
+                    rows.resize(row + 1, vec![]);
+                }
+                let line = &mut rows[row];
+                if line.len() <= col {
+                    line.resize(col + 1, BLANK);
+                }
+                let attr = ['R', 'U', 'K', 'B']
+                    .into_iter()
+                    .find(|a| attrs.contains(a))
+                    .unwrap_or(' ');
+                line[col] = Cell { ch, fg, attr };
+                col += 1;
+                continue;
+            }
+            assert_eq!(chars.next(), Some('['), "only CSI sequences are expected");
+            let mut params = String::new();
+            let end = chars
+                .by_ref()
+                .find(|c| {
+                    let end = c.is_ascii_alphabetic();
+                    if !end {
+                        params.push(*c);
+                    }
+                    end
+                })
+                .expect("the sequence should end");
+            let mut params = params.split(';').map(|p| p.parse::<usize>().unwrap_or(0));
+            match end {
+                'H' => {
+                    row = params.next().unwrap_or(1) - 1;
+                    col = params.next().unwrap_or(1) - 1;
+                }
+                'K' => {
+                    if let Some(line) = rows.get_mut(row) {
+                        // Up to the end of the line, or all of it.
+                        match params.next() {
+                            Some(2) => line.clear(),
+                            _ => line.truncate(col),
+                        }
+                    }
+                }
+                'm' => {
+                    while let Some(p) = params.next() {
+                        match p {
+                            0 => {
+                                fg = None;
+                                attrs.clear();
+                            }
+                            1 => attrs.push('B'),
+                            4 => attrs.push('U'),
+                            5 => attrs.push('K'),
+                            7 => attrs.push('R'),
+                            38 | 48 => {
+                                let color = match params.next() {
+                                    Some(5) => params.next().map(|i| i as u8),
+                                    Some(2) => {
+                                        params.by_ref().take(3).for_each(drop);
+                                        Some(u8::MAX)
+                                    }
+                                    _ => None,
+                                };
+                                if p == 38 {
+                                    fg = color;
+                                }
+                            }
+                            39 => fg = None,
+                            _ => {}
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let layer = |cell: fn(&Cell) -> char| {
+            rows.iter()
+                .map(|line| {
+                    let line: String = line.iter().map(cell).collect();
+                    line.trim_end().to_owned() + "\n"
+                })
+                .collect::<String>()
+        };
+        let mut frame = layer(|c| c.ch);
+        frame += "--- colors\n";
+        frame += &layer(|c| match c.fg {
+            None => ' ',
+            Some(i) if i < 16 => char::from_digit(i as u32, 16).unwrap(),
+            Some(_) => '*',
+        });
+        frame += "--- attributes\n";
+        frame += &layer(|c| c.attr);
+        frame
+    }
+
+    /// Tiles of the fixture, a row per line: `-` grassland, `"`
+    /// forest, `~` hill, `#` mountain, `$` mine, digits villages of
+    /// that player, `a` to `g` towns and `A` to `G` fortresses of
+    /// players 1 to 7. Blanks are void.
+    const MAP: [&str; 9] = [
+        "   --#--$--\"-",
+        "  -1---~---2-",
+        "  --\"-#-----~",
+        " -$--~--a--\"-",
+        " ---#--$---#-",
+        "-~--B--\"---3-",
+        "--\"---~---$--",
+        "-3---#--2--- ",
+        "--$----\"---  ",
+    ];
+
+    /// Population of every city of the fixture.
+    const CITY_POP: u16 = 40;
+
+    /// Game on the hand-placed map of [`MAP`], so that frames hang
+    /// neither on map generation nor on the random number generator.
+    fn game() -> GameState {
+        let mut b_opt = BasicOpts::default();
+        b_opt.seed = 1;
+        b_opt.width = MAP[0].len() as u32;
+        b_opt.height = MAP.len() as u32;
+        let mut s = GameState::new(b_opt).unwrap();
+        for (y, row) in MAP.iter().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                let habitable = |land, player: u32, landscape| Tile::Habitable {
+                    land,
+                    units: std::array::from_fn(|p| match land {
+                        HabitLand::Grassland => 0,
+                        _ if p == player as usize => CITY_POP,
+                        _ => 0,
+                    }),
+                    owner: Player(player),
+                    rally: None,
+                    landscape,
+                    road: false,
+                };
+                let city =
+                    |land, first| habitable(land, c as u32 - first as u32 + 1, Default::default());
+                *s.grid.tile_mut(Pos(x as i32, y as i32)).unwrap() = match c {
+                    '-' => habitable(HabitLand::Grassland, 0, Landscape::Plain),
+                    '"' => habitable(HabitLand::Grassland, 0, Landscape::Forest),
+                    '~' => habitable(HabitLand::Grassland, 0, Landscape::Hill),
+                    '#' => Tile::Mountain,
+                    '$' => Tile::Mine(Player::NEUTRAL),
+                    '1'..='7' => city(HabitLand::Village, '1'),
+                    'a'..='g' => city(HabitLand::Town, 'a'),
+                    'A'..='G' => city(HabitLand::Fortress, 'A'),
+                    _ => Tile::Void,
+                };
+            }
+        }
+        for country in &mut s.countries {
+            country.gold = 0;
+            country.eliminated = false;
+        }
+        s
+    }
+
+    /// Spreads the population of every city of the game over the
+    /// tiles up to two steps away, as a game some way in would.
+    fn populate(s: &mut GameState) {
+        let cities: Vec<(Pos, usize)> = (1..MAX_PLAYERS)
+            .flat_map(|p| {
+                let cities: Vec<_> = s
+                    .grid
+                    .cities(Player(p as u32))
+                    .map(|(pos, _)| pos)
+                    .collect();
+                cities.into_iter().map(move |pos| (pos, p))
+            })
+            .collect();
+        for y in 0..MAP.len() as i32 {
+            for x in 0..MAP[0].len() as i32 {
+                let pos = Pos(x, y);
+                let Some(Tile::Habitable { units, owner, .. }) = s.grid.tile_mut(pos) else {
+                    continue;
+                };
+                for &(city, p) in &cities {
+                    let dist = city.hex_distance(pos);
+                    if dist <= 2 {
+                        units[p] += (3 - dist as u16) * 10;
+                    }
+                }
+                let strongest = (0..MAX_PLAYERS).max_by_key(|&p| (units[p], MAX_PLAYERS - p));
+                *owner = match strongest {
+                    Some(p) if units[p] > 0 => Player(p as u32),
+                    _ => Player::NEUTRAL,
+                };
+            }
+        }
+    }
+
+    fn state(control: ControlMode) -> State<Vec<u8>> {
+        let s = game();
+        State {
+            ui: UI::new(&s),
+            s,
+            control,
+            narrate: false,
+            call_overlay: false,
+            braille: false,
+            stats: false,
+            log: Default::default(),
+            alert: None,
+            besieged: vec![],
+            time_left: None,
+            turns: None,
+            turn_ended: false,
+            winner: None,
+            countdown: None,
+            lang: Lang::En,
+            theme: Default::default(),
+            swipe: None,
+            last_flag: None::<(Pos, Instant)>,
+            out: vec![],
+        }
+    }
+
+    /// Compares the frame drawn with the golden one of the given name,
+    /// or writes it as the golden one if `CURSEOFRUST_BLESS` is set.
+    fn assert_golden(name: &str, st: &mut State<Vec<u8>>) {
+        draw_all_grid(st).unwrap();
+        let frame = screen(&st.out);
+        let path = format!("{}/tests/golden/{name}.txt", env!("CARGO_MANIFEST_DIR"));
+        if std::env::var_os("CURSEOFRUST_BLESS").is_some() {
+            std::fs::write(&path, &frame).unwrap();
+            return;
+        }
+        let golden = std::fs::read_to_string(&path).unwrap_or_default();
+        assert!(
+            frame == golden,
+            "frame differs from {path}, set CURSEOFRUST_BLESS to update it:\n{frame}"
+        );
+    }
+
+    #[test]
+    fn draws_new_game() {
+        assert_golden("new_game", &mut state(ControlMode::Keyboard));
+    }
+
+    #[test]
+    fn draws_flags_on_termux() {
+        let mut st = state(ControlMode::Termux);
+        let controlled = st.s.controlled;
+        let cities: Vec<Pos> = st.s.grid.cities(controlled).map(|(pos, _)| pos).collect();
+        let (enemy, enemy_city) = (2..MAX_PLAYERS as u32)
+            .map(Player)
+            .find_map(|p| Some((p, st.s.grid.cities(p).next()?.0)))
+            .expect("the game should have another player");
+        st.s.fgs[controlled.0 as usize].raise(&st.s.grid, cities[0], 3 * FLAG_POWER);
+        st.s.fgs[enemy.0 as usize].raise(&st.s.grid, cities[0], FLAG_POWER);
+        st.s.fgs[controlled.0 as usize].raise(&st.s.grid, enemy_city, FLAG_POWER);
+        st.s.speed = Speed::Pause;
+        assert_golden("flags_termux", &mut st);
+    }
+
+    #[test]
+    fn draws_patterns_without_colors() {
+        let mut st = state(ControlMode::Keyboard);
+        st.theme = Theme::new(Palette::Patterns, false);
+        populate(&mut st.s);
+        assert_golden("patterns", &mut st);
+    }
+
+    #[test]
+    fn draws_braille_overview() {
+        let mut st = state(ControlMode::Keyboard);
+        st.braille = true;
+        populate(&mut st.s);
+        assert_golden("braille", &mut st);
+    }
+}
@@ -0,0 +1,147 @@
+//! Headless simulation of AI-only games, for balancing strategies.
+
+use std::io::Write;
+
+use curseofrust::{
+    state::{BasicOpts, State},
+    Player, Strategy, MAX_PLAYERS,
+};
+
+use crate::DirectBoxedError;
+
+/// Games still running after this many steps are draws.
+const MAX_STEPS: u64 = 200_000;
+
+const STRATEGIES: [Strategy; 8] = [
+    Strategy::AggrGreedy,
+    Strategy::OneGreedy,
+    Strategy::PersistentGreedy,
+    Strategy::Opportunist,
+    Strategy::Noble,
+    Strategy::Midas,
+    Strategy::Turtle,
+    Strategy::Swarm,
+];
+
+#[inline]
+fn strategy_name(strategy: Strategy) -> &'static str {
+    match strategy {
+        Strategy::AggrGreedy => "aggr_greedy",
+        Strategy::OneGreedy => "one_greedy",
+        Strategy::PersistentGreedy => "persistent_greedy",
+        Strategy::Opportunist => "opportunist",
+        Strategy::Noble => "noble",
+        Strategy::Midas => "midas",
+        Strategy::Turtle => "turtle",
+        Strategy::Swarm => "swarm",
+        _ => "unknown",
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct Record {
+    games: u64,
+    wins: u64,
+    /// Games nobody won.
+    draws: u64,
+    /// Total steps of the games played.
+    steps: u64,
+}
+
+impl Record {
+    /// Counts a game of the given length once, won or not.
+    #[inline]
+    fn add(&mut self, won: bool, draw: bool, steps: u64) {
+        self.games += 1;
+        self.wins += won as u64;
+        self.draws += draw as u64;
+        self.steps += steps;
+    }
+
+    fn write_csv(&self, out: &mut impl Write, name: &str) -> std::io::Result<()> {
+        let games = self.games.max(1) as f64;
+        writeln!(
+            out,
+            "{},{},{},{},{:.3},{:.0}",
+            name,
+            self.games,
+            self.wins,
+            self.draws,
+            self.wins as f64 / games,
+            self.steps as f64 / games
+        )
+    }
+}
+
+/// Plays a game between kings of shuffled strategies to the end.
+///
+/// Returns the strategies of the players, the winning player
+/// and the number of steps.
+fn play(
+    b_opt: BasicOpts,
+) -> Result<([Strategy; MAX_PLAYERS], Option<Player>, u64), DirectBoxedError> {
+    let mut st = State::new(b_opt)?;
+
+    let mut strategies = [Strategy::None; MAX_PLAYERS];
+    let mut pool = STRATEGIES;
+    fastrand::shuffle(&mut pool);
+    let mut pool = pool.into_iter().cycle();
+    for (p, slot) in strategies.iter_mut().enumerate().skip(1) {
+        let player = Player(p as u32);
+        let alive = st.grid.tiles().any(|(_, t)| t.units()[p] > 0);
+        st.take_from_king(player);
+        if alive {
+            let strategy = pool.next().expect("the pool should be infinite");
+            *slot = strategy;
+            st.hand_to_king(player, strategy);
+        }
+    }
+
+    let mut steps = 0;
+    let winner = loop {
+        st.kings_move();
+        st.simulate();
+        steps += 1;
+        if let Some(winner) = st.winner() {
+            break Some(winner);
+        }
+        if steps >= MAX_STEPS {
+            break None;
+        }
+    };
+    Ok((strategies, winner, steps))
+}
+
+/// Simulates the given number of games and prints per-strategy
+/// results as CSV.
+///
+/// A game counts once for each strategy playing it. The last row,
+/// `all`, counts every game once, won by any strategy or drawn.
+pub(crate) fn run(
+    mut b_opt: BasicOpts,
+    batch: usize,
+    out: &mut impl Write,
+) -> Result<(), DirectBoxedError> {
+    let mut records = [Record::default(); STRATEGIES.len()];
+    let mut all = Record::default();
+
+    for _ in 0..batch {
+        let (strategies, winner, steps) = play(b_opt.clone())?;
+        b_opt.seed = fastrand::u64(..);
+
+        let won = winner.map(|p| strategies[p.0 as usize]);
+        for (i, &strategy) in STRATEGIES.iter().enumerate() {
+            if strategies.contains(&strategy) {
+                records[i].add(won == Some(strategy), won.is_none(), steps);
+            }
+        }
+        all.add(won.is_some(), won.is_none(), steps);
+    }
+
+    writeln!(out, "strategy,games,wins,draws,win_rate,avg_length")?;
+    for (strategy, record) in STRATEGIES.into_iter().zip(records) {
+        record.write_csv(out, strategy_name(strategy))?;
+    }
+    all.write_csv(out, "all")?;
+    Ok(())
+}
@@ -0,0 +1,90 @@
+//! Table of statistics of all players, drawn over the map.
+
+use std::io::Write;
+
+use crossterm::{
+    cursor, queue,
+    style::{self, Attribute, ContentStyle, StyledContent},
+};
+use curseofrust::{Player, MAX_PLAYERS};
+
+use crate::{narrate::player_name, output, State};
+
+/// Width of the name column, in terminal cells.
+const NAME_WIDTH: usize = 12;
+/// Widths of the number columns, in terminal cells.
+const WIDTHS: [usize; 6] = [7, 6, 6, 6, 6, 8];
+
+/// Terminal cells taken by the given text, counting
+/// non-ASCII characters as double-width.
+fn cells(s: &str) -> usize {
+    s.chars().map(|c| if c.is_ascii() { 1 } else { 2 }).sum()
+}
+
+/// Pads the text with spaces to the given number of cells.
+fn pad(s: &str, width: usize) -> String {
+    format!("{}{}", s, " ".repeat(width.saturating_sub(cells(s))))
+}
+
+/// Right-aligns the columns to [`WIDTHS`].
+fn columns(values: [&str; 6]) -> String {
+    let mut line = String::new();
+    for (value, width) in values.into_iter().zip(WIDTHS) {
+        line.push_str(&" ".repeat(width.saturating_sub(cells(value))));
+        line.push_str(value);
+    }
+    line.push(' ');
+    line
+}
+
+/// Draws the table over the top left of the map.
+///
+/// Gold of other players is only shown once the game is over.
+pub(crate) fn draw_table<W: Write>(st: &mut State<W>) -> Result<(), std::io::Error> {
+    let lang = st.lang;
+    let header = format!(
+        " {}{}",
+        pad(lang.tr("player"), NAME_WIDTH),
+        columns(["pop", "vil", "town", "fort", "mines", "gold"].map(|s| lang.tr(s))),
+    );
+    queue!(
+        st.out,
+        cursor::MoveTo(0, 1),
+        style::PrintStyledContent(StyledContent::new(
+            ContentStyle {
+                attributes: Attribute::Reverse.into(),
+                ..Default::default()
+            },
+            header,
+        )),
+    )?;
+
+    let over = st.s.winner().is_some() || st.winner.is_some();
+    for (row, p) in (1..MAX_PLAYERS).enumerate() {
+        let player = Player(p as u32);
+        let stats = st.s.stats(player);
+        let gold = if player == st.s.controlled || over {
+            stats.gold.to_string()
+        } else {
+            "?".to_owned()
+        };
+        let [villages, towns, fortresses] = stats.cities.map(|n| n.to_string());
+        let numbers = columns([
+            &stats.population.to_string(),
+            &villages,
+            &towns,
+            &fortresses,
+            &stats.mines.to_string(),
+            &gold,
+        ]);
+        let mut style = st.theme.style(player);
+        if st.s.is_eliminated(player) {
+            style.attributes.set(Attribute::CrossedOut);
+        }
+        queue!(st.out, cursor::MoveTo(0, row as u16 + 2), style::Print(' '))?;
+        let name = pad(&player_name(st, player), NAME_WIDTH);
+        output::print_styled(&mut st.out, style, name)?;
+        queue!(st.out, style::Print(numbers))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,79 @@
+//! Setup of the terminal, restored on exit, errors and panics.
+//!
+//! The modes entered are recorded, so that restoring is done
+//! once, whichever of the [`Guard`], the panic hook or the
+//! normal exit comes first.
+
+use std::{
+    io,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use crossterm::{cursor, event, execute, terminal};
+
+static RAW: AtomicBool = AtomicBool::new(false);
+static ALTERNATE: AtomicBool = AtomicBool::new(false);
+static MOUSE: AtomicBool = AtomicBool::new(false);
+
+/// Restores the terminal when dropped, including when an error
+/// returns early or a panic unwinds.
+pub(crate) struct Guard;
+
+impl Drop for Guard {
+    #[inline]
+    fn drop(&mut self) {
+        restore();
+    }
+}
+
+/// Enters raw mode, the alternate screen with the cursor hidden
+/// if `alternate`, and captures the mouse if `mouse`.
+pub(crate) fn enter(alternate: bool, mouse: bool) -> io::Result<()> {
+    let mut out = io::stdout();
+    if alternate {
+        execute!(out, terminal::EnterAlternateScreen, cursor::Hide)?;
+        ALTERNATE.store(true, Ordering::Relaxed);
+    }
+    terminal::enable_raw_mode()?;
+    RAW.store(true, Ordering::Relaxed);
+    if mouse {
+        execute!(out, event::EnableMouseCapture)?;
+        MOUSE.store(true, Ordering::Relaxed);
+    }
+    Ok(())
+}
+
+/// Suspends the process as Ctrl-Z does in the shell, which raw
+/// mode keeps from sending `SIGTSTP` itself, restoring the terminal
+/// meanwhile.
+///
+/// Returns once the process is continued, with the modes entered
+/// again. The screen should be redrawn then.
+#[cfg(unix)]
+pub(crate) fn suspend() -> io::Result<()> {
+    let alternate = ALTERNATE.load(Ordering::Relaxed);
+    let mouse = MOUSE.load(Ordering::Relaxed);
+    restore();
+    // SAFETY: the default action of the signal stops the
+    // process until `SIGCONT`.
+    unsafe {
+        libc::raise(libc::SIGTSTP);
+    }
+    enter(alternate, mouse)
+}
+
+/// Leaves the modes entered by [`enter`], if not left yet.
+///
+/// Errors are ignored, as this is also called while panicking.
+pub(crate) fn restore() {
+    let mut out = io::stdout();
+    if MOUSE.swap(false, Ordering::Relaxed) {
+        let _ = execute!(out, event::DisableMouseCapture);
+    }
+    if RAW.swap(false, Ordering::Relaxed) {
+        let _ = terminal::disable_raw_mode();
+    }
+    if ALTERNATE.swap(false, Ordering::Relaxed) {
+        let _ = execute!(out, terminal::LeaveAlternateScreen, cursor::Show);
+    }
+}
@@ -0,0 +1,150 @@
+//! Colors and symbols players are told apart by.
+
+use crossterm::style::{Attribute, Color, ContentStyle};
+use curseofrust::Player;
+use curseofrust_cli_parser::Palette;
+
+/// Colors of the Okabe-Ito palette, distinguishable with any color
+/// vision deficiency, by player. Black gives way to grey.
+const OKABE_ITO: [(u8, u8, u8); 8] = [
+    (230, 159, 0),
+    (0, 158, 115),
+    (0, 114, 178),
+    (240, 228, 66),
+    (213, 94, 0),
+    (204, 121, 167),
+    (86, 180, 233),
+    (153, 153, 153),
+];
+
+/// Symbols marking the tiles of each player without colors.
+const MARKS: [&str; 7] = ["1", "2", "3", "4", "5", "6", "7"];
+
+/// Palette the console draws with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Theme {
+    /// Never [`Palette::Auto`].
+    palette: Palette,
+    /// Shade the background of tiles by their population.
+    shade: bool,
+}
+
+impl Default for Theme {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            palette: Palette::Ansi,
+            shade: false,
+        }
+    }
+}
+
+impl Theme {
+    /// Creates the theme of the given palette, picking one the
+    /// terminal supports for [`Palette::Auto`].
+    ///
+    /// Shading takes 24-bit colors, and is left out of the
+    /// other palettes.
+    pub(crate) fn new(palette: Palette, shade: bool) -> Self {
+        let palette = match palette {
+            Palette::Auto => detect(),
+            palette => palette,
+        };
+        Self {
+            palette,
+            shade: shade && palette == Palette::Truecolor,
+        }
+    }
+
+    /// Color of the given player.
+    pub(crate) fn color(self, player: Player) -> Color {
+        match self.palette {
+            Palette::Truecolor => OKABE_ITO
+                .get(player.0 as usize)
+                .map_or(Color::Reset, |&(r, g, b)| Color::Rgb { r, g, b }),
+            Palette::Patterns => Color::Reset,
+            _ => match player {
+                Player::NEUTRAL => Color::Yellow,
+                Player(1) => Color::Green,
+                Player(2) => Color::Blue,
+                Player(3) => Color::Yellow,
+                Player(4) => Color::Red,
+                Player(5) => Color::Magenta,
+                Player(6) => Color::Cyan,
+                Player(7) => Color::DarkGrey,
+                _ => Color::Reset,
+            },
+        }
+    }
+
+    /// Style of the tiles and names of the given player.
+    pub(crate) fn style(self, player: Player) -> ContentStyle {
+        ContentStyle {
+            foreground_color: Some(self.color(player)),
+            attributes: if player.is_neutral() {
+                Default::default()
+            } else {
+                Attribute::Bold.into()
+            },
+            ..Default::default()
+        }
+    }
+
+    /// Background of a tile of the given player with the given
+    /// population, darker for fewer units, if tiles are shaded.
+    ///
+    /// Returns the foreground to draw over it as well.
+    pub(crate) fn shade(self, player: Player, pop: u16) -> Option<(Color, Color)> {
+        let (r, g, b) = *OKABE_ITO.get(player.0 as usize)?;
+        if !self.shade || player.is_neutral() {
+            return None;
+        }
+        // Populations range over hundreds, so they are shaded
+        // on a logarithmic scale.
+        let t = (pop as f32 + 1.0).ln() / 401f32.ln();
+        let k = 0.2 + 0.8 * t.min(1.0);
+        let [r, g, b] = [r, g, b].map(|c| (c as f32 * k).round() as u8);
+        let luma = 0.299 * r as f32 + 0.587 * g as f32 + 0.114 * b as f32;
+        let fg = if luma > 128.0 {
+            Color::Black
+        } else {
+            Color::White
+        };
+        Some((Color::Rgb { r, g, b }, fg))
+    }
+
+    /// The given color of the landscape, or the default one
+    /// without colors.
+    #[inline]
+    pub(crate) fn tint(self, color: Color) -> Color {
+        match self.palette {
+            Palette::Patterns => Color::Reset,
+            _ => color,
+        }
+    }
+
+    /// Symbol marking the tiles of the given player, if players are
+    /// told apart by symbols.
+    pub(crate) fn mark(self, player: Player) -> Option<&'static str> {
+        match self.palette {
+            Palette::Patterns if !player.is_neutral() => MARKS.get(player.0 as usize - 1).copied(),
+            _ => None,
+        }
+    }
+}
+
+/// Picks the palette by the environment: none if `NO_COLOR` is set
+/// or the terminal is dumb, and 24-bit colors if `COLORTERM` says so
+/// or in Windows Terminal.
+fn detect() -> Palette {
+    let var = |key| std::env::var_os(key).filter(|v| !v.is_empty());
+    if var("NO_COLOR").is_some() || var("TERM").is_some_and(|t| t == "dumb") {
+        Palette::Patterns
+    } else if var("COLORTERM").is_some_and(|c| c == "truecolor" || c == "24bit")
+        || var("WT_SESSION").is_some()
+    {
+        Palette::Truecolor
+    } else {
+        Palette::Ansi
+    }
+}
@@ -0,0 +1,62 @@
+/* C ABI of curseofrust, built by the curseofrust-ffi crate. */
+
+#ifndef CURSEOFRUST_H
+#define CURSEOFRUST_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct CORState CORState;
+
+enum {
+    COR_VOID = 0,
+    COR_MOUNTAIN = 1,
+    COR_MINE = 2,
+    COR_GRASSLAND = 3,
+    COR_VILLAGE = 4,
+    COR_TOWN = 5,
+    COR_FORTRESS = 6,
+};
+
+/* Client messages accepted by CORApplyCommand. */
+enum {
+    COR_BUILD = 20,
+    COR_FLAG_ON = 21,
+    COR_FLAG_OFF = 22,
+    COR_FLAG_OFF_ALL = 23,
+    COR_FLAG_OFF_HALF = 24,
+    COR_FLAG_CITIES = 26,
+    COR_FLAG_FRONTLINE = 27,
+    COR_BUILD_ROAD = 28,
+};
+
+typedef struct CORCell {
+    uint8_t kind;
+    uint8_t owner;
+    uint16_t pop;
+} CORCell;
+
+/* Arguments exclude the program name. Returns NULL if they are invalid. */
+CORState *CORStateNew(int argc, const char *const *argv);
+void CORStateFree(CORState *state);
+void CORStateStep(CORState *state, uint32_t steps);
+uint32_t CORStateWidth(const CORState *state);
+uint32_t CORStateHeight(const CORState *state);
+uint64_t CORStateTime(const CORState *state);
+/* Returns -1 if nobody has won yet. */
+int CORStateWinner(const CORState *state);
+/* Cell of (x, y) is at y * width + x. Writes nothing and only returns
+   the number of cells if buf is NULL or len is too small. */
+size_t CORStateSerialize(const CORState *state, CORCell *buf, size_t len);
+/* Returns 0 on success, -1 if the command was rejected. */
+int CORApplyCommand(CORState *state, uint8_t msg, int32_t x, int32_t y);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,206 @@
+//! C ABI of the engine.
+//!
+//! Aimed at game engines such as Unity and Godot, which render and
+//! control the simulation a frame at a time: [`CORStateSerialize`] packs
+//! every tile into one buffer, and [`CORApplyCommand`] applies client
+//! messages of the controlled player, so a frame takes a few calls
+//! rather than one per tile.
+//!
+//! The declarations are in `include/curseofrust.h`.
+
+#![allow(non_snake_case)]
+
+use std::ffi::{c_char, c_int, CStr};
+
+use curseofrust::{
+    grid::{HabitLand, Tile},
+    state::{BasicOpts, State},
+    Pos,
+};
+
+/// Opaque state of a game.
+pub struct CORState(State);
+
+/// Kinds of [`CORCell`]s.
+pub mod kind {
+    pub const VOID: u8 = 0;
+    pub const MOUNTAIN: u8 = 1;
+    pub const MINE: u8 = 2;
+    pub const GRASSLAND: u8 = 3;
+    pub const VILLAGE: u8 = 4;
+    pub const TOWN: u8 = 5;
+    pub const FORTRESS: u8 = 6;
+}
+
+/// Packed tile, as rendered.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CORCell {
+    /// One of the [`kind`]s.
+    pub kind: u8,
+    pub owner: u8,
+    /// Population of the owner.
+    pub pop: u16,
+}
+
+impl From<&Tile> for CORCell {
+    fn from(tile: &Tile) -> Self {
+        let kind = match tile {
+            Tile::Mountain => kind::MOUNTAIN,
+            Tile::Mine(_) => kind::MINE,
+            Tile::Habitable { land, .. } => match land {
+                HabitLand::Village => kind::VILLAGE,
+                HabitLand::Town => kind::TOWN,
+                HabitLand::Fortress => kind::FORTRESS,
+                _ => kind::GRASSLAND,
+            },
+            _ => kind::VOID,
+        };
+        let owner = tile.owner();
+        Self {
+            kind,
+            owner: owner.0 as u8,
+            pop: tile.units()[owner.0 as usize],
+        }
+    }
+}
+
+/// Starts a game with the given command line arguments,
+/// not including the program name.
+///
+/// Returns null if the arguments are invalid.
+/// The state must be freed with [`CORStateFree`].
+///
+/// # Safety
+///
+/// `argv` must be null or point to `argc` valid C strings.
+#[no_mangle]
+pub unsafe extern "C" fn CORStateNew(argc: c_int, argv: *const *const c_char) -> *mut CORState {
+    let b_opt = if argv.is_null() || argc <= 0 {
+        BasicOpts::default()
+    } else {
+        let args = std::slice::from_raw_parts(argv, argc as usize)
+            .iter()
+            .map(|&arg| CStr::from_ptr(arg).to_string_lossy().into_owned());
+        match curseofrust_cli_parser::parse_to_options(
+            std::iter::once("curseofrust".to_owned()).chain(args),
+        ) {
+            Ok(opts) => opts.basic,
+            Err(_) => return std::ptr::null_mut(),
+        }
+    };
+    match State::new(b_opt) {
+        Ok(state) => Box::into_raw(Box::new(CORState(state))),
+        Err(_) => std::ptr::null_mut(),
+    }
+}
+
+/// Frees a state.
+///
+/// # Safety
+///
+/// `state` must be null or returned by [`CORStateNew`],
+/// and not used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn CORStateFree(state: *mut CORState) {
+    if !state.is_null() {
+        drop(Box::from_raw(state));
+    }
+}
+
+/// Advances the game by the given number of steps.
+///
+/// # Safety
+///
+/// `state` must be a valid state.
+#[no_mangle]
+pub unsafe extern "C" fn CORStateStep(state: *mut CORState, steps: u32) {
+    (*state).0.step_n(steps)
+}
+
+/// # Safety
+///
+/// `state` must be a valid state.
+#[no_mangle]
+pub unsafe extern "C" fn CORStateWidth(state: *const CORState) -> u32 {
+    (*state).0.grid.width()
+}
+
+/// # Safety
+///
+/// `state` must be a valid state.
+#[no_mangle]
+pub unsafe extern "C" fn CORStateHeight(state: *const CORState) -> u32 {
+    (*state).0.grid.height()
+}
+
+/// # Safety
+///
+/// `state` must be a valid state.
+#[no_mangle]
+pub unsafe extern "C" fn CORStateTime(state: *const CORState) -> u64 {
+    (*state).0.time
+}
+
+/// Gets the player who won the game, or `-1`.
+///
+/// # Safety
+///
+/// `state` must be a valid state.
+#[no_mangle]
+pub unsafe extern "C" fn CORStateWinner(state: *const CORState) -> c_int {
+    (*state).0.winner().map_or(-1, |p| p.0 as c_int)
+}
+
+/// Packs the tiles into `buf` row by row, so the tile at `(x, y)`
+/// is at `y * width + x`.
+///
+/// Returns the number of cells of the map. Nothing is written if
+/// `len` is less than that, so a null `buf` queries the size.
+///
+/// # Safety
+///
+/// `state` must be a valid state, and `buf` must be null
+/// or point to `len` writable cells.
+#[no_mangle]
+pub unsafe extern "C" fn CORStateSerialize(
+    state: *const CORState,
+    buf: *mut CORCell,
+    len: usize,
+) -> usize {
+    let grid = &(*state).0.grid;
+    let (width, height) = (grid.width() as usize, grid.height() as usize);
+    let size = width * height;
+    if buf.is_null() || len < size {
+        return size;
+    }
+    let buf = std::slice::from_raw_parts_mut(buf, size);
+    for (Pos(x, y), tile) in grid.tiles() {
+        buf[y as usize * width + x as usize] = tile.into();
+    }
+    size
+}
+
+/// Applies a client message of the controlled player at `(x, y)`,
+/// as if received by a server.
+///
+/// Flags are raised one at a time. Returns `0` on success,
+/// and `-1` if the command was rejected.
+///
+/// # Safety
+///
+/// `state` must be a valid state.
+#[no_mangle]
+pub unsafe extern "C" fn CORApplyCommand(state: *mut CORState, msg: u8, x: i32, y: i32) -> c_int {
+    // Positions are sent as bytes, which larger ones would wrap.
+    let (Ok(x), Ok(y)) = (u8::try_from(x), u8::try_from(y)) else {
+        return -1;
+    };
+    let st = &mut (*state).0;
+    let player = st.controlled;
+    let pos = Pos(x as i32, y as i32);
+    match curseofrust_msg::apply_c2s_msg(st, player, msg, (pos, 0).into()) {
+        Ok(()) => 0,
+        Err(_) => -1,
+    }
+}
@@ -3,6 +3,8 @@ use cacao::{
         window::{Window, WindowDelegate},
         FocusRingType,
     },
+    core_graphics::base::CGFloat,
+    defaults::{UserDefaults, Value},
     foundation::NSUInteger,
     input::TextField,
     objc::msg_send,
@@ -14,12 +16,61 @@ use cacao::listview::ListView;
 #[cfg(not(feature = "high-sierra"))]
 use {crate::app::set_font, cacao::text::Label};
 
+use cli_parser::Lang;
+
 use crate::util::{app_from_objc, OnceAssign};
 
 use super::CorApp;
 
 pub const ACTIVATE: &str = "activate gui config window c191239 5444";
 
+/// Display options for low-vision players.\
+/// Unlike textual preferences, these are persisted in user defaults.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DisplayOptions {
+    /// Draws sprites at twice their size.
+    pub large_tiles: bool,
+    /// Marks owned tiles with high-contrast player colors.
+    pub high_contrast: bool,
+    /// Draws population without random jitter.
+    pub reduce_motion: bool,
+}
+
+impl DisplayOptions {
+    const LARGE_TILES: &'static str = "CORLargeTiles";
+    const HIGH_CONTRAST: &'static str = "CORHighContrast";
+    const REDUCE_MOTION: &'static str = "CORReduceMotion";
+
+    /// Loads options from user defaults.
+    pub fn load() -> Self {
+        let defaults = UserDefaults::standard();
+        let get = |key| defaults.get(key).and_then(|v| v.as_bool()).unwrap_or(false);
+        Self {
+            large_tiles: get(Self::LARGE_TILES),
+            high_contrast: get(Self::HIGH_CONTRAST),
+            reduce_motion: get(Self::REDUCE_MOTION),
+        }
+    }
+
+    /// Saves options to user defaults.
+    pub fn save(&self) {
+        let mut defaults = UserDefaults::standard();
+        defaults.insert(Self::LARGE_TILES, Value::Bool(self.large_tiles));
+        defaults.insert(Self::HIGH_CONTRAST, Value::Bool(self.high_contrast));
+        defaults.insert(Self::REDUCE_MOTION, Value::Bool(self.reduce_motion));
+    }
+
+    /// Scale factor of the game view.
+    #[inline]
+    pub fn scale(&self) -> CGFloat {
+        if self.large_tiles {
+            2.
+        } else {
+            1.
+        }
+    }
+}
+
 /// Uses `preferences` as a replacement of `config` to fit the
 /// Mac OS X language style.
 pub struct GraphicalConfigWindow {
@@ -49,7 +100,8 @@ impl WindowDelegate for GraphicalConfigWindow {
     fn did_load(&mut self, window: Window) {
         self.window.set(window);
         self.window.set_content_size(300, 200);
-        self.window.set_title("GUI Preferences");
+        self.window
+            .set_title(Lang::from_env().tr("GUI Preferences"));
         #[cfg(feature = "high-sierra")]
         {
             self.window.set_content_view(&self.list);
@@ -67,16 +119,18 @@ impl WindowDelegate for GraphicalConfigWindow {
 #[cfg(feature = "high-sierra")]
 mod list {
     use cacao::{
-        color::Color,
         layout::{Layout, LayoutConstraint},
         listview::{ListView, ListViewDelegate, ListViewRow},
         text::Label,
     };
 
-    use crate::util::OnceAssign;
+    use crate::{
+        app::CorApp,
+        util::{app_from_objc, OnceAssign},
+    };
 
     /// List containing config items in the graphical config window.\
-    /// Unfinished, but i will be back. **(2024-02-06 C191239)**
+    /// Select a row to toggle the option.
     pub struct ConfigList {
         list: OnceAssign<ListView>,
     }
@@ -101,15 +155,17 @@ mod list {
         }
 
         fn item_for(&self, row: usize) -> ListViewRow {
-            let msg = match row {
-                0 => ("Not", Color::SystemRed),
-                1 => ("Implmented", Color::SystemGreen),
-                2 => ("Yet", Color::SystemBlue),
+            let app = app_from_objc::<CorApp>();
+            let display = &app.display;
+            let (name, on) = match row {
+                0 => ("Large tiles", display.large_tiles),
+                1 => ("High contrast", display.high_contrast),
+                2 => ("Reduce motion", display.reduce_motion),
                 _ => unreachable!(),
             };
             let label = Label::new();
-            label.set_text(msg.0);
-            label.set_background_color(msg.1);
+            let mark = if on { 'x' } else { ' ' };
+            label.set_text(&format!("[{}] {}", mark, app.lang.tr(name)));
             label.set_text_alignment(cacao::text::TextAlign::Center);
             let row = ListViewRow::new();
             row.add_subview(&label);
@@ -117,9 +173,21 @@ mod list {
                 label.center_x.constraint_equal_to(&row.center_x),
                 label.center_y.constraint_equal_to(&row.center_y),
             ]);
-            row.set_identifier(msg.0);
+            row.set_identifier(name);
             row
         }
+
+        fn item_selected(&self, row: usize) {
+            let display = &mut app_from_objc::<CorApp>().display;
+            match row {
+                0 => display.large_tiles ^= true,
+                1 => display.high_contrast ^= true,
+                2 => display.reduce_motion ^= true,
+                _ => return,
+            }
+            display.save();
+            self.list.reload();
+        }
     }
 }
 
@@ -146,7 +214,7 @@ impl WindowDelegate for TextualConfigWindow {
     fn did_load(&mut self, window: Window) {
         self.window.set(window);
         self.window.set_content_size(300, 200);
-        self.window.set_title("Preferences");
+        self.window.set_title(Lang::from_env().tr("Preferences"));
 
         self.input.objc.with_mut(|obj| unsafe {
             let focus_ring_type: NSUInteger = FocusRingType::None.into();
@@ -157,9 +225,21 @@ impl WindowDelegate for TextualConfigWindow {
     }
 
     fn will_close(&self) {
-        if self.input.get_value() == ACTIVATE {
+        let value = self.input.get_value();
+        if value == ACTIVATE {
             self.input.set_text("");
             app_from_objc::<CorApp>().gui_config_window.show();
+        } else if let Ok(options) = app_from_objc::<CorApp>().load_config() {
+            // Show the effective preferences, keeping the seed
+            // random unless one was given.
+            let mut args = options.to_args();
+            let seeded = value
+                .split_whitespace()
+                .any(|arg| arg.starts_with('-') && !arg.starts_with("--") && arg.contains('R'));
+            if let (false, Some(i)) = (seeded, args.iter().position(|arg| arg == "-R")) {
+                args.drain(i..i + 2);
+            }
+            self.input.set_text(&args.join(" "));
         }
     }
 }
@@ -1,3 +1,4 @@
+use std::ffi::c_void;
 use std::mem::ManuallyDrop;
 use std::net::{SocketAddr, UdpSocket};
 use std::sync::Once;
@@ -27,32 +28,53 @@ use cacao::{
         geometry::{CGPoint, CGRect, CGSize},
     },
     events::EventModifierFlag,
-    foundation::{id, nil},
+    foundation::{id, nil, NSInteger, NSUInteger},
     image::{Image, ImageView},
     objc::{class, msg_send},
     pasteboard::Pasteboard,
     text::Label,
 };
 use cacao::{layout::Layout, utils::sync_main_thread};
-use curseofrust::grid::{HabitLand, Tile};
+use cli_parser::{Alert, Lang, MultiplayerOpts, Profile};
+use curseofrust::grid::{HabitLand, Landscape, Tile};
 use curseofrust::{
-    state::{MultiplayerOpts, State, UI},
-    Speed, FLAG_POWER,
+    snapshot::{GameEvent, Snapshot},
+    state::{State, UI},
+    Speed, FLAG_POWER, MAX_FLAG_POWER,
 };
-use curseofrust::{Player, Pos, MAX_HEIGHT, MAX_PLAYERS, MAX_WIDTH};
+use curseofrust::{Player, Pos, Preset, MAX_HEIGHT, MAX_PLAYERS, MAX_WIDTH};
 use dispatch::{Queue, QueueAttribute};
 use itoa::Buffer;
 use local_ip_address::{local_ip, local_ipv6};
-use msg::{bytemuck, server_msg, S2CData, C2S_SIZE, S2C_SIZE};
+use msg::{bytemuck, server_msg, S2CData, StateOrder, C2S_SIZE, S2C_SIZE};
 
 use self::output::{
-    draw_int, draw_line, draw_tile_2h, draw_tile_noise, is_cliff, is_within_grid, pop_to_symbol,
-    pos_x, pos_y, time_to_ymd, TILE_WIDTH,
+    draw_contrast_mark, draw_flag, draw_int, draw_line, draw_rally_mark, draw_tile_2h,
+    draw_tile_noise, draw_tint, is_cliff, is_within_grid, pop_to_symbol, pos_x, pos_y, time_to_ymd,
+    tr_ascii, Hud, TILE_WIDTH,
 };
 
 mod config;
 mod output;
 
+/// Slower.
+const K_S: u16 = 0x01;
+/// Faster.
+const K_F: u16 = 0x03;
+/// Pause game.
+const K_P: u16 = 0x23;
+
+/// Copied from `icrate`.
+#[allow(non_upper_case_globals)]
+const NSEventModifierFlagCommand: NSUInteger = 1 << 20;
+
+/// Seed of the game to start once the current one ends.
+#[derive(Debug, Clone, Copy)]
+enum Restart {
+    SameSeed,
+    NewSeed,
+}
+
 pub struct CorApp {
     // View-associated
     game_window: Window<GameWindow>,
@@ -66,7 +88,30 @@ pub struct CorApp {
     pop_variant: Option<[[i16; MAX_HEIGHT as usize]; MAX_WIDTH as usize]>,
     ui: Option<UI>,
     screen: Option<Image>,
+    /// Arrangement of the text under the map on the screen.
+    hud: Hud,
+    /// Size of the game view the screen was last fitted to, in points.
+    fitted: CGSize,
+    display: config::DisplayOptions,
+    /// Tint tiles by the call of the flags of the controlled player.
+    call_overlay: bool,
+    /// How to alert the player of losses. `None` if quiet.
+    alert: Option<Alert>,
+    /// Snapshot of the previous step, to find lost cities.
+    alert_snapshot: Option<Snapshot>,
+    /// Fortresses of the controlled player under siege.
+    besieged: Vec<Pos>,
+    /// Time left of the match time limit, if any.
+    time_left: Option<Duration>,
+    /// Steps per turn in turn-based mode, `None` if real-time.
+    turns: Option<u32>,
+    /// Whether the player ended the turn.
+    turn_ended: bool,
+    /// Log draw calls and time of each frame.
+    #[cfg(feature = "frame-stats")]
+    frame_stats: bool,
     // Misc
+    lang: Lang,
     queue: Queue,
     _listener: EventMonitor,
     /// Indicates whether:
@@ -75,6 +120,8 @@ pub struct CorApp {
     run: bool,
     /// Should terminate game and switch back to error message view.
     terminate: bool,
+    /// [`Some`] if another game is to start once this one terminates.
+    restart: Option<Restart>,
     /// [`Some`] if playing a multiplayer game.
     socket: Option<UdpSocket>,
 }
@@ -82,7 +129,7 @@ pub struct CorApp {
 impl AppDelegate for CorApp {
     fn did_finish_launching(&self) {
         self.game_window.show();
-        App::set_menu(Self::menu());
+        App::set_menu(Self::menu(self.lang));
         // Self::change_app_menu_name("CoR");
         App::activate();
         // Self::set_app_icon();
@@ -101,7 +148,7 @@ impl AppDelegate for CorApp {
 impl CorApp {
     pub fn new() -> Self {
         Self {
-            game_window: Window::with(fixed_size_window_config(), GameWindow::new()),
+            game_window: Window::with(game_window_config(), GameWindow::new()),
             about_window: Window::with(fixed_size_window_config(), AboutWindow::new()),
             gui_config_window: Window::with(
                 fixed_size_window_config(),
@@ -117,13 +164,28 @@ impl CorApp {
             pop_variant: None,
             ui: None,
             screen: None,
+            hud: Hud::Wide,
+            fitted: CGSize::new(0., 0.),
+            display: config::DisplayOptions::load(),
+            call_overlay: false,
+            alert: Some(Alert::default()),
+            alert_snapshot: None,
+            besieged: vec![],
+            time_left: None,
+            turns: None,
+            turn_ended: false,
+            #[cfg(feature = "frame-stats")]
+            frame_stats: false,
+            lang: Lang::from_env(),
             queue: Queue::create(
                 "com.dm.earth.curseofrust.worker",
                 QueueAttribute::Concurrent,
             ),
             _listener: Event::local_monitor(cacao::appkit::EventMask::KeyDown, |e| {
                 let app = app_from_objc::<Self>();
-                if app.run && app.game_window.is_key() {
+                let flags: NSUInteger = unsafe { msg_send![&e.0, modifierFlags] };
+                // Key equivalents are left to the menu.
+                if app.run && app.game_window.is_key() && flags & NSEventModifierFlagCommand == 0 {
                     let keycode: u16 = unsafe { msg_send![&e.0, keyCode] };
                     app.queue
                         .exec_sync(|| !app_from_objc::<Self>().process_input(keycode))
@@ -134,23 +196,28 @@ impl CorApp {
             }),
             run: false,
             terminate: false,
+            restart: None,
             socket: None,
         }
     }
 
-    fn menu() -> Vec<Menu> {
-        let about = MenuItem::new("About Curse of Rust").action(|| {
+    fn menu(lang: Lang) -> Vec<Menu> {
+        let about = MenuItem::new(lang.tr("About Curse of Rust")).action(|| {
             let app = app_from_objc::<Self>();
             app.about_window.show();
         });
-        let preferences = MenuItem::new("Preferences")
+        let preferences = MenuItem::new(lang.tr("Preferences"))
             .modifiers(&[EventModifierFlag::Command])
             .key(",")
             .action(|| {
                 let app = app_from_objc::<Self>();
                 app.text_config_window.show();
             });
-        let mut copy_config = MenuItem::new("Copy Preferences")
+        let display_preferences = MenuItem::new(lang.tr("Display Preferences")).action(|| {
+            let app = app_from_objc::<Self>();
+            app.gui_config_window.show();
+        });
+        let mut copy_config = MenuItem::new(lang.tr("Copy Preferences"))
             .modifiers(&[EventModifierFlag::Command])
             .key("c")
             .action(|| {
@@ -172,22 +239,23 @@ impl CorApp {
             let _: () = unsafe { msg_send![&obj, setEnabled:Bool::NO] };
             copy_config = MenuItem::Custom(obj);
         }
-        let restore_default_config = MenuItem::new("Restore Default Preferences").action(|| {
-            let app = app_from_objc::<Self>();
-            if app.text_config_window.is_key() {
-                app.text_config_window
-                    .delegate
-                    .as_ref()
-                    .unwrap()
-                    .input
-                    .set_text(match fastrand::u8(1..(36 + 1)) {
-                        // In case I forgot.
-                        36 => ACTIVATE,
-                        _ => "-i4 -q1 -dee -W16 -H16",
-                    });
-            }
-        });
-        let new_game = MenuItem::new("New Game")
+        let restore_default_config =
+            MenuItem::new(lang.tr("Restore Default Preferences")).action(|| {
+                let app = app_from_objc::<Self>();
+                if app.text_config_window.is_key() {
+                    app.text_config_window
+                        .delegate
+                        .as_ref()
+                        .unwrap()
+                        .input
+                        .set_text(match fastrand::u8(1..(36 + 1)) {
+                            // In case I forgot.
+                            36 => ACTIVATE,
+                            _ => "-i4 -q1 -dee -W16 -H16",
+                        });
+                }
+            });
+        let new_game = MenuItem::new(lang.tr("New Game"))
             .modifiers(&[EventModifierFlag::Command])
             .key("n")
             .action(|| {
@@ -196,7 +264,14 @@ impl CorApp {
                     this.queue.exec_async(|| app_from_objc::<Self>().pre_run())
                 }
             });
-        let help = MenuItem::new("Curse of Rust Help").action(|| {
+        let pause = Self::key_item(lang.tr("Pause"), K_P)
+            .modifiers(&[EventModifierFlag::Command])
+            .key("p");
+        let faster = Self::key_item(lang.tr("Faster"), K_F);
+        let slower = Self::key_item(lang.tr("Slower"), K_S);
+        let restart_same = Self::restart_item(lang.tr("Restart Same Seed"), Restart::SameSeed);
+        let restart_new = Self::restart_item(lang.tr("Restart New Seed"), Restart::NewSeed);
+        let help = MenuItem::new(lang.tr("Curse of Rust Help")).action(|| {
             let app = app_from_objc::<Self>();
             app.help_window.show();
         });
@@ -206,6 +281,7 @@ impl CorApp {
                 about,
                 MenuItem::Separator,
                 preferences,
+                display_preferences,
                 MenuItem::Separator,
                 MenuItem::Hide,
                 MenuItem::Separator,
@@ -213,7 +289,7 @@ impl CorApp {
             ],
         );
         let file_menu = Menu::new(
-            "File",
+            lang.tr("File"),
             vec![
                 new_game,
                 MenuItem::Separator,
@@ -223,10 +299,49 @@ impl CorApp {
                 restore_default_config,
             ],
         );
-        let help_menu = Menu::new("Help", vec![help]);
+        let game_menu = Menu::new(
+            lang.tr("Game"),
+            vec![
+                pause,
+                faster,
+                slower,
+                MenuItem::Separator,
+                restart_same,
+                restart_new,
+            ],
+        );
+        let help_menu = Menu::new(lang.tr("Help"), vec![help]);
         // Required for disabling menu items.
         let _: () = unsafe { msg_send![&file_menu.0, setAutoenablesItems:Bool::NO] };
-        vec![main_menu, file_menu, help_menu]
+        vec![main_menu, file_menu, game_menu, help_menu]
+    }
+
+    /// Menu item passing the given key to the running game,
+    /// as if it was pressed.
+    fn key_item(title: &str, keycode: u16) -> MenuItem {
+        MenuItem::new(title).action(move || {
+            let this = app_from_objc::<Self>();
+            if this.run {
+                this.queue.exec_sync(move || {
+                    app_from_objc::<Self>().process_input(keycode);
+                });
+            }
+        })
+    }
+
+    /// Menu item ending the running game and starting another
+    /// one with the given seed.
+    fn restart_item(title: &str, restart: Restart) -> MenuItem {
+        MenuItem::new(title).action(move || {
+            let this = app_from_objc::<Self>();
+            if this.run && this.socket.is_none() {
+                this.queue.exec_sync(move || {
+                    let this = app_from_objc::<Self>();
+                    this.restart = Some(restart);
+                    this.terminate = true;
+                });
+            }
+        })
     }
 
     /// Loses main menu's bold style.
@@ -289,16 +404,46 @@ impl CorApp {
         })
     }
 
-    /// Starts the game.
+    /// Starts the game, and the next one on every restart.
     fn pre_run(&mut self) {
+        let mut seed = None;
+        loop {
+            self.start(seed);
+            seed = match self.restart.take() {
+                Some(Restart::SameSeed) => Some(self.state.as_ref().unwrap().seed),
+                Some(Restart::NewSeed) => Some(fastrand::u64(..)),
+                None => break,
+            };
+        }
+    }
+
+    /// Starts a game with the preferences, on the given seed
+    /// if any.
+    fn start(&mut self, seed: Option<u64>) {
         sync_main_thread(|| {
             app_from_objc::<Self>().game_window.show();
         });
         fastrand::seed(UNIX_EPOCH.elapsed().unwrap_or_default().as_secs());
         match self.load_config() {
             Ok(cli_parser::Options {
-                basic, multiplayer, ..
+                mut basic,
+                multiplayer,
+                lang,
+                alert,
+                time_limit,
+                turns,
+                ..
             }) => {
+                self.time_left = time_limit;
+                self.turns = turns;
+                self.turn_ended = false;
+                self.lang = lang;
+                self.alert = alert;
+                self.alert_snapshot = None;
+                self.besieged.clear();
+                if let Some(seed) = seed {
+                    basic.seed = seed;
+                }
                 let common_init = || {
                     match State::new(basic) {
                         Ok(state) => self.state = Some(state),
@@ -323,15 +468,15 @@ impl CorApp {
                         }
                         self.run();
                     }
-                    MultiplayerOpts::Server { .. } => {
+                    MultiplayerOpts::Server { .. } | MultiplayerOpts::Relay { .. } => {
                         self.game_window.delegate.as_ref().unwrap().display_err(
-                            "Integrated server is currently not implemented, please use dedicated server.",
+                            self.lang.tr("Integrated server is currently not implemented, please use dedicated server."),
                             Some(Color::SystemOrange),
                         );
                     }
                     MultiplayerOpts::Client { .. } => {
                         self.game_window.delegate.as_ref().unwrap().display_err(
-                            "UDP multiplayer client is not usable, please use the console version.",
+                            self.lang.tr("UDP multiplayer client is not usable, please use the console version."),
                             Some(Color::SystemOrange),
                         );
                         /* if !common_init() {
@@ -352,7 +497,7 @@ impl CorApp {
                     .delegate
                     .as_ref()
                     .unwrap()
-                    .display_err(&err.to_string(), None);
+                    .display_err(&err.localized(self.lang), None);
             }
         }
     }
@@ -360,37 +505,62 @@ impl CorApp {
     /// Start a singleplayer game.
     fn run(&mut self) {
         self.run = true;
-        let seed = self.state.as_ref().unwrap().seed;
+        let title = self.lang.trf(
+            "Singleplayer - seed: {0}",
+            &[&self.state.as_ref().unwrap().seed],
+        );
         sync_main_thread(move || {
             let this = app_from_objc::<Self>();
-            this.game_window
-                .set_title(format!("Singleplayer - seed: {}", seed).as_str());
+            this.game_window.set_title(title.as_str());
             this.game_window
                 .set_content_view(&this.game_window.delegate.as_ref().unwrap().game_view);
         });
-        let (screen_size, old_frame) = self.init_screen();
-        let mut prev_time = Instant::now();
+        let old_frame = self.init_screen();
+        let tick = self.state.as_ref().unwrap().tick;
+        let started = Instant::now();
+        let mut prev_time = started;
+        // Ticks are caught up after delays, so the time limit counts
+        // down by the time passed rather than by ticks.
+        let mut counted = started;
         let mut k: u16 = 0;
         let mut itoa_buf = Buffer::new();
         while !self.terminate {
-            if Instant::now().duration_since(prev_time) >= DELAY {
-                prev_time += DELAY;
+            if Instant::now().duration_since(prev_time) >= tick {
+                prev_time += tick;
+                let now = Instant::now();
+                let elapsed = now - counted;
+                counted = now;
                 k += 1;
                 if k >= 1600 {
                     k = 0;
                 }
-                if k % slowdown(self.state.as_ref().unwrap().speed) == 0
-                    && self.state.as_ref().unwrap().speed != Speed::Pause
+                let state = self.state.as_mut().unwrap();
+                if let Some(left) = self
+                    .time_left
+                    .as_mut()
+                    .filter(|left| state.speed != Speed::Pause && !left.is_zero())
                 {
-                    let state = self.state.as_mut().unwrap();
-                    state.kings_move();
-                    state.simulate();
+                    *left = left.saturating_sub(elapsed);
+                    if left.is_zero() {
+                        state.speed = Speed::Pause;
+                        state.time_up();
+                    }
+                }
+                let speed = self.state.as_ref().unwrap().speed;
+                let steps = match self.turns {
+                    Some(ticks) if std::mem::take(&mut self.turn_ended) => ticks,
+                    Some(_) => 0,
+                    None => (k % slowdown(speed) == 0 && speed != Speed::Pause) as u32,
+                };
+                if steps > 0 {
+                    self.state.as_mut().unwrap().step_n(steps);
+                    self.check_alerts();
                 }
                 if k % 5 == 0 {
-                    self.render(screen_size, &mut itoa_buf);
+                    self.render(&mut itoa_buf);
                 }
             } else {
-                sleep(DELAY / 2);
+                sleep(tick / 2);
             }
         }
         sync_main_thread(move || {
@@ -402,6 +572,17 @@ impl CorApp {
         self.game_window.delegate.as_ref().unwrap().restore(false);
         self.terminate = false;
         self.run = false;
+
+        let state = self.state.as_ref().unwrap();
+        let won = if state.is_eliminated(state.controlled) {
+            Some(false)
+        } else {
+            state.winner().map(|winner| winner == state.controlled)
+        };
+        let mut profile = Profile::load();
+        profile.record(won, started.elapsed());
+        // A read-only config directory should not fail the game.
+        let _ = profile.save();
     }
 
     /// Start as a multiplayer client.
@@ -410,6 +591,7 @@ impl CorApp {
         server: SocketAddr,
         port: u16,
     ) -> Result<(), (String, Option<Color>)> {
+        let tick = self.state.as_ref().unwrap().tick;
         let mut prev_time = Instant::now();
         let mut k: u16 = 0;
         let local_addr = SocketAddr::new(
@@ -430,17 +612,18 @@ impl CorApp {
             .map_err(|e| ("set_nonblocking error: ".to_owned() + &e.to_string(), None))?;
         self.socket = Some(socket);
         let mut s2c_buf = [0u8; S2C_SIZE];
-        let mut screen_size: CGSize = Default::default();
         let mut old_frame: CGRect = Default::default();
         let mut itoa_buf = Buffer::new();
+        let mut order = StateOrder::default();
         while !self.terminate {
-            if Instant::now().duration_since(prev_time) >= DELAY {
-                prev_time += DELAY;
+            if Instant::now().duration_since(prev_time) >= tick {
+                prev_time += tick;
                 k += 1;
                 k %= 1600;
 
                 if k % 50 == 0 {
-                    const ALIVE_PACKET: [u8; C2S_SIZE] = [msg::client_msg::IS_ALIVE, 0, 0, 0];
+                    const ALIVE_PACKET: [u8; C2S_SIZE] =
+                        [msg::client_msg::IS_ALIVE, 0, 0, msg::capability::TILE_FLAGS];
                     self.socket
                         .as_ref()
                         .unwrap()
@@ -460,22 +643,23 @@ impl CorApp {
                     .split_first()
                     .expect("s2c_buf should be longer than one byte");
                 let data: S2CData = *bytemuck::from_bytes(body);
-                if msg == server_msg::STATE {
+                if msg == server_msg::STATE && order.accept(self.state.as_ref().unwrap(), &data) {
                     msg::apply_s2c_msg(self.state.as_mut().unwrap(), data)
                         .map_err(|e| ("apply_s2c_msg error: ".to_owned() + &e.to_string(), None))?;
+                    self.check_alerts();
                     if !self.run {
                         self.run = true;
-                        (screen_size, old_frame) = self.init_screen();
+                        old_frame = self.init_screen();
                         self.ui = Some(UI::new(self.state.as_ref().unwrap()));
                     }
                 }
                 // End fetch state
 
                 if self.run && k % 5 == 0 {
-                    self.render(screen_size, &mut itoa_buf);
+                    self.render(&mut itoa_buf);
                 }
             } else {
-                sleep(DELAY / 2);
+                sleep(tick / 2);
             }
         }
         // Clean up.
@@ -493,6 +677,69 @@ impl CorApp {
         Ok(())
     }
 
+    /// Alerts the player on losing a city, or when a fortress
+    /// of theirs comes under siege.
+    fn check_alerts(&mut self) {
+        let state = self.state.as_ref().unwrap();
+        let lang = self.lang;
+        let next = state.snapshot();
+        let events = self
+            .alert_snapshot
+            .as_ref()
+            .map(|prev| prev.events(&next))
+            .unwrap_or_default();
+        self.alert_snapshot = Some(next);
+        let besieged: Vec<Pos> = state.besieged_fortresses(state.controlled).collect();
+
+        let lost = events.iter().find_map(|event| match *event {
+            GameEvent::CityCaptured {
+                pos: Pos(x, y),
+                land,
+                from,
+                to,
+            } if from == state.controlled => Some(lang.trf(
+                "lost {0} at {1} to {2}",
+                &[
+                    &lang.tr(match land {
+                        HabitLand::Village => "village",
+                        HabitLand::Town => "town",
+                        _ => "fortress",
+                    }),
+                    &format!("{x},{y}"),
+                    &lang.trf("player {0}", &[&to]),
+                ],
+            )),
+            _ => None,
+        });
+        let sieged = besieged
+            .iter()
+            .find(|&pos| !self.besieged.contains(pos))
+            .map(|&Pos(x, y)| lang.trf("fortress at {0} is under siege", &[&format!("{x},{y}")]));
+        self.besieged = besieged;
+
+        let Some(message) = lost.or(sieged) else {
+            return;
+        };
+        match self.alert {
+            Some(Alert::Bell) => sync_main_thread(|| unsafe { NSBeep() }),
+            Some(Alert::Notify) => sync_main_thread(move || unsafe {
+                let notification: id = msg_send![class!(NSUserNotification), new];
+                let title = NSString::new("Curse of Rust");
+                let text = NSString::new(&message);
+                let _: () = msg_send![notification, setTitle:title.objc.autorelease_return()];
+                let _: () =
+                    msg_send![notification, setInformativeText:text.objc.autorelease_return()];
+                let center: id = msg_send![
+                    class!(NSUserNotificationCenter),
+                    defaultUserNotificationCenter
+                ];
+                let _: () = msg_send![center, deliverNotification:notification];
+                let _: () = msg_send![notification, release];
+            }),
+            _ => {}
+        }
+    }
+
     pub fn load_config(&self) -> Result<cli_parser::Options, cli_parser::Error> {
         let mut config_str = self
             .text_config_window
@@ -531,24 +778,37 @@ impl CorApp {
         const K_Q: u16 = 0x0C;
         /// Flag.
         const K_SPACE: u16 = 0x31;
-        /// Slower.
-        const K_S: u16 = 0x01;
-        /// Faster.
-        const K_F: u16 = 0x03;
-        /// Pause game.
-        const K_P: u16 = 0x23;
         /// Build.
         const K_R: u16 = 0x0F;
         /// Another build.
         const K_V: u16 = 0x09;
+        /// Move cursor to the suggested build.
+        const K_B: u16 = 0x0B;
         /// Remove all flags.
         const K_X: u16 = 0x07;
         /// Remove half flags.
         const K_C: u16 = 0x08;
+        /// Flag all own cities.
+        const K_O: u16 = 0x1F;
+        /// Flag the frontline.
+        const K_W: u16 = 0x0D;
+        /// Toggle the flag call overlay.
+        const K_A: u16 = 0x00;
+        /// Choose a city, then its rally point.
+        const K_M: u16 = 0x2E;
+        /// End the turn in turn-based mode.
+        const K_RETURN: u16 = 0x24;
+        /// Build a road.
+        const K_T: u16 = 0x11;
+        /// Queue an upgrade, or unqueue it.
+        const K_U: u16 = 0x20;
+        /// Toggles logging frame stats.
+        #[cfg(feature = "frame-stats")]
+        const K_D: u16 = 0x02;
 
         macro_rules! c2s_msg {
-            ($msg:ident, $info:expr) => {{
-                let data: msg::C2SData = (self.ui.as_ref().unwrap().cursor, $info).into();
+            (@data $msg:ident, $data:expr) => {{
+                let data: msg::C2SData = $data;
                 let mut buf = [0u8; C2S_SIZE];
                 let (msg, d) = buf
                     .split_first_mut()
@@ -558,6 +818,9 @@ impl CorApp {
                 let socket = self.socket.as_ref().unwrap();
                 let _ = socket.send(&buf);
             }};
+            ($msg:ident, $info:expr) => {
+                c2s_msg!(@data $msg, (self.ui.as_ref().unwrap().cursor, $info).into())
+            };
             ($msg:ident) => {
                 c2s_msg!($msg, 0)
             };
@@ -598,18 +861,22 @@ impl CorApp {
             }
             K_SPACE => {
                 let state = self.state.as_mut().unwrap();
-                let fg = &mut state.fgs[state.controlled.0 as usize];
-                let cursor = self.ui.as_ref().unwrap().cursor;
-                if !multiplayer {
-                    if fg.is_flagged(cursor) {
-                        fg.remove(&state.grid, cursor, FLAG_POWER);
+                if let Ok(i) = state.controlled.index() {
+                    let fg = &mut state.fgs[i];
+                    let cursor = self.ui.as_ref().unwrap().cursor;
+                    // Flag powers are not sent back by the server,
+                    // so they are tracked locally in multiplayer too.
+                    if fg.power(cursor) >= MAX_FLAG_POWER {
+                        fg.remove(&state.grid, cursor, MAX_FLAG_POWER);
+                        if multiplayer {
+                            c2s_msg!(FLAG_OFF);
+                        }
                     } else {
-                        fg.add(&state.grid, cursor, FLAG_POWER);
+                        fg.raise(&state.grid, cursor, FLAG_POWER);
+                        if multiplayer {
+                            c2s_msg!(FLAG_ON, 1);
+                        }
                     }
-                } else if fg.is_flagged(cursor) {
-                    c2s_msg!(FLAG_OFF);
-                } else {
-                    c2s_msg!(FLAG_ON);
                 }
             }
             K_Q => self.terminate = true,
@@ -642,18 +909,55 @@ impl CorApp {
             K_R | K_V => {
                 if !multiplayer {
                     let state = self.state.as_mut().unwrap();
-                    let _ = state.grid.build(
-                        &mut state.countries[state.controlled.0 as usize],
-                        self.ui.as_ref().unwrap().cursor,
-                    );
+                    if let Ok(i) = state.controlled.index() {
+                        let _ = state.grid.build(
+                            &mut state.countries[i],
+                            self.ui.as_ref().unwrap().cursor,
+                            &state.balance,
+                        );
+                    }
                 } else {
                     c2s_msg!(BUILD);
                 }
             }
+            K_U => {
+                let state = self.state.as_mut().unwrap();
+                let cursor = self.ui.as_ref().unwrap().cursor;
+                // Queued upgrades are not sent back by the server,
+                // so they are tracked locally in multiplayer too.
+                if state.is_build_queued(state.controlled, cursor) {
+                    state.unqueue_build(state.controlled, cursor);
+                    if multiplayer {
+                        c2s_msg!(QUEUE_BUILD, 0);
+                    }
+                } else if state.queue_build(state.controlled, cursor).is_ok() && multiplayer {
+                    c2s_msg!(QUEUE_BUILD, 1);
+                }
+            }
+            K_T => {
+                if !multiplayer {
+                    let state = self.state.as_mut().unwrap();
+                    if let Ok(i) = state.controlled.index() {
+                        let _ = state
+                            .grid
+                            .build_road(&mut state.countries[i], self.ui.as_ref().unwrap().cursor);
+                    }
+                } else {
+                    c2s_msg!(BUILD_ROAD);
+                }
+            }
+            K_B => {
+                let state = self.state.as_ref().unwrap();
+                if let Some(pos) = state.suggest_build(state.controlled) {
+                    self.ui.as_mut().unwrap().adjust_cursor(state, pos);
+                }
+            }
             K_X => {
                 if !multiplayer {
                     let state = self.state.as_mut().unwrap();
-                    state.fgs[state.controlled.0 as usize].remove_with_prob(&state.grid, 1.);
+                    if let Ok(i) = state.controlled.index() {
+                        state.fgs[i].remove_with_prob(&state.grid, 1.);
+                    }
                 } else {
                     c2s_msg!(FLAG_OFF_ALL);
                 }
@@ -661,29 +965,99 @@ impl CorApp {
             K_C => {
                 if !multiplayer {
                     let state = self.state.as_mut().unwrap();
-                    state.fgs[state.controlled.0 as usize].remove_with_prob(&state.grid, 0.5);
+                    if let Ok(i) = state.controlled.index() {
+                        state.fgs[i].remove_with_prob(&state.grid, 0.5);
+                    }
                 } else {
                     c2s_msg!(FLAG_OFF_HALF);
                 }
             }
+            K_O if !multiplayer => {
+                let state = self.state.as_mut().unwrap();
+                state.flag_cities(state.controlled);
+            }
+            K_O => c2s_msg!(FLAG_CITIES),
+            K_W if !multiplayer => {
+                let state = self.state.as_mut().unwrap();
+                state.flag_frontline(state.controlled);
+            }
+            K_W => c2s_msg!(FLAG_FRONTLINE),
+            K_A => self.call_overlay ^= true,
+            K_RETURN if !multiplayer => self.turn_ended = self.turns.is_some(),
+            K_RETURN => c2s_msg!(END_TURN),
+            #[cfg(feature = "frame-stats")]
+            K_D => self.frame_stats ^= true,
+            K_M => {
+                let state = self.state.as_mut().unwrap();
+                let ui = self.ui.as_mut().unwrap();
+                let cursor = ui.cursor;
+                match ui.rally_from.take() {
+                    Some(city) => {
+                        let target = Some(cursor).filter(|c| *c != city);
+                        if !multiplayer {
+                            let _ = state.grid.set_rally(state.controlled, city, target);
+                        } else if let Some(data) = msg::C2SData::rally(city, target) {
+                            // Rally points are not sent back by the server,
+                            // so they are tracked locally.
+                            if state.grid.set_rally(state.controlled, city, target).is_ok() {
+                                c2s_msg!(@data RALLY, data);
+                            }
+                        }
+                    }
+                    None => {
+                        if state
+                            .grid
+                            .tile(cursor)
+                            .is_some_and(|t| t.is_city() && t.owner() == state.controlled)
+                        {
+                            ui.rally_from = Some(cursor);
+                        }
+                    }
+                }
+            }
             _ => return false,
         }
         true
     }
 
     /// Render the current [`State`].
-    fn render(&mut self, screen_size: CGSize, itoa_buf: &mut Buffer) {
+    fn render(&mut self, itoa_buf: &mut Buffer) {
         let pool = ManuallyDrop::new(AutoReleasePool::new());
+        let screen_size = self.fit_screen();
+        #[cfg(feature = "frame-stats")]
+        let frame_start = {
+            output::take_frame_stats();
+            Instant::now()
+        };
         // Render start.
         unsafe {
             let background: id = msg_send![class!(NSColor), blackColor];
-            let _: () = msg_send![&self.screen.as_ref().unwrap().0, lockFocusFlipped:Bool::YES];
+            // Draw into the bitmap of the screen, flipped and scaled up
+            // by whole pixels, see `init_screen`.
+            let reps: id = msg_send![&self.screen.as_ref().unwrap().0, representations];
+            let rep: id = msg_send![reps, firstObject];
+            let pixels_wide: NSInteger = msg_send![rep, pixelsWide];
+            let pixels_high: NSInteger = msg_send![rep, pixelsHigh];
+            let scale = pixels_wide as CGFloat / screen_size.width;
+            let _: () = msg_send![class!(NSGraphicsContext), saveGraphicsState];
+            let bitmap: id =
+                msg_send![class!(NSGraphicsContext), graphicsContextWithBitmapImageRep:rep];
+            let cg: *mut c_void = msg_send![bitmap, CGContext];
+            let context: id = msg_send![class!(NSGraphicsContext), graphicsContextWithCGContext:cg flipped:Bool::YES];
+            let _: () = msg_send![class!(NSGraphicsContext), setCurrentContext:context];
+            let _: () = msg_send![context, setImageInterpolation:NSImageInterpolationNone];
+            let transform: id = msg_send![class!(NSAffineTransform), transform];
+            let _: () =
+                msg_send![transform, translateXBy:(0. as CGFloat) yBy:(pixels_high as CGFloat)];
+            let _: () = msg_send![transform, scaleXBy:scale yBy:-scale];
+            let _: () = msg_send![transform, concat];
             // Draw background
             let _: () = msg_send![background, drawSwatchInRect:CGRect::new(&CGPoint::new(0., 0.), &screen_size)];
         }
         let state = self.state.as_ref().unwrap();
         let ui = self.ui.as_ref().unwrap();
         let tile_var = self.tile_variant.as_ref().unwrap();
+        let lang = self.lang;
         for j in 0..state.grid.height() as i16 {
             for i in -1..state.grid.width() as i16 + 1 {
                 // Draw cliffs.
@@ -700,7 +1074,14 @@ impl CorApp {
                     continue;
                 }
                 match state.grid.tile(Pos(i as i32, j as i32)).unwrap() {
-                    Tile::Habitable { land, units, owner } => {
+                    Tile::Habitable {
+                        land,
+                        units,
+                        owner,
+                        landscape,
+                        road,
+                        ..
+                    } => {
                         // Draw grass.
                         draw_tile(
                             (tile_var[i as usize][j as usize] % 6).abs(),
@@ -708,6 +1089,37 @@ impl CorApp {
                             pos_x(ui, i),
                             pos_y(j),
                         );
+                        // Shade forests and hills, as the tileset has no sprites for them.
+                        match landscape {
+                            Landscape::Forest => {
+                                draw_tint((0., 0.3, 0.), 0.45, pos_x(ui, i), pos_y(j))
+                            }
+                            Landscape::Hill => {
+                                draw_tint((0.5, 0.35, 0.15), 0.4, pos_x(ui, i), pos_y(j))
+                            }
+                            _ => {}
+                        }
+                        if *road {
+                            draw_tint((0.55, 0.55, 0.55), 0.5, pos_x(ui, i), pos_y(j))
+                        }
+                        if state.is_build_queued(state.controlled, Pos(i as i32, j as i32)) {
+                            draw_tint((1., 0.85, 0.), 0.35, pos_x(ui, i), pos_y(j))
+                        }
+                        if self.call_overlay {
+                            if let Some(intensity) = state
+                                .flag_grid(state.controlled)
+                                .ok()
+                                .and_then(|fg| fg.call_intensity(Pos(i as i32, j as i32)))
+                                .filter(|intensity| *intensity > 0.)
+                            {
+                                draw_tint(
+                                    (0.2, 0.6, 1.),
+                                    0.6 * intensity as CGFloat,
+                                    pos_x(ui, i),
+                                    pos_y(j),
+                                );
+                            }
+                        }
                         match land {
                             HabitLand::Village => {
                                 draw_tile_2h(0, 7 + 3 * owner.0 as i16, pos_x(ui, i), pos_y(j))
@@ -720,7 +1132,14 @@ impl CorApp {
                             }
                             _ => {
                                 let pop = units[owner.0 as usize];
-                                if pop > 0 {
+                                if pop > 0 && self.display.reduce_motion {
+                                    draw_tile(
+                                        pop_to_symbol(pop),
+                                        8 + 3 * owner.0 as i16,
+                                        pos_x(ui, i),
+                                        pos_y(j),
+                                    );
+                                } else if pop > 0 {
                                     draw_tile_noise(
                                         pop_to_symbol(pop),
                                         8 + 3 * owner.0 as i16,
@@ -741,6 +1160,16 @@ impl CorApp {
                                 }
                             }
                         }
+                        if self.display.high_contrast {
+                            draw_contrast_mark(*owner, pos_x(ui, i), pos_y(j));
+                        }
+                        // Flash endangered cities, or keep them tinted with reduced motion.
+                        if *owner == state.controlled
+                            && (self.display.reduce_motion || state.time / 4 % 2 == 0)
+                            && state.is_endangered(Pos(i as i32, j as i32))
+                        {
+                            draw_tint((1., 0., 0.), 0.5, pos_x(ui, i), pos_y(j));
+                        }
                     }
                     Tile::Mine(owner) => {
                         // Draw grass.
@@ -783,10 +1212,21 @@ impl CorApp {
                     }
                     _ => {}
                 }
+                // Draw rally points.
+                let pos = Pos(i as i32, j as i32);
+                if ui.rally_from == Some(pos)
+                    || state
+                        .grid
+                        .tiles_owned_by(state.controlled)
+                        .any(|(_, t)| t.rally() == Some(pos))
+                {
+                    draw_rally_mark(pos_x(ui, i), pos_y(j));
+                }
                 // Draw flags.
-                for p in 0..MAX_PLAYERS as u32 {
-                    if state.fgs[p as usize].is_flagged(Pos(i as i32, j as i32)) {
-                        draw_tile_2h(
+                for (p, fg) in (0..MAX_PLAYERS as u32).zip(&state.fgs) {
+                    let level = fg.level(Pos(i as i32, j as i32));
+                    if level > 0 {
+                        draw_flag(
                             match Player(p) == state.controlled {
                                 true => 3,
                                 false => 4,
@@ -794,6 +1234,7 @@ impl CorApp {
                             7 + 3 * p as i16,
                             pos_x(ui, i),
                             pos_y(j),
+                            level,
                         );
                     }
                 }
@@ -820,99 +1261,69 @@ impl CorApp {
         );
         // Draw text.
         let base_y = (pos_y(state.grid.height() as i16) + 1) * TILE_HEIGHT;
-        draw_str("Gold:", Player::NEUTRAL, TILE_WIDTH, base_y);
+        let hud = self.hud;
+        // Position of the text at the given character and line
+        // of the wide arrangement.
+        let at = |col: i16, line: i16| {
+            let (col, line) = hud.at(col, line);
+            (TILE_WIDTH + col * TYPE_WIDTH, base_y + line * TYPE_HEIGHT)
+        };
+        draw_str(tr_ascii(lang, "Gold:"), Player::NEUTRAL, at(0, 0));
         draw_int(
-            state.countries[state.controlled.0 as usize].gold,
+            state.country(state.controlled).map_or(0, |c| c.gold),
             state.controlled,
-            TILE_WIDTH + 6 * TYPE_WIDTH,
-            base_y,
+            at(6, 0),
             itoa_buf,
         );
+        let [village, town, fortress] = [HabitLand::Village, HabitLand::Town, HabitLand::Fortress]
+            .map(|land| state.grid.price(state.controlled, land, &state.balance));
         draw_str(
-            "Prices: 160 240 320",
-            Player::NEUTRAL,
-            TILE_WIDTH,
-            base_y + TYPE_HEIGHT,
-        );
-        draw_str(
-            "Date:",
+            &format!(
+                "{} {} {} {}",
+                tr_ascii(lang, "Prices:"),
+                village,
+                town,
+                fortress
+            ),
             Player::NEUTRAL,
-            TILE_WIDTH + 54 * TYPE_WIDTH,
-            base_y,
+            at(0, 1),
         );
+        draw_str(tr_ascii(lang, "Date:"), Player::NEUTRAL, at(54, 0));
         let (y, m, d) = time_to_ymd(state.time);
-        draw_int(
-            y,
-            state.controlled,
-            TILE_WIDTH + 60 * TYPE_WIDTH,
-            base_y,
-            itoa_buf,
-        );
-        draw_str("-", state.controlled, TILE_WIDTH + 64 * TYPE_WIDTH, base_y);
+        draw_int(y, state.controlled, at(60, 0), itoa_buf);
+        draw_str("-", state.controlled, at(64, 0));
         if m > 9 {
-            draw_int(
-                m,
-                state.controlled,
-                TILE_WIDTH + 65 * TYPE_WIDTH,
-                base_y,
-                itoa_buf,
-            );
+            draw_int(m, state.controlled, at(65, 0), itoa_buf);
         } else {
-            draw_str("0", state.controlled, TILE_WIDTH + 65 * TYPE_WIDTH, base_y);
-            draw_int(
-                m,
-                state.controlled,
-                TILE_WIDTH + 66 * TYPE_WIDTH,
-                base_y,
-                itoa_buf,
-            );
+            draw_str("0", state.controlled, at(65, 0));
+            draw_int(m, state.controlled, at(66, 0), itoa_buf);
         }
-        draw_str("-", state.controlled, TILE_WIDTH + 67 * TYPE_WIDTH, base_y);
+        draw_str("-", state.controlled, at(67, 0));
         if d > 9 {
-            draw_int(
-                d,
-                state.controlled,
-                TILE_WIDTH + 68 * TYPE_WIDTH,
-                base_y,
-                itoa_buf,
-            );
+            draw_int(d, state.controlled, at(68, 0), itoa_buf);
         } else {
-            draw_str("0", state.controlled, TILE_WIDTH + 68 * TYPE_WIDTH, base_y);
-            draw_int(
-                d,
-                state.controlled,
-                TILE_WIDTH + 69 * TYPE_WIDTH,
-                base_y,
-                itoa_buf,
-            );
+            draw_str("0", state.controlled, at(68, 0));
+            draw_int(d, state.controlled, at(69, 0), itoa_buf);
         }
+        draw_str(tr_ascii(lang, "Speed:"), Player::NEUTRAL, at(54, 1));
         draw_str(
-            "Speed:",
-            Player::NEUTRAL,
-            TILE_WIDTH + 54 * TYPE_WIDTH,
-            base_y + TYPE_HEIGHT,
-        );
-        draw_str(
-            match state.speed {
-                Speed::Fast => "Fast",
-                Speed::Faster => "Faster",
-                Speed::Fastest => "Fastest",
-                Speed::Normal => "Normal",
-                Speed::Pause => "Pause",
-                Speed::Slow => "Slow",
-                Speed::Slower => "Slower",
-                Speed::Slowest => "Slowest",
-            },
-            Player::NEUTRAL,
-            TILE_WIDTH + 61 * TYPE_WIDTH,
-            base_y + TYPE_HEIGHT,
-        );
-        draw_str(
-            "Population:",
+            tr_ascii(
+                lang,
+                match state.speed {
+                    Speed::Fast => "Fast",
+                    Speed::Faster => "Faster",
+                    Speed::Fastest => "Fastest",
+                    Speed::Normal => "Normal",
+                    Speed::Pause => "Pause",
+                    Speed::Slow => "Slow",
+                    Speed::Slower => "Slower",
+                    Speed::Slowest => "Slowest",
+                },
+            ),
             Player::NEUTRAL,
-            TILE_WIDTH + 23 * TYPE_WIDTH,
-            base_y,
+            at(61, 1),
         );
+        draw_str(tr_ascii(lang, "Population:"), Player::NEUTRAL, at(23, 0));
         for p in 1..MAX_PLAYERS {
             let pop_str = itoa_buf.format(
                 state
@@ -922,47 +1333,75 @@ impl CorApp {
                     .units()[p],
             );
             let offset = 3 - pop_str.len();
+            let (x, y) = at(23 + 4 * (p as i16 - 1), 1);
+            draw_str(pop_str, Player(p as u32), (x + offset as i16, y));
+        }
+        if state
+            .grid
+            .cities(state.controlled)
+            .any(|(pos, _)| state.is_endangered(pos))
+        {
+            draw_str(tr_ascii(lang, "UNDER ATTACK"), state.controlled, at(0, 2));
+        }
+        if state.assist.is_active() {
+            draw_str(tr_ascii(lang, "ASSIST"), Player::NEUTRAL, at(23, 2));
+        }
+        if let Some(preset) = state.preset {
             draw_str(
-                pop_str,
-                Player(p as u32),
-                TILE_WIDTH + (23 + 4 * (p as i16 - 1)) * TYPE_WIDTH + offset as i16,
-                base_y + TYPE_HEIGHT,
+                tr_ascii(
+                    lang,
+                    match preset {
+                        Preset::Blitz => "BLITZ",
+                        Preset::Marathon => "MARATHON",
+                        _ => "CLASSIC",
+                    },
+                ),
+                Player::NEUTRAL,
+                at(31, 2),
             );
         }
+        if let Some(left) = self.time_left {
+            // Rounded up, so that 0:00 means the time is up.
+            let secs = left.as_secs() + (left.subsec_nanos() > 0) as u64;
+            draw_str(tr_ascii(lang, "Time left:"), Player::NEUTRAL, at(54, 2));
+            let clock = format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60);
+            draw_str(&clock, state.controlled, at(65, 2));
+        }
+        draw_str(tr_ascii(lang, "[Space] flag"), Player::NEUTRAL, at(0, 3));
         draw_str(
-            "[Space] flag",
-            Player::NEUTRAL,
-            TILE_WIDTH,
-            base_y + 3 * TYPE_HEIGHT,
-        );
-        draw_str(
-            "[R] or [V] build",
-            Player::NEUTRAL,
-            TILE_WIDTH + 27 * TYPE_WIDTH,
-            base_y + 3 * TYPE_HEIGHT,
-        );
-        draw_str(
-            "[X],[C] mass remove",
+            tr_ascii(lang, "[R] or [V] build"),
             Player::NEUTRAL,
-            TILE_WIDTH,
-            base_y + 4 * TYPE_HEIGHT,
+            at(27, 3),
         );
         draw_str(
-            "[S] slower [F] faster",
+            tr_ascii(lang, "[X],[C] mass remove"),
             Player::NEUTRAL,
-            TILE_WIDTH + 54 * TYPE_WIDTH,
-            base_y + 3 * TYPE_HEIGHT,
+            at(0, 4),
         );
         draw_str(
-            "[P] pause",
+            tr_ascii(lang, "[S] slower [F] faster"),
             Player::NEUTRAL,
-            TILE_WIDTH + 54 * TYPE_WIDTH,
-            base_y + 4 * TYPE_HEIGHT,
+            at(54, 3),
         );
-        // Draw line.
-        draw_line(base_y);
+        draw_str(tr_ascii(lang, "[P] pause"), Player::NEUTRAL, at(54, 4));
+        // Draw lines between the state and the keys.
+        let (columns, lines) = hud.size();
+        for block in (0..lines).step_by(5) {
+            draw_line(base_y + block * TYPE_HEIGHT, columns);
+        }
         unsafe {
-            let _: () = msg_send![&self.screen.as_ref().unwrap().0, unlockFocus];
+            let _: () = msg_send![class!(NSGraphicsContext), restoreGraphicsState];
+        }
+        #[cfg(feature = "frame-stats")]
+        if self.frame_stats {
+            let stats = output::take_frame_stats();
+            eprintln!(
+                "[FRAME] {} blits, {} fills, {:.2} ms",
+                stats.blits,
+                // Background.
+                stats.fills + 1,
+                frame_start.elapsed().as_secs_f64() * 1e3
+            );
         }
 
         // Flush.
@@ -979,23 +1418,85 @@ impl CorApp {
         pool.drain();
     }
 
-    /// Returns `(screen_size, old_frame)`.
-    fn init_screen(&mut self) -> (CGSize, CGRect) {
-        let screen_size = CGSize::new(
+    /// Size of the screen with the given arrangement of the text,
+    /// in pixels of the sprites.
+    fn screen_size(&self, hud: Hud) -> CGSize {
+        let (columns, lines) = hud.size();
+        CGSize::new(
             i16::max(
                 (self.ui.as_ref().unwrap().xlen + 2) as i16 * TILE_WIDTH,
-                75 * TYPE_WIDTH + TILE_WIDTH,
+                columns * TYPE_WIDTH + TILE_WIDTH,
             )
             .into(),
             ((self.state.as_ref().unwrap().grid.height() as u16 + 3) as i16 * TILE_HEIGHT
-                + 5 * TYPE_HEIGHT)
+                + lines * TYPE_HEIGHT)
                 .into(),
+        )
+    }
+
+    /// Scale factor of the display the game window is on.
+    #[inline]
+    fn backing_scale(&self) -> CGFloat {
+        unsafe { msg_send![&self.game_window.objc, backingScaleFactor] }
+    }
+
+    /// Creates the screen of the given size, with every pixel of the
+    /// sprites taking `scale` device pixels, and shows it.
+    ///
+    /// Returns the size of the screen in points.
+    fn make_screen(&mut self, screen_size: CGSize, scale: CGFloat) -> CGSize {
+        let view_size = CGSize::new(
+            screen_size.width * scale / self.backing_scale(),
+            screen_size.height * scale / self.backing_scale(),
         );
-        let old_frame: CGRect;
         unsafe {
+            // The bitmap has a pixel per device pixel of the view,
+            // and the image the size of the view in points.
+            let color_space = NSString::new("NSDeviceRGBColorSpace");
+            let alloc: id = msg_send![class!(NSBitmapImageRep), alloc];
+            let rep: id = msg_send![alloc, initWithBitmapDataPlanes:std::ptr::null_mut::<c_void>()
+                pixelsWide:((screen_size.width * scale) as NSInteger)
+                pixelsHigh:((screen_size.height * scale) as NSInteger)
+                bitsPerSample:(8 as NSInteger)
+                samplesPerPixel:(4 as NSInteger)
+                hasAlpha:Bool::YES
+                isPlanar:Bool::NO
+                colorSpaceName:&*color_space.objc
+                bytesPerRow:(0 as NSInteger)
+                bitsPerPixel:(0 as NSInteger)];
             let alloc: id = msg_send![class!(NSImage), alloc];
-            let obj: id = msg_send![alloc, initWithSize:screen_size];
+            let obj: id = msg_send![alloc, initWithSize:view_size];
+            let _: () = msg_send![obj, addRepresentation:rep];
+            let _: () = msg_send![rep, release];
+            // The bitmap is drawn into directly, so the image is not
+            // to keep a copy of it.
+            let _: () = msg_send![obj, setCacheMode:NSImageCacheNever];
             self.screen = Some(Image::with(obj));
+        }
+        self.game_window
+            .delegate
+            .as_ref()
+            .unwrap()
+            .game_view
+            .set_image(self.screen.as_ref().unwrap());
+        view_size
+    }
+
+    /// Creates the screen for a new game, and resizes the window to fit it.
+    ///
+    /// Returns the frame of the window before.
+    fn init_screen(&mut self) -> CGRect {
+        self.hud = Hud::Wide;
+        let screen_size = self.screen_size(self.hud);
+        // Sprites are scaled up by a whole number of device pixels,
+        // so that they stay crisp on Retina displays.
+        let scale = (self.display.scale() * self.backing_scale())
+            .round()
+            .max(1.);
+        let view_size = self.make_screen(screen_size, scale);
+        self.fitted = view_size;
+        let old_frame: CGRect;
+        unsafe {
             // Resize window to fit `screen`.
             old_frame = msg_send![&self.game_window.objc, frame];
             let old_content: CGRect =
@@ -1003,9 +1504,9 @@ impl CorApp {
             let new_content = CGRect::new(
                 &CGPoint::new(
                     old_content.origin.x,
-                    old_content.origin.y + old_content.size.height - screen_size.height,
+                    old_content.origin.y + old_content.size.height - view_size.height,
                 ),
-                &screen_size,
+                &view_size,
             );
             let new_frame: CGRect =
                 msg_send![&self.game_window.objc, frameRectForContentRect:new_content];
@@ -1014,16 +1515,59 @@ impl CorApp {
                 let _: () = msg_send![&this.game_window.objc, setFrame:new_frame display:Bool::YES animate:Bool::YES];
             });
         }
-        self.game_window
+        old_frame
+    }
+
+    /// Fits the screen to the game view once the window is resized,
+    /// returning its size in pixels of the sprites.
+    ///
+    /// The text is arranged for the shape of the view, and the screen
+    /// scaled up by the largest whole number of device pixels that fits,
+    /// leaving black bars around it. Views smaller than the screen scale
+    /// it down instead.
+    fn fit_screen(&mut self) -> CGSize {
+        let bounds: CGRect = self
+            .game_window
             .delegate
             .as_ref()
             .unwrap()
             .game_view
-            .set_image(self.screen.as_ref().unwrap());
-        (screen_size, old_frame)
+            .objc
+            .get(|obj| unsafe { msg_send![obj, bounds] });
+        let size = bounds.size;
+        if (size.width, size.height) == (self.fitted.width, self.fitted.height) {
+            return self.screen_size(self.hud);
+        }
+        self.fitted = size;
+        let backing = self.backing_scale();
+        let fit = |screen: CGSize| {
+            (size.width * backing / screen.width).min(size.height * backing / screen.height)
+        };
+        // The wide arrangement wins ties.
+        let hud = [Hud::Narrow, Hud::Wide]
+            .into_iter()
+            .max_by(|&a, &b| fit(self.screen_size(a)).total_cmp(&fit(self.screen_size(b))))
+            .unwrap_or(Hud::Wide);
+        let screen_size = self.screen_size(hud);
+        self.hud = hud;
+        self.make_screen(screen_size, fit(screen_size).floor().max(1.));
+        screen_size
     }
 }
 
+/// Configuration of the game window, which can be resized.
+#[inline]
+fn game_window_config() -> WindowConfig {
+    let mut config = WindowConfig::default();
+    config.set_styles(&[
+        WindowStyle::Titled,
+        WindowStyle::Closable,
+        WindowStyle::Miniaturizable,
+        WindowStyle::Resizable,
+    ]);
+    config
+}
+
 #[inline]
 fn fixed_size_window_config() -> WindowConfig {
     let mut config = WindowConfig::default();
@@ -1057,7 +1601,7 @@ impl WindowDelegate for AboutWindow {
     fn did_load(&mut self, window: Window) {
         self.window.set(window);
         self.window.set_content_size(390, 125);
-        self.window.set_title("About");
+        self.window.set_title(Lang::from_env().tr("About"));
 
         set_font(&self.text, "Menlo", None);
 
@@ -1106,8 +1650,9 @@ impl WindowDelegate for HelpWindow {
     fn did_load(&mut self, window: Window) {
         self.window.set(window);
         self.window.set_content_size(390, 600);
-        self.window.set_title("Help");
-        self.text.set_text(cli_parser::HELP_MSG);
+        let lang = Lang::from_env();
+        self.window.set_title(lang.tr("Help"));
+        self.text.set_text(lang.help_msg());
         set_font(&self.text, "Menlo", Some(8.));
         self.window.set_content_view(&self.text);
     }
@@ -1149,7 +1694,7 @@ impl GameWindow {
 
         self.err_msg.set_text_color(Color::Label);
         self.err_msg
-            .set_text("Preference parsing error will be emitted here.");
+            .set_text(Lang::from_env().tr("Preference parsing error will be emitted here."));
         if main.as_bool() {
             self.window.set_content_view(&self.err_msg);
         } else {
@@ -1184,12 +1729,33 @@ impl WindowDelegate for GameWindow {
     fn did_load(&mut self, window: Window) {
         self.window.set(window);
         self.window.set_content_size(200, 150);
+        // The screen is scaled up by whole pixels when drawn, so it is
+        // only scaled down here, to fit views smaller than it, centered
+        // between black bars.
+        self.game_view.set_background_color(Color::rgb(0, 0, 0));
+        self.game_view.objc.with_mut(|obj| unsafe {
+            let _: () = msg_send![obj, setImageScaling:NSImageScaleProportionallyDown];
+        });
         self.restore(false);
     }
 }
 
-/// 10 ms.
-const DELAY: Duration = Duration::from_nanos(10_000_000);
+/// Copied from `icrate`.
+#[allow(non_upper_case_globals)]
+const NSImageScaleProportionallyDown: NSUInteger = 0;
+
+/// Copied from `icrate`.
+#[allow(non_upper_case_globals)]
+const NSImageInterpolationNone: NSUInteger = 1;
+
+/// Copied from `icrate`.
+#[allow(non_upper_case_globals)]
+const NSImageCacheNever: NSUInteger = 3;
+
+#[link(name = "AppKit", kind = "framework")]
+extern "C" {
+    fn NSBeep();
+}
 
 #[inline]
 fn slowdown(speed: Speed) -> u16 {
@@ -3,11 +3,12 @@ use cacao::{
         base::CGFloat,
         geometry::{CGPoint, CGRect, CGSize},
     },
-    foundation::NSUInteger,
+    foundation::{id, NSUInteger},
     image::Image,
-    objc::msg_send,
+    objc::{class, msg_send},
 };
-use curseofrust::{state, Grid, Player, Pos};
+use cli_parser::Lang;
+use curseofrust::{state, Grid, Player, Pos, MAX_PLAYERS};
 use itoa::{Buffer, Integer};
 
 thread_local! {
@@ -19,15 +20,90 @@ thread_local! {
     static TILE: Image = Image::with_data(include_bytes!("../../images/tileset.gif"));
 }
 
+/// Draw calls issued since the last [`take_frame_stats`].
+#[cfg(feature = "frame-stats")]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct FrameStats {
+    /// Images drawn, which are the tiles, characters and lines.
+    pub blits: u32,
+    /// Rectangles filled with a color.
+    pub fills: u32,
+}
+
+#[cfg(feature = "frame-stats")]
+thread_local! {
+    static FRAME_STATS: std::cell::Cell<FrameStats> = Default::default();
+}
+
+/// Returns the draw calls issued since the last call, resetting them.
+#[cfg(feature = "frame-stats")]
+pub fn take_frame_stats() -> FrameStats {
+    FRAME_STATS.take()
+}
+
+/// Counts a draw call of the given kind, with the `frame-stats` feature.
+macro_rules! count_draw {
+    ($kind:ident) => {
+        #[cfg(feature = "frame-stats")]
+        FRAME_STATS.with(|stats| {
+            let mut s = stats.get();
+            s.$kind += 1;
+            stats.set(s);
+        });
+    };
+}
+
 pub const TILE_WIDTH: i16 = 32;
 pub const TILE_HEIGHT: i16 = 16;
 pub const TYPE_WIDTH: i16 = 9;
 pub const TYPE_HEIGHT: i16 = 15;
 
+/// Arrangement of the text under the map, picked by the shape
+/// of the game window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hud {
+    /// Three columns side by side.
+    Wide,
+    /// The right column under the other two, for narrow windows.
+    Narrow,
+}
+
+impl Hud {
+    /// Characters across and lines down the text takes.
+    pub fn size(self) -> (i16, i16) {
+        match self {
+            Hud::Wide => (75, 5),
+            Hud::Narrow => (54, 10),
+        }
+    }
+
+    /// Gets the character and line of the text at the given ones
+    /// of the wide arrangement.
+    #[inline]
+    pub fn at(self, column: i16, line: i16) -> (i16, i16) {
+        match self {
+            Hud::Narrow if column >= 54 => (column - 54, line + 5),
+            _ => (column, line),
+        }
+    }
+}
+
 const TYPE_FIRST: u8 = 33;
 const LINE_LENGTH: i16 = 32;
 const COLOR_OFFSET: i16 = 3;
 
+/// Player colors used in high-contrast mode, as RGB.
+const CONTRAST_COLORS: [(CGFloat, CGFloat, CGFloat); MAX_PLAYERS] = [
+    (1., 1., 1.),
+    (0., 1., 0.),
+    (0.2, 0.4, 1.),
+    (1., 1., 0.),
+    (1., 0., 0.),
+    (1., 0., 1.),
+    (0., 1., 1.),
+    (1., 1., 1.),
+];
+
 /// Copied from `icrate`.\
 /// 2024-07-01 update: `icrate` is dead.
 #[allow(non_upper_case_globals)]
@@ -45,17 +121,18 @@ pub fn pos_y(j: i16) -> i16 {
 }
 
 macro_rules! draw_raw {
-    ($image:expr,$dest:ident,$from:ident) => {
+    ($image:expr,$dest:ident,$from:ident) => {{
+        count_draw!(blits);
         unsafe{
             let _: () = msg_send![$image, drawAtPoint:$dest fromRect:$from, operation:NSCompositingOperationSourceOver fraction:(1. as CGFloat)];
         }
-    };
+    }};
 }
 
 /// Draws string with specified color.\
 /// All chars in `string` should be ascii char.\
-/// You should call `lockFocusFlipped:YES` before calling this.
-pub fn draw_str(string: &str, color: Player, dest_x: i16, dest_y: i16) {
+/// The screen should be the current, flipped context, as set up by `CorApp::render`.
+pub fn draw_str(string: &str, color: Player, (dest_x, dest_y): (i16, i16)) {
     let offset: i16 = match color {
         Player::NEUTRAL => 0,
         Player(x) => COLOR_OFFSET + x as i16,
@@ -76,6 +153,18 @@ pub fn draw_str(string: &str, color: Player, dest_x: i16, dest_y: i16) {
     }
 }
 
+/// Translates a label drawn with [`draw_str`].\
+/// Falls back to English, as the bitmap font only contains ASCII characters.
+#[inline]
+pub fn tr_ascii(lang: Lang, msgid: &'static str) -> &'static str {
+    let msg = lang.tr(msgid);
+    if msg.is_ascii() {
+        msg
+    } else {
+        msgid
+    }
+}
+
 /// Draws common tiles like grassland.
 pub fn draw_tile(src_i: i16, src_j: i16, dest_i: i16, dest_j: i16) {
     let tile_rect = CGRect::new(
@@ -105,6 +194,24 @@ pub fn draw_tile_2h(src_i: i16, src_j: i16, dest_i: i16, dest_j: i16) {
     TILE.with(|tile| draw_raw!(&tile.0, dest_point, tile_rect));
 }
 
+/// Draws a flag sprite once per stacked flag, each shifted to the right.
+pub fn draw_flag(src_i: i16, src_j: i16, dest_i: i16, dest_j: i16, level: u32) {
+    let tile_rect = CGRect::new(
+        &CGPoint::new(
+            (src_i * TILE_WIDTH) as f64,
+            ((src_j - 1) * TILE_HEIGHT) as f64,
+        ),
+        &CGSize::new(TILE_WIDTH as f64, (TILE_HEIGHT * 2) as f64),
+    );
+    for k in 0..level as i16 {
+        let dest_point = CGPoint::new(
+            (dest_i * TILE_WIDTH + dest_j * TILE_WIDTH / 2 + k * TILE_WIDTH / 8) as f64,
+            ((dest_j - 1) * TILE_HEIGHT) as f64,
+        );
+        TILE.with(|tile| draw_raw!(&tile.0, dest_point, tile_rect));
+    }
+}
+
 /// Draws tiles with offset like population.
 pub fn draw_tile_noise(src_i: i16, src_j: i16, dest_i: i16, dest_j: i16, var: i16) {
     let tile_rect = CGRect::new(
@@ -120,6 +227,66 @@ pub fn draw_tile_noise(src_i: i16, src_j: i16, dest_i: i16, dest_j: i16, var: i1
     TILE.with(|tile| draw_raw!(&tile.0, dest_point, tile_rect));
 }
 
+/// Draws a bar in the high-contrast color of `owner` under a tile.\
+/// The screen should be the current, flipped context, as set up by `CorApp::render`.
+pub fn draw_contrast_mark(owner: Player, dest_i: i16, dest_j: i16) {
+    if owner.is_neutral() {
+        return;
+    }
+    let Some(&(r, g, b)) = CONTRAST_COLORS.get(owner.0 as usize) else {
+        return;
+    };
+    let rect = CGRect::new(
+        &CGPoint::new(
+            (dest_i * TILE_WIDTH + dest_j * TILE_WIDTH / 2 + TILE_WIDTH / 4) as f64,
+            ((dest_j + 1) * TILE_HEIGHT - 3) as f64,
+        ),
+        &CGSize::new((TILE_WIDTH / 2) as f64, 3.),
+    );
+    count_draw!(fills);
+    unsafe {
+        let color: id = msg_send![class!(NSColor), colorWithCalibratedRed:r green:g blue:b alpha:(1. as CGFloat)];
+        let _: () = msg_send![color, drawSwatchInRect:rect];
+    }
+}
+
+/// Tints a tile with the given RGB color and opacity.\
+/// The screen should be the current, flipped context, as set up by `CorApp::render`.
+pub fn draw_tint((r, g, b): (CGFloat, CGFloat, CGFloat), alpha: CGFloat, dest_i: i16, dest_j: i16) {
+    let rect = CGRect::new(
+        &CGPoint::new(
+            (dest_i * TILE_WIDTH + dest_j * TILE_WIDTH / 2) as f64,
+            (dest_j * TILE_HEIGHT) as f64,
+        ),
+        &CGSize::new(TILE_WIDTH as f64, TILE_HEIGHT as f64),
+    );
+    count_draw!(fills);
+    unsafe {
+        let color: id =
+            msg_send![class!(NSColor), colorWithCalibratedRed:r green:g blue:b alpha:alpha];
+        let _: () = msg_send![color, set];
+        let _: () = msg_send![class!(NSBezierPath), fillRect:rect];
+    }
+}
+
+/// Draws a dot in the middle of a tile marking a rally point
+/// or the city choosing one.\
+/// The screen should be the current, flipped context, as set up by `CorApp::render`.
+pub fn draw_rally_mark(dest_i: i16, dest_j: i16) {
+    let rect = CGRect::new(
+        &CGPoint::new(
+            (dest_i * TILE_WIDTH + dest_j * TILE_WIDTH / 2 + TILE_WIDTH / 2 - 2) as f64,
+            (dest_j * TILE_HEIGHT + TILE_HEIGHT / 2 - 2) as f64,
+        ),
+        &CGSize::new(4., 4.),
+    );
+    count_draw!(fills);
+    unsafe {
+        let color: id = msg_send![class!(NSColor), whiteColor];
+        let _: () = msg_send![color, drawSwatchInRect:rect];
+    }
+}
+
 /// Return value:
 /// 1. left top
 /// 2. right top
@@ -176,18 +343,19 @@ pub fn time_to_ymd(time: u64) -> (u64, u64, u64) {
     (year, month, day)
 }
 
-pub fn draw_line(base_y: i16) {
+pub fn draw_line(base_y: i16, columns: i16) {
     const LINE_WIDTH: f64 = 555.;
-    let ui_rect = CGRect::new(&CGPoint::new(0., 0.), &CGSize::new(LINE_WIDTH, 1.));
+    let width = LINE_WIDTH.min((columns * TYPE_WIDTH) as f64);
+    let ui_rect = CGRect::new(&CGPoint::new(0., 0.), &CGSize::new(width, 1.));
     let dest_point = CGPoint::new(
-        TILE_WIDTH as f64 + 75. * TYPE_WIDTH as f64 / 2. - LINE_WIDTH / 2.,
+        TILE_WIDTH as f64 + columns as f64 * TYPE_WIDTH as f64 / 2. - width / 2.,
         base_y as f64 + TYPE_HEIGHT as f64 * 5. / 2.,
     );
     UI.with(|ui| draw_raw!(&ui.0, dest_point, ui_rect));
 }
 
 /// Draws int with specified color.\
-/// You should call `lockFocusFlipped:YES` before calling this.
-pub fn draw_int<I: Integer>(num: I, color: Player, dest_x: i16, dest_y: i16, buf: &mut Buffer) {
-    draw_str(buf.format(num), color, dest_x, dest_y);
+/// The screen should be the current, flipped context, as set up by `CorApp::render`.
+pub fn draw_int<I: Integer>(num: I, color: Player, dest: (i16, i16), buf: &mut Buffer) {
+    draw_str(buf.format(num), color, dest);
 }
@@ -0,0 +1,523 @@
+//! Harness for bots playing over the protocol.
+//!
+//! A [`Bot`] sees the game through an [`ObserverView`], which only
+//! exposes what its player could scout, and answers with [`Command`]s.
+//! [`BotRunner`] connects it to a server as a regular UDP client, so
+//! bots of any author can meet in tournaments without trusting each
+//! other with the full state.
+//!
+//! [`KingBot`] plays like the AI of singleplayer games.
+
+use std::{
+    io,
+    net::{SocketAddr, UdpSocket},
+    time::{Duration, Instant},
+};
+
+use curseofrust::{
+    grid::{HabitLand, Tile},
+    state::{BasicOpts, State},
+    FlagGrid, King, Player, Pos, Strategy, MAX_HEIGHT, MAX_WIDTH,
+};
+
+use crate::{
+    apply_s2c_msg, bytemuck, capability, client_msg, fragment::Reassembler, server_msg, C2SData,
+    S2CData, StateOrder, C2S_SIZE, MAX_MISSED_PINGS, PING_INTERVAL, S2C_SIZE,
+};
+
+/// Farthest distance from own tiles at which other tiles are visible.
+pub const VISION_RADIUS: u32 = 2;
+/// Interval between keep-alive packets.
+const ALIVE_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Read-only view of the game from the perspective of a player.
+///
+/// Tiles farther than [`VISION_RADIUS`] from every tile of the
+/// player are hidden by fog.
+pub struct ObserverView<'a> {
+    state: &'a State,
+    player: Player,
+    visible: Vec<Vec<bool>>,
+}
+
+impl<'a> ObserverView<'a> {
+    /// Creates a view of the given state for the given player.
+    pub fn new(state: &'a State, player: Player) -> Self {
+        let grid = &state.grid;
+        let mut visible = vec![vec![false; grid.height() as usize]; grid.width() as usize];
+        for (pos, _) in grid.tiles().filter(|(_, t)| t.owner() == player) {
+            for Pos(x, y) in (0..=VISION_RADIUS).flat_map(|r| pos.ring(r)) {
+                if let Some(v) = visible
+                    .get_mut(x as usize)
+                    .and_then(|a| a.get_mut(y as usize))
+                {
+                    *v = true;
+                }
+            }
+        }
+        Self {
+            state,
+            player,
+            visible,
+        }
+    }
+
+    /// Gets the player of this view.
+    #[inline]
+    pub fn player(&self) -> Player {
+        self.player
+    }
+
+    /// Gets the game time.
+    #[inline]
+    pub fn time(&self) -> u64 {
+        self.state.time
+    }
+
+    /// Gets the gold of the player.
+    pub fn my_gold(&self) -> u64 {
+        self.state
+            .countries
+            .get(self.player.0 as usize)
+            .map_or(0, |c| c.gold)
+    }
+
+    /// Whether the tile at the given position is out of the fog.
+    pub fn is_visible(&self, Pos(x, y): Pos) -> bool {
+        x >= 0
+            && y >= 0
+            && self
+                .visible
+                .get(x as usize)
+                .and_then(|a| a.get(y as usize))
+                .is_some_and(|v| *v)
+    }
+
+    /// Gets the tile at the given position, if visible.
+    pub fn tile(&self, pos: Pos) -> Option<&'a Tile> {
+        self.is_visible(pos)
+            .then(|| self.state.grid.tile(pos))
+            .flatten()
+    }
+
+    /// Iterates over tiles owned by the player.
+    pub fn my_tiles(&self) -> impl Iterator<Item = (Pos, &'a Tile)> + '_ {
+        self.state
+            .grid
+            .tiles()
+            .filter(move |(_, t)| t.owner() == self.player)
+    }
+
+    /// Iterates over cities of the player.
+    pub fn my_cities(&self) -> impl Iterator<Item = (Pos, HabitLand)> + 'a {
+        self.state.grid.cities(self.player)
+    }
+
+    /// Iterates over visible tiles held by other players,
+    /// with their owner and population.
+    pub fn visible_enemies(&self) -> impl Iterator<Item = (Pos, Player, u16)> + '_ {
+        self.state
+            .grid
+            .tiles()
+            .filter(move |&(pos, _)| self.is_visible(pos))
+            .filter_map(move |(pos, t)| {
+                let owner = t.owner();
+                let pop = t.units()[owner.0 as usize];
+                (owner != self.player && !owner.is_neutral() && pop > 0)
+                    .then_some((pos, owner, pop))
+            })
+    }
+}
+
+/// Command of a bot, sent to the server as a client message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Command {
+    /// Builds or upgrades a city.
+    Build(Pos),
+    /// Stacks the given number of flags.
+    Flag(Pos, u8),
+    /// Removes all flags stacked on the position.
+    Unflag(Pos),
+    UnflagAll,
+    UnflagHalf,
+    FlagCities,
+    FlagFrontline,
+    /// Builds a road.
+    BuildRoad(Pos),
+    /// Queues or unqueues an upgrade.
+    QueueBuild(Pos, bool),
+    /// Sets or clears the rally point of a city.
+    Rally {
+        city: Pos,
+        target: Option<Pos>,
+    },
+}
+
+impl Command {
+    /// Encodes this command as a client packet.
+    ///
+    /// Returns [`None`] for a rally point out of reach.
+    /// See [`C2SData::rally`].
+    pub fn encode(self) -> Option<[u8; C2S_SIZE]> {
+        let (msg, data) = match self {
+            Command::Build(pos) => (client_msg::BUILD, (pos, 0).into()),
+            Command::Flag(pos, n) => (client_msg::FLAG_ON, (pos, n).into()),
+            Command::Unflag(pos) => (client_msg::FLAG_OFF, (pos, 0).into()),
+            Command::UnflagAll => (client_msg::FLAG_OFF_ALL, (Pos(0, 0), 0).into()),
+            Command::UnflagHalf => (client_msg::FLAG_OFF_HALF, (Pos(0, 0), 0).into()),
+            Command::FlagCities => (client_msg::FLAG_CITIES, (Pos(0, 0), 0).into()),
+            Command::FlagFrontline => (client_msg::FLAG_FRONTLINE, (Pos(0, 0), 0).into()),
+            Command::BuildRoad(pos) => (client_msg::BUILD_ROAD, (pos, 0).into()),
+            Command::QueueBuild(pos, queued) => {
+                (client_msg::QUEUE_BUILD, (pos, queued as u8).into())
+            }
+            Command::Rally { city, target } => (client_msg::RALLY, C2SData::rally(city, target)?),
+        };
+        let mut packet = [0u8; C2S_SIZE];
+        packet[0] = msg;
+        packet[1..].copy_from_slice(bytemuck::bytes_of(&data));
+        Some(packet)
+    }
+}
+
+/// A bot playing through a [`BotRunner`].
+pub trait Bot {
+    /// Decides what to do on a new state.
+    ///
+    /// Commands pushed to `commands` are sent to the server.
+    fn act(&mut self, view: &ObserverView<'_>, commands: &mut Vec<Command>);
+}
+
+/// A [`Bot`] playing like the AI of singleplayer games,
+/// with a [`King`] of the given strategy.
+///
+/// Unlike other bots, kings see through the fog, as they do
+/// in singleplayer games.
+pub struct KingBot {
+    strategy: Strategy,
+    king: Option<King>,
+    /// Flags placed by the king, as sent to the server.
+    flags: FlagGrid,
+    /// Whether the map should be evaluated again, as the king built.
+    stale: bool,
+}
+
+impl KingBot {
+    /// Creates a bot playing with the given strategy.
+    pub fn new(strategy: Strategy) -> Self {
+        Self {
+            strategy,
+            king: None,
+            flags: FlagGrid::new(0, 0),
+            stale: true,
+        }
+    }
+}
+
+impl Bot for KingBot {
+    fn act(&mut self, view: &ObserverView<'_>, commands: &mut Vec<Command>) {
+        let state = view.state;
+        let grid = &state.grid;
+        let king = match self.king {
+            Some(ref mut king) if king.player() == view.player => king,
+            _ => {
+                self.stale = true;
+                self.flags = state
+                    .fgs
+                    .get(view.player.0 as usize)
+                    .cloned()
+                    .unwrap_or_else(|| FlagGrid::new(grid.width(), grid.height()));
+                self.king.insert(King::new(
+                    view.player,
+                    self.strategy,
+                    grid.width(),
+                    grid.height(),
+                ))
+            }
+        };
+        if self.stale {
+            king.evaluate_map(grid, state.difficulty, &state.balance);
+            self.stale = false;
+        }
+
+        // Flags are sent as the king places and removes them, rather
+        // than diffed against the state, which lags behind commands.
+        let mut placed = self.flags.clone();
+        king.place_flags(grid, &mut placed, &state.balance);
+        for (pos, _) in grid.tiles() {
+            match (self.flags.is_flagged(pos), placed.is_flagged(pos)) {
+                (false, true) => commands.push(Command::Flag(pos, 1)),
+                (true, false) => commands.push(Command::Unflag(pos)),
+                _ => {}
+            }
+        }
+        self.flags = placed;
+
+        if let Some(pos) = king.best_build(grid) {
+            let affordable = match grid.tile(pos) {
+                Some(&Tile::Habitable { mut land, .. }) => land.upgrade().is_some_and(|_| {
+                    view.my_gold()
+                        >= grid.price(king.player(), land, &state.balance)
+                            + king.reserve(&state.balance)
+                }),
+                _ => false,
+            };
+            if affordable {
+                commands.push(Command::Build(pos));
+                self.stale = true;
+            }
+        }
+    }
+}
+
+/// How a game ended for a bot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    /// The game was won by the given player.
+    Winner(Player),
+    /// The player of the bot was eliminated.
+    Eliminated,
+}
+
+/// Plays a [`Bot`] on a server over UDP.
+pub struct BotRunner {
+    socket: UdpSocket,
+    state: State,
+    order: StateOrder,
+    reassembler: Reassembler,
+}
+
+impl BotRunner {
+    /// Binds to `local` and connects to the server at `server`.
+    ///
+    /// The server seats the bot once it receives the first packet,
+    /// which is sent by [`Self::run`].
+    pub fn connect(local: SocketAddr, server: SocketAddr) -> io::Result<Self> {
+        let socket = UdpSocket::bind(local)?;
+        socket.connect(server)?;
+        socket.set_read_timeout(Some(ALIVE_INTERVAL))?;
+
+        // Tiles out of the map of the server are received as void,
+        // so the largest grid fits every map.
+        let mut b_opt = BasicOpts::default();
+        b_opt.width = MAX_WIDTH;
+        b_opt.height = MAX_HEIGHT;
+        let mut state = State::new(b_opt).map_err(io::Error::other)?;
+        state.time = 0;
+        Ok(Self {
+            socket,
+            state,
+            order: StateOrder::default(),
+            reassembler: Reassembler::new(),
+        })
+    }
+
+    /// Plays the given bot until the game ends.
+    ///
+    /// Fails with [`io::ErrorKind::TimedOut`] if the server stops pinging
+    /// the bot, as the connection is then likely gone.
+    pub fn run(&mut self, bot: &mut impl Bot) -> io::Result<Outcome> {
+        const ALIVE_PACKET: [u8; C2S_SIZE] = [
+            client_msg::IS_ALIVE,
+            0,
+            0,
+            capability::RLE | capability::FRAGMENT | capability::PING | capability::TILE_FLAGS,
+        ];
+        const PONG_PACKET: [u8; C2S_SIZE] = [client_msg::PONG, 0, 0, 0];
+
+        let mut buf = [0u8; S2C_SIZE];
+        let mut commands = vec![];
+        let mut alive_at = Instant::now();
+        // Servers not pinging at all are older ones, not gone ones.
+        let mut pinged_at = None::<Instant>;
+        self.socket.send(&ALIVE_PACKET)?;
+        loop {
+            if alive_at.elapsed() >= ALIVE_INTERVAL {
+                alive_at = Instant::now();
+                self.socket.send(&ALIVE_PACKET)?;
+            }
+            if pinged_at.is_some_and(|at| at.elapsed() >= PING_INTERVAL * MAX_MISSED_PINGS) {
+                return Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    "the server stopped responding",
+                ));
+            }
+
+            let nread = match self.socket.recv(&mut buf) {
+                Ok(nread) => nread,
+                Err(e)
+                    if matches!(
+                        e.kind(),
+                        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                    ) =>
+                {
+                    continue
+                }
+                Err(e) => return Err(e),
+            };
+            let reassembled;
+            let frame = match buf[..nread] {
+                [server_msg::STATE_FRAGMENT, ref fragment @ ..] => {
+                    match self.reassembler.push(fragment) {
+                        Some(frame) => {
+                            reassembled = frame;
+                            &reassembled[..]
+                        }
+                        None => continue,
+                    }
+                }
+                ref frame => frame,
+            };
+            let Some((&msg, data)) = frame.split_first() else {
+                continue;
+            };
+            let data: S2CData = match (msg, data) {
+                (server_msg::STATE, _) if frame.len() == S2C_SIZE => *bytemuck::from_bytes(data),
+                (server_msg::STATE_RLE, _) => match S2CData::decode_rle(data) {
+                    Some(data) => data,
+                    None => continue,
+                },
+                (server_msg::PING, _) => {
+                    pinged_at = Some(Instant::now());
+                    self.socket.send(&PONG_PACKET)?;
+                    continue;
+                }
+                // Bots give no password.
+                (server_msg::CONN_REJECTED, _) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::PermissionDenied,
+                        "the server rejected the bot",
+                    ))
+                }
+                (server_msg::GAME_OVER, &[winner, ..]) => {
+                    return Ok(Outcome::Winner(Player(winner as u32)))
+                }
+                (server_msg::ELIMINATED, &[player, ..])
+                    if Player(player as u32) == self.state.controlled =>
+                {
+                    return Ok(Outcome::Eliminated)
+                }
+                _ => continue,
+            };
+            // Stale states arrive out of order over UDP.
+            if !self.order.accept(&self.state, &data)
+                || apply_s2c_msg(&mut self.state, data).is_err()
+            {
+                continue;
+            }
+            if data.seq() != 0 {
+                let mut ack = [client_msg::STATE_ACK; C2S_SIZE];
+                ack[1..].copy_from_slice(bytemuck::bytes_of(&C2SData::state_ack(data.seq())));
+                self.socket.send(&ack)?;
+            }
+            if let Some(winner) = self.state.rules.winner(&self.state) {
+                return Ok(Outcome::Winner(winner));
+            }
+
+            let view = ObserverView::new(&self.state, self.state.controlled);
+            bot.act(&view, &mut commands);
+            for packet in commands.drain(..).filter_map(Command::encode) {
+                self.socket.send(&packet)?;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use curseofrust::state::{BasicOpts, State};
+
+    use super::*;
+    use crate::apply_c2s_msg;
+
+    fn sample_state() -> State {
+        let mut b_opt = BasicOpts::default();
+        b_opt.seed = 1;
+        let mut state = State::new(b_opt).unwrap();
+        for tile in state.grid.raw_tiles_mut().iter_mut().flatten() {
+            *tile = Tile::Habitable {
+                land: HabitLand::Grassland,
+                units: [0; 8],
+                owner: Player::NEUTRAL,
+                rally: None,
+                landscape: Default::default(),
+                road: false,
+            };
+        }
+        state
+    }
+
+    fn occupy(state: &mut State, pos: Pos, player: Player, pop: u16) {
+        let tile = state.grid.tile_mut(pos).unwrap();
+        tile.set_owner(player);
+        tile.units_mut().unwrap()[player.0 as usize] = pop;
+    }
+
+    #[test]
+    fn fog_hides_far_enemies() {
+        let mut state = sample_state();
+        occupy(&mut state, Pos(5, 5), Player(1), 10);
+        occupy(&mut state, Pos(6, 5), Player(2), 10);
+        occupy(&mut state, Pos(15, 5), Player(3), 10);
+
+        let view = ObserverView::new(&state, Player(1));
+        assert!(view.is_visible(Pos(5, 5)));
+        assert!(view.tile(Pos(15, 5)).is_none());
+        assert_eq!(
+            view.visible_enemies().collect::<Vec<_>>(),
+            [(Pos(6, 5), Player(2), 10)]
+        );
+        assert_eq!(
+            view.my_tiles().map(|(pos, _)| pos).collect::<Vec<_>>(),
+            [Pos(5, 5)]
+        );
+    }
+
+    #[test]
+    fn commands_apply_on_server() {
+        let mut state = sample_state();
+        occupy(&mut state, Pos(5, 5), Player(1), 10);
+        state.countries[1].gold = 1000;
+
+        for command in [Command::Build(Pos(5, 5)), Command::Flag(Pos(6, 5), 2)] {
+            let packet = command.encode().unwrap();
+            let data: C2SData = *bytemuck::from_bytes(&packet[1..]);
+            apply_c2s_msg(&mut state, Player(1), packet[0], data).unwrap();
+        }
+        assert!(matches!(
+            state.grid.tile(Pos(5, 5)),
+            Some(Tile::Habitable {
+                land: HabitLand::Village,
+                ..
+            })
+        ));
+        assert!(state.fgs[1].is_flagged(Pos(6, 5)));
+
+        let far = Command::Rally {
+            city: Pos(5, 5),
+            target: Some(Pos(20, 5)),
+        };
+        assert_eq!(far.encode(), None);
+    }
+
+    #[test]
+    fn king_bot_builds_when_affordable() {
+        let mut state = sample_state();
+        // Kings only build away from borders.
+        for pos in (0..=1).flat_map(|r| Pos(5, 5).ring(r)) {
+            occupy(&mut state, pos, Player(1), 10);
+        }
+        let mut bot = KingBot::new(Strategy::Opportunist);
+        let mut commands = vec![];
+
+        state.countries[1].gold = 0;
+        bot.act(&ObserverView::new(&state, Player(1)), &mut commands);
+        assert!(!commands.iter().any(|c| matches!(c, Command::Build(_))));
+
+        commands.clear();
+        state.countries[1].gold = 1000;
+        bot.act(&ObserverView::new(&state, Player(1)), &mut commands);
+        assert!(commands.contains(&Command::Build(Pos(5, 5))));
+    }
+}
@@ -1,51 +1,120 @@
-use curseofrust::{grid::Tile, state::State, Player};
+use curseofrust::{
+    grid::{HabitLand, Tile},
+    state::State,
+    Player, Pos, FLAG_POWER, MAX_PLAYERS,
+};
 
-use crate::{S2CData, TileClass};
+use crate::{landscape_of, S2CData, TileClass, TILE_ROAD};
 
+/// Drops states received out of order or twice.
+///
+/// States are ordered by their sequence numbers, or by their time
+/// if the server does not number them.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StateOrder {
+    last_seq: Option<u16>,
+}
+
+impl StateOrder {
+    /// Whether the given state is newer than every state accepted
+    /// before, in which case it is accepted.
+    pub fn accept(&mut self, state: &State, data: &S2CData) -> bool {
+        match (data.seq(), self.last_seq) {
+            (0, _) => data.time() > state.time,
+            (_, Some(last)) if !data.follows(last) => false,
+            (seq, _) => {
+                self.last_seq = Some(seq);
+                true
+            }
+        }
+    }
+
+    /// Gets the sequence number of the last accepted state, if numbered.
+    #[inline]
+    pub fn last_seq(&self) -> Option<u16> {
+        self.last_seq
+    }
+}
+
+/// Applies the state sent by a server.
+///
+/// States not newer than `state` are rejected, unless they are
+/// numbered, in which case they should be checked by a [`StateOrder`].
 pub fn apply_s2c_msg(state: &mut State, data: S2CData) -> curseofrust::Result<()> {
-    if u32::from_be(data.time) as u64 <= state.time {
+    if data.seq() == 0 && data.time() <= state.time {
         return Err(curseofrust::Error::DeprecatedMsg {
-            time: u32::from_be(data.time),
+            time: data.time() as u32,
         });
     }
-
-    state.time = u32::from_be(data.time) as u64;
-    for (p1, p2) in state
-        .countries
-        .iter_mut()
-        .map(|c| &mut c.gold)
-        .zip(data.gold)
+    // Ids out of range would index past the per-player arrays later.
+    let player = data.player();
+    player.index()?;
+    let (width, height) = (state.grid.width() as usize, state.grid.height() as usize);
+    if let Some(&owner) = data.owner[..width]
+        .iter()
+        .flat_map(|col| &col[..height])
+        .find(|&&owner| owner as usize >= MAX_PLAYERS)
     {
-        *p1 = u32::from_be(p2) as u64;
+        return Err(curseofrust::Error::PlayerNotFound(Player(owner as u32)));
+    }
+
+    state.time = data.time();
+    for c in &mut state.countries {
+        c.gold = data.gold(c.player).unwrap_or_default();
     }
     for fg in &mut state.fgs {
         fg.width = data.width as u32;
         fg.height = data.height as u32;
     }
-    state.controlled = Player(data.player as u32);
+    state.controlled = player;
     for (x, arr) in state.grid.raw_tiles_mut().iter_mut().enumerate() {
         for (y, tile) in arr.iter_mut().enumerate() {
-            let Some(target) = data
-                .tile
-                .get(x)
-                .and_then(|a| a.get(y))
-                .copied()
-                .map(TileClass::from)
-            else {
+            let Some(&byte) = data.tile.get(x).and_then(|a| a.get(y)) else {
                 // This make sure that the (x, y) indexes are valid for the data message.
                 continue;
             };
-            let mut t: Tile = target.into();
+            let mut t: Tile = TileClass::from(byte).into();
+            t.set_landscape(landscape_of(byte).unwrap_or_default());
+            t.set_road(byte & TILE_ROAD != 0);
             let owner = data.owner[x][y];
             t.set_owner(Player(owner as u32));
+            // Queued upgrades are not transferred either, so known ones
+            // are kept until built or lost.
+            if TileClass::from(&*tile) != TileClass::from(&t) || tile.owner() != t.owner() {
+                let pos = Pos(x as i32, y as i32);
+                for c in &mut state.countries {
+                    c.build_queue.retain(|p| *p != pos);
+                }
+            }
+            // Rally points are not transferred, so known ones are kept.
+            if let (
+                Tile::Habitable {
+                    owner: old_owner,
+                    rally: Some(old_rally),
+                    ..
+                },
+                Tile::Habitable {
+                    land, owner, rally, ..
+                },
+            ) = (&*tile, &mut t)
+            {
+                if old_owner == owner && *land != HabitLand::Grassland {
+                    *rally = Some(*old_rally);
+                }
+            }
             if let Some(unit) = t.units_mut().and_then(|us| us.get_mut(owner as usize)) {
-                *unit = u16::from_be(data.pop[x][y]);
+                *unit = data.pop(Pos(x as i32, y as i32)).unwrap_or_default();
             }
             *tile = t;
 
+            // Flag powers are not transferred, so known powers are kept.
             for (p, fg) in state.fgs.iter_mut().enumerate() {
                 fg.call[x][y] = 0;
-                fg.flags[x][y] = data.flag[x][y] & (1 << p) != 0;
+                fg.flags[x][y] = if data.flag[x][y] & (1 << p) == 0 {
+                    0
+                } else {
+                    fg.flags[x][y].max(FLAG_POWER)
+                };
             }
         }
     }
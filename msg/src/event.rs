@@ -0,0 +1,201 @@
+//! Notifications of game events, sent apart from states.
+//!
+//! Clients advertising [`capability::EVENTS`](crate::capability::EVENTS)
+//! are sent an [`EVENT`](server_msg::EVENT) frame whenever something
+//! noteworthy happens, so they can tell players without comparing
+//! consecutive states.
+//!
+//! # Wire format
+//!
+//! | Offset | Size | Field                                  |
+//! |--------|------|----------------------------------------|
+//! | 0      | 1    | message id                             |
+//! | 1      | 1    | kind of the event, one of [`kind`]     |
+//! | 2      | 1    | X of the position, speed, count or `0` |
+//! | 3      | 1    | Y of the position, or `0`              |
+//! | 4      | 1    | player the event is about              |
+//! | 5      | 1    | other player, or `0`                   |
+//! | 6      | ..   | UTF-8 text of a chat message           |
+//!
+//! Only [`CHAT`](kind::CHAT) frames carry a text, of at most
+//! [`MAX_TEXT`] bytes. Frames of other kinds are [`HEADER_SIZE`]
+//! bytes long.
+
+use curseofrust::{Player, Pos, Speed, MAX_HEIGHT, MAX_PLAYERS, MAX_WIDTH};
+
+use crate::server_msg;
+
+/// Size of an event frame without its text.
+pub const HEADER_SIZE: usize = 6;
+/// Longest text of a chat message, in bytes.
+pub const MAX_TEXT: usize = 120;
+
+/// Kinds of events.
+pub mod kind {
+    /// A city changed owner. The player is its conqueror,
+    /// and the other player its old owner.
+    pub const CITY_CAPTURED: u8 = 1;
+    /// A player was eliminated.
+    pub const ELIMINATED: u8 = 2;
+    /// A client claiming the player joined the game.
+    pub const JOINED: u8 = 3;
+    /// The client of the player left the game, and an AI
+    /// took over its country.
+    pub const LEFT: u8 = 4;
+    /// A chat message from the player, or from the server
+    /// if neutral.
+    pub const CHAT: u8 = 5;
+    /// The server changed the speed of the game, pausing it or
+    /// resuming it included. The X byte is the speed, from `0`
+    /// for a pause to `7` for the fastest.
+    pub const SPEED: u8 = 6;
+    /// The server resumes the game in the number of seconds in
+    /// the X byte, counting down to a [`SPEED`] event.
+    pub const COUNTDOWN: u8 = 7;
+}
+
+/// Speeds by their number in [`SPEED`](kind::SPEED) events.
+const SPEEDS: [Speed; 8] = [
+    Speed::Pause,
+    Speed::Slowest,
+    Speed::Slower,
+    Speed::Slow,
+    Speed::Normal,
+    Speed::Fast,
+    Speed::Faster,
+    Speed::Fastest,
+];
+
+/// A decoded event.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    CityCaptured { pos: Pos, from: Player, to: Player },
+    Eliminated(Player),
+    Joined(Player),
+    Left(Player),
+    Chat { from: Player, text: String },
+    Speed(Speed),
+    Countdown(u8),
+}
+
+impl Event {
+    /// Encodes the event as a frame, with its message id.
+    ///
+    /// Texts of chat messages are cut to [`MAX_TEXT`] bytes,
+    /// at a character boundary.
+    pub fn encode(&self) -> Vec<u8> {
+        let (kind, Pos(x, y), player, other) = match *self {
+            Event::CityCaptured { pos, from, to } => (kind::CITY_CAPTURED, pos, to, from),
+            Event::Eliminated(player) => (kind::ELIMINATED, Pos(0, 0), player, Player(0)),
+            Event::Joined(player) => (kind::JOINED, Pos(0, 0), player, Player(0)),
+            Event::Left(player) => (kind::LEFT, Pos(0, 0), player, Player(0)),
+            Event::Chat { from, .. } => (kind::CHAT, Pos(0, 0), from, Player(0)),
+            Event::Speed(speed) => (kind::SPEED, Pos(speed as i32, 0), Player(0), Player(0)),
+            Event::Countdown(secs) => (kind::COUNTDOWN, Pos(secs as i32, 0), Player(0), Player(0)),
+        };
+        let mut frame = vec![
+            server_msg::EVENT,
+            kind,
+            x as u8,
+            y as u8,
+            player.0 as u8,
+            other.0 as u8,
+        ];
+        if let Event::Chat { text, .. } = self {
+            let mut end = text.len().min(MAX_TEXT);
+            while !text.is_char_boundary(end) {
+                end -= 1;
+            }
+            frame.extend_from_slice(&text.as_bytes()[..end]);
+        }
+        frame
+    }
+
+    /// Decodes an event frame, without its message id.
+    ///
+    /// Gets the name of the malformed field on failure.
+    pub fn decode(body: &[u8]) -> Result<Self, &'static str> {
+        let &[kind, x, y, player, other, ref text @ ..] = body else {
+            return Err("length");
+        };
+        if player as usize >= MAX_PLAYERS || other as usize >= MAX_PLAYERS {
+            return Err("player");
+        }
+        if kind != kind::CHAT && !text.is_empty() {
+            return Err("length");
+        }
+        let player = Player(player as u32);
+        Ok(match kind {
+            kind::CITY_CAPTURED => {
+                if x as u32 >= MAX_WIDTH || y as u32 >= MAX_HEIGHT {
+                    return Err("position");
+                }
+                Event::CityCaptured {
+                    pos: Pos(x as i32, y as i32),
+                    from: Player(other as u32),
+                    to: player,
+                }
+            }
+            kind::ELIMINATED => Event::Eliminated(player),
+            kind::JOINED => Event::Joined(player),
+            kind::LEFT => Event::Left(player),
+            kind::SPEED => Event::Speed(*SPEEDS.get(x as usize).ok_or("speed")?),
+            kind::COUNTDOWN => Event::Countdown(x),
+            kind::CHAT => {
+                if text.len() > MAX_TEXT {
+                    return Err("length");
+                }
+                let text = std::str::from_utf8(text).map_err(|_| "text")?;
+                Event::Chat {
+                    from: player,
+                    text: text.to_owned(),
+                }
+            }
+            _ => return Err("kind"),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let events = [
+            Event::CityCaptured {
+                pos: Pos(3, 4),
+                from: Player(2),
+                to: Player(1),
+            },
+            Event::Eliminated(Player(3)),
+            Event::Joined(Player(1)),
+            Event::Left(Player(2)),
+            Event::Chat {
+                from: Player(1),
+                text: "gg".to_owned(),
+            },
+            Event::Speed(Speed::Pause),
+            Event::Speed(Speed::Fastest),
+            Event::Countdown(3),
+        ];
+        for event in events {
+            let frame = event.encode();
+            assert_eq!(Event::decode(&frame[1..]), Ok(event));
+        }
+    }
+
+    #[test]
+    fn long_chat_is_cut() {
+        let event = Event::Chat {
+            from: Player(0),
+            text: "和".repeat(MAX_TEXT),
+        };
+        let frame = event.encode();
+        assert!(frame.len() <= HEADER_SIZE + MAX_TEXT);
+        let Ok(Event::Chat { text, .. }) = Event::decode(&frame[1..]) else {
+            panic!("the frame should decode");
+        };
+        assert_eq!(text, "和".repeat(MAX_TEXT / 3));
+    }
+}
@@ -0,0 +1,142 @@
+//! Splitting of state frames into datagrams that fit the path MTU.
+//!
+//! A [`STATE`](server_msg::STATE) frame is several times the usual
+//! 1500-byte MTU, so a datagram carrying it is fragmented by IP and
+//! lost whenever one of its fragments is. Servers split state frames
+//! of clients advertising [`capability::FRAGMENT`](crate::capability::FRAGMENT)
+//! into [`STATE_FRAGMENT`](server_msg::STATE_FRAGMENT) frames of at most
+//! [`MAX_DATAGRAM`] bytes, which a [`Reassembler`] joins back.
+//!
+//! # Wire format
+//!
+//! | Offset | Size | Field                                  |
+//! |--------|------|----------------------------------------|
+//! | 0      | 1    | message id                             |
+//! | 1      | 2    | sequence number of the state           |
+//! | 3      | 1    | index of the fragment                  |
+//! | 4      | 1    | number of fragments                    |
+//! | 5      | ..   | chunk of the state frame               |
+//!
+//! Chunks are in order, and every chunk but the last one is
+//! [`MAX_CHUNK`] bytes long.
+
+use crate::{seq_follows, server_msg};
+
+/// Largest datagram sent, leaving room for IP and UDP headers
+/// within the minimum MTU of IPv6.
+pub const MAX_DATAGRAM: usize = 1200;
+/// Size of the header of a fragment.
+pub const HEADER_SIZE: usize = 5;
+/// Largest chunk of a state frame carried by a fragment.
+pub const MAX_CHUNK: usize = MAX_DATAGRAM - HEADER_SIZE;
+
+/// Splits a state frame into fragments.
+///
+/// # Panics
+///
+/// Panics if the frame takes more than [`u8::MAX`] fragments.
+pub fn split(seq: u16, frame: &[u8]) -> impl Iterator<Item = Vec<u8>> + '_ {
+    chunks(seq, frame).map(|(header, chunk)| {
+        let mut fragment = Vec::with_capacity(HEADER_SIZE + chunk.len());
+        fragment.extend_from_slice(&header);
+        fragment.extend_from_slice(chunk);
+        fragment
+    })
+}
+
+/// Splits a state frame into the headers and chunks of its fragments,
+/// without allocating.
+///
+/// # Panics
+///
+/// Panics if the frame takes more than [`u8::MAX`] fragments.
+pub fn chunks(seq: u16, frame: &[u8]) -> impl Iterator<Item = ([u8; HEADER_SIZE], &[u8])> {
+    let count = u8::try_from(frame.len().div_ceil(MAX_CHUNK))
+        .expect("the frame should take at most 255 fragments");
+    let [s0, s1] = seq.to_be_bytes();
+    frame
+        .chunks(MAX_CHUNK)
+        .enumerate()
+        .map(move |(index, chunk)| {
+            (
+                [server_msg::STATE_FRAGMENT, s0, s1, index as u8, count],
+                chunk,
+            )
+        })
+}
+
+/// Joins fragments back into state frames.
+///
+/// Only the newest state is reassembled: fragments of older states
+/// are dropped, and a newer state drops the fragments of the state
+/// being reassembled.
+#[derive(Debug, Default)]
+pub struct Reassembler {
+    seq: Option<u16>,
+    chunks: Vec<Option<Vec<u8>>>,
+}
+
+impl Reassembler {
+    /// Creates an empty reassembler.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Takes a fragment, without its message id, and gets the state
+    /// frame once all of its fragments arrived.
+    ///
+    /// Malformed fragments are ignored.
+    pub fn push(&mut self, fragment: &[u8]) -> Option<Vec<u8>> {
+        let &[s0, s1, index, count, ref chunk @ ..] = fragment else {
+            return None;
+        };
+        if index >= count || chunk.len() > MAX_CHUNK {
+            return None;
+        }
+        let seq = u16::from_be_bytes([s0, s1]);
+        match self.seq {
+            Some(current) if current == seq => {
+                if self.chunks.len() != count as usize {
+                    return None;
+                }
+            }
+            Some(current) if !seq_follows(seq, current) => return None,
+            _ => {
+                self.seq = Some(seq);
+                self.chunks.clear();
+                self.chunks.resize(count as usize, None);
+            }
+        }
+        self.chunks[index as usize] = Some(chunk.to_vec());
+        if self.chunks.iter().any(Option::is_none) {
+            return None;
+        }
+        let frame = self.chunks.drain(..).flatten().flatten().collect();
+        Some(frame)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reassembles_out_of_order() {
+        let frame: Vec<u8> = (0..3000u32).map(|i| i as u8).collect();
+        let mut fragments: Vec<_> = split(9, &frame).collect();
+        assert_eq!(fragments.len(), 3);
+        assert!(fragments.iter().all(|f| f.len() <= MAX_DATAGRAM));
+
+        let mut reassembler = Reassembler::new();
+        fragments.swap(0, 2);
+        // Fragments of an older state are dropped.
+        let stale: Vec<_> = split(8, &frame).collect();
+        assert_eq!(reassembler.push(&fragments[0][1..]), None);
+        assert_eq!(reassembler.push(&stale[0][1..]), None);
+        assert_eq!(reassembler.push(&fragments[1][1..]), None);
+        assert_eq!(reassembler.push(&fragments[2][1..]), Some(frame));
+        // A duplicate does not complete the state again.
+        assert_eq!(reassembler.push(&fragments[2][1..]), None);
+    }
+}
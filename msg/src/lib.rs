@@ -2,14 +2,22 @@
 
 use bytemuck::{AnyBitPattern, NoUninit, Zeroable};
 use curseofrust::{
-    grid::{HabitLand, Tile},
-    Pos, MAX_HEIGHT, MAX_PLAYERS, MAX_WIDTH,
+    grid::{HabitLand, Landscape, Tile},
+    Player, Pos, MAX_HEIGHT, MAX_PLAYERS, MAX_WIDTH,
 };
 
-use std::mem::offset_of;
+use std::{mem::offset_of, time::Duration};
 
+pub mod bot;
 mod client;
+pub mod event;
+pub mod fragment;
+pub mod master;
+pub mod migration;
+mod rle;
 mod server;
+pub mod spec;
+pub mod trace;
 
 pub use client::*;
 pub use server::*;
@@ -31,6 +39,14 @@ pub struct C2SData {
 
 pub const C2S_SIZE: usize = std::mem::size_of::<C2SData>() + 1;
 
+/// Longest password a [`CONNECT`](client_msg::CONNECT) frame carries,
+/// in bytes.
+pub const MAX_PASSWORD: usize = 32;
+
+/// Size of a [`CANDIDATE`](client_msg::CANDIDATE) frame, which is
+/// followed by the big-endian `u32` digest of a state.
+pub const CANDIDATE_SIZE: usize = C2S_SIZE + std::mem::size_of::<u32>();
+
 #[repr(C)]
 #[allow(dead_code)]
 struct UnsafeC2SData {
@@ -40,29 +56,260 @@ struct UnsafeC2SData {
     msg: u8,
 }
 
+/// Size of the peer id prefixed to every packet exchanged
+/// between a relay and its host.
+///
+/// A packet from the host of only the peer id asks the relay to
+/// disconnect the peer.
+pub const RELAY_HEADER_SIZE: usize = 1;
+
 /// Message a client transferred to a server.
 pub mod client_msg {
+    /// Joins the lobby of a server asking for a password, in place
+    /// of [`IS_ALIVE`] until the first state.
+    ///
+    /// The frame is that of `IS_ALIVE`, followed by the password of
+    /// up to [`MAX_PASSWORD`](crate::MAX_PASSWORD) bytes.
     pub const CONNECT: u8 = 1;
+    /// Registers the sender as the host of a relay.
+    pub const RELAY_HOST: u8 = 2;
+    /// Offers to host the game after the relay lost its host, see
+    /// [`migration`](crate::migration).
+    ///
+    /// The sequence number of the last state the sender applied is
+    /// big-endian in place of the targeting position, and the frame
+    /// is followed by the [`digest`](crate::S2CData::digest) of that
+    /// state, see [`CANDIDATE_SIZE`](crate::CANDIDATE_SIZE).
+    pub const CANDIDATE: u8 = 3;
     pub const BUILD: u8 = 20;
 
+    /// Stacks flags on the targeting position.
+    ///
+    /// The info byte is the number of flags to stack,
+    /// where `0` means one.
     pub const FLAG_ON: u8 = 21;
     pub const FLAG_OFF: u8 = 22;
     pub const FLAG_OFF_ALL: u8 = 23;
     pub const FLAG_OFF_HALF: u8 = 24;
-
+    /// Sets the rally point of the targeting city.
+    ///
+    /// See [`C2SData::rally`](crate::C2SData::rally).
+    pub const RALLY: u8 = 25;
+    /// Flags every city of the sender.
+    pub const FLAG_CITIES: u8 = 26;
+    /// Flags every tile of the sender adjacent to enemy tiles.
+    pub const FLAG_FRONTLINE: u8 = 27;
+    /// Builds a road on the targeting position.
+    pub const BUILD_ROAD: u8 = 28;
+    /// Queues an upgrade of the targeting position, built once
+    /// the sender can afford it.
+    ///
+    /// The info byte is `1` to queue the upgrade, and `0` to
+    /// remove it from the queue.
+    pub const QUEUE_BUILD: u8 = 29;
+
+    /// Keeps the sender connected.
+    ///
+    /// The info byte carries the [`capability`](crate::capability)
+    /// flags. In the lobby, the X byte is the number of players the
+    /// sender claims to control, where `0` means one. Rejoining
+    /// after a [migration](crate::migration), the Y byte is the
+    /// first of the players the sender controlled, `0` otherwise.
     pub const IS_ALIVE: u8 = 30;
+    /// Acknowledges the state of the given sequence number,
+    /// big-endian in place of the targeting position.
+    ///
+    /// See [`S2CData::seq`](crate::S2CData::seq).
+    pub const STATE_ACK: u8 = 31;
+    /// Answers a [`PING`](crate::server_msg::PING) of the server.
+    pub const PONG: u8 = 32;
+    /// Asks for a full state at once, when the state of the client
+    /// may have gone out of sync, such as after a malformed state
+    /// or a suspension of the client.
+    ///
+    /// Servers answer a client at most once every
+    /// [`RESYNC_INTERVAL`](crate::RESYNC_INTERVAL), with the state they
+    /// would send next.
+    pub const FULL_STATE_REQUEST: u8 = 33;
+    /// Pauses the game for everyone, or stops the countdown to
+    /// resuming it.
     pub const PAUSE: u8 = 40;
+    /// Resumes a paused game for everyone, after a countdown told by
+    /// [`COUNTDOWN`](crate::event::kind::COUNTDOWN) events.
     pub const UNPAUSE: u8 = 41;
+
+    /// Hands the sender's country over to an AI and makes
+    /// the sender a spectator.
+    pub const SURRENDER: u8 = 50;
+    /// Votes to restart on a new map with the same options.
+    pub const VOTE_RESTART: u8 = 51;
+    /// Ends the sender's turn in turn-based mode.
+    pub const END_TURN: u8 = 52;
+    /// Switches the player the sender controls to the next of
+    /// the players it claimed, for senders claiming several.
+    ///
+    /// See [`IS_ALIVE`].
+    pub const SWITCH_PLAYER: u8 = 53;
 }
 
 /// Message a server transferred to a client.
 pub mod server_msg {
     pub const CONN_ACCEPTED: u8 = 5;
+    /// The server refused to let the client join, and closed the
+    /// connection.
+    ///
+    /// The packet is `[CONN_REJECTED, reason]`, where `reason` is
+    /// one of [`reject`](crate::reject).
     pub const CONN_REJECTED: u8 = 6;
 
     pub const STATE: u8 = 10;
+    /// Run-length encoded state.
+    ///
+    /// See [`S2CData::encode_rle`](crate::S2CData::encode_rle).
+    pub const STATE_RLE: u8 = 11;
+
+    /// A player went idle and an AI took over its country,
+    /// or the player came back.
+    ///
+    /// The packet is `[AFK, player, idle]`, where `idle` is `1`
+    /// if the player went idle.
+    pub const AFK: u8 = 12;
+
+    /// A player surrendered.
+    ///
+    /// The packet is `[SURRENDER, player]`.
+    pub const SURRENDER: u8 = 13;
+    /// A player voted to restart.
+    ///
+    /// The packet is `[VOTE_RESTART, votes, voters]`. The game
+    /// restarts once `votes` reaches `voters`.
+    pub const VOTE_RESTART: u8 = 14;
+    /// The match time limit is up.
+    ///
+    /// The packet is `[GAME_OVER, winner]`.
+    pub const GAME_OVER: u8 = 15;
+    /// A player ended the turn.
+    ///
+    /// The packet is `[END_TURN, ended, players]`. The game
+    /// advances a turn once `ended` reaches `players`.
+    pub const END_TURN: u8 = 16;
+    /// A player was eliminated.
+    ///
+    /// The packet is `[ELIMINATED, player]`.
+    pub const ELIMINATED: u8 = 17;
+    /// The conqueror of a city plundered gold from its old owner.
+    ///
+    /// The packet is `[PLUNDER, x, y, from, to, gold...]`, where
+    /// `gold` is a big-endian `u32`.
+    pub const PLUNDER: u8 = 18;
+    /// The server accepted the reliability layer of `udp`,
+    /// so the client may wrap its commands from now on.
+    ///
+    /// The packet is `[RELIABLE]`. See [`capability::RELIABLE`](crate::capability::RELIABLE).
+    pub const RELIABLE: u8 = 19;
+    /// A fragment of a state frame too large for one datagram.
+    ///
+    /// See [`fragment`](crate::fragment) and
+    /// [`capability::FRAGMENT`](crate::capability::FRAGMENT). It is
+    /// numbered past the ids of client messages.
+    pub const STATE_FRAGMENT: u8 = 60;
+    /// Asks the client to answer with a [`PONG`](crate::client_msg::PONG).
+    ///
+    /// The packet is `[PING]`. See [`capability::PING`](crate::capability::PING).
+    /// It is numbered past the ids of client messages.
+    pub const PING: u8 = 61;
+    /// Something noteworthy happened in the game.
+    ///
+    /// See [`event`](crate::event) and
+    /// [`capability::EVENTS`](crate::capability::EVENTS). It is
+    /// numbered past the ids of client messages.
+    pub const EVENT: u8 = 62;
+    /// The relay lost its host, see [`migration`](crate::migration).
+    ///
+    /// The packet is `[HOST_LOST]`. It is numbered past the ids of
+    /// client messages.
+    pub const HOST_LOST: u8 = 63;
+    /// The relay elected the client to host the game in place of
+    /// the host it lost.
+    ///
+    /// The packet is `[ELECTED, clients]`, where `clients` is the
+    /// number of clients of the relay, the elected one included.
+    /// It is numbered past the ids of client messages.
+    pub const ELECTED: u8 = 64;
+    /// How the game is played, sent once it starts to clients
+    /// advertising [`capability::MIGRATE`](crate::capability::MIGRATE).
+    ///
+    /// The packet is `[RULES, rules...]`, where `rules` is a UTF-8
+    /// TOML document, see `Rules` of `curseofrust-cli-parser`. It is
+    /// numbered past the ids of client messages.
+    pub const RULES: u8 = 65;
+}
+
+/// Reasons a server gives in [`CONN_REJECTED`](server_msg::CONN_REJECTED).
+pub mod reject {
+    /// The password is missing or wrong.
+    pub const PASSWORD: u8 = 1;
+    /// The address of the client is not allowed to join.
+    pub const NOT_ALLOWED: u8 = 2;
+    /// The relay has no id left for another peer.
+    pub const FULL: u8 = 3;
 }
 
+/// Capabilities a client advertises in the info byte
+/// of its first packet.
+pub mod capability {
+    /// The client accepts run-length encoded states.
+    pub const RLE: u8 = 1 << 0;
+    /// The client retransmits its commands until acknowledged,
+    /// with the `reliable` layer of `curseofrust-net-foundation`,
+    /// and acknowledges the [`EVENT`](crate::server_msg::EVENT)s the
+    /// server sends through the same layer.
+    ///
+    /// Clients advertise it on every `IS_ALIVE` until the server
+    /// answers with [`RELIABLE`](crate::server_msg::RELIABLE).
+    pub const RELIABLE: u8 = 1 << 1;
+    /// The client reassembles states split into
+    /// [`STATE_FRAGMENT`](crate::server_msg::STATE_FRAGMENT)s.
+    ///
+    /// Servers split states only over `udp`.
+    pub const FRAGMENT: u8 = 1 << 2;
+    /// The client answers [`PING`](crate::server_msg::PING)s.
+    ///
+    /// Servers ping such clients every [`PING_INTERVAL`](crate::PING_INTERVAL),
+    /// and drop them after [`MAX_MISSED_PINGS`](crate::MAX_MISSED_PINGS)
+    /// unanswered pings in a row.
+    pub const PING: u8 = 1 << 3;
+    /// The client understands the landscape and road bits of tile
+    /// bytes, see [`encode_tile`](crate::encode_tile).
+    ///
+    /// Servers clear those bits for other clients, such as the ones
+    /// of curseofwar.
+    pub const TILE_FLAGS: u8 = 1 << 4;
+    /// The client shows [`EVENT`](crate::server_msg::EVENT)s.
+    ///
+    /// Servers send events to such clients only. The frames
+    /// announcing the same events to other clients, such as
+    /// [`ELIMINATED`](crate::server_msg::ELIMINATED), are still sent.
+    pub const EVENTS: u8 = 1 << 5;
+    /// The client may host the game in place of a lost host, see
+    /// [`migration`](crate::migration).
+    ///
+    /// Servers send such clients the [`RULES`](crate::server_msg::RULES)
+    /// of the game, which the server they start keeps.
+    pub const MIGRATE: u8 = 1 << 6;
+}
+
+/// Interval between [`PING`](server_msg::PING)s of a server.
+pub const PING_INTERVAL: Duration = Duration::from_secs(2);
+/// Unanswered pings in a row after which a server drops a client.
+///
+/// Clients likewise give up on a server that stopped pinging
+/// them for as long.
+pub const MAX_MISSED_PINGS: u32 = 5;
+/// Shortest interval between the answers of a server to the
+/// [`FULL_STATE_REQUEST`](client_msg::FULL_STATE_REQUEST)s of a client.
+pub const RESYNC_INTERVAL: Duration = Duration::from_secs(1);
+
 /// Class of tiles.
 #[repr(u8)]
 #[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
@@ -99,10 +346,39 @@ impl From<&Tile> for TileClass {
     }
 }
 
+/// Shift of the [`Landscape`] in the tile bytes of [`S2CData`],
+/// above the [`TileClass`].
+pub const LANDSCAPE_SHIFT: u32 = 4;
+
+/// Bit of the tile bytes of [`S2CData`] set if the tile has a road.
+pub const TILE_ROAD: u8 = 1 << 6;
+
+/// Encodes the class, landscape and road of the tile as a tile byte
+/// of [`S2CData`].
+#[inline]
+pub fn encode_tile(tile: &Tile) -> u8 {
+    let road = if tile.road() { TILE_ROAD } else { 0 };
+    TileClass::from(tile) as u8 | (tile.landscape() as u8) << LANDSCAPE_SHIFT | road
+}
+
+/// Gets the landscape of a tile byte of [`S2CData`].
+///
+/// Returns `None` if the landscape is unknown.
+#[inline]
+pub fn landscape_of(tile: u8) -> Option<Landscape> {
+    match tile >> LANDSCAPE_SHIFT & 0b11 {
+        0 => Some(Landscape::Plain),
+        1 => Some(Landscape::Forest),
+        2 => Some(Landscape::Hill),
+        _ => None,
+    }
+}
+
 impl From<u8> for TileClass {
+    /// Gets the class of a tile byte, ignoring its landscape and road.
     #[inline]
     fn from(value: u8) -> Self {
-        match value {
+        match value & ((1 << LANDSCAPE_SHIFT) - 1) {
             0 => TileClass::Void,
             1 => TileClass::Mountain,
             2 => TileClass::Mine,
@@ -124,6 +400,7 @@ impl From<TileClass> for Tile {
             TileClass::Mine => Tile::Mine(Default::default()),
             TileClass::Grassland | TileClass::Village | TileClass::Town | TileClass::Fortress => {
                 Tile::Habitable {
+                    rally: None,
                     land: match value {
                         TileClass::Grassland => HabitLand::Grassland,
                         TileClass::Village => HabitLand::Village,
@@ -133,6 +410,8 @@ impl From<TileClass> for Tile {
                     },
                     units: [0u16; MAX_PLAYERS],
                     owner: Default::default(),
+                    landscape: Landscape::Plain,
+                    road: false,
                 }
             }
             TileClass::Other => Tile::Void,
@@ -149,6 +428,10 @@ pub struct S2CData {
     pub player: u8,
     /// Pause request.
     pub pause_request: u8,
+    /// Seconds left of the match time limit.
+    ///
+    /// See [`S2CData::time_left`].
+    pub time_left: u16,
     __pad0: [u8; __S2C_PAD_0_LEN],
 
     /// Gold counts.
@@ -169,15 +452,26 @@ pub struct S2CData {
     pub pop: [[u16; MAX_HEIGHT as usize]; MAX_WIDTH as usize],
     /// Population of each grid.
     pub tile: [[u8; MAX_HEIGHT as usize]; MAX_WIDTH as usize],
-    __pad2: [u8; __S2C_PAD_2_LEN],
+    /// Sequence number, in the trailing padding.
+    ///
+    /// See [`S2CData::seq`].
+    seq: u16,
 }
 
 pub const S2C_SIZE: usize = std::mem::size_of::<S2CData>() + 1;
 
+/// Whether the state numbered `seq` was numbered after the state
+/// numbered `prev`, allowing for wrapping around.
+#[inline]
+pub fn seq_follows(seq: u16, prev: u16) -> bool {
+    (seq.wrapping_sub(prev) as i16) > 0
+}
+
 #[repr(C)]
 struct UnsafeS2CData {
     player: u8,
     pause_request: u8,
+    time_left: u16,
     gold: [u32; MAX_PLAYERS],
     time: u32,
     width: u8,
@@ -189,14 +483,17 @@ struct UnsafeS2CData {
 }
 
 const __S2C_PAD_0_LEN: usize = offset_of!(UnsafeS2CData, gold)
-    - offset_of!(UnsafeS2CData, pause_request)
-    - std::mem::size_of::<u8>();
+    - offset_of!(UnsafeS2CData, time_left)
+    - std::mem::size_of::<u16>();
 const __S2C_PAD_1_LEN: usize = offset_of!(UnsafeS2CData, pop)
     - offset_of!(UnsafeS2CData, owner)
     - std::mem::size_of::<[[u8; MAX_HEIGHT as usize]; MAX_WIDTH as usize]>();
 const __S2C_PAD_2_LEN: usize = std::mem::size_of::<UnsafeS2CData>()
     - offset_of!(UnsafeS2CData, tile)
     - std::mem::size_of::<[[u8; MAX_HEIGHT as usize]; MAX_WIDTH as usize]>();
+// The sequence number takes the trailing padding, which
+// curseofwar ignores.
+const _: () = assert!(__S2C_PAD_2_LEN == std::mem::size_of::<u16>());
 
 //SAFETY: `C2SData` and `S2CData` are manually padded.
 unsafe impl Zeroable for C2SData {}
@@ -206,6 +503,191 @@ unsafe impl Zeroable for S2CData {}
 unsafe impl AnyBitPattern for S2CData {}
 unsafe impl NoUninit for S2CData {}
 
+#[inline]
+fn grid_index(Pos(x, y): Pos) -> Option<(usize, usize)> {
+    (x >= 0 && y >= 0 && x < MAX_WIDTH as i32 && y < MAX_HEIGHT as i32)
+        .then_some((x as usize, y as usize))
+}
+
+impl S2CData {
+    /// Gets the player of the data.
+    #[inline]
+    pub fn player(&self) -> Player {
+        Player(self.player as u32)
+    }
+
+    /// Sets the player of the data.
+    #[inline]
+    pub fn set_player(&mut self, player: Player) {
+        self.player = player.0 as u8;
+    }
+
+    /// Clears the landscape and road bits of the tile bytes,
+    /// for clients not advertising [`capability::TILE_FLAGS`].
+    pub fn clear_tile_flags(&mut self) {
+        for tile in self.tile.iter_mut().flatten() {
+            *tile &= (1 << LANDSCAPE_SHIFT) - 1;
+        }
+    }
+
+    /// Gets gold count of the given player.
+    #[inline]
+    pub fn gold(&self, Player(pl): Player) -> Option<u64> {
+        let gold = self.gold;
+        gold.get(pl as usize).map(|g| u32::from_be(*g) as u64)
+    }
+
+    /// Sets gold count of the given player, saturating
+    /// at [`u32::MAX`].
+    ///
+    /// Does nothing if the player is out of bounds.
+    #[inline]
+    pub fn set_gold(&mut self, Player(pl): Player, gold: u64) {
+        let mut golds = self.gold;
+        if let Some(g) = golds.get_mut(pl as usize) {
+            *g = u32::try_from(gold).unwrap_or(u32::MAX).to_be();
+        }
+        self.gold = golds;
+    }
+
+    /// Gets the current time.
+    #[inline]
+    pub fn time(&self) -> u64 {
+        u32::from_be(self.time) as u64
+    }
+
+    /// Sets the current time, saturating at [`u32::MAX`].
+    #[inline]
+    pub fn set_time(&mut self, time: u64) {
+        self.time = u32::try_from(time).unwrap_or(u32::MAX).to_be();
+    }
+
+    /// Gets the sequence number of the state, or `0` if the server
+    /// does not number its states.
+    ///
+    /// Servers number states from `1` and skip `0` when wrapping
+    /// around. Unlike the time, the number keeps increasing across
+    /// restarts of the game.
+    #[inline]
+    pub fn seq(&self) -> u16 {
+        u16::from_be(self.seq)
+    }
+
+    /// Sets the sequence number of the state.
+    #[inline]
+    pub fn set_seq(&mut self, seq: u16) {
+        self.seq = seq.to_be();
+    }
+
+    /// Hashes the game the state describes, leaving out the fields
+    /// that differ between the clients of a server.
+    ///
+    /// Clients sharing a state have the same digest.
+    pub fn digest(&self) -> u32 {
+        let bytes = bytemuck::bytes_of(self);
+        // The player, pause request and time left lead, and the
+        // sequence number trails.
+        let game = &bytes[offset_of!(S2CData, gold)..bytes.len() - std::mem::size_of::<u16>()];
+        let hash = game.iter().fold(0xcbf2_9ce4_8422_2325u64, |h, &b| {
+            (h ^ b as u64).wrapping_mul(0x0100_0000_01b3)
+        });
+        (hash >> 32) as u32 ^ hash as u32
+    }
+
+    /// Whether this state was numbered after the state numbered `seq`,
+    /// allowing for wrapping around.
+    #[inline]
+    pub fn follows(&self, seq: u16) -> bool {
+        seq_follows(self.seq(), seq)
+    }
+
+    /// Gets the time left of the match time limit,
+    /// or [`None`] if there is no limit.
+    #[inline]
+    pub fn time_left(&self) -> Option<Duration> {
+        let secs = u16::from_be(self.time_left);
+        (secs > 0).then(|| Duration::from_secs(secs as u64))
+    }
+
+    /// Sets the time left of the match time limit, rounded up to
+    /// seconds and saturating at [`u16::MAX`] seconds.
+    ///
+    /// No time left is sent as no limit.
+    #[inline]
+    pub fn set_time_left(&mut self, time_left: Option<Duration>) {
+        let secs = time_left.map_or(0, |t| t.as_secs() + (t.subsec_nanos() > 0) as u64);
+        self.time_left = u16::try_from(secs).unwrap_or(u16::MAX).to_be();
+    }
+
+    /// Gets population of the given position.
+    #[inline]
+    pub fn pop(&self, pos: Pos) -> Option<u16> {
+        grid_index(pos).map(|(x, y)| u16::from_be(self.pop[x][y]))
+    }
+
+    /// Sets population of the given position.
+    ///
+    /// Does nothing if the position is out of bounds.
+    #[inline]
+    pub fn set_pop(&mut self, pos: Pos, pop: u16) {
+        if let Some((x, y)) = grid_index(pos) {
+            self.pop[x][y] = pop.to_be();
+        }
+    }
+}
+
+impl C2SData {
+    /// Gets the targeting position.
+    #[inline]
+    pub fn pos(&self) -> Pos {
+        Pos(self.x as i32, self.y as i32)
+    }
+}
+
+impl C2SData {
+    /// Creates a [`STATE_ACK`](client_msg::STATE_ACK) message
+    /// acknowledging the state of the given sequence number.
+    #[inline]
+    pub fn state_ack(seq: u16) -> Self {
+        let [x, y] = seq.to_be_bytes();
+        Self { x, y, msg: 0 }
+    }
+
+    /// Gets the sequence number acknowledged by a
+    /// [`STATE_ACK`](client_msg::STATE_ACK) message, or named by a
+    /// [`CANDIDATE`](client_msg::CANDIDATE) message.
+    #[inline]
+    pub fn acked_seq(&self) -> u16 {
+        u16::from_be_bytes([self.x, self.y])
+    }
+}
+
+impl C2SData {
+    /// Farthest a rally point can be from its city on either axis.
+    pub const MAX_RALLY_OFFSET: i32 = 7;
+
+    /// Creates a [`RALLY`](client_msg::RALLY) message setting the rally
+    /// point of `city` to `target`, or clearing it with [`None`].
+    ///
+    /// The info byte packs the offset of the target from the city,
+    /// X in the high nibble and Y in the low nibble. Returns [`None`] if
+    /// the offset exceeds [`Self::MAX_RALLY_OFFSET`].
+    pub fn rally(city: Pos, target: Option<Pos>) -> Option<Self> {
+        let Pos(dx, dy) = target.map_or(Pos(0, 0), |t| t - city);
+        let range = -Self::MAX_RALLY_OFFSET..=Self::MAX_RALLY_OFFSET;
+        (range.contains(&dx) && range.contains(&dy))
+            .then(|| (city, ((dx as u8 & 0xF) << 4) | (dy as u8 & 0xF)).into())
+    }
+
+    /// Gets the rally point of a [`RALLY`](client_msg::RALLY) message.
+    pub fn rally_target(&self) -> Option<Pos> {
+        // Sign-extend the nibbles.
+        let dx = (self.msg as i8) >> 4;
+        let dy = ((self.msg << 4) as i8) >> 4;
+        (self.msg != 0).then(|| self.pos() + Pos(dx as i32, dy as i32))
+    }
+}
+
 impl From<(Pos, u8)> for C2SData {
     #[inline]
     fn from(value: (Pos, u8)) -> Self {
@@ -242,6 +724,7 @@ mod tests {
         assert_offset_eq! {
             player,
             pause_request,
+            time_left,
             gold,
             time,
             width,
@@ -253,6 +736,92 @@ mod tests {
         }
     }
 
+    #[test]
+    fn s2c_data_rle_round_trip() {
+        let mut data = S2CData::zeroed();
+        data.set_player(Player(2));
+        data.set_gold(Player(1), 1000);
+        data.set_time(123456);
+        data.set_time_left(Some(Duration::from_millis(1500)));
+        data.width = 21;
+        data.height = 21;
+        for x in 0..21 {
+            for y in 0..21 {
+                data.tile[x][y] = TileClass::Grassland as u8;
+                data.set_pop(Pos(x as i32, y as i32), (x * y) as u16 % 7);
+            }
+        }
+        data.owner[3][4] = 1;
+        data.flag[5][6] = 1 << 2;
+        data.set_seq(u16::MAX);
+
+        let mut buf = [0u8; S2C_SIZE];
+        let len = data.encode_rle(&mut buf).unwrap();
+        assert!(len < S2C_SIZE / 2);
+
+        let decoded = S2CData::decode_rle(&buf[..len]).unwrap();
+        assert_eq!(bytemuck::bytes_of(&decoded), bytemuck::bytes_of(&data));
+        assert!(S2CData::decode_rle(&buf[..len - 1]).is_none());
+        assert_eq!(decoded.time_left(), Some(Duration::from_secs(2)));
+        assert_eq!(decoded.seq(), u16::MAX);
+    }
+
+    #[test]
+    fn out_of_range_players_are_rejected() {
+        let mut state = curseofrust::state::State::new(Default::default()).unwrap();
+        let player = Player(MAX_PLAYERS as u32);
+
+        let mut data = S2CData::zeroed();
+        data.set_time(state.time + 1);
+        data.set_player(player);
+        assert!(apply_s2c_msg(&mut state, data).is_err());
+        data.set_player(Player(2));
+        data.owner[0][0] = MAX_PLAYERS as u8;
+        assert!(apply_s2c_msg(&mut state, data).is_err());
+        assert_eq!(state.controlled, Player(1));
+
+        let data = C2SData::from((Pos(1, 1), 0));
+        assert!(apply_c2s_msg(&mut state, player, client_msg::FLAG_ON, data).is_err());
+        assert!(state.country(player).is_err());
+    }
+
+    #[test]
+    fn s2c_data_seq_wraps_around() {
+        let mut data = S2CData::zeroed();
+        data.set_seq(1);
+        assert!(data.follows(u16::MAX));
+        assert!(!data.follows(1));
+        assert!(!data.follows(2));
+    }
+
+    #[test]
+    fn s2c_data_clears_tile_flags() {
+        let mut data = S2CData::zeroed();
+        data.tile[1][2] = TILE_ROAD | 2 << LANDSCAPE_SHIFT | TileClass::Town as u8;
+        assert_eq!(landscape_of(data.tile[1][2]), Some(Landscape::Hill));
+        data.clear_tile_flags();
+        assert_eq!(data.tile[1][2], TileClass::Town as u8);
+    }
+
+    #[test]
+    fn c2s_data_rally_round_trip() {
+        let city = Pos(10, 10);
+        for target in [Pos(3, 17), Pos(17, 3), Pos(10, 11), Pos(9, 10)] {
+            let data = C2SData::rally(city, Some(target)).unwrap();
+            assert_eq!(data.pos(), city);
+            assert_eq!(data.rally_target(), Some(target));
+        }
+        assert_eq!(C2SData::rally(city, None).unwrap().rally_target(), None);
+        assert!(C2SData::rally(city, Some(Pos(2, 10))).is_none());
+    }
+
+    #[test]
+    fn c2s_data_covers_grid() {
+        // Grids are capped, so coordinates never truncate.
+        let corner = Pos(MAX_WIDTH as i32 - 1, MAX_HEIGHT as i32 - 1);
+        assert_eq!(C2SData::from((corner, 0)).pos(), corner);
+    }
+
     #[test]
     fn c2s_data_layout() {
         assert_eq!(
@@ -0,0 +1,154 @@
+//! Announcement of public games to a master server.
+//!
+//! A master server keeps a list of games waiting for players. Servers
+//! announce their game periodically while in the lobby, and clients
+//! query the list to pick a game to join.
+//!
+//! # Protocol
+//!
+//! The master server speaks plain HTTP/1.1, with `text/plain` bodies
+//! of one [`Listing`] per line:
+//!
+//! - `POST /servers` announces the game of the body.
+//! - `GET /servers` responds with the games announced.
+//!
+//! Fields of a listing are separated by tabs: name, address, players
+//! connected, players expected, and map. A master server should replace
+//! an unspecified IP of an address with the IP the announcement came
+//! from, and drop games not announced again within
+//! [`ANNOUNCE_TTL`].
+
+use std::{
+    io::{self, Read as _, Write as _},
+    net::{SocketAddr, TcpStream, ToSocketAddrs as _},
+    time::Duration,
+};
+
+/// Interval between announcements of a game.
+pub const ANNOUNCE_INTERVAL: Duration = Duration::from_secs(15);
+/// Time after which a game not announced again is dropped.
+pub const ANNOUNCE_TTL: Duration = Duration::from_secs(60);
+/// Time to wait for the master server.
+const TIMEOUT: Duration = Duration::from_secs(5);
+/// Path of the list of games.
+const PATH: &str = "/servers";
+
+/// A game waiting for players.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Listing {
+    /// Name of the game.
+    pub name: String,
+    /// Address clients connect to.
+    pub addr: SocketAddr,
+    /// Players connected.
+    pub players: u32,
+    /// Players the game starts with.
+    pub capacity: u32,
+    /// Description of the map, such as `rect 31x21`.
+    pub map: String,
+}
+
+impl Listing {
+    /// Formats the listing as a line, without the line break.
+    ///
+    /// Tabs and line breaks in the name and map are replaced with spaces.
+    pub fn to_line(&self) -> String {
+        let clean = |s: &str| s.replace(|c: char| c.is_control(), " ");
+        format!(
+            "{}\t{}\t{}\t{}\t{}",
+            clean(&self.name),
+            self.addr,
+            self.players,
+            self.capacity,
+            clean(&self.map)
+        )
+    }
+
+    /// Parses a line, without the line break.
+    pub fn from_line(line: &str) -> Option<Self> {
+        let mut fields = line.split('\t');
+        let listing = Self {
+            name: fields.next()?.to_owned(),
+            addr: fields.next()?.parse().ok()?,
+            players: fields.next()?.parse().ok()?,
+            capacity: fields.next()?.parse().ok()?,
+            map: fields.next()?.to_owned(),
+        };
+        fields.next().is_none().then_some(listing)
+    }
+}
+
+/// Announces a game to the master server at the given address,
+/// such as `master.example.com:8080`.
+pub fn announce(master: &str, listing: &Listing) -> io::Result<()> {
+    request(master, "POST", &listing.to_line()).map(drop)
+}
+
+/// Lists the games announced to the master server at the given address.
+///
+/// Malformed lines are skipped.
+pub fn list(master: &str) -> io::Result<Vec<Listing>> {
+    let body = request(master, "GET", "")?;
+    Ok(body.lines().filter_map(Listing::from_line).collect())
+}
+
+/// Sends a request to the master server, and gets the body of
+/// a successful response.
+fn request(master: &str, method: &str, body: &str) -> io::Result<String> {
+    let addr = master
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "master server not found"))?;
+    let mut stream = TcpStream::connect_timeout(&addr, TIMEOUT)?;
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    write!(
+        stream,
+        "{method} {PATH} HTTP/1.1\r\n\
+        Host: {master}\r\n\
+        Content-Type: text/plain\r\n\
+        Content-Length: {}\r\n\
+        Connection: close\r\n\r\n\
+        {body}",
+        body.len()
+    )?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response)?;
+
+    let (head, body) = response
+        .split_once("\r\n\r\n")
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "malformed response"))?;
+    let status = head.split_ascii_whitespace().nth(1).unwrap_or_default();
+    if !status.starts_with('2') {
+        return Err(io::Error::other(format!(
+            "master server responded with {status}"
+        )));
+    }
+    Ok(body.to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn listing_round_trip() {
+        let listing = Listing {
+            name: "tab\there".to_owned(),
+            addr: "10.0.0.1:19140".parse().unwrap(),
+            players: 1,
+            capacity: 3,
+            map: "hex 21x21".to_owned(),
+        };
+        let line = listing.to_line();
+        assert_eq!(line, "tab here\t10.0.0.1:19140\t1\t3\thex 21x21");
+        assert_eq!(
+            Listing::from_line(&line),
+            Some(Listing {
+                name: "tab here".to_owned(),
+                ..listing
+            })
+        );
+        assert_eq!(Listing::from_line("a\t10.0.0.1:1\t1\t2"), None);
+    }
+}
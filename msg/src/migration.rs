@@ -0,0 +1,165 @@
+//! Host migration of relayed games.
+//!
+//! When the host of a relay goes away, the relay tells its clients
+//! with [`HOST_LOST`](crate::server_msg::HOST_LOST). Clients able to
+//! host answer with a [`CANDIDATE`](crate::client_msg::CANDIDATE)
+//! naming the last state they applied, and the relay [`elect`]s one
+//! of them, telling it with [`ELECTED`](crate::server_msg::ELECTED).
+//! The elected client starts a server [`resume`]d from that state,
+//! which registers with the relay as its new host, and every client
+//! joins again claiming the players it had, see
+//! [`IS_ALIVE`](crate::client_msg::IS_ALIVE).
+
+use std::time::Duration;
+
+use curseofrust::{
+    state::{BasicOpts, State},
+    Player, Strategy,
+};
+
+use crate::{apply_s2c_msg, client_msg, seq_follows, C2SData, S2CData, C2S_SIZE, CANDIDATE_SIZE};
+
+/// Time the relay waits for candidates after losing its host.
+pub const ELECTION_WINDOW: Duration = Duration::from_secs(2);
+
+/// A client offering to host, as seen by the relay.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Candidate {
+    /// Peer id of the client on the relay.
+    pub peer: u8,
+    /// Sequence number of the last state the client applied.
+    pub seq: u16,
+    /// Digest of that state, see [`S2CData::digest`].
+    pub digest: u32,
+    /// Time the client took to answer [`HOST_LOST`](crate::server_msg::HOST_LOST).
+    pub latency: Duration,
+}
+
+impl Candidate {
+    /// Reads the candidate from its
+    /// [`CANDIDATE`](crate::client_msg::CANDIDATE) frame, or returns
+    /// [`None`] if the frame is no such frame.
+    pub fn read(peer: u8, frame: &[u8], latency: Duration) -> Option<Self> {
+        let frame: &[u8; CANDIDATE_SIZE] = frame.try_into().ok()?;
+        if frame[0] != client_msg::CANDIDATE {
+            return None;
+        }
+        let data: &C2SData = bytemuck::from_bytes(&frame[1..C2S_SIZE]);
+        let digest = frame[C2S_SIZE..].try_into().ok()?;
+        Some(Self {
+            peer,
+            seq: data.acked_seq(),
+            digest: u32::from_be_bytes(digest),
+            latency,
+        })
+    }
+}
+
+/// Creates a [`CANDIDATE`](crate::client_msg::CANDIDATE) frame naming
+/// the given state as the last one applied.
+pub fn candidate_frame(state: &S2CData) -> [u8; CANDIDATE_SIZE] {
+    let mut frame = [0; CANDIDATE_SIZE];
+    frame[0] = client_msg::CANDIDATE;
+    frame[1..3].copy_from_slice(&state.seq().to_be_bytes());
+    frame[C2S_SIZE..].copy_from_slice(&state.digest().to_be_bytes());
+    frame
+}
+
+/// Elects the candidate to host the game.
+///
+/// The state the most candidates agree on, by sequence number and
+/// digest, is resumed, and the newest one of those tied. Of the
+/// candidates having that state, the one answering first wins.
+pub fn elect(candidates: &[Candidate]) -> Option<&Candidate> {
+    let agreeing = |c: &Candidate| {
+        candidates
+            .iter()
+            .filter(|o| (o.seq, o.digest) == (c.seq, c.digest))
+            .count()
+    };
+    candidates.iter().max_by(|a, b| {
+        agreeing(a)
+            .cmp(&agreeing(b))
+            .then_with(|| match (a.seq, b.seq) {
+                (a, b) if a == b => std::cmp::Ordering::Equal,
+                (a, b) if seq_follows(a, b) => std::cmp::Ordering::Greater,
+                _ => std::cmp::Ordering::Less,
+            })
+            .then_with(|| b.latency.cmp(&a.latency))
+    })
+}
+
+/// Creates a game continuing from the given state, with every
+/// player left to an AI until handed back with
+/// [`State::take_from_king`].
+///
+/// What states do not carry, such as the options of the game and
+/// the strategies of AIs, is taken from `b_opt`.
+pub fn resume(mut b_opt: BasicOpts, data: &S2CData) -> curseofrust::Result<State> {
+    b_opt.width = data.width as u32;
+    b_opt.height = data.height as u32;
+    // Maps are generated for one human at least.
+    b_opt.clients = 1;
+    let mut st = State::new(b_opt)?;
+    st.time = 0;
+    apply_s2c_msg(&mut st, *data)?;
+    st.controlled = Player::NEUTRAL;
+    st.hand_to_king(Player(1), Strategy::Opportunist);
+    Ok(st)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resumed_games_match() {
+        let mut b_opt = BasicOpts::default();
+        b_opt.seed = 3;
+        b_opt.clients = 2;
+        let mut st = State::new(b_opt.clone()).unwrap();
+        st.step_n(200);
+        let mut data = S2CData::new(Player(1), &st);
+        data.set_seq(42);
+
+        let resumed = resume(BasicOpts::default(), &data).unwrap();
+        let again = S2CData::new(Player(1), &resumed);
+        assert_eq!(again.digest(), data.digest());
+        let candidate = Candidate::read(4, &candidate_frame(&data), Duration::ZERO).unwrap();
+        assert_eq!((candidate.seq, candidate.digest), (42, again.digest()));
+        assert_eq!(
+            Candidate::read(4, &candidate_frame(&data)[..C2S_SIZE], Duration::ZERO),
+            None
+        );
+        assert_eq!(resumed.time, st.time);
+        assert!(resumed.king(Player(1)).is_some());
+    }
+
+    #[test]
+    fn election_prefers_agreement() {
+        let candidate = |peer, seq, digest, ms| Candidate {
+            peer,
+            seq,
+            digest,
+            latency: Duration::from_millis(ms),
+        };
+        // Two agree on an older state, one is ahead on its own.
+        let candidates = [
+            candidate(0, 10, 7, 50),
+            candidate(1, 10, 7, 20),
+            candidate(2, 11, 9, 5),
+        ];
+        assert_eq!(elect(&candidates).map(|c| c.peer), Some(1));
+        // Ties go to the newest state, across wrapping.
+        let candidates = [candidate(0, u16::MAX, 1, 5), candidate(1, 1, 2, 50)];
+        assert_eq!(elect(&candidates).map(|c| c.peer), Some(1));
+        // Digests differing in their high bytes only disagree.
+        let candidates = [
+            candidate(0, 10, 0x0100_0007, 5),
+            candidate(1, 10, 0x0200_0007, 10),
+            candidate(2, 10, 0x0200_0007, 20),
+        ];
+        assert_eq!(elect(&candidates).map(|c| c.peer), Some(1));
+        assert_eq!(elect(&[]), None);
+    }
+}
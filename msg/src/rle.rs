@@ -0,0 +1,102 @@
+//! Run-length encoding of [`S2CData`] grids.
+//!
+//! Most rows of the grids are long runs of identical values,
+//! so encoding them shrinks state packets greatly.
+//! The header fields are kept as is, and each plane is encoded
+//! as `(count, value)` pairs, where `count` is a single byte.
+//! The sequence number follows the planes, and may be left out
+//! if it is `0`.
+
+use std::mem::{offset_of, size_of};
+
+use bytemuck::Zeroable;
+use curseofrust::{MAX_HEIGHT, MAX_WIDTH};
+
+use crate::S2CData;
+
+const PLANE_LEN: usize = (MAX_WIDTH * MAX_HEIGHT) as usize;
+
+/// Byte offsets and element sizes of the encoded planes.
+const PLANES: [(usize, usize); 4] = [
+    (offset_of!(S2CData, flag), size_of::<u8>()),
+    (offset_of!(S2CData, owner), size_of::<u8>()),
+    (offset_of!(S2CData, pop), size_of::<u16>()),
+    (offset_of!(S2CData, tile), size_of::<u8>()),
+];
+
+const HEADER_LEN: usize = offset_of!(S2CData, flag);
+
+impl S2CData {
+    /// Encodes this data into the given buffer and returns
+    /// the encoded length.
+    ///
+    /// Returns `None` if the buffer is too small.
+    pub fn encode_rle(&self, buf: &mut [u8]) -> Option<usize> {
+        let bytes = bytemuck::bytes_of(self);
+        buf.get_mut(..HEADER_LEN)?
+            .copy_from_slice(&bytes[..HEADER_LEN]);
+        let mut len = HEADER_LEN;
+
+        for (offset, elem) in PLANES {
+            let mut plane = bytes[offset..offset + PLANE_LEN * elem].chunks_exact(elem);
+            let mut current = plane.next()?;
+            let mut count = 1u8;
+            for value in plane.chain(std::iter::once(&[][..])) {
+                if value == current && count < u8::MAX {
+                    count += 1;
+                    continue;
+                }
+                let run = buf.get_mut(len..len + 1 + elem)?;
+                run[0] = count;
+                run[1..].copy_from_slice(current);
+                len += 1 + elem;
+                current = value;
+                count = 1;
+            }
+        }
+
+        if self.seq != 0 {
+            buf.get_mut(len..len + size_of::<u16>())?
+                .copy_from_slice(&bytes[offset_of!(S2CData, seq)..][..size_of::<u16>()]);
+            len += size_of::<u16>();
+        }
+        Some(len)
+    }
+
+    /// Decodes data encoded by [`S2CData::encode_rle`].
+    ///
+    /// Returns `None` if the encoded data is malformed.
+    pub fn decode_rle(encoded: &[u8]) -> Option<Self> {
+        let mut data = Self::zeroed();
+        let bytes = bytemuck::bytes_of_mut(&mut data);
+        bytes[..HEADER_LEN].copy_from_slice(encoded.get(..HEADER_LEN)?);
+        let mut encoded = &encoded[HEADER_LEN..];
+
+        for (offset, elem) in PLANES {
+            let mut plane = bytes[offset..offset + PLANE_LEN * elem].chunks_exact_mut(elem);
+            let mut filled = 0;
+            while filled < PLANE_LEN {
+                if encoded.len() < 1 + elem {
+                    return None;
+                }
+                let (run, rest) = encoded.split_at(1 + elem);
+                let count = run[0] as usize;
+                if count == 0 || filled + count > PLANE_LEN {
+                    return None;
+                }
+                for value in plane.by_ref().take(count) {
+                    value.copy_from_slice(&run[1..]);
+                }
+                filled += count;
+                encoded = rest;
+            }
+        }
+
+        match encoded {
+            [] => {}
+            &[s0, s1] => data.seq = u16::from_ne_bytes([s0, s1]),
+            _ => return None,
+        }
+        Some(data)
+    }
+}
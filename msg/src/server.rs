@@ -2,12 +2,12 @@
 
 use std::{borrow::Cow, net::SocketAddr};
 
-use curseofrust::{state::State, Player, Pos, FLAG_POWER, MAX_HEIGHT, MAX_WIDTH};
-
-use crate::{
-    client_msg::*, C2SData, S2CData, TileClass, __S2C_PAD_0_LEN, __S2C_PAD_1_LEN, __S2C_PAD_2_LEN,
+use curseofrust::{
+    state::State, Player, Pos, FLAG_POWER, MAX_FLAG_POWER, MAX_HEIGHT, MAX_PLAYERS, MAX_WIDTH,
 };
 
+use crate::{client_msg::*, encode_tile, C2SData, S2CData, __S2C_PAD_0_LEN, __S2C_PAD_1_LEN};
+
 #[derive(Debug, Clone)]
 #[deprecated = "use self-defined client struct instead"]
 pub struct ClientRecord {
@@ -44,38 +44,40 @@ impl S2CData {
         }
 
         let mut owner = [[0u8; MAX_HEIGHT as usize]; MAX_WIDTH as usize];
-        let mut pop = [[0u16; MAX_HEIGHT as usize]; MAX_WIDTH as usize];
         let mut tile = [[0u8; MAX_HEIGHT as usize]; MAX_WIDTH as usize];
         for (x, arr) in state.grid.raw_tiles().iter().enumerate() {
             for (y, t) in arr.iter().enumerate() {
-                let ow = t.owner().0;
-                owner[x][y] = ow as u8;
-                pop[x][y] = t.units()[ow as usize].to_be();
-                tile[x][y] = TileClass::from(t) as u8;
+                owner[x][y] = t.owner().0 as u8;
+                tile[x][y] = encode_tile(t);
             }
         }
 
-        S2CData {
+        let mut data = S2CData {
             player: player.0 as u8,
             pause_request: 0,
-            gold: state.countries.each_ref().map(|c| (c.gold as u32).to_be()),
-            time: (state.time as u32).to_be(),
+            time_left: 0,
+            gold: [0; MAX_PLAYERS],
+            time: 0,
             width: state.grid.width() as u8,
             height: state.grid.height() as u8,
             flag,
             owner,
-            pop,
+            pop: [[0; MAX_HEIGHT as usize]; MAX_WIDTH as usize],
             tile,
             __pad0: [0; __S2C_PAD_0_LEN],
             __pad1: [0; __S2C_PAD_1_LEN],
-            __pad2: [0; __S2C_PAD_2_LEN],
+            seq: 0,
+        };
+        data.set_time(state.time);
+        for c in &state.countries {
+            data.set_gold(c.player, c.gold);
         }
-    }
-
-    /// Sets the player of the data.
-    #[inline]
-    pub fn set_player(&mut self, player: Player) {
-        self.player = player.0 as u8;
+        for (x, arr) in state.grid.raw_tiles().iter().enumerate() {
+            for (y, t) in arr.iter().enumerate() {
+                data.set_pop(Pos(x as i32, y as i32), t.units()[t.owner().0 as usize]);
+            }
+        }
+        data
     }
 }
 
@@ -85,39 +87,32 @@ pub fn apply_c2s_msg(
     msg: u8,
     data: C2SData,
 ) -> curseofrust::Result<()> {
-    let pl = player.0 as usize;
-    let pos = Pos(data.x as i32, data.y as i32);
+    let pl = player.index()?;
+    let pos = data.pos();
+    if matches!(
+        msg,
+        BUILD | FLAG_ON | FLAG_OFF | RALLY | BUILD_ROAD | QUEUE_BUILD
+    ) && (pos.0 as u32 >= state.grid.width() || pos.1 as u32 >= state.grid.height())
+    {
+        return Err(curseofrust::Error::PosOutOfBound(pos));
+    }
 
     match msg {
         BUILD => {
-            return state.grid.build(
-                state
-                    .countries
-                    .get_mut(pl)
-                    .ok_or(curseofrust::Error::PlayerNotFound(player))?,
-                pos,
-            )
+            return state
+                .grid
+                .build(&mut state.countries[pl], pos, &state.balance)
         }
-        FLAG_ON => state
-            .fgs
-            .get_mut(pl)
-            .ok_or(curseofrust::Error::PlayerNotFound(player))?
-            .add(&state.grid, pos, FLAG_POWER),
-        FLAG_OFF => state
-            .fgs
-            .get_mut(pl)
-            .ok_or(curseofrust::Error::PlayerNotFound(player))?
-            .remove(&state.grid, pos, FLAG_POWER),
-        FLAG_OFF_ALL => state
-            .fgs
-            .get_mut(pl)
-            .ok_or(curseofrust::Error::PlayerNotFound(player))?
-            .remove_with_prob(&state.grid, 1.0),
-        FLAG_OFF_HALF => state
-            .fgs
-            .get_mut(pl)
-            .ok_or(curseofrust::Error::PlayerNotFound(player))?
-            .remove_with_prob(&state.grid, 0.5),
+        FLAG_ON => state.fgs[pl].raise(&state.grid, pos, data.msg.max(1) as i32 * FLAG_POWER),
+        FLAG_OFF => state.fgs[pl].remove(&state.grid, pos, MAX_FLAG_POWER),
+        RALLY => state.grid.set_rally(player, pos, data.rally_target())?,
+        FLAG_OFF_ALL => state.fgs[pl].remove_with_prob(&state.grid, 1.0),
+        FLAG_OFF_HALF => state.fgs[pl].remove_with_prob(&state.grid, 0.5),
+        FLAG_CITIES => state.flag_cities(player),
+        FLAG_FRONTLINE => state.flag_frontline(player),
+        QUEUE_BUILD if data.msg == 0 => state.unqueue_build(player, pos),
+        QUEUE_BUILD => return state.queue_build(player, pos),
+        BUILD_ROAD => return state.grid.build_road(&mut state.countries[pl], pos),
         _ => {}
     }
     Ok(())
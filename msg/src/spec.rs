@@ -0,0 +1,709 @@
+//! Wire specification of the protocol, for third-party clients.
+//!
+//! Every frame starts with a message id. Ids of [`client_msg`] and
+//! [`server_msg`] do not overlap, so the id tells the direction of
+//! a frame. Frames exchanged between a relay and its host are
+//! prefixed with a [`RELAY_HEADER_SIZE`](crate::RELAY_HEADER_SIZE)-byte peer id, which is not
+//! part of the frames described here. Multi-byte integers are
+//! big-endian.
+//!
+//! # Client to server
+//!
+//! Every client frame is [`C2S_SIZE`] bytes long, but for
+//! [`CONNECT`](client_msg::CONNECT) frames, which are followed by
+//! a password of up to [`MAX_PASSWORD`](crate::MAX_PASSWORD) bytes,
+//! and [`CANDIDATE`](client_msg::CANDIDATE) frames, which are
+//! [`CANDIDATE_SIZE`](crate::CANDIDATE_SIZE) bytes long:
+//!
+//! | Offset | Size | Field                                        |
+//! |--------|------|----------------------------------------------|
+//! | 0      | 1    | message id, one of [`client_msg`]            |
+//! | 1      | 1    | X of the targeting position                  |
+//! | 2      | 1    | Y of the targeting position                  |
+//! | 3      | 1    | info byte, depending on the message          |
+//!
+//! The info byte of the first frame a client sends carries its
+//! [`capability`] flags, and its X byte the number of players it
+//! claims, see [`IS_ALIVE`](client_msg::IS_ALIVE). Servers asking
+//! for a password, or allowing some addresses only, answer clients
+//! they refuse with [`CONN_REJECTED`](server_msg::CONN_REJECTED).
+//! Clients rejoining after a host [`migration`](crate::migration)
+//! give the first player they controlled in its Y byte.
+//!
+//! # Server to client
+//!
+//! A [`STATE`](server_msg::STATE) frame is [`S2C_SIZE`] bytes long,
+//! the id followed by [`S2CData`]:
+//!
+//! | Offset | Size | Field                                        |
+//! |--------|------|----------------------------------------------|
+//! | 0      | 1    | message id                                   |
+//! | 1      | 1    | player the client controls                   |
+//! | 2      | 1    | pause request                                |
+//! | 3      | 2    | seconds left of the time limit, `0` if none  |
+//! | 5      | 32   | gold of each player, as `u32`                |
+//! | 37     | 4    | time, as `u32`                               |
+//! | 41     | 1    | width of the grid                            |
+//! | 42     | 1    | height of the grid                           |
+//! | 43     | 1160 | flags, bit `p` set if player `p` has one     |
+//! | 1203   | 1160 | owner of each tile                           |
+//! | 2363   | 2320 | population of the owner, as `u16`            |
+//! | 4683   | 1160 | [`TileClass`] and flags of each tile         |
+//! | 5843   | 2    | sequence number, `0` if unnumbered           |
+//!
+//! Grids are indexed by X, then Y, and are [`MAX_WIDTH`] by
+//! [`MAX_HEIGHT`] regardless of the size of the map. Tile bytes
+//! hold the [`Landscape`](curseofrust::grid::Landscape) above
+//! [`LANDSCAPE_SHIFT`] and the [`TILE_ROAD`] bit, see
+//! [`encode_tile`](crate::encode_tile), only for clients advertising
+//! [`TILE_FLAGS`](capability::TILE_FLAGS).
+//!
+//! A [`STATE_RLE`](server_msg::STATE_RLE) frame carries the same data
+//! with each grid run-length encoded, see [`S2CData::encode_rle`].
+//!
+//! Over `udp`, either frame may be split into
+//! [`STATE_FRAGMENT`](server_msg::STATE_FRAGMENT) frames, see the
+//! [`fragment`](crate::fragment) module. Fragments are to be
+//! reassembled before validating, though [`validate_frame`] checks
+//! their headers.
+//!
+//! Other server frames announce events, and are exactly as long
+//! as documented on their ids in [`server_msg`], but for
+//! [`EVENT`](server_msg::EVENT) frames, see the [`event`](crate::event)
+//! module, and [`RULES`](server_msg::RULES) frames, whose text runs
+//! to the end of the frame.
+//! [`CONN_ACCEPTED`](server_msg::CONN_ACCEPTED) is reserved.
+//!
+//! Once a server answers a client with
+//! [`RELIABLE`](server_msg::RELIABLE), frames of that client, and
+//! [`EVENT`](server_msg::EVENT) frames to it, may be wrapped in the
+//! envelope of the `reliable` module of `curseofrust-net-foundation`,
+//! whose leading bytes are no message ids. Envelopes are to be removed
+//! before validating.
+//!
+//! [`validate_frame`] checks a frame against this specification,
+//! and [`fixtures`] generates a conforming frame of every message.
+
+use std::fmt::Display;
+
+use bytemuck::Zeroable;
+use curseofrust::{Player, Pos, MAX_HEIGHT, MAX_PLAYERS, MAX_WIDTH};
+
+use crate::{
+    capability, client_msg, event::Event, fragment, landscape_of, migration, reject, server_msg,
+    C2SData, S2CData, TileClass, C2S_SIZE, CANDIDATE_SIZE, LANDSCAPE_SHIFT, MAX_PASSWORD, S2C_SIZE,
+    TILE_ROAD,
+};
+
+/// Ids of client messages.
+pub(crate) const CLIENT_MSGS: [u8; 23] = [
+    client_msg::CONNECT,
+    client_msg::RELAY_HOST,
+    client_msg::CANDIDATE,
+    client_msg::BUILD,
+    client_msg::FLAG_ON,
+    client_msg::FLAG_OFF,
+    client_msg::FLAG_OFF_ALL,
+    client_msg::FLAG_OFF_HALF,
+    client_msg::RALLY,
+    client_msg::FLAG_CITIES,
+    client_msg::FLAG_FRONTLINE,
+    client_msg::BUILD_ROAD,
+    client_msg::QUEUE_BUILD,
+    client_msg::IS_ALIVE,
+    client_msg::STATE_ACK,
+    client_msg::PONG,
+    client_msg::FULL_STATE_REQUEST,
+    client_msg::PAUSE,
+    client_msg::UNPAUSE,
+    client_msg::SURRENDER,
+    client_msg::VOTE_RESTART,
+    client_msg::END_TURN,
+    client_msg::SWITCH_PLAYER,
+];
+
+/// Lengths of server frames announcing events.
+const EVENT_LENGTHS: [(u8, usize); 12] = [
+    (server_msg::AFK, 3),
+    (server_msg::SURRENDER, 2),
+    (server_msg::VOTE_RESTART, 3),
+    (server_msg::GAME_OVER, 2),
+    (server_msg::END_TURN, 3),
+    (server_msg::ELIMINATED, 2),
+    (server_msg::PLUNDER, 9),
+    (server_msg::RELIABLE, 1),
+    (server_msg::PING, 1),
+    (server_msg::CONN_REJECTED, 2),
+    (server_msg::HOST_LOST, 1),
+    (server_msg::ELECTED, 2),
+];
+
+/// Client messages targeting a position on the grid.
+const POSITIONAL: [u8; 6] = [
+    client_msg::BUILD,
+    client_msg::BUILD_ROAD,
+    client_msg::QUEUE_BUILD,
+    client_msg::FLAG_ON,
+    client_msg::FLAG_OFF,
+    client_msg::RALLY,
+];
+
+/// Direction of a frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    ClientToServer,
+    ServerToClient,
+}
+
+/// Reason a frame does not conform to the specification.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum FrameError {
+    /// The frame is empty.
+    Empty,
+    /// The message id is not known.
+    UnknownMessage(u8),
+    /// The frame is not as long as its message requires.
+    Length {
+        msg: u8,
+        expected: usize,
+        actual: usize,
+    },
+    /// The run-length encoded grids are malformed.
+    MalformedRle,
+    /// A field is out of its range.
+    InvalidField { msg: u8, field: &'static str },
+}
+
+impl Display for FrameError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FrameError::Empty => write!(f, "empty frame"),
+            FrameError::UnknownMessage(msg) => write!(f, "unknown message id {msg}"),
+            FrameError::Length {
+                msg,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "message {msg} should be {expected} bytes long, got {actual}"
+            ),
+            FrameError::MalformedRle => write!(f, "malformed run-length encoded state"),
+            FrameError::InvalidField { msg, field } => {
+                write!(f, "field {field} of message {msg} is out of range")
+            }
+        }
+    }
+}
+
+impl std::error::Error for FrameError {}
+
+/// Checks the given frame against the specification,
+/// and returns its direction.
+///
+/// The frame must not carry a relay header.
+pub fn validate_frame(frame: &[u8]) -> Result<Direction, FrameError> {
+    let (&msg, body) = frame.split_first().ok_or(FrameError::Empty)?;
+    let expect_len = |expected: usize| {
+        (frame.len() == expected)
+            .then_some(())
+            .ok_or(FrameError::Length {
+                msg,
+                expected,
+                actual: frame.len(),
+            })
+    };
+    let invalid = |field| FrameError::InvalidField { msg, field };
+
+    match msg {
+        msg if CLIENT_MSGS.contains(&msg) => {
+            // Connect frames are followed by the password.
+            match frame.len() {
+                len if msg == client_msg::CONNECT && len > C2S_SIZE => {
+                    expect_len(len.min(C2S_SIZE + MAX_PASSWORD))?
+                }
+                _ if msg == client_msg::CANDIDATE => expect_len(CANDIDATE_SIZE)?,
+                _ => expect_len(C2S_SIZE)?,
+            }
+            let data: C2SData = *bytemuck::from_bytes(&body[..C2S_SIZE - 1]);
+            if POSITIONAL.contains(&msg)
+                && (data.x as u32 >= MAX_WIDTH || data.y as u32 >= MAX_HEIGHT)
+            {
+                return Err(invalid("position"));
+            }
+            if msg == client_msg::QUEUE_BUILD && data.msg > 1 {
+                return Err(invalid("queued"));
+            }
+            if matches!(msg, client_msg::IS_ALIVE | client_msg::CONNECT)
+                && data.x as usize >= MAX_PLAYERS
+            {
+                return Err(invalid("slots"));
+            }
+            if matches!(msg, client_msg::IS_ALIVE | client_msg::CONNECT)
+                && data.y as usize >= MAX_PLAYERS
+            {
+                return Err(invalid("claim"));
+            }
+            // Offsets of `-8` cannot be encoded.
+            if msg == client_msg::RALLY && (data.msg >> 4 == 8 || data.msg & 0xF == 8) {
+                return Err(invalid("rally offset"));
+            }
+            Ok(Direction::ClientToServer)
+        }
+        server_msg::STATE => {
+            expect_len(S2C_SIZE)?;
+            validate_state(msg, bytemuck::from_bytes(body))?;
+            Ok(Direction::ServerToClient)
+        }
+        server_msg::STATE_RLE => {
+            let data = S2CData::decode_rle(body).ok_or(FrameError::MalformedRle)?;
+            validate_state(msg, &data)?;
+            Ok(Direction::ServerToClient)
+        }
+        server_msg::STATE_FRAGMENT => match *body {
+            [_, _, index, count, ref chunk @ ..] => {
+                if index >= count {
+                    Err(invalid("index"))
+                } else if chunk.is_empty() || chunk.len() > fragment::MAX_CHUNK {
+                    Err(invalid("chunk"))
+                } else {
+                    Ok(Direction::ServerToClient)
+                }
+            }
+            _ => Err(FrameError::Length {
+                msg,
+                expected: fragment::HEADER_SIZE + 1,
+                actual: frame.len(),
+            }),
+        },
+        server_msg::RULES => match std::str::from_utf8(body) {
+            Ok(_) => Ok(Direction::ServerToClient),
+            Err(_) => Err(invalid("rules")),
+        },
+        server_msg::EVENT => match Event::decode(body) {
+            Ok(_) => Ok(Direction::ServerToClient),
+            Err("length") => Err(FrameError::Length {
+                msg,
+                expected: crate::event::HEADER_SIZE,
+                actual: frame.len(),
+            }),
+            Err(field) => Err(invalid(field)),
+        },
+        _ => {
+            let &(_, len) = EVENT_LENGTHS
+                .iter()
+                .find(|(id, _)| *id == msg)
+                .ok_or(FrameError::UnknownMessage(msg))?;
+            expect_len(len)?;
+            let is_player = |b: u8| (b as usize) < MAX_PLAYERS;
+            let field = match (msg, body) {
+                (server_msg::AFK, &[player, _]) if !is_player(player) => Some("player"),
+                (server_msg::AFK, &[_, idle]) if idle > 1 => Some("idle"),
+                (
+                    server_msg::SURRENDER | server_msg::GAME_OVER | server_msg::ELIMINATED,
+                    &[player],
+                ) if !is_player(player) => Some("player"),
+                (server_msg::CONN_REJECTED, &[reason])
+                    if !matches!(
+                        reason,
+                        reject::PASSWORD | reject::NOT_ALLOWED | reject::FULL
+                    ) =>
+                {
+                    Some("reason")
+                }
+                (server_msg::ELECTED, &[0]) => Some("clients"),
+                (server_msg::VOTE_RESTART | server_msg::END_TURN, &[count, of]) if count > of => {
+                    Some("count")
+                }
+                (server_msg::PLUNDER, &[x, y, ..])
+                    if x as u32 >= MAX_WIDTH || y as u32 >= MAX_HEIGHT =>
+                {
+                    Some("position")
+                }
+                (server_msg::PLUNDER, &[_, _, from, to, ..])
+                    if !is_player(from) || !is_player(to) =>
+                {
+                    Some("player")
+                }
+                _ => None,
+            };
+            match field {
+                Some(field) => Err(invalid(field)),
+                None => Ok(Direction::ServerToClient),
+            }
+        }
+    }
+}
+
+fn validate_state(msg: u8, data: &S2CData) -> Result<(), FrameError> {
+    let invalid = |field| FrameError::InvalidField { msg, field };
+    if data.player as usize >= MAX_PLAYERS {
+        return Err(invalid("player"));
+    }
+    if data.width as u32 > MAX_WIDTH || data.height as u32 > MAX_HEIGHT {
+        return Err(invalid("size"));
+    }
+    for x in 0..data.width as usize {
+        for y in 0..data.height as usize {
+            if TileClass::from(data.tile[x][y]) == TileClass::Other {
+                return Err(invalid("tile"));
+            }
+            if landscape_of(data.tile[x][y]).is_none() {
+                return Err(invalid("landscape"));
+            }
+            if data.owner[x][y] as usize >= MAX_PLAYERS {
+                return Err(invalid("owner"));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// A conforming frame of a message, for testing implementations.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fixture {
+    /// Name of the message.
+    pub name: &'static str,
+    pub frame: Vec<u8>,
+}
+
+/// Generates a conforming frame of every message.
+///
+/// The frames are deterministic, so they can be stored
+/// and compared byte by byte.
+pub fn fixtures() -> Vec<Fixture> {
+    let c2s = |name, msg, data: C2SData| {
+        let mut frame = vec![msg];
+        frame.extend_from_slice(bytemuck::bytes_of(&data));
+        Fixture { name, frame }
+    };
+    let event = |name, frame: &[u8]| Fixture {
+        name,
+        frame: frame.to_vec(),
+    };
+
+    let state = sample_state();
+    let mut rle = vec![0u8; S2C_SIZE];
+    let len = state
+        .encode_rle(&mut rle[1..])
+        .expect("a state should fit in a state frame");
+    rle.truncate(len + 1);
+    rle[0] = server_msg::STATE_RLE;
+    let mut full = vec![server_msg::STATE];
+    full.extend_from_slice(bytemuck::bytes_of(&state));
+    let first_fragment = fragment::split(state.seq(), &full)
+        .next()
+        .expect("a state frame should have fragments");
+
+    let mut rules = vec![server_msg::RULES];
+    rules.extend_from_slice(b"args = [\"-d\", \"h\"]\n");
+
+    let rally = C2SData::rally(Pos(10, 10), Some(Pos(13, 8)))
+        .expect("the rally offset should be encodable");
+    vec![
+        c2s(
+            "connect",
+            client_msg::CONNECT,
+            (Pos(0, 0), capability::RLE).into(),
+        ),
+        c2s("relay_host", client_msg::RELAY_HOST, Zeroable::zeroed()),
+        Fixture {
+            name: "candidate",
+            frame: migration::candidate_frame(&state).to_vec(),
+        },
+        c2s("build", client_msg::BUILD, (Pos(3, 4), 0).into()),
+        c2s("flag_on", client_msg::FLAG_ON, (Pos(5, 6), 2).into()),
+        c2s("flag_off", client_msg::FLAG_OFF, (Pos(5, 6), 0).into()),
+        c2s("flag_off_all", client_msg::FLAG_OFF_ALL, Zeroable::zeroed()),
+        c2s(
+            "flag_off_half",
+            client_msg::FLAG_OFF_HALF,
+            Zeroable::zeroed(),
+        ),
+        c2s("rally", client_msg::RALLY, rally),
+        c2s("flag_cities", client_msg::FLAG_CITIES, Zeroable::zeroed()),
+        c2s(
+            "flag_frontline",
+            client_msg::FLAG_FRONTLINE,
+            Zeroable::zeroed(),
+        ),
+        c2s("build_road", client_msg::BUILD_ROAD, (Pos(3, 5), 0).into()),
+        c2s(
+            "queue_build",
+            client_msg::QUEUE_BUILD,
+            (Pos(3, 4), 1).into(),
+        ),
+        c2s(
+            "is_alive",
+            client_msg::IS_ALIVE,
+            (Pos(0, 0), capability::RLE).into(),
+        ),
+        c2s("state_ack", client_msg::STATE_ACK, C2SData::state_ack(7)),
+        c2s("pong", client_msg::PONG, Zeroable::zeroed()),
+        c2s(
+            "full_state_request",
+            client_msg::FULL_STATE_REQUEST,
+            Zeroable::zeroed(),
+        ),
+        c2s("pause", client_msg::PAUSE, Zeroable::zeroed()),
+        c2s("unpause", client_msg::UNPAUSE, Zeroable::zeroed()),
+        c2s("surrender", client_msg::SURRENDER, Zeroable::zeroed()),
+        c2s("vote_restart", client_msg::VOTE_RESTART, Zeroable::zeroed()),
+        c2s("end_turn", client_msg::END_TURN, Zeroable::zeroed()),
+        c2s(
+            "switch_player",
+            client_msg::SWITCH_PLAYER,
+            Zeroable::zeroed(),
+        ),
+        Fixture {
+            name: "state",
+            frame: full,
+        },
+        Fixture {
+            name: "state_rle",
+            frame: rle,
+        },
+        Fixture {
+            name: "state_fragment",
+            frame: first_fragment,
+        },
+        event("afk", &[server_msg::AFK, 2, 1]),
+        event("surrender", &[server_msg::SURRENDER, 2]),
+        event("vote_restart", &[server_msg::VOTE_RESTART, 1, 2]),
+        event("game_over", &[server_msg::GAME_OVER, 1]),
+        event("end_turn", &[server_msg::END_TURN, 1, 2]),
+        event("eliminated", &[server_msg::ELIMINATED, 3]),
+        event("plunder", &[server_msg::PLUNDER, 3, 4, 2, 1, 0, 0, 1, 0]),
+        event("reliable", &[server_msg::RELIABLE]),
+        event("ping", &[server_msg::PING]),
+        event("event", &[server_msg::EVENT, 1, 3, 4, 1, 2]),
+        event(
+            "conn_rejected",
+            &[server_msg::CONN_REJECTED, reject::PASSWORD],
+        ),
+        event("host_lost", &[server_msg::HOST_LOST]),
+        event("elected", &[server_msg::ELECTED, 2]),
+        event("rules", &rules),
+    ]
+}
+
+/// A small deterministic state: a 21x21 grassland with
+/// a village of player 1, a town of player 2, a forest and a road.
+fn sample_state() -> S2CData {
+    let mut data = S2CData::zeroed();
+    data.set_player(Player(1));
+    data.set_gold(Player(1), 160);
+    data.set_gold(Player(2), 240);
+    data.set_time(1000);
+    data.width = 21;
+    data.height = 21;
+    for x in 0..21 {
+        for y in 0..21 {
+            data.tile[x][y] = TileClass::Grassland as u8;
+        }
+    }
+    for (Pos(x, y), player, class, pop) in [
+        (Pos(3, 4), 1, TileClass::Village, 50),
+        (Pos(15, 12), 2, TileClass::Town, 120),
+    ] {
+        data.tile[x as usize][y as usize] = class as u8;
+        data.owner[x as usize][y as usize] = player;
+        data.set_pop(Pos(x, y), pop);
+    }
+    data.tile[8][8] |= 1 << LANDSCAPE_SHIFT;
+    data.tile[3][5] |= TILE_ROAD;
+    data.flag[5][6] = 1 << 1;
+    data.set_seq(7);
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fixtures_conform() {
+        let fixtures = fixtures();
+        for Fixture { name, frame } in &fixtures {
+            let expected = if CLIENT_MSGS.contains(&frame[0]) {
+                Direction::ClientToServer
+            } else {
+                Direction::ServerToClient
+            };
+            assert_eq!(validate_frame(frame), Ok(expected), "fixture {name}");
+        }
+
+        // Every message has a fixture.
+        let server_msgs = [
+            server_msg::STATE,
+            server_msg::STATE_RLE,
+            server_msg::STATE_FRAGMENT,
+            server_msg::EVENT,
+        ];
+        let events = EVENT_LENGTHS.map(|(msg, _)| msg);
+        for msg in CLIENT_MSGS.into_iter().chain(server_msgs).chain(events) {
+            assert!(fixtures.iter().any(|f| f.frame[0] == msg), "message {msg}");
+        }
+    }
+
+    #[test]
+    fn golden_frames() {
+        let fixtures = fixtures();
+        let frame = |name| {
+            &fixtures
+                .iter()
+                .find(|f| f.name == name)
+                .expect("fixture should exist")
+                .frame
+        };
+
+        assert_eq!(frame("flag_on"), &[21, 5, 6, 2]);
+        assert_eq!(frame("rally"), &[25, 10, 10, 0x3E]);
+        assert_eq!(frame("is_alive"), &[30, 0, 0, 1]);
+        assert_eq!(frame("build_road"), &[28, 3, 5, 0]);
+        assert_eq!(frame("queue_build"), &[29, 3, 4, 1]);
+        assert_eq!(frame("switch_player"), &[53, 0, 0, 0]);
+
+        let state = frame("state");
+        assert_eq!(state.len(), 5845);
+        assert_eq!(state[1], 1);
+        // Gold of player 1 and 2.
+        assert_eq!(state[9..13], 160u32.to_be_bytes());
+        assert_eq!(state[13..17], 240u32.to_be_bytes());
+        assert_eq!(state[37..41], 1000u32.to_be_bytes());
+        assert_eq!(state[41..43], [21, 21]);
+        // Flag on (5, 6), owner and population of the village on (3, 4).
+        assert_eq!(state[43 + 5 * 29 + 6], 1 << 1);
+        assert_eq!(state[1203 + 3 * 29 + 4], 1);
+        assert_eq!(state[2363 + (3 * 29 + 4) * 2..][..2], 50u16.to_be_bytes());
+        assert_eq!(state[4683 + 3 * 29 + 4], TileClass::Village as u8);
+        // Forest on (8, 8).
+        assert_eq!(state[4683 + 8 * 29 + 8], 0x10 | TileClass::Grassland as u8);
+        // Road on (3, 5).
+        assert_eq!(state[4683 + 3 * 29 + 5], 0x40 | TileClass::Grassland as u8);
+        assert_eq!(state[5843..], 7u16.to_be_bytes());
+        assert_eq!(frame("state_ack"), &[31, 0, 7, 0]);
+        assert_eq!(frame("pong"), &[32, 0, 0, 0]);
+        assert_eq!(frame("full_state_request"), &[33, 0, 0, 0]);
+        assert_eq!(frame("ping"), &[61]);
+        assert_eq!(frame("event"), &[62, 1, 3, 4, 1, 2]);
+        assert_eq!(frame("conn_rejected"), &[6, 1]);
+        let candidate = frame("candidate");
+        assert_eq!(candidate[..4], [3, 0, 7, 0]);
+        assert_eq!(candidate[4..], sample_state().digest().to_be_bytes());
+        assert_eq!(frame("host_lost"), &[63]);
+        assert_eq!(frame("elected"), &[64, 2]);
+        assert_eq!(frame("rules")[0], 65);
+
+        let fragment = frame("state_fragment");
+        assert_eq!(fragment[..5], [60, 0, 7, 0, 5]);
+        assert_eq!(fragment[5..], state[..fragment::MAX_CHUNK]);
+    }
+
+    #[test]
+    fn state_round_trip() {
+        let fixtures = fixtures();
+        let frame = |name| {
+            &fixtures
+                .iter()
+                .find(|f| f.name == name)
+                .expect("fixture should exist")
+                .frame
+        };
+        let full: S2CData = *bytemuck::from_bytes(&frame("state")[1..]);
+        let decoded = S2CData::decode_rle(&frame("state_rle")[1..]).unwrap();
+        assert_eq!(bytemuck::bytes_of(&decoded), bytemuck::bytes_of(&full));
+        assert_eq!(full.gold(Player(2)), Some(240));
+        assert_eq!(full.pop(Pos(15, 12)), Some(120));
+    }
+
+    #[test]
+    fn invalid_frames() {
+        assert_eq!(validate_frame(&[]), Err(FrameError::Empty));
+        assert_eq!(validate_frame(&[99]), Err(FrameError::UnknownMessage(99)));
+        assert!(matches!(
+            validate_frame(&[client_msg::BUILD, 1, 2]),
+            Err(FrameError::Length { .. })
+        ));
+        assert!(matches!(
+            validate_frame(&[client_msg::BUILD, 40, 2, 0]),
+            Err(FrameError::InvalidField { .. })
+        ));
+        assert!(matches!(
+            validate_frame(&[client_msg::CANDIDATE, 0, 7, 0]),
+            Err(FrameError::Length { .. })
+        ));
+        assert!(matches!(
+            validate_frame(&[client_msg::QUEUE_BUILD, 3, 4, 2]),
+            Err(FrameError::InvalidField {
+                field: "queued",
+                ..
+            })
+        ));
+        assert!(matches!(
+            validate_frame(&[client_msg::IS_ALIVE, 8, 0, 0]),
+            Err(FrameError::InvalidField { field: "slots", .. })
+        ));
+        let mut connect = vec![client_msg::CONNECT, 0, 0, 0];
+        connect.extend_from_slice(b"hunter2");
+        assert_eq!(validate_frame(&connect), Ok(Direction::ClientToServer));
+        connect.resize(C2S_SIZE + MAX_PASSWORD + 1, b'!');
+        assert!(matches!(
+            validate_frame(&connect),
+            Err(FrameError::Length { .. })
+        ));
+        assert!(matches!(
+            validate_frame(&[server_msg::CONN_REJECTED, 9]),
+            Err(FrameError::InvalidField {
+                field: "reason",
+                ..
+            })
+        ));
+        assert!(matches!(
+            validate_frame(&[server_msg::RULES, 0xff]),
+            Err(FrameError::InvalidField { field: "rules", .. })
+        ));
+        assert!(matches!(
+            validate_frame(&[server_msg::AFK, 8, 1]),
+            Err(FrameError::InvalidField { .. })
+        ));
+        assert_eq!(
+            validate_frame(&[server_msg::STATE_RLE, 0, 0]),
+            Err(FrameError::MalformedRle)
+        );
+
+        let mut state = sample_state();
+        state.tile[0][0] = 42;
+        let mut frame = vec![server_msg::STATE];
+        frame.extend_from_slice(bytemuck::bytes_of(&state));
+        assert!(matches!(
+            validate_frame(&frame),
+            Err(FrameError::InvalidField { field: "tile", .. })
+        ));
+
+        let mut state = sample_state();
+        state.tile[0][0] = 0xf0 | TileClass::Grassland as u8;
+        let mut frame = vec![server_msg::STATE];
+        frame.extend_from_slice(bytemuck::bytes_of(&state));
+        assert!(matches!(
+            validate_frame(&frame),
+            Err(FrameError::InvalidField {
+                field: "landscape",
+                ..
+            })
+        ));
+
+        assert!(matches!(
+            validate_frame(&[server_msg::EVENT, 9, 0, 0, 1, 0]),
+            Err(FrameError::InvalidField { field: "kind", .. })
+        ));
+        assert!(matches!(
+            validate_frame(&[server_msg::EVENT, 2, 0, 0, 1, 0, b'!']),
+            Err(FrameError::Length { .. })
+        ));
+        assert!(matches!(
+            validate_frame(&[server_msg::EVENT, 6, 8, 0, 0, 0]),
+            Err(FrameError::InvalidField { field: "speed", .. })
+        ));
+    }
+}
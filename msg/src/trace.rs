@@ -0,0 +1,177 @@
+//! Tracing of frames on the wire, for debugging protocol changes.
+//!
+//! A [`Tracer`] formats every frame sent or received as a line,
+//! with the time since it started, the direction and the decoded
+//! fields of the frame. Frames with unknown ids, such as reliability
+//! envelopes, are dumped as hex.
+
+use std::{fmt::Write as _, time::Instant};
+
+use crate::{client_msg, server_msg, spec::CLIENT_MSGS, C2SData, S2CData, C2S_SIZE, S2C_SIZE};
+
+/// Names of messages, as in [`spec::fixtures`](crate::spec::fixtures).
+///
+/// Ids of client and server messages do not overlap.
+const NAMES: [(u8, &str); 41] = [
+    (client_msg::CONNECT, "connect"),
+    (client_msg::RELAY_HOST, "relay_host"),
+    (client_msg::CANDIDATE, "candidate"),
+    (client_msg::BUILD, "build"),
+    (client_msg::FLAG_ON, "flag_on"),
+    (client_msg::FLAG_OFF, "flag_off"),
+    (client_msg::FLAG_OFF_ALL, "flag_off_all"),
+    (client_msg::FLAG_OFF_HALF, "flag_off_half"),
+    (client_msg::RALLY, "rally"),
+    (client_msg::FLAG_CITIES, "flag_cities"),
+    (client_msg::FLAG_FRONTLINE, "flag_frontline"),
+    (client_msg::BUILD_ROAD, "build_road"),
+    (client_msg::QUEUE_BUILD, "queue_build"),
+    (client_msg::IS_ALIVE, "is_alive"),
+    (client_msg::STATE_ACK, "state_ack"),
+    (client_msg::PONG, "pong"),
+    (client_msg::FULL_STATE_REQUEST, "full_state_request"),
+    (client_msg::PAUSE, "pause"),
+    (client_msg::UNPAUSE, "unpause"),
+    (client_msg::SURRENDER, "surrender"),
+    (client_msg::VOTE_RESTART, "vote_restart"),
+    (client_msg::END_TURN, "end_turn"),
+    (client_msg::SWITCH_PLAYER, "switch_player"),
+    (server_msg::STATE, "state"),
+    (server_msg::STATE_RLE, "state_rle"),
+    (server_msg::STATE_FRAGMENT, "state_fragment"),
+    (server_msg::AFK, "afk"),
+    (server_msg::SURRENDER, "surrender"),
+    (server_msg::VOTE_RESTART, "vote_restart"),
+    (server_msg::GAME_OVER, "game_over"),
+    (server_msg::END_TURN, "end_turn"),
+    (server_msg::ELIMINATED, "eliminated"),
+    (server_msg::PLUNDER, "plunder"),
+    (server_msg::RELIABLE, "reliable"),
+    (server_msg::PING, "ping"),
+    (server_msg::EVENT, "event"),
+    (server_msg::CONN_ACCEPTED, "conn_accepted"),
+    (server_msg::CONN_REJECTED, "conn_rejected"),
+    (server_msg::HOST_LOST, "host_lost"),
+    (server_msg::ELECTED, "elected"),
+    (server_msg::RULES, "rules"),
+];
+
+/// Direction of a traced frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flow {
+    Sent,
+    Received,
+}
+
+/// Formats frames exchanged with peers as lines.
+#[derive(Debug, Clone, Copy)]
+pub struct Tracer {
+    started: Instant,
+}
+
+impl Default for Tracer {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Tracer {
+    /// Creates a tracer, timing frames from now on.
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            started: Instant::now(),
+        }
+    }
+
+    /// Formats a frame sent to or received from the given peer.
+    pub fn line(&self, peer: &str, flow: Flow, frame: &[u8]) -> String {
+        let arrow = match flow {
+            Flow::Sent => "->",
+            Flow::Received => "<-",
+        };
+        format!(
+            "[{:>10.3}] {arrow} {peer} {}",
+            self.started.elapsed().as_secs_f64(),
+            describe(frame)
+        )
+    }
+}
+
+/// Describes a frame by its message and fields.
+///
+/// Short frames are followed by their hex dump.
+pub fn describe(frame: &[u8]) -> String {
+    let Some((&msg, body)) = frame.split_first() else {
+        return "empty frame".to_owned();
+    };
+    let mut out = match NAMES.iter().find(|(id, _)| *id == msg) {
+        Some((_, name)) => (*name).to_owned(),
+        None => format!("unknown 0x{msg:02x}"),
+    };
+
+    let state = match msg {
+        server_msg::STATE if frame.len() == S2C_SIZE => Some(*bytemuck::from_bytes(body)),
+        server_msg::STATE_RLE => S2CData::decode_rle(body),
+        _ => None,
+    };
+    if let Some(data) = state {
+        let _ = write!(
+            out,
+            " player={} time={} size={}x{} seq={}",
+            data.player().0,
+            data.time(),
+            data.width,
+            data.height,
+            data.seq()
+        );
+    } else if let (server_msg::STATE_FRAGMENT, &[s0, s1, index, count, ..]) = (msg, body) {
+        let _ = write!(
+            out,
+            " seq={} fragment={}/{}",
+            u16::from_be_bytes([s0, s1]),
+            index as usize + 1,
+            count
+        );
+    } else if frame.len() >= C2S_SIZE && CLIENT_MSGS.contains(&msg) {
+        let data: C2SData = *bytemuck::from_bytes(&body[..C2S_SIZE - 1]);
+        let _ = write!(out, " x={} y={} info={}", data.x, data.y, data.msg);
+    }
+
+    let _ = write!(out, " ({} bytes)", frame.len());
+    // Passwords are kept out of traces.
+    let dumped = match msg {
+        client_msg::CONNECT => &frame[..frame.len().min(C2S_SIZE)],
+        _ => frame,
+    };
+    if frame.len() <= 16 {
+        out.push_str(" |");
+        for byte in dumped {
+            let _ = write!(out, " {byte:02x}");
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spec::{fixtures, Fixture};
+
+    #[test]
+    fn fixtures_are_named() {
+        for Fixture { name, frame } in fixtures() {
+            assert!(describe(&frame).starts_with(name), "fixture {name}");
+        }
+        assert_eq!(
+            describe(&[21, 5, 6, 2]),
+            "flag_on x=5 y=6 info=2 (4 bytes) | 15 05 06 02"
+        );
+        assert_eq!(describe(&[0xF1, 0, 3]), "unknown 0xf1 (3 bytes) | f1 00 03");
+        assert_eq!(
+            describe(&[1, 0, 0, 1, b'p', b'w']),
+            "connect x=0 y=0 info=1 (6 bytes) | 01 00 00 01"
+        );
+    }
+}
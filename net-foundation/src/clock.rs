@@ -0,0 +1,157 @@
+//! Sources of time for game loops.
+//!
+//! Loops read the time and wait for their next tick through a
+//! [`Clock`] instead of doing so themselves, so that tests can run
+//! them on a [`MockClock`], whose time only moves when told to or
+//! when a loop waits on one of its timers, without sleeping.
+
+use std::{
+    cell::Cell,
+    future::Future,
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+/// A source of time.
+pub trait Clock {
+    /// Gets the current time.
+    fn now(&self) -> Instant;
+
+    /// Waits until `duration` has passed since the call.
+    fn timer(&self, duration: Duration) -> impl Future<Output = ()> + 'static;
+
+    /// Gets the time passed since `earlier`.
+    #[inline]
+    fn elapsed(&self, earlier: Instant) -> Duration {
+        self.now().saturating_duration_since(earlier)
+    }
+
+    /// Starts an [`Interval`] of the given period from now.
+    #[inline]
+    fn interval(&self, period: Duration) -> Interval {
+        Interval::new(self.now(), period)
+    }
+}
+
+/// The clock of the system, with timers of `async-io`.
+#[cfg(feature = "system-clock")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+#[cfg(feature = "system-clock")]
+impl Clock for SystemClock {
+    #[inline]
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn timer(&self, duration: Duration) -> impl Future<Output = ()> + 'static {
+        let timer = async_io::Timer::after(duration);
+        async {
+            timer.await;
+        }
+    }
+}
+
+/// A clock for tests, whose time only moves when
+/// [advanced](Self::advance) or waited on.
+///
+/// Waiting on a timer moves the time to its deadline at once, so a
+/// loop on this clock runs as fast as it can while seeing the time
+/// pass as it would. Clones share their time.
+#[derive(Debug, Clone)]
+pub struct MockClock(Rc<Cell<Instant>>);
+
+impl MockClock {
+    /// Creates a clock starting at the current time.
+    pub fn new() -> Self {
+        Self(Rc::new(Cell::new(Instant::now())))
+    }
+
+    /// Moves the time forward by `duration`.
+    #[inline]
+    pub fn advance(&self, duration: Duration) {
+        self.0.set(self.0.get() + duration);
+    }
+}
+
+impl Default for MockClock {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for MockClock {
+    #[inline]
+    fn now(&self) -> Instant {
+        self.0.get()
+    }
+
+    fn timer(&self, duration: Duration) -> impl Future<Output = ()> + 'static {
+        let (time, deadline) = (self.0.clone(), self.now() + duration);
+        async move {
+            if time.get() < deadline {
+                time.set(deadline);
+            }
+        }
+    }
+}
+
+/// A period repeating from the last time it was over, such as of
+/// keepalives.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Interval {
+    start: Instant,
+    period: Duration,
+}
+
+impl Interval {
+    /// Creates an interval whose first period starts at `start`.
+    #[inline]
+    pub fn new(start: Instant, period: Duration) -> Self {
+        Self { start, period }
+    }
+
+    /// Whether the period is over at `now`, in which case the next
+    /// one starts from `now`.
+    pub fn tick(&mut self, now: Instant) -> bool {
+        let over = now.saturating_duration_since(self.start) >= self.period;
+        if over {
+            self.start = now;
+        }
+        over
+    }
+
+    /// Starts the period over from `now`.
+    #[inline]
+    pub fn reset(&mut self, now: Instant) {
+        self.start = now;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn intervals_tick_on_mock_time() {
+        let clock = MockClock::new();
+        let mut interval = clock.interval(Duration::from_secs(2));
+        assert!(!interval.tick(clock.now()));
+        clock.advance(Duration::from_secs(1));
+        assert!(!interval.tick(clock.now()));
+        clock.advance(Duration::from_secs(1));
+        assert!(interval.tick(clock.now()));
+        // The next period starts from the tick.
+        assert!(!interval.tick(clock.now()));
+
+        let start = clock.now();
+        futures_lite::future::block_on(async {
+            for _ in 0..3 {
+                clock.timer(Duration::from_millis(100)).await;
+            }
+        });
+        assert_eq!(clock.elapsed(start), Duration::from_millis(300));
+    }
+}
@@ -0,0 +1,636 @@
+//! HTTP long-polling transport, for networks where only HTTP passes.
+//!
+//! Clients pick a random session id, and send their frames with
+//! `POST /<session>`. They fetch frames of the server with
+//! `GET /<session>?<next>`, which is held until a frame is queued or
+//! [`POLL_TIMEOUT`] passes. Both bodies are frames prefixed with their
+//! length as a big-endian `u16`, so one response carries every frame
+//! queued since the last poll. The first request of a session is
+//! accepted as a new connection, with the address it came from.
+//!
+//! `next` counts the frames the client has received, in hexadecimal.
+//! The server keeps frames until a poll counts them, so that frames of
+//! a poll whose response is lost, or whose read is cancelled, are sent
+//! again with the next one. A newer poll of a session answers the ones
+//! held before it. Sessions not heard from for [`SESSION_TIMEOUT`]
+//! expire, failing the reads of the server.
+//!
+//! Every request is sent on a connection of its own, and served on
+//! a thread of its own, up to [`MAX_REQUESTS`] at once. Clients send
+//! requests through the proxy of the `http_proxy` variable of the
+//! environment, if any, so the game stays playable behind corporate
+//! proxies.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    io::{Read as _, Write as _},
+    net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs as _},
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc, Condvar, Mutex, MutexGuard, OnceLock,
+    },
+    task::{Poll, Waker},
+    time::{Duration, Instant},
+};
+
+use async_io::Async;
+use futures_lite::{AsyncReadExt as _, AsyncWriteExt as _};
+
+/// Longest time a poll is held without frames to answer with.
+///
+/// Shorter than the idle timeouts of common proxies.
+pub const POLL_TIMEOUT: Duration = Duration::from_secs(20);
+/// Longest time a session lasts without requests.
+pub const SESSION_TIMEOUT: Duration = Duration::from_secs(60);
+/// Requests served at once, beyond which new ones are refused.
+pub const MAX_REQUESTS: usize = 256;
+/// Time to wait for a request or response besides polls.
+const TIMEOUT: Duration = Duration::from_secs(10);
+/// Longest request head, and longest body, accepted.
+const MAX_HEAD: usize = 8 * 1024;
+const MAX_BODY: usize = 64 * 1024;
+
+#[inline]
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// A queue whose consumer awaits items pushed from other threads.
+#[derive(Debug)]
+struct Queue<T>(Mutex<(VecDeque<T>, Option<Waker>)>);
+
+impl<T> Queue<T> {
+    fn new() -> Self {
+        Self(Mutex::new((VecDeque::new(), None)))
+    }
+
+    fn push(&self, item: T) {
+        let mut queue = lock(&self.0);
+        queue.0.push_back(item);
+        if let Some(waker) = queue.1.take() {
+            waker.wake();
+        }
+    }
+
+    async fn pop(&self) -> T {
+        std::future::poll_fn(|cx| {
+            let mut queue = lock(&self.0);
+            match queue.0.pop_front() {
+                Some(item) => Poll::Ready(item),
+                None => {
+                    queue.1 = Some(cx.waker().clone());
+                    Poll::Pending
+                }
+            }
+        })
+        .await
+    }
+
+    fn is_empty(&self) -> bool {
+        lock(&self.0).0.is_empty()
+    }
+}
+
+/// A client as seen by the server.
+#[derive(Debug)]
+pub(crate) struct Session {
+    id: u64,
+    /// Frames of the client, yet to be received, and `None`
+    /// once the session expired.
+    inbound: Queue<Option<Vec<u8>>>,
+    /// Frames to the client not counted by a poll yet, and the
+    /// number of frames counted before them.
+    outbound: Mutex<(VecDeque<Vec<u8>>, u64)>,
+    polled: Condvar,
+    /// Number of polls of the session, of which only the last is held.
+    polls: AtomicU64,
+    /// Time of the last request of the session.
+    seen: Mutex<Instant>,
+}
+
+#[derive(Debug)]
+pub(crate) struct Shared {
+    sessions: Mutex<HashMap<u64, Arc<Session>>>,
+    accepted: Queue<(Arc<Session>, SocketAddr)>,
+    /// Requests being served.
+    requests: AtomicUsize,
+}
+
+impl Shared {
+    /// Ends the sessions without requests for the given time.
+    fn expire(&self, timeout: Duration) {
+        lock(&self.sessions).retain(|_, session| {
+            let alive = lock(&session.seen).elapsed() < timeout;
+            if !alive {
+                session.inbound.push(None);
+            }
+            alive
+        });
+    }
+}
+
+/// Address of the transport, served once listened on.
+#[derive(Debug)]
+pub(crate) struct Handle {
+    addr: SocketAddr,
+    shared: OnceLock<Arc<Shared>>,
+}
+
+impl Handle {
+    #[inline]
+    pub(crate) fn bind(addr: SocketAddr) -> Self {
+        Self {
+            addr,
+            shared: OnceLock::new(),
+        }
+    }
+
+    /// Serves requests in the background.
+    pub(crate) fn listen(&self) -> Result<Listener, std::io::Error> {
+        if let Some(shared) = self.shared.get() {
+            return Ok(Listener(shared.clone()));
+        }
+        let listener = TcpListener::bind(self.addr)?;
+        let shared = self
+            .shared
+            .get_or_init(|| {
+                Arc::new(Shared {
+                    sessions: Mutex::new(HashMap::new()),
+                    accepted: Queue::new(),
+                    requests: AtomicUsize::new(0),
+                })
+            })
+            .clone();
+        let serving = shared.clone();
+        std::thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                if serving.requests.load(Ordering::Relaxed) >= MAX_REQUESTS {
+                    let _ = respond(&mut stream, "503 Service Unavailable", &[]);
+                    continue;
+                }
+                serving.requests.fetch_add(1, Ordering::Relaxed);
+                let shared = serving.clone();
+                // Polls are held, so every request has a thread.
+                std::thread::spawn(move || {
+                    // A request failing to read or write only fails itself.
+                    let _ = serve(&shared, stream);
+                    shared.requests.fetch_sub(1, Ordering::Relaxed);
+                });
+            }
+        });
+        let expiring = shared.clone();
+        std::thread::spawn(move || loop {
+            std::thread::sleep(SESSION_TIMEOUT / 4);
+            expiring.expire(SESSION_TIMEOUT);
+        });
+        Ok(Listener(shared))
+    }
+
+    /// Starts a session with the server at the address.
+    ///
+    /// Nothing is sent until the first frame.
+    #[inline]
+    pub(crate) fn connect(&self, server: SocketAddr) -> Connection {
+        Connection::Client(Client {
+            server,
+            session: fastrand::u64(..),
+            received: VecDeque::new(),
+            next: 0,
+        })
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct Listener(Arc<Shared>);
+
+impl Listener {
+    pub(crate) async fn accept(&self) -> (Connection, SocketAddr) {
+        let (session, peer) = self.0.accepted.pop().await;
+        (
+            Connection::Server {
+                session,
+                shared: self.0.clone(),
+            },
+            peer,
+        )
+    }
+}
+
+#[derive(Debug)]
+pub(crate) enum Connection {
+    /// A client, from the side of the server.
+    Server {
+        session: Arc<Session>,
+        shared: Arc<Shared>,
+    },
+    /// The server, from the side of a client.
+    Client(Client),
+}
+
+#[derive(Debug)]
+pub(crate) struct Client {
+    server: SocketAddr,
+    session: u64,
+    /// Frames of the last poll, yet to be received.
+    received: VecDeque<Vec<u8>>,
+    /// Number of frames polled.
+    next: u64,
+}
+
+impl Connection {
+    pub(crate) async fn write(&mut self, data: &[u8]) -> Result<usize, std::io::Error> {
+        match self {
+            Connection::Server { session, .. } => {
+                lock(&session.outbound).0.push_back(data.to_vec());
+                session.polled.notify_all();
+            }
+            Connection::Client(client) => {
+                let mut body = Vec::with_capacity(data.len() + 2);
+                push_frame(&mut body, data)?;
+                client.request("POST", &body).await?;
+            }
+        }
+        Ok(data.len())
+    }
+
+    pub(crate) async fn read(&mut self, data: &mut [u8]) -> Result<usize, std::io::Error> {
+        let frame = match self {
+            Connection::Server { session, .. } => match session.inbound.pop().await {
+                Some(frame) => frame,
+                None => {
+                    // Later reads fail as well.
+                    session.inbound.push(None);
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::TimedOut,
+                        "(http) session expired",
+                    ));
+                }
+            },
+            Connection::Client(client) => loop {
+                if let Some(frame) = client.received.pop_front() {
+                    break frame;
+                }
+                // Cancelling the poll leaves its frames to the next one.
+                let frames = frames(&client.request("GET", &[]).await?)?;
+                client.next += frames.len() as u64;
+                client.received.extend(frames);
+            },
+        };
+        let len = frame.len().min(data.len());
+        data[..len].copy_from_slice(&frame[..len]);
+        Ok(len)
+    }
+
+    pub(crate) fn poll_readable(&self) -> bool {
+        match self {
+            Connection::Server { session, .. } => !session.inbound.is_empty(),
+            // Polls are only sent when read.
+            Connection::Client(client) => !client.received.is_empty(),
+        }
+    }
+
+    pub(crate) async fn close(self) -> Result<(), std::io::Error> {
+        if let Connection::Server { session, shared } = self {
+            lock(&shared.sessions).remove(&session.id);
+        }
+        Ok(())
+    }
+}
+
+impl Client {
+    /// Sends a request of the session, and gets the body of a
+    /// successful response.
+    async fn request(&self, method: &str, body: &[u8]) -> Result<Vec<u8>, std::io::Error> {
+        let proxy = proxy()?;
+        let path = match method {
+            "GET" => format!("/{:016x}?{:x}", self.session, self.next),
+            _ => format!("/{:016x}", self.session),
+        };
+        let target = match proxy {
+            // Proxies take absolute URIs.
+            Some(_) => format!("http://{}{path}", self.server),
+            None => path,
+        };
+        let mut stream = Async::<TcpStream>::connect(proxy.unwrap_or(self.server)).await?;
+        let head = format!(
+            "{method} {target} HTTP/1.1\r\n\
+            Host: {}\r\n\
+            Content-Type: application/octet-stream\r\n\
+            Content-Length: {}\r\n\
+            Cache-Control: no-store\r\n\
+            Connection: close\r\n\r\n",
+            self.server,
+            body.len()
+        );
+        stream.write_all(head.as_bytes()).await?;
+        stream.write_all(body).await?;
+        stream.flush().await?;
+
+        let mut response = Vec::new();
+        stream.read_to_end(&mut response).await?;
+        let split = find_head_end(&response).ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::InvalidData, "(http) malformed response")
+        })?;
+        let head = String::from_utf8_lossy(&response[..split]);
+        let status = head.split_ascii_whitespace().nth(1).unwrap_or_default();
+        if !status.starts_with('2') {
+            return Err(std::io::Error::other(format!(
+                "(http) server responded with {status}"
+            )));
+        }
+        Ok(response.split_off(split + 4))
+    }
+}
+
+/// Address of the proxy of the `http_proxy` variable, if any.
+fn proxy() -> Result<Option<SocketAddr>, std::io::Error> {
+    let Some(proxy) = ["http_proxy", "HTTP_PROXY"]
+        .into_iter()
+        .find_map(|var| std::env::var(var).ok().filter(|v| !v.is_empty()))
+    else {
+        return Ok(None);
+    };
+    let authority = proxy.strip_prefix("http://").unwrap_or(&proxy);
+    let authority = authority.split('/').next().unwrap_or_default();
+    authority
+        .to_socket_addrs()?
+        .next()
+        .map(Some)
+        .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::NotFound, "(http) proxy not found"))
+}
+
+/// Answers a request of a client.
+fn serve(shared: &Shared, mut stream: TcpStream) -> Result<(), std::io::Error> {
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    let peer = stream.peer_addr()?;
+    let mut buf = vec![0u8; MAX_HEAD];
+    let mut len = 0;
+    let split = loop {
+        if let Some(split) = find_head_end(&buf[..len]) {
+            break split;
+        }
+        if len == buf.len() {
+            return respond(&mut stream, "431 Request Header Fields Too Large", &[]);
+        }
+        match stream.read(&mut buf[len..])? {
+            0 => return Ok(()),
+            n => len += n,
+        }
+    };
+
+    let head = String::from_utf8_lossy(&buf[..split]).into_owned();
+    let mut lines = head.lines();
+    let mut words = lines.next().unwrap_or_default().split_ascii_whitespace();
+    let (method, target) = (words.next(), words.next().unwrap_or_default());
+    // Proxies may forward absolute URIs.
+    let path = match target.find("://") {
+        Some(i) => target[i + 3..]
+            .find('/')
+            .map_or("/", |j| &target[i + 3 + j..]),
+        None => target,
+    };
+    let (path, next) = path.split_once('?').unwrap_or((path, "0"));
+    let (Some(id), Ok(next)) = (
+        path.strip_prefix('/')
+            .and_then(|id| u64::from_str_radix(id, 16).ok()),
+        u64::from_str_radix(next, 16),
+    ) else {
+        return respond(&mut stream, "404 Not Found", &[]);
+    };
+    let content_length = lines
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("content-length"))
+        .and_then(|(_, value)| value.trim().parse::<usize>().ok())
+        .unwrap_or(0);
+    if content_length > MAX_BODY {
+        return respond(&mut stream, "413 Content Too Large", &[]);
+    }
+    let mut body = buf[split + 4..len].to_vec();
+    body.truncate(content_length);
+    if body.len() < content_length {
+        let start = body.len();
+        body.resize(content_length, 0);
+        stream.read_exact(&mut body[start..])?;
+    }
+
+    let method = match method {
+        Some(method @ ("GET" | "POST")) => method,
+        _ => return respond(&mut stream, "405 Method Not Allowed", &[]),
+    };
+    let frames = match frames(&body) {
+        Ok(frames) => frames,
+        Err(_) => return respond(&mut stream, "400 Bad Request", &[]),
+    };
+    let session = {
+        let mut sessions = lock(&shared.sessions);
+        match sessions.get(&id) {
+            Some(session) => session.clone(),
+            None => {
+                let session = Arc::new(Session {
+                    id,
+                    inbound: Queue::new(),
+                    outbound: Mutex::new((VecDeque::new(), 0)),
+                    polled: Condvar::new(),
+                    polls: AtomicU64::new(0),
+                    seen: Mutex::new(Instant::now()),
+                });
+                sessions.insert(id, session.clone());
+                // Frames of the first request come with the connection.
+                for frame in frames.iter().cloned() {
+                    session.inbound.push(Some(frame));
+                }
+                shared.accepted.push((session, peer));
+                return match method {
+                    "GET" => respond(&mut stream, "200 OK", &[]),
+                    _ => respond(&mut stream, "204 No Content", &[]),
+                };
+            }
+        }
+    };
+
+    *lock(&session.seen) = Instant::now();
+
+    if method == "POST" {
+        for frame in frames {
+            session.inbound.push(Some(frame));
+        }
+        return respond(&mut stream, "204 No Content", &[]);
+    }
+    let mut outbound = lock(&session.outbound);
+    // Frames the client counted are received.
+    let (queued, counted) = &mut *outbound;
+    let received = next.saturating_sub(*counted).min(queued.len() as u64);
+    queued.drain(..received as usize);
+    *counted += received;
+
+    let poll = session.polls.fetch_add(1, Ordering::Relaxed) + 1;
+    session.polled.notify_all();
+    let (outbound, _) = session
+        .polled
+        .wait_timeout_while(outbound, POLL_TIMEOUT, |(frames, _)| {
+            frames.is_empty() && session.polls.load(Ordering::Relaxed) == poll
+        })
+        .unwrap_or_else(|e| e.into_inner());
+    let mut body = Vec::new();
+    for frame in &outbound.0 {
+        push_frame(&mut body, frame)?;
+    }
+    drop(outbound);
+    respond(&mut stream, "200 OK", &body)
+}
+
+fn respond(stream: &mut TcpStream, status: &str, body: &[u8]) -> Result<(), std::io::Error> {
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\n\
+        Content-Type: application/octet-stream\r\n\
+        Content-Length: {}\r\n\
+        Cache-Control: no-store\r\n\
+        Connection: close\r\n\r\n",
+        body.len()
+    )?;
+    stream.write_all(body)
+}
+
+#[inline]
+fn find_head_end(buf: &[u8]) -> Option<usize> {
+    buf.windows(4).position(|w| w == b"\r\n\r\n")
+}
+
+/// Appends the frame to the body, prefixed with its length.
+fn push_frame(body: &mut Vec<u8>, frame: &[u8]) -> Result<(), std::io::Error> {
+    let len = u16::try_from(frame.len()).map_err(|_| {
+        std::io::Error::new(std::io::ErrorKind::InvalidInput, "(http) frame too long")
+    })?;
+    body.extend_from_slice(&len.to_be_bytes());
+    body.extend_from_slice(frame);
+    Ok(())
+}
+
+/// Splits a body into its frames.
+fn frames(mut body: &[u8]) -> Result<Vec<Vec<u8>>, std::io::Error> {
+    let mut frames = Vec::new();
+    while let [hi, lo, rest @ ..] = body {
+        let len = u16::from_be_bytes([*hi, *lo]) as usize;
+        if rest.len() < len {
+            break;
+        }
+        frames.push(rest[..len].to_vec());
+        body = &rest[len..];
+    }
+    if body.is_empty() {
+        Ok(frames)
+    } else {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "(http) truncated frame",
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frames_round_trip() {
+        let mut body = Vec::new();
+        push_frame(&mut body, &[30, 0, 0, 1]).unwrap();
+        push_frame(&mut body, &[]).unwrap();
+        push_frame(&mut body, &[61]).unwrap();
+        assert_eq!(body[..6], [0, 4, 30, 0, 0, 1]);
+        assert_eq!(
+            frames(&body).unwrap(),
+            [vec![30, 0, 0, 1], vec![], vec![61]]
+        );
+        assert!(frames(&body[..body.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn exchanges_frames() {
+        // Listen on a free port.
+        let probe = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = probe.local_addr().unwrap();
+        drop(probe);
+        let handle = Handle::bind(addr);
+        let listener = handle.listen().unwrap();
+
+        futures_lite::future::block_on(async {
+            let mut client = handle.connect(addr);
+            client.write(&[30, 0, 0, 1]).await.unwrap();
+            let (mut server, _) = listener.accept().await;
+            let mut buf = [0u8; 8];
+            assert_eq!(server.read(&mut buf).await.unwrap(), 4);
+            assert_eq!(buf[..4], [30, 0, 0, 1]);
+
+            server.write(&[61]).await.unwrap();
+            server.write(&[12, 2, 1]).await.unwrap();
+            assert_eq!(client.read(&mut buf).await.unwrap(), 1);
+            assert_eq!(client.read(&mut buf).await.unwrap(), 3);
+            assert_eq!(buf[..3], [12, 2, 1]);
+        });
+    }
+
+    /// Listens on a free port, and connects to it.
+    fn pair() -> (Listener, Connection, Connection) {
+        let probe = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = probe.local_addr().unwrap();
+        drop(probe);
+        let handle = Handle::bind(addr);
+        let listener = handle.listen().unwrap();
+        let mut client = handle.connect(addr);
+        let server = futures_lite::future::block_on(async {
+            client.write(&[30, 0, 0, 1]).await.unwrap();
+            listener.accept().await.0
+        });
+        (listener, client, server)
+    }
+
+    #[test]
+    fn resends_frames_of_lost_polls() {
+        let (_listener, mut client, mut server) = pair();
+        futures_lite::future::block_on(async {
+            server.write(&[61]).await.unwrap();
+            let Connection::Client(polling) = &client else {
+                unreachable!()
+            };
+            // A poll whose response never makes it to the client.
+            assert_eq!(
+                frames(&polling.request("GET", &[]).await.unwrap()).unwrap(),
+                [vec![61]]
+            );
+
+            server.write(&[12, 2, 1]).await.unwrap();
+            let mut buf = [0u8; 8];
+            assert_eq!(client.read(&mut buf).await.unwrap(), 1);
+            assert_eq!(buf[0], 61);
+            assert_eq!(client.read(&mut buf).await.unwrap(), 3);
+            let Connection::Server { session, .. } = &server else {
+                unreachable!()
+            };
+            let session = session.clone();
+            // The frames are dropped once counted by the next poll.
+            server.write(&[16]).await.unwrap();
+            assert_eq!(client.read(&mut buf).await.unwrap(), 1);
+            assert_eq!(lock(&session.outbound).1, 2);
+        });
+    }
+
+    #[test]
+    fn expires_idle_sessions() {
+        let (listener, _client, mut server) = pair();
+        let mut buf = [0u8; 8];
+        futures_lite::future::block_on(async {
+            assert_eq!(server.read(&mut buf).await.unwrap(), 4);
+            listener.0.expire(SESSION_TIMEOUT);
+            assert!(!server.poll_readable());
+            listener.0.expire(Duration::ZERO);
+            assert!(lock(&listener.0.sessions).is_empty());
+            for _ in 0..2 {
+                let err = server.read(&mut buf).await.unwrap_err();
+                assert_eq!(err.kind(), std::io::ErrorKind::TimedOut);
+            }
+        });
+    }
+}
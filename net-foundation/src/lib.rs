@@ -2,10 +2,24 @@
 
 #![warn(missing_docs)]
 
-use std::net::{SocketAddr, ToSocketAddrs};
+use std::{
+    future::{poll_fn, Future},
+    net::{SocketAddr, ToSocketAddrs},
+    pin::pin,
+    task::Poll,
+};
 
 use unisock::*;
 
+pub mod clock;
+#[cfg(feature = "http")]
+pub mod http;
+pub mod reliable;
+#[cfg(feature = "sim")]
+pub mod sim;
+#[cfg(feature = "ws")]
+mod tls;
+pub mod url;
 mod util;
 
 #[allow(unused_imports)]
@@ -25,6 +39,18 @@ pub enum Protocol {
     /// WebSocket.
     #[cfg(feature = "ws")]
     WebSocket,
+    /// HTTP long-polling, see the [`http`] module.
+    #[cfg(feature = "http")]
+    Http,
+}
+
+/// Outcome of [`Connection::recv_or`].
+#[derive(Debug)]
+pub enum RecvOr<T> {
+    /// Data was received, or receiving failed.
+    Recv(Result<usize, std::io::Error>),
+    /// The other future completed first.
+    Other(T),
 }
 
 /// The main handler.
@@ -37,6 +63,8 @@ enum HandleInner {
     Udp(unisock_smol::UdpSingle),
     #[cfg(feature = "ws")]
     WebSocket(unisock_smol_tungstenite::WebSocket),
+    #[cfg(feature = "http")]
+    Http(http::Handle),
 }
 
 macro_rules! call {
@@ -46,6 +74,10 @@ macro_rules! call {
             $thist::Udp(ref mut back) => back.$fun($($i),*).await,
             #[cfg(feature = "ws")]
             $thist::WebSocket(ref mut back) => back.$fun($($i),*).await.map_err(err_ws2io),
+            #[cfg(feature = "ws")]
+            $thist::Url(ref mut back) => tls::$fun(back, $($i),*).await,
+            #[cfg(feature = "http")]
+            $thist::Http(ref mut back) => back.$fun($($i),*).await,
         }
     };
 }
@@ -72,6 +104,9 @@ impl Handle {
                     Ok(back) => return Ok(Self(HandleInner::WebSocket(back))),
                     Err(e) => err = Some(err_ws2io(e)),
                 },
+                // Bound once listened on, as clients do not bind.
+                #[cfg(feature = "http")]
+                Protocol::Http => return Ok(Self(HandleInner::Http(http::Handle::bind(addr)))),
             }
         }
 
@@ -90,6 +125,8 @@ impl Handle {
                 .listen()
                 .map(|l| Listener(ListenerInner::WebSocket(l)))
                 .map_err(err_ws2io),
+            #[cfg(feature = "http")]
+            HandleInner::Http(back) => back.listen().map(|l| Listener(ListenerInner::Http(l))),
         }
     }
 
@@ -114,6 +151,10 @@ impl Handle {
                     Ok(conn) => return Ok(Connection(ConnectionInner::WebSocket(conn))),
                     Err(e) => err = Some(err_ws2io(e)),
                 },
+                #[cfg(feature = "http")]
+                HandleInner::Http(back) => {
+                    return Ok(Connection(ConnectionInner::Http(back.connect(addr))))
+                }
             }
         }
 
@@ -133,6 +174,8 @@ enum ListenerInner<'a> {
     Udp(&'a unisock_smol::UdpSingle),
     #[cfg(feature = "ws")]
     WebSocket(unisock_smol_tungstenite::Listener),
+    #[cfg(feature = "http")]
+    Http(http::Listener),
 }
 
 impl Listener<'_> {
@@ -153,6 +196,11 @@ impl Listener<'_> {
                 .await
                 .map(|(c, a)| (Connection(ConnectionInner::WebSocket(c)), a))
                 .map_err(err_ws2io),
+            #[cfg(feature = "http")]
+            ListenerInner::Http(back) => {
+                let (c, a) = back.accept().await;
+                Ok((Connection(ConnectionInner::Http(c)), a))
+            }
         }
     }
 }
@@ -167,9 +215,39 @@ enum ConnectionInner<'a> {
     Udp(unisock_smol::udp_single_sock::Connection<'a>),
     #[cfg(feature = "ws")]
     WebSocket(unisock_smol_tungstenite::Connection),
+    /// WebSocket connected to by URL, possibly over TLS.
+    #[cfg(feature = "ws")]
+    Url(tls::Stream),
+    #[cfg(feature = "http")]
+    Http(http::Connection),
+}
+
+#[cfg(feature = "ws")]
+impl Connection<'static> {
+    /// Connect to the WebSocket URL, over TLS for `wss`.
+    ///
+    /// Unlike [`Handle::connect`], the local address is chosen by
+    /// the operating system.
+    pub async fn connect_url(url: &url::WsUrl) -> Result<Self, std::io::Error> {
+        tls::connect(url)
+            .await
+            .map(|stream| Connection(ConnectionInner::Url(stream)))
+    }
 }
 
 impl Connection<'_> {
+    /// Returns the protocol of the connection.
+    pub fn protocol(&self) -> Protocol {
+        match &self.0 {
+            ConnectionInner::Tcp(_) => Protocol::Tcp,
+            ConnectionInner::Udp(_) => Protocol::Udp,
+            #[cfg(feature = "ws")]
+            ConnectionInner::WebSocket(_) | ConnectionInner::Url(_) => Protocol::WebSocket,
+            #[cfg(feature = "http")]
+            ConnectionInner::Http(_) => Protocol::Http,
+        }
+    }
+
     /// Send data.
     pub async fn send(&mut self, data: &[u8]) -> Result<usize, std::io::Error> {
         call!(self.0, ConnectionInner => write(data).await)
@@ -180,6 +258,43 @@ impl Connection<'_> {
         call!(self.0, ConnectionInner => read(data).await)
     }
 
+    /// Receive data, unless `other` completes first.
+    ///
+    /// Unlike racing [`recv`](Self::recv) against `other`, no data
+    /// is lost when `other` wins, so a task owning the connection can
+    /// wait for incoming data and for data to send at once.
+    pub async fn recv_or<F: Future>(&mut self, data: &mut [u8], other: F) -> RecvOr<F::Output> {
+        let mut other = pin!(other);
+        // Readiness of these is not polled, but reading them is
+        // cancel-safe: WebSocket messages read in part stay buffered,
+        // and frames of HTTP polls are sent again by the next one.
+        let race = match self.0 {
+            #[cfg(feature = "ws")]
+            ConnectionInner::Url(_) => true,
+            #[cfg(feature = "http")]
+            ConnectionInner::Http(_) => true,
+            _ => false,
+        };
+        if race {
+            let mut read = pin!(self.recv(data));
+            return poll_fn(|cx| match other.as_mut().poll(cx) {
+                Poll::Ready(output) => Poll::Ready(RecvOr::Other(output)),
+                Poll::Pending => read.as_mut().poll(cx).map(RecvOr::Recv),
+            })
+            .await;
+        }
+        let readable = poll_fn(|cx| match other.as_mut().poll(cx) {
+            Poll::Ready(output) => Poll::Ready(Some(output)),
+            Poll::Pending if self.poll_readable(cx) => Poll::Ready(None),
+            Poll::Pending => Poll::Pending,
+        })
+        .await;
+        match readable {
+            Some(output) => RecvOr::Other(output),
+            None => RecvOr::Recv(self.recv(data).await),
+        }
+    }
+
     /// Poll the connection for readability.
     pub fn poll_readable(&self, cx: &mut std::task::Context<'_>) -> bool {
         match &self.0 {
@@ -187,6 +302,11 @@ impl Connection<'_> {
             ConnectionInner::Udp(back) => back.poll_readable(cx),
             #[cfg(feature = "ws")]
             ConnectionInner::WebSocket(back) => back.poll_readable(cx),
+            // Readiness of the underlying stream is hidden by the TLS layer.
+            #[cfg(feature = "ws")]
+            ConnectionInner::Url(_) => true,
+            #[cfg(feature = "http")]
+            ConnectionInner::Http(back) => back.poll_readable(),
         }
     }
 
@@ -198,6 +318,11 @@ impl Connection<'_> {
             ConnectionInner::Udp(back) => back.poll_writable(cx),
             #[cfg(feature = "ws")]
             ConnectionInner::WebSocket(back) => back.poll_writable(cx),
+            #[cfg(feature = "ws")]
+            ConnectionInner::Url(_) => true,
+            // Frames are queued without blocking.
+            #[cfg(feature = "http")]
+            ConnectionInner::Http(_) => true,
         }
     }
 
@@ -209,6 +334,56 @@ impl Connection<'_> {
             ConnectionInner::Udp(back) => back.close().await,
             #[cfg(feature = "ws")]
             ConnectionInner::WebSocket(back) => back.close().await.map_err(err_ws2io),
+            #[cfg(feature = "ws")]
+            ConnectionInner::Url(back) => tls::close(back).await,
+            #[cfg(feature = "http")]
+            ConnectionInner::Http(back) => back.close().await,
         }
     }
 }
+
+#[cfg(all(test, feature = "http"))]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn recv_or_keeps_frames_when_other_wins() {
+        // Listen on a free port.
+        let probe = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = probe.local_addr().unwrap();
+        drop(probe);
+        let handle = Handle::bind(addr, Protocol::Http).unwrap();
+        let listener = handle.listen().unwrap();
+
+        futures_lite::future::block_on(async {
+            let mut client = handle.connect(addr).await.unwrap();
+            client.send(&[30]).await.unwrap();
+            let (mut server, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 4];
+            assert_eq!(server.recv(&mut buf).await.unwrap(), 1);
+
+            let mut received = vec![];
+            for i in 0..10 {
+                // The poll held for the frame is cut short before it comes.
+                loop {
+                    let timer = async_io::Timer::after(Duration::from_millis(20));
+                    match client.recv_or(&mut buf, timer).await {
+                        RecvOr::Recv(len) => {
+                            assert_eq!(len.unwrap(), 1);
+                            received.push(buf[0]);
+                        }
+                        RecvOr::Other(_) => break,
+                    }
+                }
+                server.send(&[i]).await.unwrap();
+            }
+            while received.len() < 10 {
+                assert_eq!(client.recv(&mut buf).await.unwrap(), 1);
+                received.push(buf[0]);
+            }
+            assert_eq!(received, (0..10).collect::<Vec<_>>());
+        });
+    }
+}
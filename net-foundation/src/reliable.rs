@@ -0,0 +1,182 @@
+//! Light reliability layer for datagram protocols.
+//!
+//! Critical packets are wrapped in an envelope carrying a sequence
+//! number, which the peer acknowledges. Unacknowledged packets are
+//! retransmitted until acknowledged or given up on, and duplicates
+//! are delivered only once. Other packets are sent as they are,
+//! at best effort.
+//!
+//! [`Reliability`] holds no socket: its owner sends what it returns,
+//! and feeds it what it receives.
+//!
+//! # Wire format
+//!
+//! | Packet   | Layout                           |
+//! |----------|----------------------------------|
+//! | reliable | `[RELIABLE, seq.., payload..]`   |
+//! | ack      | `[ACK, seq..]`                   |
+//!
+//! `seq` is a big-endian `u16`. [`RELIABLE`] and [`ACK`] are outside
+//! the range of message ids, so wrapped and unwrapped packets can be
+//! told apart.
+
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+/// Leading byte of a reliable packet.
+pub const RELIABLE: u8 = 0xF0;
+/// Leading byte of an acknowledgement.
+pub const ACK: u8 = 0xF1;
+/// Size of the envelope of a reliable packet.
+pub const HEADER_SIZE: usize = 3;
+
+/// Time after which an unacknowledged packet is sent again.
+pub const RETRANSMIT_TIMEOUT: Duration = Duration::from_millis(200);
+/// Number of times a packet is sent before giving up on it.
+const MAX_TRIES: u8 = 10;
+/// Number of recently received sequence numbers remembered
+/// to drop duplicates.
+const RECEIVED_WINDOW: usize = 64;
+
+#[derive(Debug)]
+struct Pending {
+    seq: u16,
+    packet: Vec<u8>,
+    sent_at: Instant,
+    tries: u8,
+}
+
+/// A packet received through a [`Reliability`].
+#[derive(Debug, PartialEq, Eq)]
+pub enum Received<'a> {
+    /// A packet sent at best effort.
+    Unreliable(&'a [u8]),
+    /// A reliable packet, whose acknowledgement should be sent back.
+    ///
+    /// The payload is `None` if the packet was a duplicate.
+    Reliable {
+        /// Payload of the packet.
+        payload: Option<&'a [u8]>,
+        /// Acknowledgement of the packet.
+        ack: [u8; HEADER_SIZE],
+    },
+    /// An acknowledgement of a packet sent.
+    Ack,
+}
+
+/// State of the reliability layer of one peer.
+#[derive(Debug, Default)]
+pub struct Reliability {
+    next_seq: u16,
+    pending: VecDeque<Pending>,
+    received: VecDeque<u16>,
+}
+
+impl Reliability {
+    /// Creates the state of a new peer.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Wraps a payload into a reliable packet, to be sent now.
+    pub fn wrap(&mut self, payload: &[u8]) -> Vec<u8> {
+        let seq = self.next_seq;
+        self.next_seq = seq.wrapping_add(1);
+        let mut packet = Vec::with_capacity(HEADER_SIZE + payload.len());
+        packet.push(RELIABLE);
+        packet.extend_from_slice(&seq.to_be_bytes());
+        packet.extend_from_slice(payload);
+        self.pending.push_back(Pending {
+            seq,
+            packet: packet.clone(),
+            sent_at: Instant::now(),
+            tries: 1,
+        });
+        packet
+    }
+
+    /// Unwraps a received packet.
+    pub fn unwrap<'a>(&mut self, packet: &'a [u8]) -> Received<'a> {
+        match *packet {
+            [RELIABLE, s0, s1, ref payload @ ..] => {
+                let seq = u16::from_be_bytes([s0, s1]);
+                let duplicate = self.received.contains(&seq);
+                if !duplicate {
+                    if self.received.len() >= RECEIVED_WINDOW {
+                        self.received.pop_front();
+                    }
+                    self.received.push_back(seq);
+                }
+                Received::Reliable {
+                    payload: (!duplicate).then_some(payload),
+                    ack: [ACK, s0, s1],
+                }
+            }
+            [ACK, s0, s1, ..] => {
+                let seq = u16::from_be_bytes([s0, s1]);
+                self.pending.retain(|p| p.seq != seq);
+                Received::Ack
+            }
+            _ => Received::Unreliable(packet),
+        }
+    }
+
+    /// Returns packets to send again, as they were not acknowledged
+    /// in time.
+    ///
+    /// Packets sent too many times are given up on.
+    pub fn due(&mut self) -> Vec<Vec<u8>> {
+        let now = Instant::now();
+        self.pending.retain(|p| p.tries < MAX_TRIES);
+        self.pending
+            .iter_mut()
+            .filter(|p| now.duration_since(p.sent_at) >= RETRANSMIT_TIMEOUT)
+            .map(|p| {
+                p.sent_at = now;
+                p.tries += 1;
+                p.packet.clone()
+            })
+            .collect()
+    }
+
+    /// Number of packets waiting for acknowledgement.
+    #[inline]
+    pub fn in_flight(&self) -> usize {
+        self.pending.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delivers_once_until_acked() {
+        let mut sender = Reliability::new();
+        let mut receiver = Reliability::new();
+
+        let packet = sender.wrap(&[21, 3, 4, 0]);
+        let Received::Reliable { payload, ack } = receiver.unwrap(&packet) else {
+            panic!("packet should be reliable");
+        };
+        assert_eq!(payload, Some(&[21, 3, 4, 0][..]));
+        // A retransmission after a lost ack is acked, not delivered.
+        assert_eq!(
+            receiver.unwrap(&packet),
+            Received::Reliable { payload: None, ack }
+        );
+
+        assert_eq!(sender.in_flight(), 1);
+        assert_eq!(sender.unwrap(&ack), Received::Ack);
+        assert_eq!(sender.in_flight(), 0);
+        assert!(sender.due().is_empty());
+
+        assert_eq!(
+            receiver.unwrap(&[10, 0]),
+            Received::Unreliable(&[10, 0][..])
+        );
+    }
+}
@@ -0,0 +1,101 @@
+//! Network impairment for testing multiplayer robustness.
+//!
+//! [`Impaired`] wraps a [`Connection`] and delays, duplicates or drops
+//! the packets sent through it, as configured by an [`Impairment`].
+//! Randomness comes from a seeded generator, so a test replays the
+//! same losses on every run. Wrap both ends to impair both directions.
+
+use std::time::Duration;
+
+use crate::{Connection, Protocol};
+
+/// Conditions of an impaired network.
+///
+/// The default is a perfect network.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Impairment {
+    /// Delay of every packet.
+    pub latency: Duration,
+    /// Largest random delay added to the latency.
+    pub jitter: Duration,
+    /// Probability of a packet being sent twice.
+    pub duplicate: f32,
+    /// Probability of a packet being lost.
+    ///
+    /// Only applies to UDP, as stream protocols never lose packets.
+    pub drop: f32,
+}
+
+/// A connection sending through an impaired network.
+#[derive(Debug)]
+pub struct Impaired<'a> {
+    inner: Connection<'a>,
+    impairment: Impairment,
+    rng: fastrand::Rng,
+}
+
+impl<'a> Impaired<'a> {
+    /// Wraps the connection, with randomness seeded by `seed`.
+    pub fn new(inner: Connection<'a>, impairment: Impairment, seed: u64) -> Self {
+        Self {
+            inner,
+            impairment,
+            rng: fastrand::Rng::with_seed(seed),
+        }
+    }
+
+    /// Returns the impairment of the network.
+    #[inline]
+    pub fn impairment(&self) -> Impairment {
+        self.impairment
+    }
+
+    /// Sets the impairment of the network, such as to cut it off.
+    #[inline]
+    pub fn set_impairment(&mut self, impairment: Impairment) {
+        self.impairment = impairment
+    }
+
+    /// Send data.
+    ///
+    /// The delay holds the sender, so packets stay in order.
+    /// Lost packets are reported as sent.
+    pub async fn send(&mut self, data: &[u8]) -> Result<usize, std::io::Error> {
+        let Impairment {
+            latency,
+            jitter,
+            duplicate,
+            drop,
+        } = self.impairment;
+        let lossy = self.inner.protocol() == Protocol::Udp;
+        // Roll every die on every packet, so that changing one
+        // probability does not shift the others.
+        let lost = self.rng.f32() < drop;
+        let duplicated = self.rng.f32() < duplicate;
+        let delay = latency + jitter.mul_f32(self.rng.f32());
+
+        if !delay.is_zero() {
+            async_io::Timer::after(delay).await;
+        }
+        if lossy && lost {
+            return Ok(data.len());
+        }
+        let len = self.inner.send(data).await?;
+        if lossy && duplicated {
+            self.inner.send(data).await?;
+        }
+        Ok(len)
+    }
+
+    /// Receive data.
+    #[inline]
+    pub async fn recv(&mut self, data: &mut [u8]) -> Result<usize, std::io::Error> {
+        self.inner.recv(data).await
+    }
+
+    /// Returns the wrapped connection.
+    #[inline]
+    pub fn into_inner(self) -> Connection<'a> {
+        self.inner
+    }
+}
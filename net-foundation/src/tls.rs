@@ -0,0 +1,90 @@
+//! Client connections to [`WsUrl`]s, secured with rustls for `wss`.
+//!
+//! The WebSocket backend of `unisock` only connects to `ws` addresses
+//! at the root path, so URLs are connected to with `async-tungstenite`
+//! directly.
+
+use std::net::{TcpStream, ToSocketAddrs as _};
+
+use async_io::Async;
+use async_tungstenite::{
+    async_tls::{client_async_tls, ClientStream},
+    tungstenite::{self, Message},
+    WebSocketStream,
+};
+use futures_util::{SinkExt as _, StreamExt as _};
+
+use crate::url::WsUrl;
+
+pub(crate) type Stream = WebSocketStream<ClientStream<Async<TcpStream>>>;
+
+/// Connects to the URL, performing the TLS handshake for `wss`.
+pub(crate) async fn connect(url: &WsUrl) -> Result<Stream, std::io::Error> {
+    let mut err = None;
+    for addr in url.to_socket_addrs()? {
+        match Async::<TcpStream>::connect(addr).await {
+            Ok(tcp) => {
+                return client_async_tls(url.to_string(), tcp)
+                    .await
+                    .map(|(stream, _)| stream)
+                    .map_err(err_tungstenite2io)
+            }
+            Err(e) => err = Some(e),
+        }
+    }
+
+    Err(err.unwrap_or_else(|| {
+        std::io::Error::new(std::io::ErrorKind::InvalidInput, "no valid address found")
+    }))
+}
+
+/// Sends the data as one binary message.
+pub(crate) async fn write(stream: &mut Stream, data: &[u8]) -> Result<usize, std::io::Error> {
+    stream
+        .send(Message::binary(data.to_vec()))
+        .await
+        .map_err(err_tungstenite2io)?;
+    Ok(data.len())
+}
+
+/// Receives the next binary message, truncated to the buffer.
+pub(crate) async fn read(stream: &mut Stream, buf: &mut [u8]) -> Result<usize, std::io::Error> {
+    loop {
+        match stream.next().await {
+            Some(Ok(Message::Binary(data))) => {
+                let len = data.len().min(buf.len());
+                buf[..len].copy_from_slice(&data[..len]);
+                return Ok(len);
+            }
+            Some(Ok(Message::Close(_))) | None => {
+                return Err(err_tungstenite2io(tungstenite::Error::ConnectionClosed))
+            }
+            // Pings are answered by tungstenite itself.
+            Some(Ok(_)) => {}
+            Some(Err(e)) => return Err(err_tungstenite2io(e)),
+        }
+    }
+}
+
+/// Closes the connection with a close frame.
+pub(crate) async fn close(mut stream: Stream) -> Result<(), std::io::Error> {
+    stream.close(None).await.map_err(err_tungstenite2io)
+}
+
+fn err_tungstenite2io(err: tungstenite::Error) -> std::io::Error {
+    match err {
+        tungstenite::Error::ConnectionClosed => std::io::Error::new(
+            std::io::ErrorKind::ConnectionAborted,
+            "(wss) connection closed",
+        ),
+        tungstenite::Error::AlreadyClosed => {
+            std::io::Error::new(std::io::ErrorKind::BrokenPipe, "(wss) already closed")
+        }
+        tungstenite::Error::Io(io) => io,
+        tungstenite::Error::Url(err) => std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("(wss) url error: {}", err),
+        ),
+        err => std::io::Error::other(format!("(wss) {}", err)),
+    }
+}
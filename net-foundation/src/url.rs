@@ -0,0 +1,192 @@
+//! URLs of WebSocket servers, such as `wss://example.com/curseofrust`.
+//!
+//! Connecting to a URL rather than an address lets a client reach a
+//! server behind a reverse proxy, which terminates TLS and forwards
+//! the given path to the server.
+
+use std::{
+    fmt::Display,
+    net::{SocketAddr, ToSocketAddrs},
+    str::FromStr,
+};
+
+/// URL of a WebSocket server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WsUrl {
+    /// Whether the connection is secured with TLS, for `wss`.
+    pub tls: bool,
+    /// Host name or IP, without the brackets of IPv6.
+    pub host: String,
+    /// Port, `80` for `ws` and `443` for `wss` by default.
+    pub port: u16,
+    /// Path of the HTTP request, `/` by default, with the query if any.
+    pub path: String,
+}
+
+/// Reason a URL is not a valid WebSocket URL.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ParseUrlError {
+    /// The scheme is neither `ws` nor `wss`.
+    Scheme,
+    /// The host is missing.
+    Host,
+    /// The port is not a number.
+    Port,
+    /// The URL has a fragment, which WebSocket URLs must not have.
+    Fragment,
+}
+
+impl Display for ParseUrlError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ParseUrlError::Scheme => "expected a ws:// or wss:// URL",
+            ParseUrlError::Host => "missing host",
+            ParseUrlError::Port => "invalid port",
+            ParseUrlError::Fragment => "unexpected fragment",
+        })
+    }
+}
+
+impl std::error::Error for ParseUrlError {}
+
+impl WsUrl {
+    /// Default port of the scheme.
+    #[inline]
+    fn default_port(tls: bool) -> u16 {
+        if tls {
+            443
+        } else {
+            80
+        }
+    }
+}
+
+impl FromStr for WsUrl {
+    type Err = ParseUrlError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (scheme, rest) = s.split_once("://").ok_or(ParseUrlError::Scheme)?;
+        let tls = if scheme.eq_ignore_ascii_case("wss") {
+            true
+        } else if scheme.eq_ignore_ascii_case("ws") {
+            false
+        } else {
+            return Err(ParseUrlError::Scheme);
+        };
+        if rest.contains('#') {
+            return Err(ParseUrlError::Fragment);
+        }
+
+        let (authority, path) = match rest.find(['/', '?']) {
+            Some(i) if rest[i..].starts_with('/') => (&rest[..i], rest[i..].to_owned()),
+            Some(i) => (&rest[..i], format!("/{}", &rest[i..])),
+            None => (rest, "/".to_owned()),
+        };
+        let (host, port) = match authority.strip_prefix('[') {
+            // IPv6, whose colons are not the port.
+            Some(v6) => {
+                let (host, port) = v6.split_once(']').ok_or(ParseUrlError::Host)?;
+                match port {
+                    "" => (host, None),
+                    port => (
+                        host,
+                        Some(port.strip_prefix(':').ok_or(ParseUrlError::Port)?),
+                    ),
+                }
+            }
+            None => match authority.rsplit_once(':') {
+                Some((host, port)) => (host, Some(port)),
+                None => (authority, None),
+            },
+        };
+        if host.is_empty() {
+            return Err(ParseUrlError::Host);
+        }
+        let port = match port {
+            Some(port) => port.parse().map_err(|_| ParseUrlError::Port)?,
+            None => Self::default_port(tls),
+        };
+
+        Ok(Self {
+            tls,
+            host: host.to_owned(),
+            port,
+            path,
+        })
+    }
+}
+
+impl Display for WsUrl {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(if self.tls { "wss://" } else { "ws://" })?;
+        if self.host.contains(':') {
+            write!(f, "[{}]", self.host)?;
+        } else {
+            f.write_str(&self.host)?;
+        }
+        if self.port != Self::default_port(self.tls) {
+            write!(f, ":{}", self.port)?;
+        }
+        f.write_str(&self.path)
+    }
+}
+
+impl ToSocketAddrs for WsUrl {
+    type Iter = std::vec::IntoIter<SocketAddr>;
+
+    #[inline]
+    fn to_socket_addrs(&self) -> std::io::Result<Self::Iter> {
+        (self.host.as_str(), self.port).to_socket_addrs()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_urls() {
+        let url: WsUrl = "wss://example.com/curseofrust".parse().unwrap();
+        assert_eq!(
+            url,
+            WsUrl {
+                tls: true,
+                host: "example.com".to_owned(),
+                port: 443,
+                path: "/curseofrust".to_owned(),
+            }
+        );
+        assert_eq!(url.to_string(), "wss://example.com/curseofrust");
+
+        let url: WsUrl = "ws://[::1]:19140".parse().unwrap();
+        assert_eq!((url.host.as_str(), url.port), ("::1", 19140));
+        assert_eq!(url.path, "/");
+        assert_eq!(url.to_string(), "ws://[::1]:19140/");
+
+        let url: WsUrl = "WS://10.0.0.1?room=2".parse().unwrap();
+        assert_eq!(url.path, "/?room=2");
+        assert_eq!(url.to_string(), "ws://10.0.0.1/?room=2");
+    }
+
+    #[test]
+    fn rejects_invalid_urls() {
+        assert_eq!(
+            "http://example.com".parse::<WsUrl>(),
+            Err(ParseUrlError::Scheme)
+        );
+        assert_eq!(
+            "example.com:80".parse::<WsUrl>(),
+            Err(ParseUrlError::Scheme)
+        );
+        assert_eq!("wss:///path".parse::<WsUrl>(), Err(ParseUrlError::Host));
+        assert_eq!(
+            "wss://example.com:https/".parse::<WsUrl>(),
+            Err(ParseUrlError::Port)
+        );
+        assert_eq!(
+            "wss://example.com/#top".parse::<WsUrl>(),
+            Err(ParseUrlError::Fragment)
+        );
+    }
+}
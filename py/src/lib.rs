@@ -0,0 +1,245 @@
+//! Python bindings of the engine.
+//!
+//! Built with the `python` feature, this crate is a Python extension
+//! module named `curseofrust`, exposing [`State`], [`Grid`] and [`Tile`]
+//! for balance experiments and learning agents:
+//!
+//! ```python
+//! import curseofrust
+//!
+//! st = curseofrust.State(["-W", "30", "-R", "42"])
+//! st.flag(1, 5, 5)
+//! st.step(100)
+//! print(st.time, st.winner, st.grid.tile(5, 5).units)
+//! ```
+//!
+//! Players other than the controlled one are played by kings,
+//! just as in a singleplayer game.
+
+#![cfg(feature = "python")]
+
+use curseofrust::{grid::HabitLand, state::BasicOpts, Player, Pos, Strategy};
+use curseofrust_msg::client_msg;
+use pyo3::{exceptions::PyValueError, prelude::*};
+
+fn value_error(e: impl std::fmt::Display) -> PyErr {
+    PyValueError::new_err(e.to_string())
+}
+
+/// State of a game.
+#[pyclass(module = "curseofrust")]
+pub struct State(curseofrust::state::State);
+
+#[pymethods]
+impl State {
+    /// Starts a game with the given command line arguments,
+    /// or the default options.
+    #[new]
+    #[pyo3(signature = (args = None))]
+    fn new(args: Option<Vec<String>>) -> PyResult<Self> {
+        let b_opt = match args {
+            Some(args) => {
+                curseofrust_cli_parser::parse_to_options(
+                    std::iter::once("curseofrust".to_owned()).chain(args),
+                )
+                .map_err(value_error)?
+                .basic
+            }
+            None => BasicOpts::default(),
+        };
+        curseofrust::state::State::new(b_opt)
+            .map(Self)
+            .map_err(value_error)
+    }
+
+    /// Advances the game by the given number of steps.
+    #[pyo3(signature = (steps = 1))]
+    fn step(&mut self, steps: u32) {
+        self.0.step_n(steps)
+    }
+
+    #[getter]
+    fn time(&self) -> u64 {
+        self.0.time
+    }
+
+    #[getter]
+    fn seed(&self) -> u64 {
+        self.0.seed
+    }
+
+    /// Player controlled by the user.
+    #[getter]
+    fn controlled(&self) -> u32 {
+        self.0.controlled.0
+    }
+
+    /// Player who won the game, if any.
+    #[getter]
+    fn winner(&self) -> Option<u32> {
+        self.0.winner().map(|p| p.0)
+    }
+
+    #[getter]
+    fn grid(slf: Py<Self>) -> Grid {
+        Grid(slf)
+    }
+
+    fn gold(&self, player: u32) -> u64 {
+        self.0.country(Player(player)).map_or(0, |c| c.gold)
+    }
+
+    fn is_eliminated(&self, player: u32) -> bool {
+        self.0.is_eliminated(Player(player))
+    }
+
+    /// Builds or upgrades a city of the given player.
+    fn build(&mut self, player: u32, x: i32, y: i32) -> PyResult<()> {
+        self.apply(player, client_msg::BUILD, Pos(x, y), 0)
+    }
+
+    /// Builds a road on a tile of the given player.
+    fn build_road(&mut self, player: u32, x: i32, y: i32) -> PyResult<()> {
+        self.apply(player, client_msg::BUILD_ROAD, Pos(x, y), 0)
+    }
+
+    /// Stacks flags of the given player.
+    #[pyo3(signature = (player, x, y, flags = 1))]
+    fn flag(&mut self, player: u32, x: i32, y: i32, flags: u8) -> PyResult<()> {
+        self.apply(player, client_msg::FLAG_ON, Pos(x, y), flags)
+    }
+
+    /// Removes all flags of the given player stacked on a tile.
+    fn unflag(&mut self, player: u32, x: i32, y: i32) -> PyResult<()> {
+        self.apply(player, client_msg::FLAG_OFF, Pos(x, y), 0)
+    }
+
+    /// Hands the given player to a king, who plays it on every step.
+    ///
+    /// `strategy` is one of `aggr_greedy`, `one_greedy`,
+    /// `persistent_greedy`, `opportunist`, `noble`, `midas`,
+    /// `turtle` and `swarm`.
+    #[pyo3(signature = (player, strategy = "opportunist"))]
+    fn hand_to_king(&mut self, player: u32, strategy: &str) -> PyResult<()> {
+        let strategy = match strategy {
+            "aggr_greedy" => Strategy::AggrGreedy,
+            "one_greedy" => Strategy::OneGreedy,
+            "persistent_greedy" => Strategy::PersistentGreedy,
+            "opportunist" => Strategy::Opportunist,
+            "noble" => Strategy::Noble,
+            "midas" => Strategy::Midas,
+            "turtle" => Strategy::Turtle,
+            "swarm" => Strategy::Swarm,
+            _ => return Err(value_error(format!("unknown strategy: {strategy}"))),
+        };
+        self.0.hand_to_king(Player(player), strategy);
+        Ok(())
+    }
+
+    /// Takes the given player back from its king.
+    fn take_from_king(&mut self, player: u32) -> bool {
+        self.0.take_from_king(Player(player))
+    }
+}
+
+impl State {
+    fn apply(&mut self, player: u32, msg: u8, pos: Pos, info: u8) -> PyResult<()> {
+        curseofrust_msg::apply_c2s_msg(&mut self.0, Player(player), msg, (pos, info).into())
+            .map_err(value_error)
+    }
+}
+
+/// Map of a game, reading through its state.
+#[pyclass(module = "curseofrust")]
+pub struct Grid(Py<State>);
+
+#[pymethods]
+impl Grid {
+    #[getter]
+    fn width(&self, py: Python<'_>) -> u32 {
+        self.0.borrow(py).0.grid.width()
+    }
+
+    #[getter]
+    fn height(&self, py: Python<'_>) -> u32 {
+        self.0.borrow(py).0.grid.height()
+    }
+
+    /// Gets a copy of the tile at the given position.
+    fn tile(&self, py: Python<'_>, x: i32, y: i32) -> Option<Tile> {
+        self.0.borrow(py).0.grid.tile(Pos(x, y)).map(Tile::from)
+    }
+
+    /// Gets copies of all tiles, indexed by `x` then `y`.
+    fn tiles(&self, py: Python<'_>) -> Vec<Vec<Tile>> {
+        self.0
+            .borrow(py)
+            .0
+            .grid
+            .raw_tiles()
+            .iter()
+            .map(|col| col.iter().map(Tile::from).collect())
+            .collect()
+    }
+}
+
+/// Copy of a tile.
+#[pyclass(module = "curseofrust", frozen, get_all)]
+#[derive(Clone)]
+pub struct Tile {
+    /// One of `void`, `mountain`, `mine`, `grassland`, `village`,
+    /// `town` and `fortress`.
+    kind: &'static str,
+    owner: u32,
+    /// Population of each player.
+    units: Vec<u16>,
+    /// Rally point of the city, as `(x, y)`.
+    rally: Option<(i32, i32)>,
+    /// Whether a road is built on the tile.
+    road: bool,
+}
+
+impl From<&curseofrust::grid::Tile> for Tile {
+    fn from(tile: &curseofrust::grid::Tile) -> Self {
+        use curseofrust::grid::Tile as T;
+        let kind = match tile {
+            T::Void => "void",
+            T::Mountain => "mountain",
+            T::Mine(_) => "mine",
+            T::Habitable { land, .. } => match land {
+                HabitLand::Grassland => "grassland",
+                HabitLand::Village => "village",
+                HabitLand::Town => "town",
+                HabitLand::Fortress => "fortress",
+                _ => "grassland",
+            },
+            _ => "void",
+        };
+        Self {
+            kind,
+            owner: tile.owner().0,
+            units: tile.units().to_vec(),
+            rally: tile.rally().map(|Pos(x, y)| (x, y)),
+            road: tile.road(),
+        }
+    }
+}
+
+#[pymethods]
+impl Tile {
+    fn __repr__(&self) -> String {
+        format!(
+            "Tile(kind={:?}, owner={}, units={:?})",
+            self.kind, self.owner, self.units
+        )
+    }
+}
+
+#[pymodule]
+#[pyo3(name = "curseofrust")]
+fn curseofrust_py(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<State>()?;
+    m.add_class::<Grid>()?;
+    m.add_class::<Tile>()?;
+    Ok(())
+}
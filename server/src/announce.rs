@@ -0,0 +1,60 @@
+//! Announcement of the game to a master server while in the lobby.
+//!
+//! Announcements are sent from a thread of their own, as the lobby
+//! blocks on clients, and stop once the game starts.
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicU32, Ordering},
+        OnceLock,
+    },
+    thread::Thread,
+};
+
+use curseofrust_msg::master::{self, Listing, ANNOUNCE_INTERVAL};
+
+static PLAYERS: AtomicU32 = AtomicU32::new(0);
+static STOPPED: AtomicBool = AtomicBool::new(false);
+static ANNOUNCER: OnceLock<Thread> = OnceLock::new();
+
+/// Announces the game to the master server at the given address
+/// in the background, until [`stop`]ped.
+pub(crate) fn spawn(master: String, mut listing: Listing) {
+    let handle = std::thread::spawn(move || {
+        let mut failing = false;
+        while !STOPPED.load(Ordering::Relaxed) {
+            listing.players = PLAYERS.load(Ordering::Relaxed);
+            match master::announce(&master, &listing) {
+                Ok(()) if failing => {
+                    failing = false;
+                    println!("[ANNOUNCE] announcing to {} again", master);
+                }
+                Ok(()) => {}
+                // Only report the first of failures in a row.
+                Err(e) if !failing => {
+                    failing = true;
+                    eprintln!("[ANNOUNCE] error announcing to {}: {}", master, e);
+                }
+                Err(_) => {}
+            }
+            std::thread::park_timeout(ANNOUNCE_INTERVAL);
+        }
+    });
+    let _ = ANNOUNCER.set(handle.thread().clone());
+}
+
+/// Updates the number of players connected, announcing it now.
+pub(crate) fn set_players(players: u32) {
+    PLAYERS.store(players, Ordering::Relaxed);
+    if let Some(announcer) = ANNOUNCER.get() {
+        announcer.unpark();
+    }
+}
+
+/// Stops announcing the game.
+///
+/// The master server drops the game once it is not announced
+/// for a while.
+pub(crate) fn stop() {
+    STOPPED.store(true, Ordering::Relaxed);
+}
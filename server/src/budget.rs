@@ -0,0 +1,155 @@
+//! Time spent by the game loop on each tick, against its budget.
+//!
+//! The loop times the moves of kings, the simulation and the
+//! broadcast of states, and warns when a tick takes longer than
+//! the tick itself. Averages over the last [`WINDOW`] ticks are
+//! published for the `/metrics` endpoint of [`health`](crate::health).
+
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, Instant},
+};
+
+/// Number of ticks averaged over.
+pub(crate) const WINDOW: usize = 100;
+/// Shortest interval between warnings, so that a slow machine
+/// does not flood the log.
+const WARN_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Averages, in microseconds, of the moves of kings, the
+/// simulation, the broadcast and the whole tick.
+static AVERAGES: [AtomicU64; 4] = [const { AtomicU64::new(0) }; 4];
+/// Budget of a tick, in microseconds.
+static BUDGET: AtomicU64 = AtomicU64::new(0);
+/// Ticks over the budget since the game started.
+static OVERRUNS: AtomicU64 = AtomicU64::new(0);
+
+/// Time spent on each part of a tick.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct Tick {
+    pub(crate) kings: Duration,
+    pub(crate) simulate: Duration,
+    pub(crate) broadcast: Duration,
+}
+
+impl Tick {
+    #[inline]
+    fn total(&self) -> Duration {
+        self.kings + self.simulate + self.broadcast
+    }
+}
+
+/// Times the given closure, adding the time taken to `spent`.
+#[inline]
+pub(crate) fn time<T>(spent: &mut Duration, f: impl FnOnce() -> T) -> T {
+    let start = Instant::now();
+    let result = f();
+    *spent += start.elapsed();
+    result
+}
+
+/// Ticks of the last [`WINDOW`], and when the last warning was.
+#[derive(Debug)]
+pub(crate) struct Budget {
+    tick: Duration,
+    ticks: Vec<Tick>,
+    next: usize,
+    warned_at: Option<Instant>,
+}
+
+impl Budget {
+    /// Creates the budget of ticks of the given length.
+    pub(crate) fn new(tick: Duration) -> Self {
+        BUDGET.store(tick.as_micros() as u64, Ordering::Relaxed);
+        Self {
+            tick,
+            ticks: Vec::with_capacity(WINDOW),
+            next: 0,
+            warned_at: None,
+        }
+    }
+
+    /// Records a tick, warning if it was over the budget.
+    pub(crate) fn record(&mut self, tick: Tick) {
+        if self.ticks.len() < WINDOW {
+            self.ticks.push(tick);
+        } else {
+            self.ticks[self.next] = tick;
+        }
+        self.next = (self.next + 1) % WINDOW;
+
+        let n = self.ticks.len() as u32;
+        let sum = self.ticks.iter().fold(Tick::default(), |a, b| Tick {
+            kings: a.kings + b.kings,
+            simulate: a.simulate + b.simulate,
+            broadcast: a.broadcast + b.broadcast,
+        });
+        let average = Tick {
+            kings: sum.kings / n,
+            simulate: sum.simulate / n,
+            broadcast: sum.broadcast / n,
+        };
+        for (slot, spent) in AVERAGES.iter().zip([
+            average.kings,
+            average.simulate,
+            average.broadcast,
+            average.total(),
+        ]) {
+            slot.store(spent.as_micros() as u64, Ordering::Relaxed);
+        }
+
+        if tick.total() <= self.tick {
+            return;
+        }
+        OVERRUNS.fetch_add(1, Ordering::Relaxed);
+        if self
+            .warned_at
+            .is_some_and(|at| at.elapsed() < WARN_INTERVAL)
+        {
+            return;
+        }
+        self.warned_at = Some(Instant::now());
+        eprintln!(
+            "[PERF] tick took {:?} of a {:?} budget: kings {:?}, simulate {:?}, broadcast {:?}; \
+            {:?} on average over the last {} ticks",
+            tick.total(),
+            self.tick,
+            tick.kings,
+            tick.simulate,
+            tick.broadcast,
+            average.total(),
+            n,
+        );
+    }
+}
+
+/// Writes the published figures in the text format of Prometheus.
+pub(crate) fn metrics() -> String {
+    let [kings, simulate, broadcast, total] =
+        AVERAGES.each_ref().map(|a| a.load(Ordering::Relaxed));
+    format!(
+        "# HELP curseofrust_tick_seconds Average time spent per tick over the last {WINDOW} ticks.\n\
+        # TYPE curseofrust_tick_seconds gauge\n\
+        curseofrust_tick_seconds{{part=\"kings\"}} {}\n\
+        curseofrust_tick_seconds{{part=\"simulate\"}} {}\n\
+        curseofrust_tick_seconds{{part=\"broadcast\"}} {}\n\
+        curseofrust_tick_seconds{{part=\"total\"}} {}\n\
+        # HELP curseofrust_tick_budget_seconds Length of a tick.\n\
+        # TYPE curseofrust_tick_budget_seconds gauge\n\
+        curseofrust_tick_budget_seconds {}\n\
+        # HELP curseofrust_tick_overruns_total Ticks that took longer than their budget.\n\
+        # TYPE curseofrust_tick_overruns_total counter\n\
+        curseofrust_tick_overruns_total {}\n",
+        seconds(kings),
+        seconds(simulate),
+        seconds(broadcast),
+        seconds(total),
+        seconds(BUDGET.load(Ordering::Relaxed)),
+        OVERRUNS.load(Ordering::Relaxed),
+    )
+}
+
+#[inline]
+fn seconds(micros: u64) -> f64 {
+    micros as f64 / 1e6
+}
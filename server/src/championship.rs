@@ -0,0 +1,166 @@
+//! Championships of several rounds played back to back.
+//!
+//! Rounds are listed in a file, one per line as options over those
+//! the server was started with, such as `-s 42 -S hex`. Blank lines
+//! and lines starting with `#` are skipped.
+
+use std::{
+    ffi::OsString,
+    fmt::Write as _,
+    io,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use curseofrust::{
+    state::{BasicOpts, State},
+    Player, MAX_PLAYERS,
+};
+
+/// Time between the end of a round and the start of the next.
+const INTERMISSION: Duration = Duration::from_secs(10);
+
+/// Rounds of a championship and the points scored in them.
+#[derive(Debug)]
+pub(crate) struct Championship {
+    rounds: Vec<BasicOpts>,
+    /// Index of the round being played.
+    round: usize,
+    /// When the next round starts, if the current one is over.
+    next_at: Option<Instant>,
+    /// Winners of the rounds played.
+    winners: Vec<Option<Player>>,
+    points: [u32; MAX_PLAYERS],
+    /// File the final standings are written to.
+    summary: PathBuf,
+}
+
+impl Championship {
+    /// Loads the rounds listed in the file, each parsed over the
+    /// given arguments of the server.
+    ///
+    /// Fails on the first line whose options are invalid, or whose
+    /// game cannot be created.
+    pub(crate) fn load(path: &Path, args: &[OsString]) -> io::Result<Self> {
+        let invalid = |line: usize, e: &dyn std::fmt::Display| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}:{}: {}", path.display(), line + 1, e),
+            )
+        };
+        let mut rounds = Vec::new();
+        for (i, line) in std::fs::read_to_string(path)?.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let args = args
+                .iter()
+                .cloned()
+                .chain(line.split_whitespace().map(OsString::from));
+            let opts = curseofrust_cli_parser::parse_to_options(args)
+                .map_err(|e| invalid(i, &e))?
+                .basic;
+            State::new(opts.clone()).map_err(|e| invalid(i, &e))?;
+            rounds.push(opts);
+        }
+        if rounds.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: no rounds listed", path.display()),
+            ));
+        }
+        Ok(Self {
+            rounds,
+            round: 0,
+            next_at: None,
+            winners: Vec::new(),
+            points: [0; MAX_PLAYERS],
+            summary: path.with_extension("summary"),
+        })
+    }
+
+    /// Options of the round being played.
+    #[inline]
+    pub(crate) fn opts(&self) -> &BasicOpts {
+        &self.rounds[self.round]
+    }
+
+    /// Whether the round being played is over.
+    #[inline]
+    pub(crate) fn round_over(&self) -> bool {
+        self.winners.len() > self.round
+    }
+
+    /// Ends the round, awarding each of the given players a point per
+    /// player it outranks: the winner first, then survivors by score.
+    ///
+    /// Returns the standings to tell the players.
+    pub(crate) fn end_round(&mut self, st: &State, players: &[Player], now: Instant) -> String {
+        let winner = st.winner();
+        let rank = |p: Player| (winner == Some(p), !st.is_eliminated(p), st.score(p));
+        for &p in players {
+            let outranked = players.iter().filter(|&&q| rank(q) < rank(p)).count();
+            self.points[p.0 as usize] += outranked as u32;
+        }
+        self.winners.push(winner);
+        if self.round + 1 < self.rounds.len() {
+            self.next_at = Some(now + INTERMISSION);
+        }
+
+        let mut standings = format!(
+            "round {}/{} over, standings:",
+            self.round + 1,
+            self.rounds.len()
+        );
+        for (i, (p, points)) in self.standings(players).enumerate() {
+            let sep = if i == 0 { " " } else { ", " };
+            let _ = write!(standings, "{}player{} {}", sep, p, points);
+        }
+        standings
+    }
+
+    /// Moves on to the next round once the intermission is over at
+    /// `now`, returning its options.
+    pub(crate) fn next_round(&mut self, now: Instant) -> Option<&BasicOpts> {
+        self.next_at.filter(|&at| now >= at)?;
+        self.next_at = None;
+        self.round += 1;
+        Some(self.opts())
+    }
+
+    /// Whether every round has been played.
+    #[inline]
+    pub(crate) fn finished(&self) -> bool {
+        self.winners.len() == self.rounds.len()
+    }
+
+    /// Writes the winners of the rounds and the final standings of
+    /// the given players next to the list of rounds, returning the
+    /// path written to.
+    pub(crate) fn write_summary(&self, players: &[Player]) -> io::Result<&Path> {
+        let mut summary = String::new();
+        for (i, winner) in self.winners.iter().enumerate() {
+            let _ = match winner {
+                Some(p) => writeln!(summary, "round {}: player{} won", i + 1, p),
+                None => writeln!(summary, "round {}: no winner", i + 1),
+            };
+        }
+        let _ = writeln!(summary, "standings:");
+        for (i, (p, points)) in self.standings(players).enumerate() {
+            let _ = writeln!(summary, "{}. player{} {} points", i + 1, p, points);
+        }
+        std::fs::write(&self.summary, summary)?;
+        Ok(&self.summary)
+    }
+
+    /// The given players with their points, the most first.
+    fn standings<'a>(&'a self, players: &[Player]) -> impl Iterator<Item = (Player, u32)> + 'a {
+        let mut standings: Vec<_> = players
+            .iter()
+            .map(|&p| (p, self.points[p.0 as usize]))
+            .collect();
+        standings.sort_by_key(|&(p, points)| (std::cmp::Reverse(points), p.0));
+        standings.into_iter()
+    }
+}
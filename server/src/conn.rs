@@ -0,0 +1,414 @@
+//! Tasks owning the connections to clients.
+//!
+//! Each connection is owned by a task of its own, which sends the
+//! frames queued for it and passes the commands it receives on to the
+//! game loop, so that no two tasks ever use a connection at once.
+//! Clients reached through a relay share the task of the connection
+//! to the relay, which tells them apart by their peer ids there.
+
+use std::rc::Rc;
+
+use async_channel::{Receiver, Sender, TrySendError};
+use curseofrust::MAX_PLAYERS;
+use curseofrust_msg::{
+    capability, client_msg, fragment, server_msg, trace::Flow, C2S_SIZE, MAX_PASSWORD,
+    RELAY_HEADER_SIZE, S2C_SIZE,
+};
+use curseofrust_net_foundation::{
+    clock::{Clock, SystemClock},
+    reliable::{self, Received, Reliability},
+    Connection, Protocol, RecvOr,
+};
+
+use crate::trace;
+
+/// Frames queued for a connection before newer ones are dropped.
+pub(crate) const QUEUE: usize = 256;
+/// Commands received and not yet applied by the game loop before
+/// newer ones are dropped.
+pub(crate) const COMMANDS: usize = 1024;
+
+/// Offset of the player byte in state frames of either encoding.
+const PLAYER_OFFSET: usize = 1;
+
+/// A frame to send to a client.
+#[derive(Debug)]
+pub(crate) enum Frame {
+    Owned(Vec<u8>),
+    /// A state frame shared by clients, sent with the player of the
+    /// client patched in, and split into fragments if `split`.
+    State {
+        frame: Rc<[u8]>,
+        player: u8,
+        seq: u16,
+        split: bool,
+    },
+    /// Asks the relay to disconnect a client reached through it.
+    Close,
+}
+
+/// A frame queued for the connection of a client.
+#[derive(Debug)]
+pub(crate) struct Outgoing {
+    /// Id of the client, for tracing.
+    client: u32,
+    /// Peer id of the client on the relay, if relayed.
+    relay_id: Option<u8>,
+    frame: Frame,
+}
+
+#[cfg(test)]
+impl Outgoing {
+    /// Bytes of the frame as sent to a client connected directly,
+    /// not split into fragments.
+    pub(crate) fn bytes(&self) -> Vec<u8> {
+        match self.frame {
+            Frame::Owned(ref packet) => packet.clone(),
+            Frame::State {
+                ref frame, player, ..
+            } => {
+                let mut frame = frame.to_vec();
+                frame[PLAYER_OFFSET] = player;
+                frame
+            }
+            Frame::Close => vec![],
+        }
+    }
+}
+
+/// Queue of frames to a client.
+#[derive(Debug)]
+pub(crate) struct Outbox {
+    id: u32,
+    relay_id: Option<u8>,
+    tx: Sender<Outgoing>,
+}
+
+impl Outbox {
+    /// Creates the queue of a client connected directly, and the
+    /// receiving end for its task.
+    pub(crate) fn direct(id: u32) -> (Self, Receiver<Outgoing>) {
+        let (tx, rx) = async_channel::bounded(QUEUE);
+        (
+            Self {
+                id,
+                relay_id: None,
+                tx,
+            },
+            rx,
+        )
+    }
+
+    /// Creates the queue of a client reached through the relay whose
+    /// task receives from the given queue.
+    pub(crate) fn relayed(id: u32, relay_id: u8, upstream: &Sender<Outgoing>) -> Self {
+        Self {
+            id,
+            relay_id: Some(relay_id),
+            tx: upstream.clone(),
+        }
+    }
+
+    #[inline]
+    pub(crate) fn relay_id(&self) -> Option<u8> {
+        self.relay_id
+    }
+
+    /// Source of the commands of the client.
+    #[inline]
+    pub(crate) fn source(&self) -> Source {
+        match self.relay_id {
+            Some(id) => Source::Relayed(id),
+            None => Source::Client(self.id),
+        }
+    }
+
+    /// Queues the frame, dropping it if the queue is full, as
+    /// a congested network would.
+    pub(crate) fn push(&self, frame: Frame) {
+        let outgoing = Outgoing {
+            client: self.id,
+            relay_id: self.relay_id,
+            frame,
+        };
+        if let Err(TrySendError::Full(_)) = self.tx.try_send(outgoing) {
+            eprintln!("[PLAY] queue of client{} is full, frame dropped", self.id);
+        }
+    }
+
+    /// Stops the task of a client connected directly, closing its
+    /// connection. The relay is kept for other clients, and asked
+    /// to disconnect the client instead.
+    pub(crate) fn close(&self) {
+        match self.relay_id {
+            None => {
+                self.tx.close();
+            }
+            Some(_) => self.push(Frame::Close),
+        }
+    }
+}
+
+/// Creates the queue of frames to clients reached through the relay,
+/// and the receiving end for the task of the relay.
+pub(crate) fn relay_queue() -> (Sender<Outgoing>, Receiver<Outgoing>) {
+    async_channel::bounded(QUEUE * MAX_PLAYERS)
+}
+
+/// Where a command comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Source {
+    /// A client connected directly, by its id.
+    Client(u32),
+    /// A client reached through the relay, by its peer id there.
+    Relayed(u8),
+}
+
+/// A command received from a client.
+#[derive(Debug)]
+pub(crate) struct Command {
+    pub(crate) from: Source,
+    pub(crate) packet: [u8; C2S_SIZE],
+}
+
+/// Forwards the command to the game loop, dropping it if the game
+/// loop is behind.
+#[inline]
+fn forward(commands: &Sender<Command>, command: Command) {
+    let _ = commands.try_send(command);
+}
+
+/// Buffers of a task for the frames it sends.
+struct Buffers {
+    state: [u8; S2C_SIZE],
+    datagram: [u8; RELAY_HEADER_SIZE + S2C_SIZE],
+}
+
+impl Buffers {
+    fn new() -> Self {
+        Self {
+            state: [0; S2C_SIZE],
+            datagram: [0; RELAY_HEADER_SIZE + S2C_SIZE],
+        }
+    }
+
+    /// Sends the frame, fragmented if asked to.
+    ///
+    /// Events are sent reliably to clients that accepted the
+    /// reliability layer.
+    async fn send(
+        &mut self,
+        conn: &mut Connection<'_>,
+        outgoing: Outgoing,
+        reliability: Option<&mut Reliability>,
+    ) {
+        let Outgoing {
+            client,
+            relay_id,
+            frame,
+        } = outgoing;
+        match frame {
+            Frame::Owned(packet) => {
+                let packet = match reliability {
+                    Some(reliability) if packet.first() == Some(&server_msg::EVENT) => {
+                        reliability.wrap(&packet)
+                    }
+                    _ => packet,
+                };
+                let _ = write(conn, client, relay_id, &packet, &mut self.datagram).await;
+            }
+            Frame::State {
+                frame,
+                player,
+                seq,
+                split,
+            } => {
+                let state = &mut self.state[..frame.len()];
+                state.copy_from_slice(&frame);
+                state[PLAYER_OFFSET] = player;
+                if !split {
+                    let _ = write(conn, client, relay_id, state, &mut self.datagram).await;
+                    return;
+                }
+                let mut packet = [0u8; fragment::MAX_DATAGRAM];
+                for (header, chunk) in fragment::chunks(seq, state) {
+                    let len = fragment::HEADER_SIZE + chunk.len();
+                    packet[..fragment::HEADER_SIZE].copy_from_slice(&header);
+                    packet[fragment::HEADER_SIZE..len].copy_from_slice(chunk);
+                    let _ = write(conn, client, relay_id, &packet[..len], &mut self.datagram).await;
+                }
+            }
+            Frame::Close => {
+                if let Some(id) = relay_id {
+                    let _ = conn.send(&[id]).await;
+                }
+            }
+        }
+    }
+}
+
+/// Sends the packet to the client, through the relay if relayed.
+async fn write(
+    conn: &mut Connection<'_>,
+    client: u32,
+    relay_id: Option<u8>,
+    packet: &[u8],
+    datagram: &mut [u8],
+) -> Result<usize, std::io::Error> {
+    trace(
+        format_args!("client{}", client),
+        Some(client),
+        Flow::Sent,
+        packet,
+    );
+    let Some(id) = relay_id else {
+        return conn.send(packet).await;
+    };
+    let len = RELAY_HEADER_SIZE + packet.len();
+    datagram[0] = id;
+    datagram[RELAY_HEADER_SIZE..len].copy_from_slice(packet);
+    conn.send(&datagram[..len]).await
+}
+
+/// Whether the connection is gone after failing to receive.
+///
+/// Failures of datagram sockets are transient, such as rejections
+/// of earlier datagrams by the peer.
+#[inline]
+fn closed(conn: &Connection<'_>, received: &Result<usize, std::io::Error>) -> bool {
+    match received {
+        Ok(0) | Err(_) => conn.protocol() != Protocol::Udp,
+        Ok(_) => false,
+    }
+}
+
+/// Runs the connection to a client connected directly, until either
+/// side closes it.
+pub(crate) async fn run(
+    mut conn: Connection<'_>,
+    id: u32,
+    mut reliability: Option<Reliability>,
+    outbox: Receiver<Outgoing>,
+    commands: Sender<Command>,
+) {
+    let clock = SystemClock;
+    let mut buffers = Buffers::new();
+    let mut buf = [0u8; reliable::HEADER_SIZE + C2S_SIZE + MAX_PASSWORD];
+    // Whether the client accepted the reliability layer.
+    let mut reliable_peer = false;
+    loop {
+        let in_flight = reliability.as_ref().is_some_and(|r| r.in_flight() > 0);
+        let retransmit = async {
+            match in_flight {
+                true => clock.timer(reliable::RETRANSMIT_TIMEOUT).await,
+                false => std::future::pending().await,
+            }
+            None
+        };
+        let queued = async { Some(outbox.recv().await) };
+        let nread = match conn
+            .recv_or(&mut buf, futures_lite::future::or(queued, retransmit))
+            .await
+        {
+            RecvOr::Other(Some(Ok(outgoing))) => {
+                let reliability = reliability.as_mut().filter(|_| reliable_peer);
+                buffers.send(&mut conn, outgoing, reliability).await;
+                continue;
+            }
+            RecvOr::Other(None) => {
+                for packet in reliability
+                    .as_mut()
+                    .map(Reliability::due)
+                    .unwrap_or_default()
+                {
+                    trace(format_args!("client{}", id), Some(id), Flow::Sent, &packet);
+                    let _ = conn.send(&packet).await;
+                }
+                continue;
+            }
+            RecvOr::Other(Some(Err(_))) => break,
+            RecvOr::Recv(received) if closed(&conn, &received) => break,
+            RecvOr::Recv(Ok(nread)) if nread > 0 => nread,
+            RecvOr::Recv(_) => continue,
+        };
+        let trace = |flow, frame: &[u8]| trace(format_args!("client{}", id), Some(id), flow, frame);
+        trace(Flow::Received, &buf[..nread]);
+        let packet = match reliability {
+            Some(ref mut reliability) => match reliability.unwrap(&buf[..nread]) {
+                Received::Unreliable(packet) => Some(packet),
+                Received::Reliable { payload, ack } => {
+                    trace(Flow::Sent, &ack);
+                    let _ = conn.send(&ack).await;
+                    payload
+                }
+                Received::Ack => None,
+            },
+            None => Some(&buf[..nread]),
+        };
+        // Passwords were checked when the client joined.
+        let packet = packet.map(|packet| match packet {
+            [client_msg::CONNECT, ..] => &packet[..packet.len().min(C2S_SIZE)],
+            packet => packet,
+        });
+        match packet.map(<[u8; C2S_SIZE]>::try_from) {
+            Some(Ok(packet)) => {
+                if matches!(packet[0], client_msg::IS_ALIVE | client_msg::CONNECT)
+                    && packet[C2S_SIZE - 1] & capability::RELIABLE != 0
+                    && reliability.is_some()
+                {
+                    trace(Flow::Sent, &[server_msg::RELIABLE]);
+                    let _ = conn.send(&[server_msg::RELIABLE]).await;
+                    reliable_peer = true;
+                }
+                let from = Source::Client(id);
+                forward(&commands, Command { from, packet });
+            }
+            Some(Err(_)) => eprintln!(
+                "[PLAY] error recv packet from client{}, expected {} bytes, have {}",
+                id,
+                C2S_SIZE,
+                packet.map_or(0, <[u8]>::len)
+            ),
+            None => {}
+        }
+    }
+    let _ = conn.close().await;
+}
+
+/// Runs the connection to the relay, for all clients reached through it.
+pub(crate) async fn run_relay(
+    mut conn: Connection<'_>,
+    outbox: Receiver<Outgoing>,
+    commands: Sender<Command>,
+) {
+    let mut buffers = Buffers::new();
+    let mut buf = [0u8; RELAY_HEADER_SIZE + C2S_SIZE];
+    loop {
+        let nread = match conn.recv_or(&mut buf, outbox.recv()).await {
+            RecvOr::Other(Ok(outgoing)) => {
+                buffers.send(&mut conn, outgoing, None).await;
+                continue;
+            }
+            RecvOr::Other(Err(_)) => break,
+            RecvOr::Recv(received) if closed(&conn, &received) => break,
+            RecvOr::Recv(Ok(nread)) if nread > 0 => nread,
+            RecvOr::Recv(_) => continue,
+        };
+        let [id, ref frame @ ..] = buf[..nread] else {
+            continue;
+        };
+        trace(format_args!("relayed{}", id), None, Flow::Received, frame);
+        match <[u8; C2S_SIZE]>::try_from(frame) {
+            Ok(packet) => {
+                let from = Source::Relayed(id);
+                forward(&commands, Command { from, packet });
+            }
+            Err(_) => eprintln!(
+                "[PLAY] error recv packet from relay, expected {} bytes, have {}",
+                RELAY_HEADER_SIZE + C2S_SIZE,
+                nread
+            ),
+        }
+    }
+    eprintln!("[PLAY] connection to the relay closed");
+}
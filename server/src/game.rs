@@ -0,0 +1,906 @@
+//! The game played once the lobby is full, one tick at a time.
+//!
+//! The loop of `main` waits for each tick and hands the commands
+//! received in between to [`Game::apply`], so that tests can play a
+//! game on a [`MockClock`](curseofrust_net_foundation::clock::MockClock)
+//! without sockets.
+
+use std::{
+    cell::RefCell,
+    time::{Duration, Instant},
+};
+
+use curseofrust::{
+    snapshot::GameEvent,
+    state::{BasicOpts, State},
+    Player, Pos, Speed, Strategy,
+};
+use curseofrust_msg::{
+    bytemuck, client_msg, event::Event, server_msg, C2SData, S2CData, C2S_SIZE, MAX_MISSED_PINGS,
+    PING_INTERVAL, RESYNC_INTERVAL,
+};
+use curseofrust_net_foundation::clock::{Clock, Interval};
+
+use crate::{budget, championship::Championship, conn::Source, states, Client};
+
+/// Seconds counted down to clients before resuming a paused game.
+const RESUME_COUNTDOWN: u8 = 3;
+
+/// How the game is played, as given when the server starts.
+#[derive(Debug, Default)]
+pub(crate) struct Settings {
+    /// Time after which the player with the highest score wins.
+    pub(crate) time_limit: Option<Duration>,
+    /// Steps the game advances per turn in turn-based mode.
+    pub(crate) turns: Option<u32>,
+    /// Time after which idle clients are handed over to AI.
+    pub(crate) afk_timeout: Option<Duration>,
+    /// Rounds played back to back, if any.
+    pub(crate) championship: Option<Championship>,
+}
+
+/// A game played by the clients of the lobby.
+pub(crate) struct Game<'a, C> {
+    st: &'a RefCell<State>,
+    cl: &'a [Client],
+    clock: C,
+    b_opt: BasicOpts,
+    /// Players of all clients.
+    players: Vec<Player>,
+    /// Real time of a tick.
+    tick: Duration,
+    tick_budget: budget::Budget,
+    time_limit: Option<Duration>,
+    turns: Option<u32>,
+    afk_timeout: Option<Duration>,
+    championship: Option<Championship>,
+    /// Ticks since the game started, wrapping.
+    time: i32,
+    /// Time left of the time limit, which pauses do not count toward.
+    time_left: Option<Duration>,
+    turn_done: bool,
+    /// Sequence number of the last state sent, skipping `0`
+    /// which marks unnumbered states.
+    seq: u16,
+    /// Time the last state was broadcast.
+    sent_at: Instant,
+    keepalive: Interval,
+    state_frames: states::StateFrames,
+    /// Seconds left to count down before resuming the game, and
+    /// when to tell the next one.
+    countdown: Option<(u8, Instant)>,
+}
+
+impl<'a, C: Clock> Game<'a, C> {
+    /// Starts the game, going on from the given state if resumed,
+    /// with the time left it had.
+    pub(crate) fn start(
+        st: &'a RefCell<State>,
+        cl: &'a [Client],
+        clock: C,
+        b_opt: BasicOpts,
+        settings: Settings,
+        resumed: Option<&S2CData>,
+    ) -> Self {
+        let tick = st.borrow().tick;
+        notify(cl, &Event::Speed(st.borrow().speed));
+        Self {
+            st,
+            cl,
+            players: cl
+                .iter()
+                .flat_map(|client| client.players.iter().copied())
+                .collect(),
+            tick,
+            tick_budget: budget::Budget::new(tick),
+            time_limit: settings.time_limit,
+            turns: settings.turns,
+            afk_timeout: settings.afk_timeout,
+            championship: settings.championship,
+            time: 0,
+            time_left: settings
+                .time_limit
+                .map(|limit| resumed.and_then(S2CData::time_left).unwrap_or(limit)),
+            turn_done: false,
+            seq: resumed.map_or(0, S2CData::seq),
+            sent_at: clock.now(),
+            keepalive: clock.interval(PING_INTERVAL),
+            state_frames: states::StateFrames::new(),
+            countdown: None,
+            clock,
+            b_opt,
+        }
+    }
+
+    /// Real time of a tick.
+    #[inline]
+    pub(crate) fn tick_time(&self) -> Duration {
+        self.tick
+    }
+
+    /// Applies a packet received from the given source.
+    pub(crate) fn apply(&self, from: Source, packet: &[u8; C2S_SIZE]) {
+        if let Some(client) = self.cl.iter().find(|client| client.outbox.source() == from) {
+            apply_packet(client, packet, self.st, self.clock.now());
+        }
+    }
+
+    /// Plays a tick, sending clients what changed.
+    pub(crate) fn tick(&mut self) {
+        self.time += 1;
+        if self.time >= 1600 {
+            self.time = 0
+        }
+
+        {
+            let mut st = self.st.borrow_mut();
+            if let Some(left) = self
+                .time_left
+                .as_mut()
+                .filter(|left| st.speed != Speed::Pause && !left.is_zero())
+            {
+                *left = left.saturating_sub(self.tick);
+                if left.is_zero() {
+                    st.speed = Speed::Pause;
+                    let winner = st.time_up().unwrap_or_default();
+                    println!("[PLAY] time is up, player{} won", winner);
+                    println!("[PLAY] fingerprint {}", st.fingerprint());
+                    if let Some(seed) = st.ai_personality_seed {
+                        println!("[PLAY] AI personality seed was {}", seed);
+                    }
+                    let packet = [server_msg::GAME_OVER, winner.0 as u8];
+                    broadcast(self.cl, packet);
+                }
+            }
+
+            let (steps, send) = match self.turns {
+                // Resent between turns for clients yet to get a state.
+                Some(ticks) if std::mem::take(&mut self.turn_done) => (ticks, true),
+                Some(_) => (0, self.time % 100 == 0),
+                None => {
+                    let step = self.time.checked_rem(slowdown(st.speed)) == Some(0)
+                        && st.speed != Speed::Pause;
+                    (step as u32, step)
+                }
+            };
+            let eliminated = st.countries.each_ref().map(|c| c.eliminated);
+            // Captures are told from the states, only if anyone is told.
+            let prev =
+                (steps > 0 && self.cl.iter().any(|client| client.events)).then(|| st.snapshot());
+            let mut spent = budget::Tick::default();
+            for _ in 0..steps {
+                budget::time(&mut spent.kings, || st.kings_move());
+                budget::time(&mut spent.simulate, || st.simulate());
+            }
+            for event in prev
+                .map(|prev| prev.events(&st.snapshot()))
+                .unwrap_or_default()
+            {
+                if let GameEvent::CityCaptured { pos, from, to, .. } = event {
+                    let event = Event::CityCaptured { pos, from, to };
+                    notify(self.cl, &event);
+                }
+            }
+            for (p, _) in eliminated
+                .into_iter()
+                .enumerate()
+                .filter(|&(p, was)| !was && st.countries[p].eliminated)
+            {
+                println!("[PLAY] player{} was eliminated", p);
+                println!("[PLAY] fingerprint {}", st.fingerprint());
+                // Clients whose players are all eliminated hold up
+                // neither turns nor votes.
+                for client in self
+                    .cl
+                    .iter()
+                    .filter(|client| client.players.contains(&Player(p as u32)))
+                {
+                    if client.players.iter().all(|&p| st.is_eliminated(p)) {
+                        client.spectator.set(true);
+                        client.restart_vote.set(false);
+                    } else if client.pl.get() == Player(p as u32) {
+                        client.switch_player(&st);
+                    }
+                }
+                let packet = [server_msg::ELIMINATED, p as u8];
+                broadcast(self.cl, packet);
+                let event = Event::Eliminated(Player(p as u32));
+                notify(self.cl, &event);
+            }
+            for event in st.take_events() {
+                if let GameEvent::CityPlundered {
+                    pos: Pos(x, y),
+                    from,
+                    to,
+                    gold,
+                } = event
+                {
+                    println!(
+                        "[PLAY] player{} plundered {} gold from player{}",
+                        to, gold, from
+                    );
+                    let [g0, g1, g2, g3] = u32::try_from(gold).unwrap_or(u32::MAX).to_be_bytes();
+                    let packet = [
+                        server_msg::PLUNDER,
+                        x as u8,
+                        y as u8,
+                        from.0 as u8,
+                        to.0 as u8,
+                        g0,
+                        g1,
+                        g2,
+                        g3,
+                    ];
+                    broadcast(self.cl, packet);
+                }
+            }
+            if let Some(championship) = self
+                .championship
+                .as_mut()
+                .filter(|championship| !championship.round_over())
+            {
+                if let Some(winner) = st.winner() {
+                    // Rounds ended by the time limit are told already.
+                    st.speed = Speed::Pause;
+                    if !self.time_left.is_some_and(|left| left.is_zero()) {
+                        println!("[PLAY] player{} won", winner);
+                        println!("[PLAY] fingerprint {}", st.fingerprint());
+                        let packet = [server_msg::GAME_OVER, winner.0 as u8];
+                        broadcast(self.cl, packet);
+                        notify(self.cl, &Event::Speed(Speed::Pause));
+                    }
+                    let standings = championship.end_round(&st, &self.players, self.clock.now());
+                    println!("[PLAY] {}", standings);
+                    let event = Event::Chat {
+                        from: Player(0),
+                        text: standings,
+                    };
+                    notify(self.cl, &event);
+                    if championship.finished() {
+                        match championship.write_summary(&self.players) {
+                            Ok(path) => {
+                                println!(
+                                    "[PLAY] championship summary written to {}",
+                                    path.display()
+                                )
+                            }
+                            Err(e) => {
+                                eprintln!("[PLAY] error writing championship summary: {}", e)
+                            }
+                        }
+                    }
+                }
+            }
+
+            let broadcast_start = Instant::now();
+            let now = self.clock.now();
+            // While no states are broadcast, the last one is resent
+            // to clients acknowledging states that have not
+            // acknowledged it, in case it was lost.
+            if !send && now - self.sent_at >= RESYNC_INTERVAL {
+                for client in self.cl.iter().filter(|client| {
+                    !client.dropped.get()
+                        && client.acked_seq.get() != 0
+                        && client.acked_seq.get() != self.seq
+                }) {
+                    client.resync.set(true);
+                }
+                self.sent_at = now;
+            }
+            let resync = self.cl.iter().any(|client| client.resync.get());
+            if send || resync {
+                let mut data = S2CData::new(Default::default(), &st);
+                data.set_time_left(self.time_left);
+                self.seq = self.seq.wrapping_add(1).max(1);
+                data.set_seq(self.seq);
+
+                for client in self.cl.iter().filter(|client| !client.dropped.get()) {
+                    // Clears the request of clients sent to anyway.
+                    client.state_due.set(client.resync.take() || send);
+                }
+                self.state_frames.encode(&data, self.cl);
+                self.state_frames.send(self.seq, self.cl);
+                self.sent_at = now;
+            }
+            spent.broadcast = broadcast_start.elapsed();
+            // Paused ticks would hide the cost of the others.
+            if steps > 0 || send {
+                self.tick_budget.record(spent);
+            }
+        }
+
+        if let Some(timeout) = self.afk_timeout.filter(|_| self.time % 100 == 0) {
+            let mut st = self.st.borrow_mut();
+            for client in self.cl.iter().filter(|client| !client.spectator.get()) {
+                let idle = self.clock.elapsed(client.last_seen.get()) >= timeout;
+                if idle == client.idle.get() {
+                    continue;
+                }
+                client.idle.set(idle);
+                if idle {
+                    println!("[PLAY] client{} is idle, handed over to AI", client.id);
+                } else {
+                    println!("[PLAY] client{} came back", client.id);
+                }
+                for &pl in &client.players {
+                    if idle {
+                        st.hand_to_king(pl, Strategy::Opportunist);
+                    } else {
+                        st.take_from_king(pl);
+                    }
+                    let packet = [server_msg::AFK, pl.0 as u8, idle as u8];
+                    broadcast(self.cl, packet);
+                }
+            }
+        }
+
+        if self.keepalive.tick(self.clock.now()) {
+            for client in self
+                .cl
+                .iter()
+                .filter(|client| client.ping && !client.dropped.get())
+            {
+                let missed = client.pings_missed.get();
+                if missed < MAX_MISSED_PINGS {
+                    client.pings_missed.set(missed + 1);
+                    client.send(&[server_msg::PING]);
+                    continue;
+                }
+
+                // A half-open connection, whose country is handed
+                // over for good.
+                client.dropped.set(true);
+                client.outbox.close();
+                client.restart_vote.set(false);
+                client.turn_ended.set(false);
+                let playing = !client.spectator.get() && !client.idle.get();
+                client.spectator.set(true);
+                client.idle.set(true);
+                println!(
+                    "[PLAY] client{} missed {} pings, dropped",
+                    client.id, missed
+                );
+                for &pl in &client.players {
+                    notify(self.cl, &Event::Left(pl));
+                }
+                if playing {
+                    for &pl in &client.players {
+                        self.st.borrow_mut().hand_to_king(pl, Strategy::Opportunist);
+                        let packet = [server_msg::AFK, pl.0 as u8, 1];
+                        broadcast(self.cl, packet);
+                    }
+                }
+            }
+        }
+
+        let mut votes_changed = false;
+        let mut turns_changed = false;
+        for (client, request) in self
+            .cl
+            .iter()
+            .filter(|client| !client.dropped.get())
+            .flat_map(|client| client.requests.take().into_iter().map(move |r| (client, r)))
+        {
+            // Requests queued behind a surrender are void.
+            if client.spectator.get() {
+                continue;
+            }
+            match request {
+                client_msg::SURRENDER => {
+                    client.spectator.set(true);
+                    client.restart_vote.set(false);
+                    println!("[PLAY] client{} surrendered", client.id);
+                    for &pl in &client.players {
+                        self.st.borrow_mut().hand_to_king(pl, Strategy::Opportunist);
+                        let packet = [server_msg::SURRENDER, pl.0 as u8];
+                        broadcast(self.cl, packet);
+                    }
+                    votes_changed = true;
+                }
+                // Rounds of championships are replayed only while played.
+                client_msg::VOTE_RESTART
+                    if !client.restart_vote.get()
+                        && !self
+                            .championship
+                            .as_ref()
+                            .is_some_and(|championship| championship.round_over()) =>
+                {
+                    client.restart_vote.set(true);
+                    votes_changed = true;
+                }
+                client_msg::END_TURN if self.turns.is_some() && !client.turn_ended.get() => {
+                    client.turn_ended.set(true);
+                    turns_changed = true;
+                }
+                // Turns do not run at a speed.
+                client_msg::PAUSE if self.turns.is_none() => {
+                    let mut st = self.st.borrow_mut();
+                    if self.countdown.take().is_some() {
+                        println!("[PLAY] client{} stopped the countdown", client.id);
+                    } else if st.speed != Speed::Pause {
+                        println!("[PLAY] client{} paused the game", client.id);
+                        st.prev_speed = st.speed;
+                        st.speed = Speed::Pause;
+                    } else {
+                        continue;
+                    }
+                    notify(self.cl, &Event::Speed(Speed::Pause));
+                }
+                client_msg::UNPAUSE
+                    if self.turns.is_none()
+                        && self.countdown.is_none()
+                        && self.st.borrow().speed == Speed::Pause
+                        && !self.time_left.is_some_and(|left| left.is_zero())
+                        && !self
+                            .championship
+                            .as_ref()
+                            .is_some_and(|championship| championship.round_over()) =>
+                {
+                    println!("[PLAY] client{} resumed the game", client.id);
+                    self.countdown = Some((RESUME_COUNTDOWN, self.clock.now()));
+                }
+                _ => {}
+            }
+        }
+
+        if let Some((secs, at)) = self.countdown.filter(|&(_, at)| self.clock.now() >= at) {
+            if secs > 0 {
+                notify(self.cl, &Event::Countdown(secs));
+                self.countdown = Some((secs - 1, at + Duration::from_secs(1)));
+            } else {
+                let mut st = self.st.borrow_mut();
+                // Games started paused resume at the default speed.
+                st.speed = match st.prev_speed {
+                    Speed::Pause => Speed::default(),
+                    speed => speed,
+                };
+                self.countdown = None;
+                notify(self.cl, &Event::Speed(st.speed));
+            }
+        }
+
+        // Idle clients and spectators do not hold up turns.
+        let players = self
+            .cl
+            .iter()
+            .filter(|client| !client.spectator.get() && !client.idle.get());
+        let ended = players
+            .clone()
+            .filter(|client| client.turn_ended.get())
+            .count();
+        if turns_changed && ended > 0 {
+            let players = players.clone().count();
+            println!("[PLAY] {}/{} clients ended the turn", ended, players);
+            let packet = [server_msg::END_TURN, ended as u8, players as u8];
+            broadcast(self.cl, packet);
+        }
+        if self.turns.is_some() && ended > 0 && ended >= players.count() {
+            self.turn_done = true;
+            for client in self.cl {
+                client.turn_ended.set(false);
+            }
+        }
+
+        // Idle clients cannot vote, so only active players count.
+        let voters = self
+            .cl
+            .iter()
+            .filter(|client| !client.spectator.get() && !client.idle.get());
+        let votes = voters
+            .clone()
+            .filter(|client| client.restart_vote.get())
+            .count();
+        if votes_changed && votes > 0 {
+            let voters = voters.count();
+            println!("[PLAY] {}/{} clients voted to restart", votes, voters);
+            let packet = [server_msg::VOTE_RESTART, votes as u8, voters as u8];
+            broadcast(self.cl, packet);
+
+            if votes >= voters {
+                // Rounds of championships are replayed as listed.
+                if self.championship.is_none() {
+                    self.b_opt.seed = fastrand::u64(..);
+                    if let Some(seed) = &mut self.b_opt.ai_personality_seed {
+                        println!("[PLAY] AI personality seed was {}", seed);
+                        *seed = fastrand::u64(..);
+                    }
+                }
+                match State::new(self.b_opt.clone()) {
+                    Ok(restarted) => {
+                        restart(&mut self.st.borrow_mut(), restarted, self.cl);
+                        self.time_left = self.time_limit;
+                        self.countdown = None;
+                        println!("[PLAY] restarted with seed {}", self.b_opt.seed);
+                    }
+                    Err(e) => eprintln!("[PLAY] error restarting: {}", e),
+                }
+            }
+        }
+
+        if let Some(opts) = self
+            .championship
+            .as_mut()
+            .and_then(|championship| championship.next_round(self.clock.now()))
+        {
+            let clients = self.b_opt.clients;
+            self.b_opt = opts.clone();
+            self.b_opt.clients = clients;
+            match State::new(self.b_opt.clone()) {
+                Ok(next) => {
+                    restart(&mut self.st.borrow_mut(), next, self.cl);
+                    self.time_left = self.time_limit;
+                    self.countdown = None;
+                    println!("[PLAY] next round started with seed {}", self.b_opt.seed);
+                }
+                Err(e) => eprintln!("[PLAY] error starting the next round: {}", e),
+            }
+        }
+    }
+}
+
+/// Applies a packet received from the client at `now`.
+fn apply_packet(cl: &Client, buf: &[u8; C2S_SIZE], st: &RefCell<State>, now: Instant) {
+    if cl.dropped.get() {
+        return;
+    }
+    cl.last_seen.set(now);
+    let (&msg, od) = buf
+        .split_first()
+        .expect("the buffer should longer than one byte");
+    match msg {
+        client_msg::PONG => {
+            cl.pings_missed.set(0);
+            return;
+        }
+        client_msg::FULL_STATE_REQUEST => {
+            if cl
+                .resynced_at
+                .get()
+                .is_none_or(|at| now - at >= RESYNC_INTERVAL)
+            {
+                cl.resynced_at.set(Some(now));
+                cl.resync.set(true);
+            }
+            return;
+        }
+        client_msg::STATE_ACK => {
+            let data: C2SData = *bytemuck::from_bytes(od);
+            let seq = data.acked_seq();
+            if cl.acked_seq.get() == 0 || curseofrust_msg::seq_follows(seq, cl.acked_seq.get()) {
+                cl.acked_seq.set(seq);
+            }
+            return;
+        }
+        _ if cl.spectator.get() => return,
+        client_msg::SURRENDER
+        | client_msg::VOTE_RESTART
+        | client_msg::END_TURN
+        | client_msg::PAUSE
+        | client_msg::UNPAUSE => {
+            let mut requests = cl.requests.borrow_mut();
+            // Retransmissions are queued once.
+            if requests.last() != Some(&msg) {
+                requests.push(msg);
+            }
+            return;
+        }
+        client_msg::SWITCH_PLAYER => {
+            cl.switch_player(&st.borrow());
+            return;
+        }
+        _ => {}
+    }
+    let data: C2SData = *bytemuck::from_bytes(od);
+    let mut st = st.borrow_mut();
+    if let Err(e) = curseofrust_msg::apply_c2s_msg(&mut st, cl.pl.get(), msg, data) {
+        eprintln!("[PLAY] error performing action for player{}: {}", cl.id, e)
+    }
+}
+
+/// Replaces the game with the given one, from which every client
+/// not dropped plays anew.
+fn restart(st: &mut State, mut restarted: State, cl: &[Client]) {
+    // Clients drop states older than the last one they got.
+    restarted.time = restarted.time.max(st.time + 1);
+    *st = restarted;
+    notify(cl, &Event::Speed(st.speed));
+    for client in cl {
+        let dropped = client.dropped.get();
+        client.idle.set(dropped);
+        client.spectator.set(dropped);
+        client.restart_vote.set(false);
+        client.turn_ended.set(false);
+        client.pl.set(client.players[0]);
+        if dropped {
+            for &pl in &client.players {
+                st.hand_to_king(pl, Strategy::Opportunist);
+            }
+        }
+    }
+}
+
+/// Sends the packet to all clients not dropped.
+pub(crate) fn broadcast<const N: usize>(cl: &[Client], packet: [u8; N]) {
+    for client in cl.iter().filter(|client| !client.dropped.get()) {
+        client.send(&packet);
+    }
+}
+
+/// Sends the event to all clients showing events and not dropped.
+pub(crate) fn notify(cl: &[Client], event: &Event) {
+    let frame = event.encode();
+    for client in cl
+        .iter()
+        .filter(|client| client.events && !client.dropped.get())
+    {
+        client.send(&frame);
+    }
+}
+
+#[inline]
+fn slowdown(speed: Speed) -> i32 {
+    match speed {
+        Speed::Pause => 0,
+        Speed::Slowest => 160,
+        Speed::Slower => 80,
+        Speed::Slow => 40,
+        Speed::Normal => 20,
+        Speed::Fast => 10,
+        Speed::Faster => 5,
+        Speed::Fastest => 2,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use async_channel::Receiver;
+    use curseofrust_msg::{apply_s2c_msg, capability, StateOrder};
+    use curseofrust_net_foundation::{clock::MockClock, Protocol};
+
+    use super::*;
+    use crate::{
+        conn::{Outbox, Outgoing, QUEUE},
+        lobby,
+    };
+
+    fn opts(seed: u64) -> BasicOpts {
+        let mut b_opt = BasicOpts::default();
+        b_opt.seed = seed;
+        b_opt.clients = 2;
+        b_opt.speed = Speed::Fastest;
+        b_opt
+    }
+
+    /// Clients of the given capabilities joining the lobby of a game
+    /// for two, and the frames queued for each.
+    fn clients(info: u8, clock: &MockClock) -> (Vec<Client>, Vec<Receiver<Outgoing>>) {
+        let mut cl = vec![];
+        let mut rx = vec![];
+        for id in 0..2 {
+            let mut packet = [0; C2S_SIZE];
+            packet[0] = client_msg::IS_ALIVE;
+            packet[C2S_SIZE - 1] = info;
+            let addr = ([127, 0, 0, 1], 19140 + id as u16).into();
+            let request = lobby::admit(&packet, addr, &[], None).unwrap();
+            let players = lobby::claim(&cl, 2, false, request);
+            let (outbox, queued) = Outbox::direct(id);
+            let info = request.info;
+            cl.push(Client::new(
+                id,
+                addr,
+                players,
+                outbox,
+                info,
+                Protocol::Udp,
+                clock.now(),
+            ));
+            rx.push(queued);
+        }
+        (cl, rx)
+    }
+
+    fn command(msg: u8, pos: Pos, info: u8) -> [u8; C2S_SIZE] {
+        let mut packet = [msg; C2S_SIZE];
+        packet[1..].copy_from_slice(bytemuck::bytes_of(&C2SData::from((pos, info))));
+        packet
+    }
+
+    /// A client as far as the game goes, applying the states it gets.
+    struct Runtime {
+        state: State,
+        order: StateOrder,
+    }
+
+    impl Runtime {
+        fn new() -> Self {
+            // The map comes from the server, not from the seed.
+            let mut state = State::new(opts(2)).unwrap();
+            state.time = 0;
+            Self {
+                state,
+                order: StateOrder::default(),
+            }
+        }
+
+        fn receive(&mut self, rx: &Receiver<Outgoing>) {
+            while let Ok(outgoing) = rx.try_recv() {
+                let frame = outgoing.bytes();
+                let data = match frame.split_first() {
+                    Some((&server_msg::STATE_RLE, data)) => S2CData::decode_rle(data),
+                    _ => continue,
+                };
+                let data = data.expect("the state should decode");
+                assert!(self.order.accept(&self.state, &data));
+                apply_s2c_msg(&mut self.state, data).unwrap();
+            }
+        }
+    }
+
+    /// Bytes of the state as a server would send it, which covers
+    /// every tile, flag and the gold of every player.
+    fn encoded(state: &State) -> Vec<u8> {
+        bytemuck::bytes_of(&S2CData::new(Player::NEUTRAL, state)).to_vec()
+    }
+
+    #[test]
+    fn clients_converge_with_server() {
+        let clock = MockClock::new();
+        let st = RefCell::new(State::new(opts(1)).unwrap());
+        let (cl, rx) = clients(capability::RLE, &clock);
+        let mut game = Game::start(&st, &cl, clock.clone(), opts(1), Settings::default(), None);
+        let mut runtimes = [Runtime::new(), Runtime::new()];
+        let players = [Player(1), Player(2)];
+
+        // Each player goes for the first city of the other.
+        let city = |player| st.borrow().grid.cities(player).next().unwrap().0;
+        let targets = [city(players[1]), city(players[0])];
+        let homes = players.map(city);
+
+        for tick in 1..=600 {
+            for (i, client) in cl.iter().enumerate() {
+                let packet = match tick % 200 {
+                    20 => command(client_msg::FLAG_ON, targets[i], 2),
+                    80 => command(client_msg::BUILD, homes[i], 0),
+                    180 => command(client_msg::FLAG_OFF, targets[i], 0),
+                    _ => command(client_msg::IS_ALIVE, Pos(0, 0), 0),
+                };
+                game.apply(client.outbox.source(), &packet);
+            }
+            game.tick();
+            clock.advance(game.tick_time());
+            if tick % 200 == 20 {
+                assert!(st.borrow().fgs[1].is_flagged(targets[0]));
+                assert!(st.borrow().fgs[2].is_flagged(targets[1]));
+            }
+            for (runtime, rx) in runtimes.iter_mut().zip(&rx) {
+                runtime.receive(rx);
+            }
+        }
+
+        let server = st.borrow();
+        assert!(server.time > 0);
+        for (runtime, player) in runtimes.iter().zip(players) {
+            assert_eq!(runtime.state.controlled, player);
+            assert_eq!(runtime.state.time, server.time);
+            assert!(encoded(&runtime.state) == encoded(&server));
+        }
+    }
+
+    /// Frames queued for a client, oldest first.
+    fn frames(rx: &Receiver<Outgoing>) -> Vec<Vec<u8>> {
+        std::iter::from_fn(|| rx.try_recv().ok())
+            .map(|outgoing| outgoing.bytes())
+            .collect()
+    }
+
+    /// Plays a paused game through `duration` on the clock, with `each`
+    /// run before every tick.
+    fn play(
+        game: &mut Game<'_, MockClock>,
+        clock: &MockClock,
+        duration: Duration,
+        each: impl Fn(&Game<'_, MockClock>),
+    ) {
+        let end = clock.now() + duration;
+        while clock.now() <= end {
+            each(game);
+            game.tick();
+            clock.advance(game.tick_time());
+        }
+    }
+
+    #[test]
+    fn drops_clients_missing_pings() {
+        let clock = MockClock::new();
+        let st = RefCell::new(State::new(opts(1)).unwrap());
+        st.borrow_mut().speed = Speed::Pause;
+        let (cl, rx) = clients(capability::PING, &clock);
+        let mut game = Game::start(&st, &cl, clock.clone(), opts(1), Settings::default(), None);
+
+        // Only the first client answers.
+        let pong = command(client_msg::PONG, Pos(0, 0), 0);
+        let answer = |game: &Game<_>| game.apply(cl[0].outbox.source(), &pong);
+        play(&mut game, &clock, PING_INTERVAL * MAX_MISSED_PINGS, answer);
+        assert!(!cl[1].dropped.get());
+        let pings = |frames: Vec<Vec<u8>>| {
+            frames
+                .iter()
+                .filter(|frame| frame[..] == [server_msg::PING])
+                .count() as u32
+        };
+        assert_eq!(pings(frames(&rx[1])), MAX_MISSED_PINGS);
+
+        play(&mut game, &clock, PING_INTERVAL, answer);
+        assert!(!cl[0].dropped.get());
+        assert!(cl[1].dropped.get() && cl[1].idle.get());
+        assert!(rx[1].is_closed());
+        assert!(frames(&rx[0]).contains(&vec![server_msg::AFK, 2, 1]));
+        assert!(st.borrow().kings.iter().any(|k| k.player() == Player(2)));
+    }
+
+    #[test]
+    fn hands_idle_clients_to_ai() {
+        let clock = MockClock::new();
+        let st = RefCell::new(State::new(opts(1)).unwrap());
+        st.borrow_mut().speed = Speed::Pause;
+        let (cl, rx) = clients(0, &clock);
+        let settings = Settings {
+            afk_timeout: Some(Duration::from_secs(5)),
+            ..Default::default()
+        };
+        let mut game = Game::start(&st, &cl, clock.clone(), opts(1), settings, None);
+        let alive = command(client_msg::IS_ALIVE, Pos(0, 0), 0);
+
+        // Only the first client is heard from.
+        let first = |game: &Game<_>| game.apply(cl[0].outbox.source(), &alive);
+        play(&mut game, &clock, Duration::from_secs(4), first);
+        assert!(!cl[1].idle.get());
+        play(&mut game, &clock, Duration::from_secs(2), first);
+        assert!(!cl[0].idle.get() && cl[1].idle.get());
+        assert!(frames(&rx[0]).contains(&vec![server_msg::AFK, 2, 1]));
+        assert!(st.borrow().kings.iter().any(|k| k.player() == Player(2)));
+
+        let both = |game: &Game<_>| {
+            for client in &cl {
+                game.apply(client.outbox.source(), &alive);
+            }
+        };
+        play(&mut game, &clock, Duration::from_secs(1), both);
+        assert!(!cl[1].idle.get());
+        assert!(frames(&rx[0]).contains(&vec![server_msg::AFK, 2, 0]));
+        assert!(!st.borrow().kings.iter().any(|k| k.player() == Player(2)));
+    }
+
+    #[test]
+    fn slow_clients_do_not_hold_back_others() {
+        let clock = MockClock::new();
+        let st = RefCell::new(State::new(opts(1)).unwrap());
+        let (cl, rx) = clients(0, &clock);
+        let mut game = Game::start(&st, &cl, clock.clone(), opts(1), Settings::default(), None);
+
+        // The second client never reads its queue.
+        let states = |rx: &Receiver<Outgoing>| {
+            frames(rx)
+                .iter()
+                .filter(|frame| frame[0] == server_msg::STATE)
+                .count()
+        };
+        let mut received = 0;
+        while rx[1].len() < QUEUE {
+            received += states(&rx[0]);
+            game.tick();
+            clock.advance(game.tick_time());
+        }
+        for _ in 0..QUEUE {
+            game.tick();
+            clock.advance(game.tick_time());
+        }
+        assert_eq!(rx[1].len(), QUEUE);
+        assert!(!cl[1].dropped.get());
+        // The first client keeps getting every state past the full queue.
+        let sent = game.seq as usize;
+        received += states(&rx[0]);
+        assert!(received > QUEUE);
+        assert_eq!(received, sent);
+    }
+}
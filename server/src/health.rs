@@ -0,0 +1,88 @@
+//! Liveness endpoint for container orchestrators.
+//!
+//! Answers `GET /healthz` over HTTP from a thread of its own, so that
+//! probes are answered while the lobby waits for clients. Once playing,
+//! the game loop beats every tick, and a loop that stopped beating
+//! is reported as unhealthy.
+//!
+//! `GET /metrics` answers the time spent per tick, see [`budget`](crate::budget).
+
+use std::{
+    io::{self, Read as _, Write as _},
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        OnceLock,
+    },
+    time::{Duration, Instant},
+};
+
+/// Time without beats after which the game loop is considered stuck.
+const STALL: Duration = Duration::from_secs(5);
+/// Longest request read, headers included.
+const MAX_REQUEST: usize = 1024;
+
+static STARTED: OnceLock<Instant> = OnceLock::new();
+/// Milliseconds since [`STARTED`] at the last beat,
+/// `0` while in the lobby.
+static LAST_BEAT: AtomicU64 = AtomicU64::new(0);
+
+/// Records that the game loop is running.
+pub(crate) fn beat() {
+    let started = STARTED.get_or_init(Instant::now);
+    let millis = started.elapsed().as_millis() as u64;
+    LAST_BEAT.store(millis.max(1), Ordering::Relaxed);
+}
+
+/// Binds the endpoint and answers probes in the background.
+pub(crate) fn serve(addr: SocketAddr) -> io::Result<()> {
+    let listener = TcpListener::bind(addr)?;
+    STARTED.get_or_init(Instant::now);
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            // A probe failing to read or write only fails itself.
+            let _ = answer(stream);
+        }
+    });
+    Ok(())
+}
+
+fn answer(mut stream: TcpStream) -> io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(1)))?;
+    let mut buf = [0u8; MAX_REQUEST];
+    let mut len = 0;
+    while !buf[..len].windows(4).any(|w| w == b"\r\n\r\n") && len < buf.len() {
+        match stream.read(&mut buf[len..])? {
+            0 => break,
+            n => len += n,
+        }
+    }
+
+    let request = String::from_utf8_lossy(&buf[..len]);
+    let mut words = request.split_ascii_whitespace();
+    let (status, body) = match (words.next(), words.next()) {
+        (Some("GET"), Some("/healthz")) => match LAST_BEAT.load(Ordering::Relaxed) {
+            0 => ("200 OK", "lobby\n".to_owned()),
+            last => {
+                let now = STARTED.get().map_or(0, |s| s.elapsed().as_millis() as u64);
+                if now.saturating_sub(last) > STALL.as_millis() as u64 {
+                    ("503 Service Unavailable", "stalled\n".to_owned())
+                } else {
+                    ("200 OK", "playing\n".to_owned())
+                }
+            }
+        },
+        (Some("GET"), Some("/metrics")) => ("200 OK", crate::budget::metrics()),
+        (Some("GET"), _) => ("404 Not Found", String::new()),
+        _ => ("405 Method Not Allowed", String::new()),
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\n\
+        Content-Type: text/plain\r\n\
+        Content-Length: {}\r\n\
+        Connection: close\r\n\r\n\
+        {body}",
+        body.len()
+    )
+}
@@ -0,0 +1,219 @@
+//! Clients joining the lobby, before the game starts.
+//!
+//! Clients connecting directly are checked against the password
+//! and the allowlist with their first packet, which also tells the
+//! players they claim and their capabilities.
+
+use std::net::{IpAddr, SocketAddr, ToSocketAddrs as _};
+
+use curseofrust::{
+    state::{BasicOpts, State},
+    Player, MAX_PLAYERS,
+};
+use curseofrust_msg::{client_msg, migration, reject, S2CData, C2S_SIZE};
+
+use crate::Client;
+
+/// What a client asks for with the packet it joins with.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct Request {
+    /// Number of players the client would control.
+    pub(crate) slots: u8,
+    /// First player the client claims, `0` for any.
+    pub(crate) claim: u8,
+    /// Capabilities of the client.
+    pub(crate) info: u8,
+}
+
+impl Request {
+    /// Reads the request of the given first packet, which asks for
+    /// nothing if shorter than a command, as of curseofwar clients.
+    /// Passwords past the command are ignored.
+    pub(crate) fn read(packet: &[u8]) -> Self {
+        match packet.get(..C2S_SIZE) {
+            Some(packet) => Self {
+                slots: packet[1],
+                claim: packet[2],
+                info: packet[C2S_SIZE - 1],
+            },
+            None => Self::default(),
+        }
+    }
+}
+
+/// Admits the client connecting directly from `peer` with the given
+/// first packet, reading its request.
+///
+/// Fails with the reason to reject the client, or with `None` if the
+/// packet asks to join nothing.
+pub(crate) fn admit(
+    packet: &[u8],
+    peer: SocketAddr,
+    allowed: &[IpAddr],
+    password: Option<&str>,
+) -> Result<Request, Option<u8>> {
+    if packet.first().is_none_or(|&msg| msg == 0) {
+        return Err(None);
+    }
+    match rejection(packet, peer, allowed, password) {
+        Some(reason) => Err(Some(reason)),
+        None => Ok(Request::read(packet)),
+    }
+}
+
+/// Gets the players to hand a client making the given request, after
+/// those of the clients joined before it.
+///
+/// Players are numbered in the order they are claimed, without
+/// claiming more than the `clients` the game has room for. Resumed
+/// games hand clients the players they had.
+pub(crate) fn claim(cl: &[Client], clients: usize, resumed: bool, request: Request) -> Vec<Player> {
+    let Request { slots, claim, .. } = request;
+    let claimed = cl.iter().map(|client| client.players.len()).sum::<usize>();
+    if resumed {
+        let taken = |p: &Player| cl.iter().any(|client| client.players.contains(p));
+        let first = (claim as usize).max(1);
+        let wanted = (first..first + (slots as usize).max(1))
+            .map(|p| Player(p as u32))
+            .collect::<Vec<_>>();
+        if claim != 0 && wanted.iter().all(|p| p.index().is_ok() && !taken(p)) {
+            wanted
+        } else {
+            (1..MAX_PLAYERS as u32)
+                .map(Player)
+                .filter(|p| !taken(p))
+                .take(wanted.len())
+                .collect()
+        }
+    } else {
+        let slots = (slots as usize).clamp(1, clients.saturating_sub(claimed).max(1));
+        (claimed..claimed + slots)
+            .map(|p| Player(p as u32 + 1))
+            .collect()
+    }
+}
+
+/// Resumes the game of the given state, handing the joined clients
+/// the players they claimed.
+pub(crate) fn resume(
+    b_opt: BasicOpts,
+    data: &S2CData,
+    cl: &[Client],
+) -> curseofrust::Result<State> {
+    let mut st = migration::resume(b_opt, data)?;
+    let players: Vec<_> = cl
+        .iter()
+        .flat_map(|client| client.players.iter().copied())
+        .collect();
+    for &pl in &players {
+        st.take_from_king(pl);
+    }
+    st.humans = players;
+    Ok(st)
+}
+
+/// Resolves the addresses and host names of the clients allowed
+/// to join.
+pub(crate) fn resolve_allowed(allow: &[String]) -> std::io::Result<Vec<IpAddr>> {
+    let mut allowed = vec![];
+    for entry in allow {
+        match entry.parse::<IpAddr>() {
+            Ok(ip) => allowed.push(ip),
+            Err(_) => allowed.extend(
+                (entry.as_str(), 0)
+                    .to_socket_addrs()?
+                    .map(|addr| addr.ip().to_canonical()),
+            ),
+        }
+    }
+    Ok(allowed)
+}
+
+/// Gets the reason to reject the client joining with the given
+/// first packet, if any.
+fn rejection(
+    packet: &[u8],
+    peer: SocketAddr,
+    allowed: &[IpAddr],
+    password: Option<&str>,
+) -> Option<u8> {
+    if !allowed.is_empty() && !allowed.contains(&peer.ip().to_canonical()) {
+        return Some(reject::NOT_ALLOWED);
+    }
+    let given = match packet {
+        [client_msg::CONNECT, ..] => packet.get(C2S_SIZE..),
+        _ => None,
+    };
+    match password {
+        Some(password) if given != Some(password.as_bytes()) => Some(reject::PASSWORD),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use curseofrust_msg::capability;
+
+    const PEER: ([u8; 4], u16) = ([192, 0, 2, 1], 19140);
+
+    /// A CONNECT packet claiming `slots` from player `claim`, with the
+    /// given capabilities and password.
+    fn connect(slots: u8, claim: u8, info: u8, password: &str) -> Vec<u8> {
+        let mut packet = vec![0; C2S_SIZE];
+        packet[0] = client_msg::CONNECT;
+        packet[1] = slots;
+        packet[2] = claim;
+        packet[C2S_SIZE - 1] = info;
+        packet.extend_from_slice(password.as_bytes());
+        packet
+    }
+
+    #[test]
+    fn admits_connect_with_password() {
+        let packet = connect(2, 3, capability::RLE | capability::PING, "hunter2");
+        assert_eq!(
+            admit(&packet, PEER.into(), &[], Some("hunter2")),
+            Ok(Request {
+                slots: 2,
+                claim: 3,
+                info: capability::RLE | capability::PING,
+            })
+        );
+        assert_eq!(
+            admit(&packet, PEER.into(), &[], Some("hunter3")),
+            Err(Some(reject::PASSWORD))
+        );
+        assert_eq!(
+            admit(&connect(1, 0, 0, ""), PEER.into(), &[], Some("hunter2")),
+            Err(Some(reject::PASSWORD))
+        );
+        // Passwords are ignored when the server asks for none.
+        assert!(admit(&packet, PEER.into(), &[], None).is_ok());
+    }
+
+    #[test]
+    fn rejects_clients_not_allowed() {
+        let packet = connect(1, 0, 0, "");
+        let allowed = [IpAddr::from([192, 0, 2, 1])];
+        assert!(admit(&packet, PEER.into(), &allowed, None).is_ok());
+        let mapped = SocketAddr::from(([0, 0, 0, 0, 0, 0xffff, 0xc000, 0x0201], 19140));
+        assert!(admit(&packet, mapped, &allowed, None).is_ok());
+        assert_eq!(
+            admit(&packet, ([192, 0, 2, 2], 19140).into(), &allowed, None),
+            Err(Some(reject::NOT_ALLOWED))
+        );
+    }
+
+    #[test]
+    fn short_packets_ask_for_nothing() {
+        let packet = [client_msg::IS_ALIVE, 0];
+        assert_eq!(
+            admit(&packet, PEER.into(), &[], None),
+            Ok(Request::default())
+        );
+        assert_eq!(admit(&[], PEER.into(), &[], None), Err(None));
+        assert_eq!(admit(&[0; C2S_SIZE], PEER.into(), &[], None), Err(None));
+    }
+}
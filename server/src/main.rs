@@ -1,28 +1,166 @@
 use std::{
-    cell::{Cell, RefCell, UnsafeCell},
-    fmt::Debug,
-    net::SocketAddr,
-    time::{Duration, SystemTime},
+    cell::{Cell, RefCell},
+    fmt::{Debug, Display},
+    io::IsTerminal as _,
+    net::{IpAddr, SocketAddr},
+    sync::OnceLock,
+    time::{Instant, SystemTime},
 };
 
 use async_executor::LocalExecutor;
-use curseofrust::{
-    state::{MultiplayerOpts, State},
-    Player, Speed,
+use curseofrust::{grid::Stencil, state::State, Player};
+use curseofrust_cli_parser::{MultiplayerOpts, Options, Profile, Rules};
+use curseofrust_msg::{
+    bytemuck, capability,
+    event::Event,
+    master::Listing,
+    reject, server_msg,
+    trace::{Flow, Tracer},
+    S2CData, C2S_SIZE, MAX_PASSWORD, S2C_SIZE,
+};
+use curseofrust_net_foundation::{
+    clock::{Clock, SystemClock},
+    reliable::Reliability,
+    Handle, Protocol,
 };
-use curseofrust_cli_parser::Options;
-use curseofrust_msg::{bytemuck, C2SData, S2CData, C2S_SIZE, S2C_SIZE};
-use curseofrust_net_foundation::{Connection, Handle, Protocol};
 
-const DURATION: Duration = Duration::from_millis(10);
+mod announce;
+mod budget;
+mod championship;
+mod conn;
+mod game;
+mod health;
+mod lobby;
+mod observer;
+mod relay;
+mod rpc;
+mod states;
 
 #[derive(Debug)]
-struct Client<'sock> {
+struct Client {
     id: u32,
     addr: SocketAddr,
-    pl: Player,
-    socket: UnsafeCell<Connection<'sock>>,
-    reads: Cell<usize>,
+    /// Player the client controls now, one of its `players`.
+    pl: Cell<Player>,
+    /// Players claimed by the client in the lobby.
+    players: Vec<Player>,
+    outbox: conn::Outbox,
+    /// Whether the client accepts run-length encoded states.
+    rle: bool,
+    /// Whether the client reassembles states split to fit the MTU.
+    fragment: bool,
+    /// Whether the client answers pings.
+    ping: bool,
+    /// Whether the client understands the landscape and road bits
+    /// of tile bytes.
+    tile_flags: bool,
+    /// Whether the client shows event notifications.
+    events: bool,
+    /// Whether the client may host in place of a lost host,
+    /// and is sent the rules of the game.
+    migrate: bool,
+    /// Pings sent to the client since its last answer.
+    pings_missed: Cell<u32>,
+    /// Whether the client stopped answering pings, and is no longer
+    /// sent to or read from.
+    dropped: Cell<bool>,
+    /// Sequence number of the last state acknowledged by the client,
+    /// `0` if none, in which case lost states are not resent.
+    acked_seq: Cell<u16>,
+    /// Whether the client is to be sent a state at once.
+    resync: Cell<bool>,
+    /// Time the last full state request of the client was answered.
+    resynced_at: Cell<Option<Instant>>,
+    /// Whether the state being broadcast is to be sent to the client.
+    state_due: Cell<bool>,
+    /// Time the last packet was received from the client.
+    last_seen: Cell<Instant>,
+    /// Whether an AI has taken over the client's country.
+    idle: Cell<bool>,
+    /// Whether the client surrendered and only watches the game.
+    spectator: Cell<bool>,
+    /// Whether the client voted to restart.
+    restart_vote: Cell<bool>,
+    /// Whether the client ended the turn in turn-based mode.
+    turn_ended: Cell<bool>,
+    /// Surrenders, restart votes, ends of turn, pauses and resumptions
+    /// of the client waiting to be handled by the game loop, in the
+    /// order they were received.
+    requests: RefCell<Vec<u8>>,
+}
+
+impl Client {
+    /// Creates a client joining with the given capabilities,
+    /// heard from at `now`.
+    fn new(
+        id: u32,
+        addr: SocketAddr,
+        players: Vec<Player>,
+        outbox: conn::Outbox,
+        info: u8,
+        protocol: Protocol,
+        now: Instant,
+    ) -> Self {
+        Self {
+            id,
+            addr,
+            pl: Cell::new(players[0]),
+            players,
+            outbox,
+            rle: info & capability::RLE != 0,
+            fragment: info & capability::FRAGMENT != 0 && protocol == Protocol::Udp,
+            ping: info & capability::PING != 0,
+            tile_flags: info & capability::TILE_FLAGS != 0,
+            events: info & capability::EVENTS != 0,
+            migrate: info & capability::MIGRATE != 0,
+            pings_missed: Cell::new(0),
+            dropped: Cell::new(false),
+            acked_seq: Cell::new(0),
+            resync: Cell::new(false),
+            resynced_at: Cell::new(None),
+            state_due: Cell::new(false),
+            last_seen: Cell::new(now),
+            idle: Cell::new(false),
+            spectator: Cell::new(false),
+            restart_vote: Cell::new(false),
+            turn_ended: Cell::new(false),
+            requests: Default::default(),
+        }
+    }
+
+    /// Switches the player the client controls to the next
+    /// of its players still playing, if any.
+    ///
+    /// See [`State::next_playing`].
+    fn switch_player(&self, st: &State) {
+        if let Some(next) = st.next_playing(&self.players, self.pl.get()) {
+            self.pl.set(next);
+        }
+    }
+
+    /// Queues the packet for the client.
+    #[inline]
+    fn send(&self, packet: &[u8]) {
+        self.outbox.push(conn::Frame::Owned(packet.to_vec()));
+    }
+}
+
+/// Tracer of frames exchanged with clients, and whether to color its
+/// lines, if `--trace-wire` is given.
+static TRACER: OnceLock<(Tracer, bool)> = OnceLock::new();
+
+/// Prints the frame exchanged with the given peer if tracing,
+/// colored by client on terminals.
+fn trace(peer: impl Display, client: Option<u32>, flow: Flow, frame: &[u8]) {
+    let Some((tracer, colored)) = TRACER.get() else {
+        return;
+    };
+    let line = tracer.line(&peer.to_string(), flow, frame);
+    match client.filter(|_| *colored) {
+        // Red to cyan, skipping black and white.
+        Some(id) => println!("\x1b[{}m{}\x1b[0m", 31 + id % 6, line),
+        None => println!("{}", line),
+    }
 }
 
 fn main() -> Result<(), DirectBoxedError> {
@@ -33,34 +171,81 @@ fn main() -> Result<(), DirectBoxedError> {
             .as_secs(),
     );
 
+    let options = curseofrust_cli_parser::parse_to_options(std::env::args_os())?;
+    // Canonical arguments of the game, to reproduce it.
+    let args = options.to_args().join(" ");
+    // Rules of the game, which a server taking over keeps.
+    let rules = Rules::of(&options).encode()?;
     let Options {
         basic: mut b_opt,
         multiplayer: m_opt,
         exit,
         protocol,
+        afk_timeout,
+        time_limit,
+        turns,
+        observe,
+        rpc,
+        trace_wire,
+        bind,
+        lobby_print,
+        healthz,
+        announce,
+        name,
+        warnings,
+        maps,
+        resume,
+        password,
+        allow,
         ..
-    } = curseofrust_cli_parser::parse_to_options(std::env::args_os())?;
+    } = options;
+    for warning in &warnings {
+        eprintln!("warning: {warning}");
+    }
     if exit {
         return Ok(());
     }
-
-    let MultiplayerOpts::Server { port } = m_opt else {
+    let championship = maps
+        .map(|path| {
+            let args: Vec<_> = std::env::args_os().collect();
+            championship::Championship::load(&path, &args)
+        })
+        .transpose()?;
+    if let Some(ref championship) = championship {
+        b_opt = championship.opts().clone();
+    }
+    // The state of a relayed game whose host was lost.
+    let resumed = resume
+        .map(|path| {
+            let bytes = std::fs::read(&path)?;
+            if bytes.len() != S2C_SIZE - 1 {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("{}: not a saved state", path.display()),
+                ));
+            }
+            Ok(bytemuck::pod_read_unaligned::<S2CData>(&bytes))
+        })
+        .transpose()?;
+    if trace_wire {
+        let _ = TRACER.set((Tracer::new(), std::io::stdout().is_terminal()));
+    }
+    if let Some(port) = rpc {
+        #[cfg(feature = "rpc")]
+        return Ok(rpc::serve(port, b_opt)?);
+        #[cfg(not(feature = "rpc"))]
         return Err(DirectBoxedError {
-            inner: "server information is required".into(),
+            inner: format!("rpc feature not enabled, cannot serve on port {port}").into(),
         });
-    };
-
-    let addr: SocketAddr = (
-        local_ip_address::local_ip().or_else(|_| local_ip_address::local_ipv6())?,
-        port,
-    )
-        .into();
+    }
 
     let protocol = match protocol {
         curseofrust_cli_parser::Protocol::Tcp => Protocol::Tcp,
         curseofrust_cli_parser::Protocol::Udp => Protocol::Udp,
         #[cfg(feature = "ws")]
         curseofrust_cli_parser::Protocol::WebSocket => Protocol::WebSocket,
+        #[cfg(feature = "http")]
+        curseofrust_cli_parser::Protocol::Http => Protocol::Http,
         _ => {
             return Err(DirectBoxedError {
                 inner: "given protocol is not supported in this build".into(),
@@ -68,94 +253,291 @@ fn main() -> Result<(), DirectBoxedError> {
         }
     };
 
+    let m_opt = match m_opt {
+        MultiplayerOpts::None => {
+            curseofrust_cli_parser::env_server_port().map_or(MultiplayerOpts::None, |port| {
+                MultiplayerOpts::Server { port, relay: None }
+            })
+        }
+        m_opt => m_opt,
+    };
+    let (port, relay_addr) = match m_opt {
+        MultiplayerOpts::Server { port, relay } => (port, relay),
+        MultiplayerOpts::Relay { port } => {
+            let addr: SocketAddr = (bind_ip(bind)?, port).into();
+            let handle = Handle::bind(addr, protocol)?;
+            let listener = handle.listen()?;
+            println!("[RELAY] relay listening on socket {}", addr);
+            relay::run(&listener);
+            return Ok(());
+        }
+        _ => {
+            return Err(DirectBoxedError {
+                inner: "server information is required".into(),
+            })
+        }
+    };
+
+    if password
+        .as_ref()
+        .is_some_and(|password| password.len() > MAX_PASSWORD)
+    {
+        return Err(DirectBoxedError {
+            inner: format!("password longer than {MAX_PASSWORD} bytes").into(),
+        });
+    }
+    // Relays forward neither passwords nor the addresses of clients.
+    if relay_addr.is_some() && (password.is_some() || !allow.is_empty()) {
+        return Err(DirectBoxedError {
+            inner: "clients joining through a relay cannot be checked".into(),
+        });
+    }
+    let allowed = lobby::resolve_allowed(&allow)?;
+
+    let addr: SocketAddr = (bind_ip(bind)?, port).into();
+    if let Some(port) = healthz {
+        health::serve((addr.ip(), port).into())?;
+        println!("[HEALTH] answering probes on port {}", port);
+    }
+
     let handle = Handle::bind(addr, protocol)?;
-    let listener = handle.listen()?;
+    let (listener, upstream) = if let Some(relay_addr) = relay_addr {
+        let mut upstream = futures_lite::future::block_on(handle.connect(relay_addr))?;
+        let mut packet = [0u8; C2S_SIZE];
+        packet[0] = curseofrust_msg::client_msg::RELAY_HOST;
+        futures_lite::future::block_on(upstream.send(&packet))?;
+        if lobby_print {
+            println!("[LOBBY] server hosting via relay {}", relay_addr);
+        }
+        (None, Some(upstream))
+    } else {
+        if lobby_print {
+            println!("[LOBBY] server listening on socket {}", addr);
+        }
+        (Some(handle.listen()?), None)
+    };
 
-    let mut cl: Vec<Client<'_>> = vec![];
+    if let Some(master) = announce {
+        let name = name
+            .or_else(|| Some(Profile::load().name).filter(|name| !name.is_empty()))
+            .unwrap_or_else(|| "curseofrust".to_owned());
+        let shape = match b_opt.shape {
+            Stencil::Rhombus => "rhombus",
+            Stencil::Rect => "rect",
+            Stencil::Hex => "hex",
+        };
+        println!("[ANNOUNCE] announcing {} to {}", name, master);
+        announce::spawn(
+            master,
+            Listing {
+                name,
+                // Clients of a relayed game join through the relay.
+                addr: relay_addr.unwrap_or(addr),
+                players: 0,
+                capacity: b_opt.clients as u32,
+                map: format!("{} {}x{}", shape, b_opt.width, b_opt.height),
+            },
+        );
+    }
+
+    #[cfg(feature = "observer")]
+    let observer = observe
+        .map(|port| {
+            println!("[OBSERVER] serving spectators on port {}", port);
+            async_io::Async::<std::net::TcpListener>::bind((addr.ip(), port))
+        })
+        .transpose()?;
+    #[cfg(feature = "observer")]
+    let observer_accepting = Cell::new(false);
+    #[cfg(not(feature = "observer"))]
+    if observe.is_some() {
+        return Err(DirectBoxedError {
+            inner: "observer feature not enabled".into(),
+        });
+    }
+
+    // Declared before the executor, whose tasks borrow it.
+    let st;
+    let executor = LocalExecutor::new();
+    let (commands_tx, commands) = async_channel::bounded(conn::COMMANDS);
+    let upstream = upstream.map(|upstream| {
+        let (tx, rx) = conn::relay_queue();
+        executor
+            .spawn(conn::run_relay(upstream, rx, commands_tx.clone()))
+            .detach();
+        tx
+    });
 
-    let mut c2s_buf = [0u8; C2S_SIZE];
+    let clock = SystemClock;
+    let mut cl: Vec<Client> = vec![];
 
-    println!("[LOBBY] server listening on socket {}", addr);
+    let mut c2s_buf = [0u8; C2S_SIZE + MAX_PASSWORD];
 
-    futures_lite::future::block_on(async {
-        'lobby: loop {
-            let Ok((mut connection, peer)) = listener.accept().await else {
-                continue;
+    futures_lite::future::block_on(executor.run(async {
+        loop {
+            // Either the connection of a new client, or its peer id on the relay.
+            let (connection, relay_id, peer, request) = if upstream.is_some() {
+                let Ok(conn::Command {
+                    from: conn::Source::Relayed(id),
+                    packet,
+                }) = commands.recv().await
+                else {
+                    continue;
+                };
+                if packet[0] == 0 {
+                    continue;
+                }
+                (
+                    None,
+                    Some(id),
+                    relay_addr.unwrap(),
+                    lobby::Request::read(&packet),
+                )
+            } else {
+                let listener = listener
+                    .as_ref()
+                    .expect("listener should exist without relay");
+                let Ok((mut connection, peer)) = listener.accept().await else {
+                    continue;
+                };
+                let Ok(nread) = connection.recv(&mut c2s_buf).await else {
+                    continue;
+                };
+                trace(peer, None, Flow::Received, &c2s_buf[..nread]);
+                match lobby::admit(&c2s_buf[..nread], peer, &allowed, password.as_deref()) {
+                    Ok(request) => (Some(connection), None, peer, request),
+                    Err(Some(reason)) => {
+                        let packet = [server_msg::CONN_REJECTED, reason];
+                        trace(peer, None, Flow::Sent, &packet);
+                        let _ = connection.send(&packet).await;
+                        let _ = connection.close().await;
+                        if lobby_print {
+                            let why = match reason {
+                                reject::PASSWORD => "wrong password",
+                                _ => "not allowed",
+                            };
+                            println!("[LOBBY] client@{} rejected: {}", peer, why);
+                        }
+                        continue;
+                    }
+                    Err(None) => continue,
+                }
             };
-            if let Ok(nread) = connection.recv(&mut c2s_buf).await {
-                if nread >= 1 && c2s_buf[0] > 0 {
-                    if !cl.iter().any(|rec| rec.addr == peer) {
-                        let id = cl.len() as u32;
-                        cl.push(Client {
-                            addr: peer,
-                            pl: Player(id + 1),
-                            id,
-                            socket: UnsafeCell::new(connection),
-                            reads: Cell::new(0),
-                        });
-
-                        println!("[LOBBY] client{}@{} connected", id, peer);
+
+            if !cl
+                .iter()
+                .any(|rec| rec.addr == peer && rec.outbox.relay_id() == relay_id)
+            {
+                let id = cl.len() as u32;
+                let claimed = cl.iter().map(|client| client.players.len()).sum::<usize>();
+                let players = lobby::claim(&cl, b_opt.clients, resumed.is_some(), request);
+                if players.is_empty() {
+                    continue;
+                }
+                let slots = players.len();
+                // Relays forward fixed-size packets only.
+                let reliable = request.info & capability::RELIABLE != 0
+                    && protocol == Protocol::Udp
+                    && relay_id.is_none();
+                let outbox = match connection {
+                    Some(connection) => {
+                        let (outbox, rx) = conn::Outbox::direct(id);
+                        let reliability = reliable.then(Reliability::new);
+                        let commands = commands_tx.clone();
+                        executor
+                            .spawn(conn::run(connection, id, reliability, rx, commands))
+                            .detach();
+                        outbox
                     }
+                    None => conn::Outbox::relayed(
+                        id,
+                        relay_id.expect("clients not connected directly should be relayed"),
+                        upstream
+                            .as_ref()
+                            .expect("relayed clients should have a relay"),
+                    ),
+                };
+                cl.push(Client::new(
+                    id,
+                    peer,
+                    players,
+                    outbox,
+                    request.info,
+                    protocol,
+                    clock.now(),
+                ));
 
-                    if cl.len() >= b_opt.clients {
-                        b_opt.clients = cl.len();
-                        println!(
-                            "[LOBBY] server mode switched to PLAY with {} clients",
-                            cl.len()
-                        );
-                        break 'lobby;
+                if lobby_print {
+                    println!("[LOBBY] client{}@{} connected", id, peer);
+                    if slots > 1 {
+                        println!("[LOBBY] client{} claimed {} players", id, slots);
                     }
                 }
+                announce::set_players((claimed + slots) as u32);
+
+                let new = cl.last().expect("the client should have been added");
+                for &pl in &new.players {
+                    game::notify(&cl, &Event::Joined(pl));
+                }
+            }
+
+            let claimed = cl.iter().map(|client| client.players.len()).sum::<usize>();
+            // Clients of resumed games are counted rather than players.
+            let joined = if resumed.is_some() { cl.len() } else { claimed };
+            if joined >= b_opt.clients {
+                b_opt.clients = claimed;
+                announce::stop();
+                if lobby_print {
+                    println!(
+                        "[LOBBY] server mode switched to PLAY with {} clients",
+                        cl.len()
+                    );
+                }
+                break;
             }
         }
-    });
+    }));
 
-    let st = RefCell::new(State::new(b_opt)?);
-    let mut time = 0i32;
-    let executor = LocalExecutor::new();
+    st = RefCell::new(match resumed {
+        Some(ref data) => {
+            let st = lobby::resume(b_opt.clone(), data, &cl)?;
+            println!("[PLAY] resumed from state {}", data.seq());
+            st
+        }
+        None => State::new(b_opt.clone())?,
+    });
+    println!("[PLAY] started with options: {}", args);
+    let settings = game::Settings {
+        time_limit,
+        turns,
+        afk_timeout,
+        championship,
+    };
+    let mut game = game::Game::start(&st, &cl, clock, b_opt, settings, resumed.as_ref());
+    let mut packet = vec![server_msg::RULES];
+    packet.extend_from_slice(rules.as_bytes());
+    for client in cl.iter().filter(|client| client.migrate) {
+        client.send(&packet);
+    }
 
     futures_lite::future::block_on(executor.run(async {
         loop {
-            let timer = async_io::Timer::after(DURATION);
-            time += 1;
-            if time >= 1600 {
-                time = 0
+            let timer = clock.timer(game.tick_time());
+            health::beat();
+            while let Ok(conn::Command { from, packet }) = commands.try_recv() {
+                game.apply(from, &packet);
             }
+            game.tick();
 
-            {
-                let mut st = st.borrow_mut();
-                if time.checked_rem(slowdown(st.speed)) == Some(0) && st.speed != Speed::Pause {
-                    st.kings_move();
-                    st.simulate();
-                    let data = S2CData::new(Default::default(), &st);
-
-                    for client in &cl {
-                        let mut data = data;
-                        data.set_player(client.pl);
-                        let mut buf = [0u8; S2C_SIZE];
-                        let (msg, od) = buf
-                            .split_first_mut()
-                            .expect("the buffer should longer than one byte");
-                        *msg = curseofrust_msg::server_msg::STATE;
-                        od.copy_from_slice(bytemuck::bytes_of(&data));
-                        let socket = &client.socket;
-                        executor
-                            .spawn(async move {
-                                let ptr = socket.get();
-                                let _ = unsafe { (*ptr).send(&buf).await };
-                            })
-                            .detach()
-                    }
+            #[cfg(feature = "observer")]
+            if let Some(ref listener) = observer {
+                if !observer_accepting.replace(true) {
+                    executor
+                        .spawn(observer::accept(listener, &observer_accepting, &st))
+                        .detach();
                 }
             }
 
-            for client in cl.iter() {
-                let reads = client.reads.get();
-                if reads < 2 {
-                    client.reads.set(reads + 1);
-                    executor.spawn(recv_fut(client, &st)).detach();
-                }
-            }
             timer.await;
         }
     }));
@@ -163,27 +545,12 @@ fn main() -> Result<(), DirectBoxedError> {
     Ok(())
 }
 
-async fn recv_fut(cl: &Client<'_>, st: &RefCell<State>) {
-    let mut buf = [0u8; C2S_SIZE];
-    let sptr = cl.socket.get();
-    match unsafe { (*sptr).recv(&mut buf).await } {
-        Ok(C2S_SIZE) => {
-            let (&msg, od) = buf
-                .split_first()
-                .expect("the buffer should longer than one byte");
-            let data: C2SData = *bytemuck::from_bytes(od);
-            let mut st = st.borrow_mut();
-            if let Err(e) = curseofrust_msg::apply_c2s_msg(&mut st, cl.pl, msg, data) {
-                eprintln!("[PLAY] error performing action for player{}: {}", cl.id, e)
-            }
-        }
-        Err(_) | Ok(0) => {}
-        Ok(nread) => eprintln!(
-            "[PLAY] error recv packet from client{}, expected {} bytes, have {}",
-            cl.id, C2S_SIZE, nread
-        ),
-    }
-    cl.reads.set(cl.reads.get() - 1);
+/// Gets the address to bind, detecting the local one if not given.
+fn bind_ip(bind: Option<IpAddr>) -> Result<IpAddr, local_ip_address::Error> {
+    bind.map_or_else(
+        || local_ip_address::local_ip().or_else(|_| local_ip_address::local_ipv6()),
+        Ok,
+    )
 }
 
 struct DirectBoxedError {
@@ -210,17 +577,3 @@ where
 }
 
 type BoxedError = Box<dyn std::error::Error>;
-
-#[inline]
-fn slowdown(speed: Speed) -> i32 {
-    match speed {
-        Speed::Pause => 0,
-        Speed::Slowest => 160,
-        Speed::Slower => 80,
-        Speed::Slow => 40,
-        Speed::Normal => 20,
-        Speed::Fast => 10,
-        Speed::Faster => 5,
-        Speed::Fastest => 2,
-    }
-}
@@ -0,0 +1,178 @@
+//! Read-only observer endpoint.
+//!
+//! Serves the game state as JSON over HTTP, so that spectator
+//! web pages can render live matches without implementing
+//! the binary protocol:
+//!
+//! - `GET /state` responds with the current state.
+//! - `GET /events` streams the state as server-sent events
+//!   whenever it changes.
+
+#![cfg(feature = "observer")]
+
+use std::{
+    cell::{Cell, RefCell},
+    net::{TcpListener, TcpStream},
+    time::Duration,
+};
+
+use async_io::{Async, Timer};
+use curseofrust::{grid::Tile, state::State, MAX_PLAYERS};
+use curseofrust_msg::TileClass;
+use futures_lite::{AsyncReadExt as _, AsyncWriteExt as _};
+use serde::Serialize;
+
+/// Interval at which event streams check for a new state.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// Longest request accepted, headers included.
+const MAX_REQUEST: usize = 1024;
+
+#[derive(Serialize)]
+pub(crate) struct StateView {
+    time: u64,
+    width: u32,
+    height: u32,
+    /// Gold of each player, indexed by player.
+    gold: [u64; MAX_PLAYERS],
+    /// Tiles indexed by X, then Y.
+    tiles: Vec<Vec<TileView>>,
+    winner: Option<u32>,
+}
+
+#[derive(Serialize)]
+struct TileView {
+    class: &'static str,
+    owner: u32,
+    /// Population of each player, indexed by player.
+    units: [u16; MAX_PLAYERS],
+}
+
+impl From<&Tile> for TileView {
+    fn from(tile: &Tile) -> Self {
+        Self {
+            class: match TileClass::from(tile) {
+                TileClass::Void => "void",
+                TileClass::Mountain => "mountain",
+                TileClass::Mine => "mine",
+                TileClass::Grassland => "grassland",
+                TileClass::Village => "village",
+                TileClass::Town => "town",
+                TileClass::Fortress => "fortress",
+                TileClass::Other => "unknown",
+            },
+            owner: tile.owner().0,
+            units: *tile.units(),
+        }
+    }
+}
+
+/// Gets the view of the state served to spectators.
+pub(crate) fn view(st: &State) -> StateView {
+    StateView {
+        time: st.time,
+        width: st.grid.width(),
+        height: st.grid.height(),
+        gold: st.countries.each_ref().map(|c| c.gold),
+        tiles: st
+            .grid
+            .raw_tiles()
+            .iter()
+            .map(|col| col.iter().map(TileView::from).collect())
+            .collect(),
+        winner: st.winner().map(|p| p.0),
+    }
+}
+
+fn to_json(st: &State) -> String {
+    serde_json::to_string(&view(st)).expect("the state view should be serializable")
+}
+
+/// Accepts an observer on the given listener and serves it.
+///
+/// `accepting` is cleared once an observer is accepted,
+/// so that the game loop spawns the next acceptor.
+pub(crate) async fn accept(
+    listener: &Async<TcpListener>,
+    accepting: &Cell<bool>,
+    st: &RefCell<State>,
+) {
+    let accepted = listener.accept().await;
+    accepting.set(false);
+    let Ok((stream, peer)) = accepted else {
+        return;
+    };
+    if let Err(e) = serve(stream, st).await {
+        eprintln!("[OBSERVER] error serving {}: {}", peer, e);
+    }
+}
+
+async fn serve(mut stream: Async<TcpStream>, st: &RefCell<State>) -> std::io::Result<()> {
+    let mut buf = [0u8; MAX_REQUEST];
+    let mut len = 0;
+    while !buf[..len].windows(4).any(|w| w == b"\r\n\r\n") {
+        if len == buf.len() {
+            return respond(&mut stream, "413 Content Too Large", "text/plain", "").await;
+        }
+        match stream.read(&mut buf[len..]).await? {
+            0 => return Ok(()),
+            n => len += n,
+        }
+    }
+
+    let request = String::from_utf8_lossy(&buf[..len]);
+    let mut words = request.split_ascii_whitespace();
+    match (words.next(), words.next()) {
+        (Some("GET"), Some("/state")) => {
+            let json = to_json(&st.borrow());
+            respond(&mut stream, "200 OK", "application/json", &json).await
+        }
+        (Some("GET"), Some("/events")) => {
+            stream
+                .write_all(
+                    b"HTTP/1.1 200 OK\r\n\
+                    Content-Type: text/event-stream\r\n\
+                    Cache-Control: no-cache\r\n\
+                    Access-Control-Allow-Origin: *\r\n\r\n",
+                )
+                .await?;
+            let mut last = None;
+            loop {
+                let json = {
+                    let st = st.borrow();
+                    // The winner may change while paused.
+                    let version = (st.time, st.winner());
+                    (last != Some(version)).then(|| {
+                        last = Some(version);
+                        to_json(&st)
+                    })
+                };
+                if let Some(json) = json {
+                    stream
+                        .write_all(format!("data: {json}\n\n").as_bytes())
+                        .await?;
+                }
+                Timer::after(POLL_INTERVAL).await;
+            }
+        }
+        (Some("GET"), _) => respond(&mut stream, "404 Not Found", "text/plain", "").await,
+        _ => respond(&mut stream, "405 Method Not Allowed", "text/plain", "").await,
+    }
+}
+
+async fn respond(
+    stream: &mut Async<TcpStream>,
+    status: &str,
+    content_type: &str,
+    body: &str,
+) -> std::io::Result<()> {
+    let response = format!(
+        "HTTP/1.1 {status}\r\n\
+        Content-Type: {content_type}\r\n\
+        Content-Length: {}\r\n\
+        Access-Control-Allow-Origin: *\r\n\
+        Connection: close\r\n\r\n\
+        {body}",
+        body.len()
+    );
+    stream.write_all(response.as_bytes()).await
+}
@@ -0,0 +1,402 @@
+//! Relay mode.
+//!
+//! A relay forwards game traffic between a host and its clients,
+//! so that neither of them has to accept inbound connections.
+//! Packets exchanged with the host are prefixed with the peer id
+//! of the client they come from or go to.
+//!
+//! Each connection is owned by a task of its own, and the others
+//! queue the packets to forward to it.
+//!
+//! A host gone in the middle of a game, or silent while pinging its
+//! peers, is taken as lost, and one of the peers is elected to host
+//! in its place, see the `migration` module of `curseofrust-msg`.
+
+use std::{
+    cell::{Cell, RefCell},
+    time::{Duration, Instant},
+};
+
+use async_channel::{Receiver, Sender};
+use async_executor::LocalExecutor;
+use curseofrust_msg::{
+    client_msg,
+    migration::{self, Candidate, ELECTION_WINDOW},
+    reject, server_msg, C2S_SIZE, CANDIDATE_SIZE, PING_INTERVAL, RELAY_HEADER_SIZE, S2C_SIZE,
+};
+use curseofrust_net_foundation::{
+    clock::{Clock, SystemClock},
+    Connection, Listener, RecvOr,
+};
+
+/// Packets queued for a connection before newer ones are dropped.
+const QUEUE: usize = 256;
+/// Silence after which a host pinging a connected peer is taken
+/// as lost.
+///
+/// Hosts ping such peers every [`PING_INTERVAL`], even while paused,
+/// so this is shorter than they take to give up on the host
+/// themselves. Hosts of peers not answering pings may stay silent
+/// for long, and are only lost once disconnected.
+const HOST_TIMEOUT: Duration = Duration::from_secs(PING_INTERVAL.as_secs() * 2);
+
+/// Queue of packets to a connection.
+type Peer = Sender<Vec<u8>>;
+
+/// Candidates offering to host in place of a lost host.
+#[derive(Debug)]
+struct Election {
+    started: Instant,
+    candidates: Vec<Candidate>,
+}
+
+impl Election {
+    fn new(started: Instant) -> Self {
+        Self {
+            started,
+            candidates: vec![],
+        }
+    }
+
+    /// Takes the offer of peer `id` made with the given frame at
+    /// `now`, in place of any earlier one.
+    fn offer(&mut self, id: u8, frame: &[u8], now: Instant) {
+        let latency = now.saturating_duration_since(self.started);
+        if let Some(candidate) = Candidate::read(id, frame, latency) {
+            self.candidates.retain(|c| c.peer != id);
+            self.candidates.push(candidate);
+        }
+    }
+
+    /// Elects the candidate to host among those still connected.
+    fn elect<'a>(&self, peers: &'a [(u8, Peer)]) -> Option<(Candidate, &'a Peer)> {
+        let connected = self
+            .candidates
+            .iter()
+            .filter(|c| peers.iter().any(|(id, _)| *id == c.peer))
+            .copied()
+            .collect::<Vec<_>>();
+        let elected = *migration::elect(&connected)?;
+        let (_, peer) = peers.iter().find(|(id, _)| *id == elected.peer)?;
+        Some((elected, peer))
+    }
+}
+
+/// Runs the relay on the given listener.
+pub(crate) fn run(listener: &Listener<'_>) {
+    let host: RefCell<Option<Peer>> = RefCell::new(None);
+    let peers: RefCell<Vec<(u8, Peer)>> = RefCell::new(vec![]);
+    let election: RefCell<Option<Election>> = RefCell::new(None);
+    let next_id = Cell::new(0u8);
+    let executor = LocalExecutor::new();
+
+    let mut c2s_buf = [0u8; C2S_SIZE];
+
+    futures_lite::future::block_on(executor.run(async {
+        loop {
+            let Ok((mut connection, addr)) = listener.accept().await else {
+                continue;
+            };
+            let Ok(nread) = connection.recv(&mut c2s_buf).await else {
+                continue;
+            };
+
+            match c2s_buf[..nread] {
+                [client_msg::RELAY_HOST, ..] => {
+                    let (peer, rx) = async_channel::bounded(QUEUE);
+                    if let Some(old) = host.borrow_mut().replace(peer.clone()) {
+                        old.close();
+                    }
+                    election.take();
+                    println!("[RELAY] host@{} registered", addr);
+                    executor
+                        .spawn(host_fut(connection, peer, rx, &host, &peers, &election))
+                        .detach();
+                }
+                [msg, ..] if msg > 0 => {
+                    let Some(id) = free_id(next_id.get(), &peers.borrow()) else {
+                        println!("[RELAY] peer@{} rejected: relay full", addr);
+                        let _ = connection
+                            .send(&[server_msg::CONN_REJECTED, reject::FULL])
+                            .await;
+                        let _ = connection.close().await;
+                        continue;
+                    };
+                    next_id.set(id.wrapping_add(1));
+                    let (peer, rx) = async_channel::bounded(QUEUE);
+                    peers.borrow_mut().push((id, peer));
+                    println!("[RELAY] peer{}@{} connected", id, addr);
+
+                    forward_to_host(&host, id, &c2s_buf[..nread]);
+                    executor
+                        .spawn(peer_fut(id, connection, rx, &host, &peers, &election))
+                        .detach();
+                }
+                _ => {}
+            }
+        }
+    }))
+}
+
+/// Forwards packets from the host to the addressed peers, and sends
+/// those queued for the host.
+async fn host_fut(
+    mut connection: Connection<'_>,
+    host: Peer,
+    rx: Receiver<Vec<u8>>,
+    current: &RefCell<Option<Peer>>,
+    peers: &RefCell<Vec<(u8, Peer)>>,
+    election: &RefCell<Option<Election>>,
+) {
+    let clock = SystemClock;
+    let mut buf = [0u8; RELAY_HEADER_SIZE + S2C_SIZE];
+    // Time the host last sent anything, as hosts stay silent until
+    // their game starts.
+    let mut heard_at = None;
+    // Peers the host pinged, of which the host is heard from
+    // regularly while any is connected.
+    let mut pinged = vec![];
+    loop {
+        let queued = async { rx.recv().await.map(Some) };
+        let pinging = pinging(&pinged, &peers.borrow());
+        let silence = async {
+            match heard_at {
+                Some(at) if pinging => {
+                    clock
+                        .timer(HOST_TIMEOUT.saturating_sub(clock.elapsed(at)))
+                        .await
+                }
+                _ => std::future::pending().await,
+            }
+            Ok(None)
+        };
+        match connection
+            .recv_or(&mut buf, futures_lite::future::or(queued, silence))
+            .await
+        {
+            RecvOr::Recv(Ok(nread)) if nread > RELAY_HEADER_SIZE => {
+                heard_at = Some(clock.now());
+                let id = buf[0];
+                if buf[RELAY_HEADER_SIZE..nread] == [server_msg::PING] && !pinged.contains(&id) {
+                    pinged.push(id);
+                }
+                if let Some((_, peer)) = peers.borrow().iter().find(|(i, _)| *i == id) {
+                    let _ = peer.try_send(buf[RELAY_HEADER_SIZE..nread].to_vec());
+                }
+            }
+            RecvOr::Recv(Ok(RELAY_HEADER_SIZE)) => {
+                heard_at = Some(clock.now());
+                let id = buf[0];
+                if let Some((_, peer)) = peers.borrow().iter().find(|(i, _)| *i == id) {
+                    peer.close();
+                }
+            }
+            RecvOr::Recv(Err(_) | Ok(0)) | RecvOr::Other(Err(_) | Ok(None)) => break,
+            RecvOr::Recv(Ok(_)) => {}
+            RecvOr::Other(Ok(Some(packet))) => {
+                let _ = connection.send(&packet).await;
+            }
+        }
+    }
+
+    {
+        let mut current = current.borrow_mut();
+        if !current.as_ref().is_some_and(|h| h.same_channel(&host)) {
+            return;
+        }
+        *current = None;
+        println!("[RELAY] host disconnected");
+    }
+    if heard_at.is_some() && !peers.borrow().is_empty() {
+        migrate(peers, election).await;
+    }
+}
+
+/// Tells the peers that the host was lost, and elects one of those
+/// offering to host in its place, unless a host registers first.
+async fn migrate(peers: &RefCell<Vec<(u8, Peer)>>, election: &RefCell<Option<Election>>) {
+    let clock = SystemClock;
+    *election.borrow_mut() = Some(Election::new(clock.now()));
+    for (_, peer) in peers.borrow().iter() {
+        let _ = peer.try_send(vec![server_msg::HOST_LOST]);
+    }
+    clock.timer(ELECTION_WINDOW).await;
+
+    let Some(election) = election.take() else {
+        return;
+    };
+    let peers = peers.borrow();
+    match election.elect(&peers) {
+        Some((candidate, peer)) => {
+            let _ = peer.try_send(vec![server_msg::ELECTED, peers.len() as u8]);
+            println!(
+                "[RELAY] peer{} elected to host from state {}",
+                candidate.peer, candidate.seq
+            );
+        }
+        None => println!("[RELAY] no peer can host, game lost"),
+    }
+}
+
+/// Forwards packets from a peer to the host, and sends those queued
+/// for the peer.
+async fn peer_fut(
+    id: u8,
+    mut connection: Connection<'_>,
+    rx: Receiver<Vec<u8>>,
+    host: &RefCell<Option<Peer>>,
+    peers: &RefCell<Vec<(u8, Peer)>>,
+    election: &RefCell<Option<Election>>,
+) {
+    let clock = SystemClock;
+    let mut buf = [0u8; CANDIDATE_SIZE];
+    loop {
+        match connection.recv_or(&mut buf, rx.recv()).await {
+            RecvOr::Recv(Ok(0) | Err(_)) | RecvOr::Other(Err(_)) => break,
+            RecvOr::Recv(Ok(nread)) if buf[0] == client_msg::CANDIDATE => {
+                if let Some(election) = election.borrow_mut().as_mut() {
+                    election.offer(id, &buf[..nread], clock.now());
+                }
+            }
+            RecvOr::Recv(Ok(nread)) => forward_to_host(host, id, &buf[..nread]),
+            RecvOr::Other(Ok(packet)) => {
+                let _ = connection.send(&packet).await;
+            }
+        }
+    }
+    peers.borrow_mut().retain(|(i, _)| *i != id);
+    let _ = connection.close().await;
+    println!("[RELAY] peer{} disconnected", id);
+}
+
+/// First id from `next` on not taken by any of the peers,
+/// if any is left.
+fn free_id(next: u8, peers: &[(u8, Peer)]) -> Option<u8> {
+    (0..=u8::MAX)
+        .map(|offset| next.wrapping_add(offset))
+        .find(|id| peers.iter().all(|(taken, _)| taken != id))
+}
+
+/// Whether any of the `pinged` peers is still connected.
+fn pinging(pinged: &[u8], peers: &[(u8, Peer)]) -> bool {
+    peers.iter().any(|(id, _)| pinged.contains(id))
+}
+
+fn forward_to_host(host: &RefCell<Option<Peer>>, id: u8, packet: &[u8]) {
+    let Some(ref host) = *host.borrow() else {
+        return;
+    };
+    let len = RELAY_HEADER_SIZE + packet.len().min(C2S_SIZE);
+    let mut buf = vec![0u8; len];
+    buf[0] = id;
+    buf[RELAY_HEADER_SIZE..].copy_from_slice(&packet[..len - RELAY_HEADER_SIZE]);
+    let _ = host.try_send(buf);
+}
+
+#[cfg(test)]
+mod tests {
+    use curseofrust::{
+        state::{BasicOpts, State},
+        Player,
+    };
+    use curseofrust_msg::{S2CData, C2S_SIZE};
+    use curseofrust_net_foundation::{clock::MockClock, Protocol};
+
+    use super::*;
+    use crate::{conn::Outbox, lobby, Client};
+
+    fn peers(ids: &[u8]) -> Vec<(u8, Peer)> {
+        ids.iter()
+            .map(|&id| (id, async_channel::bounded(QUEUE).0))
+            .collect()
+    }
+
+    #[test]
+    fn ids_of_connected_peers_are_skipped() {
+        assert_eq!(free_id(0, &[]), Some(0));
+        assert_eq!(free_id(1, &peers(&[1, 2, 4])), Some(3));
+        assert_eq!(free_id(255, &peers(&[255, 0])), Some(1));
+        let ids: Vec<u8> = (0..=u8::MAX).collect();
+        assert_eq!(free_id(7, &peers(&ids)), None);
+    }
+
+    #[test]
+    fn silence_counts_while_pinged_peers_stay() {
+        let mut peers = peers(&[0, 1, 2]);
+        assert!(!pinging(&[], &peers));
+        assert!(pinging(&[1], &peers));
+        peers.retain(|(id, _)| *id != 1);
+        assert!(!pinging(&[1], &peers));
+    }
+
+    #[test]
+    fn elected_peer_resumes_with_rejoining_clients() {
+        let mut b_opt = BasicOpts::default();
+        b_opt.seed = 5;
+        b_opt.clients = 3;
+        let mut st = State::new(b_opt).unwrap();
+        st.step_n(100);
+        let mut older = S2CData::new(Player(2), &st);
+        older.set_seq(41);
+        st.step_n(10);
+        let state = |player, seq| {
+            let mut data = S2CData::new(Player(player), &st);
+            data.set_seq(seq);
+            data
+        };
+
+        // Peers 0 and 2 agree on the newest state, and peer 2
+        // answers first. Peer 1 lags behind, though quicker.
+        let clock = MockClock::new();
+        let mut election = Election::new(clock.now());
+        let ms = Duration::from_millis;
+        clock.advance(ms(10));
+        election.offer(1, &migration::candidate_frame(&older), clock.now());
+        clock.advance(ms(20));
+        election.offer(2, &migration::candidate_frame(&state(3, 42)), clock.now());
+        // Frames of other sizes are no offers.
+        election.offer(2, &[client_msg::CANDIDATE; C2S_SIZE], clock.now());
+        clock.advance(ms(20));
+        election.offer(0, &migration::candidate_frame(&state(1, 42)), clock.now());
+
+        let mut peers = peers(&[0, 1, 2]);
+        let elected = election.elect(&peers).map(|(c, _)| c.peer);
+        assert_eq!(elected, Some(2));
+        // Candidates gone before the election are not elected.
+        peers.retain(|(id, _)| *id != 2);
+        let (elected, _) = election.elect(&peers).unwrap();
+        assert_eq!((elected.peer, elected.seq), (0, 42));
+        assert_eq!(elected.latency, ms(50));
+
+        // The elected peer resumes its state, and the clients join
+        // again in any order, claiming the players they had.
+        let data = state(1, 42);
+        let mut cl = vec![];
+        for (id, claim) in [(0, 2), (1, 1)] {
+            let packet = [client_msg::IS_ALIVE, 1, claim, 0];
+            let addr = ([127, 0, 0, 1], 19140 + id as u16).into();
+            let request = lobby::admit(&packet, addr, &[], None).unwrap();
+            let players = lobby::claim(&cl, 3, true, request);
+            let (outbox, _) = Outbox::direct(id);
+            cl.push(Client::new(
+                id,
+                addr,
+                players,
+                outbox,
+                request.info,
+                Protocol::Udp,
+                clock.now(),
+            ));
+        }
+        assert_eq!(cl[0].players, [Player(2)]);
+        assert_eq!(cl[1].players, [Player(1)]);
+
+        let resumed = lobby::resume(BasicOpts::default(), &data, &cl).unwrap();
+        assert_eq!(S2CData::new(Player(1), &resumed).digest(), elected.digest);
+        assert_eq!(resumed.humans, [Player(2), Player(1)]);
+        assert!(resumed.king(Player(1)).is_none());
+        assert!(resumed.king(Player(2)).is_none());
+        // The player of the client gone is left to an AI.
+        assert!(resumed.king(Player(3)).is_some());
+    }
+}
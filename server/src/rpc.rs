@@ -0,0 +1,225 @@
+//! Local JSON-RPC control endpoint.
+//!
+//! Lets tools written in other languages drive the engine directly,
+//! without hosting a game. Requests and responses are JSON-RPC 2.0
+//! objects, one per line, over TCP on the loopback interface:
+//!
+//! - `new_game` starts a game. `args` takes command line arguments
+//!   such as `["-W", "30", "-R", "42"]`, defaulting to the arguments
+//!   of the server.
+//! - `step` advances the game by `steps` steps, `1` by default.
+//! - `apply_command` applies `command` of `player` at `x`, `y`, where
+//!   `command` is one of `build`, `flag_on`, `flag_off`, `flag_off_all`,
+//!   `flag_off_half`, `flag_cities`, `flag_frontline`, `build_road`,
+//!   `queue_build` and `rally`.
+//!   `info` is the number of flags of `flag_on`, `1` to queue and `0`
+//!   to unqueue for `queue_build`, and `target` the `[x, y]` rally
+//!   point of `rally`.
+//! - `get_state` gets the state, as served to spectators.
+
+#![cfg(feature = "rpc")]
+
+use std::{
+    io::{BufRead as _, BufReader, Write as _},
+    net::{Ipv4Addr, TcpListener, TcpStream},
+};
+
+use curseofrust::{
+    state::{BasicOpts, State},
+    Player, Pos,
+};
+use curseofrust_msg::{client_msg, C2SData};
+use serde_json::{json, Value};
+
+use crate::observer;
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+/// The request is valid, but the engine rejected it.
+const ENGINE_ERROR: i64 = -32000;
+
+struct Engine {
+    /// Options of games started without arguments.
+    b_opt: BasicOpts,
+    state: Option<State>,
+}
+
+type RpcResult = Result<Value, (i64, String)>;
+
+fn invalid_params(msg: impl Into<String>) -> (i64, String) {
+    (INVALID_PARAMS, msg.into())
+}
+
+impl Engine {
+    fn state(&mut self) -> Result<&mut State, (i64, String)> {
+        self.state
+            .as_mut()
+            .ok_or_else(|| (ENGINE_ERROR, "no game, call new_game first".to_owned()))
+    }
+
+    fn call(&mut self, method: &str, params: &Value) -> RpcResult {
+        match method {
+            "new_game" => {
+                let b_opt = match params.get("args") {
+                    Some(args) => {
+                        let args = args
+                            .as_array()
+                            .and_then(|a| a.iter().map(Value::as_str).collect::<Option<Vec<_>>>())
+                            .ok_or_else(|| invalid_params("args must be an array of strings"))?;
+                        curseofrust_cli_parser::parse_to_options(
+                            std::iter::once("curseofrust").chain(args),
+                        )
+                        .map_err(|e| invalid_params(e.to_string()))?
+                        .basic
+                    }
+                    None => self.b_opt.clone(),
+                };
+                let state = State::new(b_opt).map_err(|e| (ENGINE_ERROR, e.to_string()))?;
+                let result = json!({
+                    "width": state.grid.width(),
+                    "height": state.grid.height(),
+                    "seed": state.seed,
+                });
+                self.state = Some(state);
+                Ok(result)
+            }
+            "step" => {
+                let steps = match params.get("steps") {
+                    Some(steps) => steps
+                        .as_u64()
+                        .and_then(|s| u32::try_from(s).ok())
+                        .ok_or_else(|| invalid_params("steps must be a non-negative integer"))?,
+                    None => 1,
+                };
+                let st = self.state()?;
+                st.step_n(steps);
+                Ok(json!({
+                    "time": st.time,
+                    "winner": st.winner().map(|p| p.0),
+                }))
+            }
+            "apply_command" => {
+                let int = |key: &str| params.get(key).and_then(Value::as_u64);
+                let player = int("player")
+                    .and_then(|p| u32::try_from(p).ok())
+                    .ok_or_else(|| invalid_params("missing player"))?;
+                let st = self.state()?;
+                let coord = |key: &str, len: u32| {
+                    let value = params
+                        .get(key)
+                        .ok_or_else(|| invalid_params(format!("missing {key}")))?;
+                    value
+                        .as_u64()
+                        .filter(|&c| c < len as u64)
+                        .map(|c| c as i32)
+                        .ok_or_else(|| invalid_params(format!("{key} is outside the grid")))
+                };
+                let pos = Pos(coord("x", st.grid.width())?, coord("y", st.grid.height())?);
+                let info = int("info").unwrap_or_default().min(u8::MAX as u64) as u8;
+                let (msg, data) = match params.get("command").and_then(Value::as_str) {
+                    Some("build") => (client_msg::BUILD, (pos, 0).into()),
+                    Some("flag_on") => (client_msg::FLAG_ON, (pos, info).into()),
+                    Some("flag_off") => (client_msg::FLAG_OFF, (pos, 0).into()),
+                    Some("flag_off_all") => (client_msg::FLAG_OFF_ALL, (pos, 0).into()),
+                    Some("flag_off_half") => (client_msg::FLAG_OFF_HALF, (pos, 0).into()),
+                    Some("flag_cities") => (client_msg::FLAG_CITIES, (pos, 0).into()),
+                    Some("flag_frontline") => (client_msg::FLAG_FRONTLINE, (pos, 0).into()),
+                    Some("build_road") => (client_msg::BUILD_ROAD, (pos, 0).into()),
+                    Some("queue_build") => (client_msg::QUEUE_BUILD, (pos, info.min(1)).into()),
+                    Some("rally") => {
+                        let target = match params.get("target") {
+                            None | Some(Value::Null) => None,
+                            Some(target) => match target.as_array().map(Vec::as_slice) {
+                                Some([x, y]) => x
+                                    .as_i64()
+                                    .and_then(|x| i32::try_from(x).ok())
+                                    .zip(y.as_i64().and_then(|y| i32::try_from(y).ok())),
+                                _ => None,
+                            }
+                            .map(|(x, y)| Some(Pos(x, y)))
+                            .ok_or_else(|| invalid_params("target must be [x, y]"))?,
+                        };
+                        let data = C2SData::rally(pos, target)
+                            .ok_or_else(|| invalid_params("rally point out of reach"))?;
+                        (client_msg::RALLY, data)
+                    }
+                    _ => return Err(invalid_params("unknown command")),
+                };
+                curseofrust_msg::apply_c2s_msg(st, Player(player), msg, data)
+                    .map_err(|e| (ENGINE_ERROR, e.to_string()))?;
+                Ok(Value::Null)
+            }
+            "get_state" => {
+                let st = self.state()?;
+                Ok(serde_json::to_value(observer::view(st))
+                    .expect("the state view should be serializable"))
+            }
+            _ => Err((METHOD_NOT_FOUND, format!("unknown method: {method}"))),
+        }
+    }
+
+    /// Handles a request line, and gets the response line if any.
+    fn handle(&mut self, line: &str) -> Option<String> {
+        let (id, result) = match serde_json::from_str::<Value>(line) {
+            Ok(Value::Object(request)) => {
+                let id = request.get("id").cloned();
+                let result = match request.get("method").and_then(Value::as_str) {
+                    Some(method) => {
+                        self.call(method, request.get("params").unwrap_or(&Value::Null))
+                    }
+                    None => Err((INVALID_REQUEST, "missing method".to_owned())),
+                };
+                // Notifications are not answered.
+                (id?, result)
+            }
+            Ok(_) => (
+                Value::Null,
+                Err((INVALID_REQUEST, "request must be an object".to_owned())),
+            ),
+            Err(e) => (Value::Null, Err((PARSE_ERROR, e.to_string()))),
+        };
+        let response = match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err((code, message)) => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": code, "message": message },
+            }),
+        };
+        Some(response.to_string())
+    }
+
+    fn serve(&mut self, stream: TcpStream) -> std::io::Result<()> {
+        let mut out = stream.try_clone()?;
+        for line in BufReader::new(stream).lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            if let Some(response) = self.handle(&line) {
+                writeln!(out, "{}", response)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Serves the engine to one local client at a time on the given port.
+///
+/// Games outlive connections, so a tool may reconnect and go on.
+pub(crate) fn serve(port: u16, b_opt: BasicOpts) -> std::io::Result<()> {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))?;
+    println!("[RPC] serving the engine on port {}", port);
+    let mut engine = Engine { b_opt, state: None };
+    for stream in listener.incoming() {
+        let Ok(stream) = stream else {
+            continue;
+        };
+        if let Err(e) = engine.serve(stream) {
+            eprintln!("[RPC] error serving: {}", e);
+        }
+    }
+    Ok(())
+}
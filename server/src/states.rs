@@ -0,0 +1,89 @@
+//! State frames sent to clients, encoded once per tick.
+//!
+//! Clients differ only in whether they accept run-length encoded
+//! states, whether they understand tile flags, and the player they
+//! control. The state is encoded once for each combination in use,
+//! into a frame shared by the clients of that combination, and the
+//! player byte is patched in by the task sending to each client.
+
+use std::rc::Rc;
+
+use curseofrust_msg::{bytemuck, fragment, server_msg, S2CData, S2C_SIZE};
+
+use crate::{conn::Frame, Client};
+
+/// Bits of the index of a frame in [`StateFrames`].
+const RLE: usize = 1 << 0;
+const TILE_FLAGS: usize = 1 << 1;
+
+#[inline]
+fn variant(client: &Client) -> usize {
+    (if client.rle { RLE } else { 0 }) | (if client.tile_flags { TILE_FLAGS } else { 0 })
+}
+
+/// Frames of the state being sent, by encoding and tile flags.
+#[derive(Debug)]
+pub(crate) struct StateFrames {
+    /// Buffer the state is encoded into.
+    buf: Vec<u8>,
+    /// Frames, `None` if not needed by any client.
+    frames: [Option<Rc<[u8]>>; 4],
+}
+
+impl StateFrames {
+    pub(crate) fn new() -> Self {
+        Self {
+            buf: vec![0; S2C_SIZE],
+            frames: Default::default(),
+        }
+    }
+
+    /// Encodes the state for the clients it is due to.
+    pub(crate) fn encode(&mut self, data: &S2CData, cl: &[Client]) {
+        for (i, frame) in self.frames.iter_mut().enumerate() {
+            *frame = None;
+            if !cl
+                .iter()
+                .any(|client| client.state_due.get() && variant(client) == i)
+            {
+                continue;
+            }
+            let mut data = *data;
+            if i & TILE_FLAGS == 0 {
+                data.clear_tile_flags();
+            }
+            let (msg, od) = self
+                .buf
+                .split_first_mut()
+                .expect("the buffer should longer than one byte");
+            let len = match (i & RLE != 0).then(|| data.encode_rle(od)).flatten() {
+                Some(len) => {
+                    *msg = server_msg::STATE_RLE;
+                    len + 1
+                }
+                None => {
+                    *msg = server_msg::STATE;
+                    od.copy_from_slice(bytemuck::bytes_of(&data));
+                    S2C_SIZE
+                }
+            };
+            *frame = Some(self.buf[..len].into());
+        }
+    }
+
+    /// Queues the frames for the clients they are due to, each with
+    /// the player it controls.
+    pub(crate) fn send(&self, seq: u16, cl: &[Client]) {
+        for client in cl.iter().filter(|client| client.state_due.take()) {
+            let Some(ref frame) = self.frames[variant(client)] else {
+                continue;
+            };
+            client.outbox.push(Frame::State {
+                split: client.fragment && frame.len() > fragment::MAX_DATAGRAM,
+                frame: frame.clone(),
+                player: client.pl.get().0 as u8,
+                seq,
+            });
+        }
+    }
+}
@@ -0,0 +1,207 @@
+//! Kings adapting to the aggression of human players.
+//!
+//! [`Adaptive`] counts the tiles changing hands, and every
+//! [`Adaptive::PERIOD`] steps moves the strategies of kings along
+//! [`LADDER`]: toward aggression while humans take more tiles than
+//! the kings take from them, and back while the kings are ahead,
+//! so that neither side runs away with the game.
+
+use alloc::vec::Vec;
+
+use crate::{grid::Grid, King, Player, Strategy, MAX_PLAYERS};
+
+/// Strategies kings move along, the calmest first.
+///
+/// Kings playing other strategies keep them.
+pub const LADDER: [Strategy; 5] = [
+    Strategy::Turtle,
+    Strategy::Noble,
+    Strategy::Opportunist,
+    Strategy::PersistentGreedy,
+    Strategy::AggrGreedy,
+];
+
+/// Aggression of a player, counted in tiles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Aggression {
+    /// Tiles the player took from other players.
+    pub attacks: u32,
+    /// Tiles the player took from human players.
+    pub taken_from_humans: u32,
+}
+
+/// Shifts the strategies of kings with the aggression of humans.
+#[derive(Debug, Clone, Default)]
+pub struct Adaptive {
+    /// Owner of every tile at the last step, in the order of
+    /// [`Grid::tiles`].
+    owners: Vec<Player>,
+    total: [Aggression; MAX_PLAYERS],
+    /// Aggression since the last shift.
+    period: [Aggression; MAX_PLAYERS],
+    steps: u32,
+}
+
+impl Adaptive {
+    /// Steps between shifts of strategies.
+    pub const PERIOD: u32 = 500;
+    /// Tiles either side has to be ahead by in a period
+    /// for the kings to shift.
+    pub const MARGIN: i64 = 8;
+
+    /// Gets the aggression of the given player over the game.
+    #[inline]
+    pub fn aggression(&self, player: Player) -> Aggression {
+        self.total
+            .get(player.0 as usize)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Counts the tiles that changed hands since the last step,
+    /// and shifts the strategies of the kings not playing for
+    /// humans once a period is over.
+    ///
+    /// Returns whether any king shifted, in which case the kings
+    /// are to evaluate the map again.
+    pub fn step(&mut self, grid: &Grid, kings: &mut [King], humans: &[Player]) -> bool {
+        let first = self.owners.is_empty();
+        self.owners.resize(grid.tiles().count(), Player::NEUTRAL);
+        for ((_, tile), old) in grid.tiles().zip(&mut self.owners) {
+            let new = tile.owner();
+            if !first && new != *old && !new.is_neutral() && !old.is_neutral() {
+                let from_human = humans.contains(old);
+                for counts in [&mut self.total, &mut self.period] {
+                    if let Some(a) = counts.get_mut(new.0 as usize) {
+                        a.attacks += 1;
+                        a.taken_from_humans += from_human as u32;
+                    }
+                }
+            }
+            *old = new;
+        }
+
+        self.steps += 1;
+        if self.steps < Self::PERIOD {
+            return false;
+        }
+        self.steps = 0;
+        let period = core::mem::take(&mut self.period);
+        let of = |p: &Player| period.get(p.0 as usize).copied().unwrap_or_default();
+        let human_attacks: i64 = humans.iter().map(|p| of(p).attacks as i64).sum();
+        let kings_taken: i64 = kings
+            .iter()
+            .filter(|k| !humans.contains(&k.player()))
+            .map(|k| of(&k.player()).taken_from_humans as i64)
+            .sum();
+        let shift: isize = match human_attacks - kings_taken {
+            d if d > Self::MARGIN => 1,
+            d if d < -Self::MARGIN => -1,
+            _ => return false,
+        };
+
+        let mut shifted = false;
+        for king in kings.iter_mut().filter(|k| !humans.contains(&k.player())) {
+            let Some(at) = LADDER.iter().position(|&s| s == king.strategy()) else {
+                continue;
+            };
+            let to = LADDER[at.saturating_add_signed(shift).min(LADDER.len() - 1)];
+            if to != king.strategy() {
+                king.set_strategy(to);
+                shifted = true;
+            }
+        }
+        shifted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        grid::{HabitLand, Tile},
+        Pos,
+    };
+
+    const HUMANS: [Player; 1] = [Player(1)];
+
+    /// Grid of grassland, the left half held by the human
+    /// and the right half by the king.
+    fn grid() -> Grid {
+        let mut grid = Grid::new(20, 2);
+        for x in 0..20 {
+            for y in 0..2 {
+                *grid.tile_mut(Pos(x, y)).unwrap() = Tile::Habitable {
+                    land: HabitLand::Grassland,
+                    units: [0; MAX_PLAYERS],
+                    owner: Player(if x < 10 { 1 } else { 2 }),
+                    rally: None,
+                    landscape: Default::default(),
+                    road: false,
+                };
+            }
+        }
+        grid
+    }
+
+    /// Hands `n` tiles of `from` to `to`.
+    fn capture(grid: &mut Grid, n: usize, from: Player, to: Player) {
+        let taken: Vec<Pos> = grid
+            .tiles_owned_by(from)
+            .map(|(pos, _)| pos)
+            .take(n)
+            .collect();
+        assert_eq!(taken.len(), n);
+        for pos in taken {
+            grid.tile_mut(pos).unwrap().set_owner(to);
+        }
+    }
+
+    /// Steps a period, with `n` tiles taken by the human if
+    /// positive, or by the king if negative, after its first step.
+    fn period(adaptive: &mut Adaptive, grid: &mut Grid, kings: &mut [King], n: i32) -> bool {
+        let mut shifted = false;
+        for i in 0..Adaptive::PERIOD {
+            if i == 1 {
+                match n {
+                    0.. => capture(grid, n as usize, Player(2), Player(1)),
+                    _ => capture(grid, n.unsigned_abs() as usize, Player(1), Player(2)),
+                }
+            }
+            shifted |= adaptive.step(grid, kings, &HUMANS);
+        }
+        shifted
+    }
+
+    #[test]
+    fn ladder() {
+        let mut grid = grid();
+        let mut kings = [
+            King::new(Player(2), Strategy::Opportunist, 20, 2),
+            King::new(Player(3), Strategy::Midas, 20, 2),
+        ];
+        let mut adaptive = Adaptive::default();
+        let strategies = |kings: &[King; 2]| kings.each_ref().map(King::strategy);
+        let margin = Adaptive::MARGIN as i32;
+
+        assert!(period(&mut adaptive, &mut grid, &mut kings, margin + 1));
+        assert_eq!(
+            strategies(&kings),
+            [Strategy::PersistentGreedy, Strategy::Midas]
+        );
+        assert_eq!(adaptive.aggression(Player(1)).attacks, margin as u32 + 1);
+
+        assert!(period(&mut adaptive, &mut grid, &mut kings, -(margin + 1)));
+        assert!(period(&mut adaptive, &mut grid, &mut kings, -(margin + 1)));
+        assert_eq!(strategies(&kings), [Strategy::Noble, Strategy::Midas]);
+        assert_eq!(
+            adaptive.aggression(Player(2)).taken_from_humans,
+            2 * (margin as u32 + 1)
+        );
+
+        // Within the margin either way.
+        assert!(!period(&mut adaptive, &mut grid, &mut kings, margin));
+        assert!(!period(&mut adaptive, &mut grid, &mut kings, -margin));
+        assert_eq!(strategies(&kings), [Strategy::Noble, Strategy::Midas]);
+    }
+}
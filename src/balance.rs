@@ -0,0 +1,239 @@
+//! Tunable balance parameters of the simulation and AI.
+
+#[cfg(not(feature = "std"))]
+use crate::float::Float as _;
+use crate::{grid::HabitLand, Speed};
+
+/// Balance parameters of the game.
+///
+/// With the `serde` feature, missing fields of deserialized
+/// values take their default.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default, deny_unknown_fields))]
+pub struct GameBalance {
+    /// Desire above which kings place flags.
+    pub flag_threshold: f32,
+    /// Desire above which persistent greedy kings place flags.
+    pub persistent_flag_threshold: f32,
+    /// Desire below which persistent greedy kings remove flags.
+    pub persistent_unflag_threshold: f32,
+    /// Exponent of the army size in desire of kings.
+    pub army_exponent: f32,
+    /// Exponent of the army size in desire of persistent greedy kings.
+    pub persistent_army_exponent: f32,
+    /// Steps between flag placements of kings, by difficulty
+    /// from easiest to hardest. `1` places flags every step.
+    pub flag_cooldown: [u32; 5],
+    /// Steps between builds of kings, by difficulty
+    /// from easiest to hardest. `1` builds every step.
+    pub build_cooldown: [u32; 5],
+
+    /// Value a fortress spreads to its surroundings.
+    pub fortress_spread: i32,
+    /// Value a fortress spreads for noble kings.
+    pub noble_fortress_spread: i32,
+    pub town_spread: i32,
+    pub village_spread: i32,
+    /// Value a village spreads for noble kings.
+    pub noble_village_spread: i32,
+    pub mine_spread: i32,
+    /// Value a mine spreads for midas kings.
+    pub midas_mine_spread: i32,
+    /// Value a fortress spreads for turtle kings.
+    pub turtle_fortress_spread: i32,
+    /// Value a town spreads for turtle kings.
+    pub turtle_town_spread: i32,
+    /// Gold turtle kings keep in reserve rather than build with.
+    pub turtle_reserve: u64,
+    /// Share of the population of the strongest enemy below
+    /// which swarm kings retreat to their cities.
+    pub swarm_retreat: f32,
+    /// Largest share by which personalities of kings scale
+    /// their flag thresholds and army exponents.
+    ///
+    /// See [`Personality`](crate::king::Personality).
+    pub personality_jitter: f32,
+
+    /// Share of the maximum population a defender must lose
+    /// in one step for its city to possibly burn, halved.
+    pub attack: f32,
+    /// Share of population moving to each neighbour per step.
+    #[cfg_attr(feature = "serde", serde(rename = "move"))]
+    pub movement: f32,
+    /// Share of population moving per step, per unit of
+    /// flag call difference.
+    pub call_move: f32,
+    /// Share of population moving to each neighbour per step,
+    /// between two tiles with roads.
+    pub road_movement: f32,
+
+    /// Share of combat damage the owner of a forest takes.
+    pub forest_defense: f32,
+    /// Share of combat damage the owner of a hill takes.
+    pub hill_defense: f32,
+    /// Share of growth of cities on hills.
+    pub hill_growth: f32,
+
+    /// Multiplier of the growth of cities.
+    pub growth: f32,
+    /// Multiplier of the prices of cities.
+    ///
+    /// See [`GameBalance::price`].
+    pub price_scale: f32,
+    /// Multiplier of the prices of cities for every city the builder
+    /// owns beyond the first, curbing snowballing. `1.0` disables it.
+    pub city_price_multiplier: f32,
+    /// Gold kings earn per mine and step on top of the usual income.
+    pub king_income: u64,
+
+    /// Score of one gold, relative to one unit of population,
+    /// when a time limit ends the game.
+    pub gold_score: f32,
+    /// Population below which a country without cities
+    /// is eliminated.
+    pub elimination_pop: u32,
+    /// Share of the gold of the owner of a captured city
+    /// the conqueror plunders, from `0.0` to `1.0`.
+    /// `0.0` disables plunder.
+    pub plunder: f32,
+}
+
+impl Default for GameBalance {
+    fn default() -> Self {
+        Self {
+            flag_threshold: 5000.0,
+            persistent_flag_threshold: 9000.0,
+            persistent_unflag_threshold: 900.0,
+            army_exponent: 0.5,
+            persistent_army_exponent: 0.7,
+            flag_cooldown: [4, 2, 1, 1, 1],
+            build_cooldown: [4, 2, 1, 1, 1],
+            fortress_spread: 16,
+            noble_fortress_spread: 32,
+            town_spread: 8,
+            village_spread: 4,
+            noble_village_spread: 2,
+            mine_spread: 4,
+            midas_mine_spread: 8,
+            turtle_fortress_spread: 24,
+            turtle_town_spread: 16,
+            turtle_reserve: 240,
+            swarm_retreat: 0.5,
+            personality_jitter: 0.1,
+            attack: 0.1,
+            movement: 0.05,
+            call_move: 0.10,
+            road_movement: 0.15,
+            forest_defense: 0.8,
+            hill_defense: 0.7,
+            hill_growth: 0.5,
+            growth: 1.0,
+            price_scale: 1.0,
+            city_price_multiplier: 1.0,
+            king_income: 0,
+            gold_score: 1.0,
+            elimination_pop: 10,
+            plunder: 0.0,
+        }
+    }
+}
+
+impl GameBalance {
+    /// Gets the price of the given type of land for a builder
+    /// owning the given number of cities, scaled by
+    /// [`price_scale`](Self::price_scale) and
+    /// [`city_price_multiplier`](Self::city_price_multiplier).
+    ///
+    /// See [`Grid::price`](crate::Grid::price).
+    #[inline]
+    pub fn price(&self, land: HabitLand, cities: usize) -> u64 {
+        let scale = self.price_scale
+            * self
+                .city_price_multiplier
+                .powi(cities.saturating_sub(1).min(i32::MAX as usize) as i32);
+        (land.price() as f32 * scale).round() as u64
+    }
+}
+
+/// Named bundle of balance parameters and speed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum Preset {
+    /// Rules of the original game, with the default balance,
+    /// which throttles kings on easier difficulties.
+    #[default]
+    Classic,
+    /// Short games, with cheap and fast-growing cities
+    /// and kings that act every step.
+    Blitz,
+    /// Long games, with expensive and slow-growing cities
+    /// and kings earning extra gold from mines.
+    Marathon,
+}
+
+impl Preset {
+    /// Gets the balance of this preset.
+    pub fn balance(self) -> GameBalance {
+        let classic = GameBalance::default();
+        match self {
+            Preset::Classic => classic,
+            Preset::Blitz => GameBalance {
+                flag_cooldown: [1; 5],
+                build_cooldown: [1; 5],
+                movement: 0.08,
+                growth: 1.5,
+                price_scale: 0.75,
+                ..classic
+            },
+            Preset::Marathon => GameBalance {
+                growth: 0.75,
+                price_scale: 1.5,
+                king_income: 1,
+                ..classic
+            },
+        }
+    }
+
+    /// Gets the game speed of this preset.
+    pub fn speed(self) -> Speed {
+        match self {
+            Preset::Classic => Speed::Normal,
+            Preset::Blitz => Speed::Fast,
+            Preset::Marathon => Speed::Slow,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prices_rise_with_cities() {
+        let mut balance = GameBalance::default();
+        for cities in 0..5 {
+            assert_eq!(balance.price(HabitLand::Village, cities), 160);
+        }
+
+        balance.city_price_multiplier = 1.5;
+        // Neither the first city nor none at all raise the price.
+        assert_eq!(balance.price(HabitLand::Village, 0), 160);
+        assert_eq!(balance.price(HabitLand::Village, 1), 160);
+        assert_eq!(balance.price(HabitLand::Village, 2), 240);
+        assert_eq!(balance.price(HabitLand::Town, 3), 540);
+        balance.price_scale = 0.5;
+        assert_eq!(balance.price(HabitLand::Fortress, 2), 240);
+        assert_eq!(balance.price(HabitLand::Grassland, 9), 0);
+    }
+
+    #[test]
+    fn prices_saturate() {
+        let balance = GameBalance {
+            city_price_multiplier: 1e30,
+            ..Default::default()
+        };
+        assert_eq!(balance.price(HabitLand::Village, 3), u64::MAX);
+        assert_eq!(balance.price(HabitLand::Fortress, usize::MAX), u64::MAX);
+    }
+}
@@ -0,0 +1,37 @@
+//! Float math for builds without `std`, where `f32` lacks the methods
+//! that need a math library.
+
+pub(crate) trait Float {
+    fn round(self) -> Self;
+    fn sqrt(self) -> Self;
+    fn exp(self) -> Self;
+    fn powf(self, n: Self) -> Self;
+    fn powi(self, n: i32) -> Self;
+}
+
+impl Float for f32 {
+    #[inline]
+    fn round(self) -> Self {
+        libm::roundf(self)
+    }
+
+    #[inline]
+    fn sqrt(self) -> Self {
+        libm::sqrtf(self)
+    }
+
+    #[inline]
+    fn exp(self) -> Self {
+        libm::expf(self)
+    }
+
+    #[inline]
+    fn powf(self, n: Self) -> Self {
+        libm::powf(self, n)
+    }
+
+    #[inline]
+    fn powi(self, n: i32) -> Self {
+        libm::powf(self, n as f32)
+    }
+}
@@ -1,8 +1,13 @@
-use std::ops::IndexMut;
+use alloc::{vec, vec::Vec};
+use core::ops::IndexMut;
 
+#[cfg(not(feature = "std"))]
+use crate::float::Float as _;
 use crate::*;
 
 pub const FLAG_POWER: i32 = 8;
+/// Maximum power of flags stacked on a single tile.
+pub const MAX_FLAG_POWER: i32 = 3 * FLAG_POWER;
 
 /// 2D array of tiles with width and height
 /// information.
@@ -39,6 +44,8 @@ pub struct ConflictDescriptor<'a> {
     ///
     /// `None` leaves for a randomly generated value.
     pub ineq: Option<u32>,
+    /// Extra starting population of human players.
+    pub ui_pop: u16,
 }
 
 impl Grid {
@@ -103,6 +110,68 @@ impl Grid {
         &mut self.tiles
     }
 
+    /// Iterates over all tiles, with their positions.
+    pub fn tiles(&self) -> impl Iterator<Item = (Pos, &Tile)> + '_ {
+        self.tiles.iter().enumerate().flat_map(|(x, arr)| {
+            arr.iter()
+                .enumerate()
+                .map(move |(y, tile)| (Pos(x as i32, y as i32), tile))
+        })
+    }
+
+    /// Iterates over tiles owned by the given player,
+    /// including mines.
+    pub fn tiles_owned_by(&self, player: Player) -> impl Iterator<Item = (Pos, &Tile)> + '_ {
+        self.tiles().filter(move |(_, tile)| tile.owner() == player)
+    }
+
+    /// Iterates over cities owned by the given player,
+    /// with their lands.
+    pub fn cities(&self, player: Player) -> impl Iterator<Item = (Pos, HabitLand)> + '_ {
+        self.tiles_owned_by(player)
+            .filter_map(|(pos, tile)| match tile {
+                Tile::Habitable { land, .. } if *land != HabitLand::Grassland => Some((pos, *land)),
+                _ => None,
+            })
+    }
+
+    /// Gets the total population of the given player on all tiles.
+    pub fn population(&self, Player(p): Player) -> u32 {
+        self.tiles()
+            .map(|(_, t)| t.units().get(p as usize).copied().unwrap_or_default() as u32)
+            .sum()
+    }
+
+    /// Iterates over habitable tiles owned by the given player
+    /// adjacent to tiles of other players.
+    pub fn frontline(&self, player: Player) -> impl Iterator<Item = Pos> + '_ {
+        self.tiles_owned_by(player)
+            .filter(|(_, tile)| tile.is_habitable())
+            .filter(move |&(pos, _)| {
+                self.neighbors(pos).any(|(_, t)| {
+                    let owner = t.owner();
+                    owner != player && !owner.is_neutral()
+                })
+            })
+            .map(|(pos, _)| pos)
+    }
+
+    /// Iterates over all mines, with their owners.
+    pub fn mines(&self) -> impl Iterator<Item = (Pos, Player)> + '_ {
+        self.tiles().filter_map(|(pos, tile)| match tile {
+            Tile::Mine(owner) => Some((pos, *owner)),
+            _ => None,
+        })
+    }
+
+    /// Iterates over tiles adjacent to the given position.
+    pub fn neighbors(&self, pos: Pos) -> impl Iterator<Item = (Pos, &Tile)> + '_ {
+        Pos::DIRS.into_iter().filter_map(move |dir| {
+            let pos = pos.offset(dir);
+            self.tile(pos).map(|tile| (pos, tile))
+        })
+    }
+
     /// Enhances an already initialized grid.
     ///
     /// Places at most 4 players at the corners of the map,
@@ -116,22 +185,31 @@ impl Grid {
             ui_players,
             conditions,
             ineq,
+            ui_pop,
         } = descriptor;
 
         // Remove all cities.
         for arr in self.tiles.iter_mut() {
             for tile in arr {
-                if let Tile::Habitable { land, units, owner } = tile {
+                if let Tile::Habitable {
+                    land,
+                    units,
+                    owner,
+                    rally,
+                    ..
+                } = tile
+                {
                     units.copy_from_slice(&[0; 8]);
                     *owner = Player::NEUTRAL;
                     *land = HabitLand::Grassland;
+                    *rally = None;
                 }
             }
         }
 
         let locs_num = in_segment!(locs_num, 2, locs.len());
         let num = locs_num.min(players.len() + ui_players.len());
-        let di = fastrand::usize(..locs.len());
+        let di = crate::rng::usize(..locs.len());
 
         let mut chosen_locs = vec![Pos(0, 0); num];
         for (i, loc) in chosen_locs.iter_mut().enumerate() {
@@ -140,13 +218,16 @@ impl Grid {
             self.tiles[x as usize][y as usize].set_habitable(HabitLand::Fortress);
 
             // Place mines nearby
-            let Pos(ri, rj) = fastrand::choice(Pos::DIRS).unwrap();
+            let Pos(ri, rj) = crate::rng::choice(Pos::DIRS).unwrap();
             self.tiles[(x + ri) as usize][(y + rj) as usize] = Tile::Mine(Player::NEUTRAL);
             self.tiles[(x - 2 * ri) as usize][(y - 2 * rj) as usize] = Tile::Mine(Player::NEUTRAL);
             self.tiles[(x - ri) as usize][(y - rj) as usize] = Tile::Habitable {
                 land: HabitLand::Grassland,
                 units: [0; 8],
                 owner: Player::NEUTRAL,
+                rally: None,
+                landscape: Landscape::Plain,
+                road: false,
             };
         }
 
@@ -176,19 +257,19 @@ impl Grid {
 
         // Suffled computer players.
         let mut sh_players_comp = players.to_vec();
-        fastrand::shuffle(&mut sh_players_comp);
+        crate::rng::shuffle(&mut sh_players_comp);
         let sh_players_comp = sh_players_comp;
 
         // Shuffled copy of the players array.
         let mut sh_players = ui_players.to_vec();
-        let (p0, p1) = sh_players_comp.split_at(fastrand::usize(..players.len()));
+        let (p0, p1) = sh_players_comp.split_at(crate::rng::usize(..players.len()));
         sh_players.extend_from_slice(p1);
         sh_players.extend_from_slice(p0);
-        fastrand::shuffle(&mut sh_players[..num]);
+        crate::rng::shuffle(&mut sh_players[..num]);
 
         // Human player index.
         let ihuman = conditions.map_or_else(
-            || fastrand::u32(..num as u32),
+            || crate::rng::u32(..num as u32),
             // Choose specific conditions {1,... N}, 1 => best, N => worst
             |c| loc_index[(num - c as usize).min(num - 1)] as u32,
         );
@@ -204,9 +285,14 @@ impl Grid {
                 tile.set_owner(sh_players_comp[i]);
             }
 
-            let Player(owner) = tile.owner();
+            let owner = tile.owner();
+            let pop = if ui_players.contains(&owner) {
+                10 + ui_pop
+            } else {
+                10
+            };
             if let Tile::Habitable { units, .. } = tile {
-                units[owner as usize] = 10;
+                units[owner.0 as usize] = pop;
             }
         }
 
@@ -231,52 +317,32 @@ impl Grid {
             self.floodfill_closest(&mut u, &mut d, loc, i as i32, 0);
         });
 
-        for (i, arr) in self.tiles.iter().enumerate() {
-            for j in arr.iter().enumerate().filter_map(|(i, t)| {
-                if matches!(t, Tile::Mine(_)) {
-                    Some(i)
-                } else {
-                    None
-                }
-            }) {
-                let mut max_dist = 0;
-                let mut min_dist = (MAX_WIDTH * MAX_HEIGHT + 1) as i32;
-
-                let mut single_owner = UNREACHABLE;
-
-                for (x, y) in Pos::DIRS.into_iter().filter_map(|Pos(x, y)| {
-                    let (x, y) = (i as i32 + x, j as i32 + y);
-                    if x < 0
-                        || x >= self.width as i32
-                        || y < 0
-                        || y >= self.height as i32
-                        || !self.tiles[x as usize][y as usize].is_habitable()
-                    {
-                        None
-                    } else {
-                        Some((x as usize, y as usize))
-                    }
-                }) {
-                    let dd = d[x][y];
-                    let uu = u[x][y];
-                    if single_owner == UNREACHABLE {
-                        single_owner = uu;
-                        max_dist = dd;
-                        min_dist = dd;
-                    } else if uu == single_owner {
-                        max_dist = max_dist.max(dd);
-                        min_dist = min_dist.min(dd);
-                    } else if uu != UNREACHABLE {
-                        single_owner = COMPETITION
-                    }
+        for (pos, _) in self.mines() {
+            let mut max_dist = 0;
+            let mut min_dist = (MAX_WIDTH * MAX_HEIGHT + 1) as i32;
+
+            let mut single_owner = UNREACHABLE;
+
+            for (Pos(x, y), _) in self.neighbors(pos).filter(|(_, t)| t.is_habitable()) {
+                let dd = d[x as usize][y as usize];
+                let uu = u[x as usize][y as usize];
+                if single_owner == UNREACHABLE {
+                    single_owner = uu;
+                    max_dist = dd;
+                    min_dist = dd;
+                } else if uu == single_owner {
+                    max_dist = max_dist.max(dd);
+                    min_dist = min_dist.min(dd);
+                } else if uu != UNREACHABLE {
+                    single_owner = COMPETITION
                 }
+            }
 
-                if single_owner != COMPETITION && single_owner != UNREACHABLE {
-                    result[single_owner as usize] += (100.0
-                        * (MAX_WIDTH + MAX_HEIGHT) as f32
-                        * (-10.0 * (max_dist * min_dist) as f32 / (MAX_WIDTH * MAX_HEIGHT) as f32)
-                            .exp()) as i32;
-                }
+            if single_owner != COMPETITION && single_owner != UNREACHABLE {
+                result[single_owner as usize] += (100.0
+                    * (MAX_WIDTH + MAX_HEIGHT) as f32
+                    * (-10.0 * (max_dist * min_dist) as f32 / (MAX_WIDTH * MAX_HEIGHT) as f32)
+                        .exp()) as i32;
             }
         }
     }
@@ -287,7 +353,7 @@ impl Grid {
         &self,
         u: &mut [Vec<i32>],
         d: &mut [Vec<i32>],
-        Pos(x, y): Pos,
+        pos @ Pos(x, y): Pos,
         val: i32,
         dist: i32,
     ) {
@@ -304,12 +370,12 @@ impl Grid {
         u[x as usize][y as usize] = val;
         d[x as usize][y as usize] = dist;
 
-        for Pos(dx, dy) in Pos::DIRS {
-            self.floodfill_closest(u, d, Pos(x + dx, y + dy), val, dist + 1);
+        for dir in Pos::DIRS {
+            self.floodfill_closest(u, d, pos.offset(dir), val, dist + 1);
         }
     }
 
-    fn floodfill(&self, u: &mut [Vec<i32>], Pos(x, y): Pos, val: i32) {
+    fn floodfill(&self, u: &mut [Vec<i32>], pos @ Pos(x, y): Pos, val: i32) {
         if x < 0
             || x >= self.width as i32
             || y < 0
@@ -320,8 +386,29 @@ impl Grid {
             return;
         }
         u[x as usize][y as usize] = val;
-        for Pos(dx, dy) in Pos::DIRS {
-            self.floodfill(u, Pos(x + dx, y + dy), val)
+        for dir in Pos::DIRS {
+            self.floodfill(u, pos.offset(dir), val)
+        }
+    }
+
+    /// Scatters forests and hills over grassland.
+    ///
+    /// Draws from its own generator, so that the rest of the game
+    /// plays out the same as on a map without landscapes.
+    pub(crate) fn scatter_landscapes(&mut self, rng: &mut fastrand::Rng) {
+        for tile in self.tiles.iter_mut().flatten() {
+            if let Tile::Habitable {
+                land: HabitLand::Grassland,
+                landscape,
+                ..
+            } = tile
+            {
+                *landscape = match rng.u32(..16) {
+                    0..=2 => Landscape::Forest,
+                    3 => Landscape::Hill,
+                    _ => Landscape::Plain,
+                };
+            }
         }
     }
 
@@ -329,20 +416,12 @@ impl Grid {
     pub fn is_connected(&self) -> bool {
         let mut colored = false;
         let mut m = vec![vec![0; self.height as usize]; self.width as usize];
-        for (i, arr_g) in self.tiles.iter().enumerate() {
-            for j in arr_g.iter().enumerate().filter_map(|(j, t)| {
-                if t.owner().is_neutral() {
-                    None
-                } else {
-                    Some(j)
-                }
-            }) {
-                if colored && m[i][j] == 0 {
-                    return false;
-                }
-                colored = true;
-                self.floodfill(&mut m, Pos(i as i32, j as i32), 1)
+        for (pos @ Pos(i, j), _) in self.tiles().filter(|(_, t)| !t.owner().is_neutral()) {
+            if colored && m[i as usize][j as usize] == 0 {
+                return false;
             }
+            colored = true;
+            self.floodfill(&mut m, pos, 1)
         }
         true
     }
@@ -367,6 +446,85 @@ impl Pos {
         Self(1, -1),
         Self(-1, 1),
     ];
+
+    /// [`Pos::DIRS`] in counterclockwise order,
+    /// starting from the right.
+    const CYCLE: [Self; 6] = [
+        Self(1, 0),
+        Self(1, -1),
+        Self(0, -1),
+        Self(-1, 0),
+        Self(-1, 1),
+        Self(0, 1),
+    ];
+
+    /// Gets the position moved by the given direction.
+    #[inline]
+    pub const fn offset(self, Pos(dx, dy): Pos) -> Self {
+        Self(self.0 + dx, self.1 + dy)
+    }
+
+    /// Converts this position to offset coordinates,
+    /// as `(column, row)` with odd rows shoved right.
+    ///
+    /// Positions themselves are axial coordinates, where
+    /// every row is shifted half a tile from the previous one.
+    #[inline]
+    pub const fn to_offset(self) -> (i32, i32) {
+        (self.0 + self.1.div_euclid(2), self.1)
+    }
+
+    /// Converts offset coordinates back to a position.
+    ///
+    /// See [`Pos::to_offset`].
+    #[inline]
+    pub const fn from_offset(column: i32, row: i32) -> Self {
+        Self(column - row.div_euclid(2), row)
+    }
+
+    /// Gets the number of steps between two positions.
+    #[inline]
+    pub const fn hex_distance(self, other: Pos) -> u32 {
+        let (dx, dy) = (self.0 - other.0, self.1 - other.1);
+        (dx.unsigned_abs() + dy.unsigned_abs() + (dx + dy).unsigned_abs()) / 2
+    }
+
+    /// Iterates over positions exactly `radius` steps away
+    /// from this position.
+    ///
+    /// A radius of `0` yields this position only.
+    pub fn ring(self, radius: u32) -> impl Iterator<Item = Pos> {
+        let r = radius as i32;
+        let sides = Self::CYCLE
+            .into_iter()
+            .flat_map(move |dir| core::iter::repeat_n(dir, radius as usize));
+        (radius == 0).then_some(self).into_iter().chain(sides.scan(
+            Pos(self.0 - r, self.1 + r),
+            |pos, dir| {
+                let current = *pos;
+                *pos = pos.offset(dir);
+                Some(current)
+            },
+        ))
+    }
+}
+
+impl core::ops::Add for Pos {
+    type Output = Self;
+
+    #[inline]
+    fn add(self, rhs: Self) -> Self::Output {
+        self.offset(rhs)
+    }
+}
+
+impl core::ops::Sub for Pos {
+    type Output = Self;
+
+    #[inline]
+    fn sub(self, Pos(x, y): Self) -> Self::Output {
+        Self(self.0 - x, self.1 - y)
+    }
 }
 
 impl From<(u32, u32)> for Pos {
@@ -391,6 +549,12 @@ pub enum Tile {
         /// Population information of this tile.
         units: [u16; MAX_PLAYERS],
         owner: Player,
+        /// Destination population grown in this city
+        /// is sent toward, set by the owner.
+        rally: Option<Pos>,
+        landscape: Landscape,
+        /// Whether a road is built on this tile.
+        road: bool,
     },
 }
 
@@ -404,6 +568,46 @@ impl Tile {
         }
     }
 
+    /// Gets the rally point of this tile, if it is a city with one.
+    #[inline]
+    pub fn rally(&self) -> Option<Pos> {
+        match self {
+            Self::Habitable { rally, .. } => *rally,
+            _ => None,
+        }
+    }
+
+    /// Gets the landscape of this tile, plain if it is not habitable.
+    #[inline]
+    pub fn landscape(&self) -> Landscape {
+        match self {
+            Self::Habitable { landscape, .. } => *landscape,
+            _ => Landscape::Plain,
+        }
+    }
+
+    /// Whether a road is built on this tile.
+    #[inline]
+    pub fn road(&self) -> bool {
+        matches!(self, Self::Habitable { road: true, .. })
+    }
+
+    /// Sets whether a road is built on this tile if it is habitable.
+    #[inline]
+    pub fn set_road(&mut self, value: bool) {
+        if let Self::Habitable { road, .. } = self {
+            *road = value;
+        }
+    }
+
+    /// Sets the landscape of this tile if it is habitable.
+    #[inline]
+    pub fn set_landscape(&mut self, value: Landscape) {
+        if let Self::Habitable { landscape, .. } = self {
+            *landscape = value;
+        }
+    }
+
     #[inline]
     pub fn set_owner(&mut self, player: Player) {
         match self {
@@ -416,28 +620,31 @@ impl Tile {
     /// Randomly generates a tile from scratch.
     pub(crate) fn new() -> Self {
         let mut this = Self::default();
-        match fastrand::u32(..20) {
+        match crate::rng::u32(..20) {
             0 => {
                 this = Tile::Habitable {
-                    land: match fastrand::u32(..6) {
+                    land: match crate::rng::u32(..6) {
                         0 => HabitLand::Fortress,
                         1 | 2 => HabitLand::Town,
                         _ => HabitLand::Village,
                     },
                     units: [0; MAX_PLAYERS],
                     owner: Default::default(),
+                    rally: None,
+                    landscape: Landscape::Plain,
+                    road: false,
                 }
             }
             1..=4 => {
                 // Mountains and mineis
-                this = if fastrand::u32(..10) == 0 {
+                this = if crate::rng::u32(..10) == 0 {
                     Tile::Mine(Default::default())
                 } else {
                     Tile::Mountain
                 };
             }
             _ => {
-                this.set_owner(Player(fastrand::u32(..crate::MAX_PLAYERS as u32)));
+                this.set_owner(Player(crate::rng::u32(..crate::MAX_PLAYERS as u32)));
             }
         }
 
@@ -466,6 +673,9 @@ impl Tile {
                 land: l,
                 units: [0; MAX_PLAYERS],
                 owner: Player::NEUTRAL,
+                rally: None,
+                landscape: Landscape::Plain,
+                road: false,
             }
         }
     }
@@ -497,6 +707,9 @@ impl Default for Tile {
             land: Default::default(),
             units: [0; MAX_PLAYERS],
             owner: Default::default(),
+            rally: None,
+            landscape: Landscape::Plain,
+            road: false,
         }
     }
 }
@@ -562,6 +775,42 @@ impl HabitLand {
     }
 }
 
+/// Natural features of a habitable tile, which outlast
+/// the cities built on it.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+#[repr(u8)]
+#[non_exhaustive]
+pub enum Landscape {
+    #[default]
+    Plain,
+    /// Shelters its owner from attacks.
+    Forest,
+    /// Shelters its owner even more, but slows growth.
+    Hill,
+}
+
+impl Landscape {
+    /// Gets the share of combat damage the owner
+    /// of a tile with this landscape takes.
+    #[inline]
+    pub const fn defense(self, balance: &GameBalance) -> f32 {
+        match self {
+            Landscape::Forest => balance.forest_defense,
+            Landscape::Hill => balance.hill_defense,
+            Landscape::Plain => 1.0,
+        }
+    }
+
+    /// Gets the share of growth of cities on this landscape.
+    #[inline]
+    pub const fn growth(self, balance: &GameBalance) -> f32 {
+        match self {
+            Landscape::Hill => balance.hill_growth,
+            _ => 1.0,
+        }
+    }
+}
+
 impl Tile {
     /// Whether this tile is inhabitable.
     #[inline]
@@ -699,8 +948,9 @@ pub struct FlagGrid {
     pub width: u32,
     pub height: u32,
 
-    /// Whether a position has a flag.
-    pub flags: Vec<Vec<bool>>,
+    /// Power of the flag on each position,
+    /// `0` if the position has no flag.
+    pub flags: Vec<Vec<i32>>,
 
     /// Information of power of attraction
     /// a position has.
@@ -720,13 +970,14 @@ impl FlagGrid {
         Self {
             width,
             height,
-            flags: vec![vec![false; height as usize]; width as usize],
+            flags: vec![vec![0; height as usize]; width as usize],
             call: vec![vec![0; height as usize]; width as usize],
         }
     }
 
-    /// Adds a flag on the given position with the given power.
-    pub fn add(&mut self, grid: &Grid, Pos(x, y): Pos, power: i32) {
+    /// Sets the power of the flag on the given position
+    /// and updates the call accordingly.
+    fn set_power(&mut self, grid: &Grid, Pos(x, y): Pos, power: i32) {
         let (xu, yu) = (x as usize, y as usize);
 
         if x < 0
@@ -734,33 +985,50 @@ impl FlagGrid {
             || y < 0
             || y >= self.height as i32
             || !grid.tiles[xu][yu].is_habitable()
-            || self.flags[xu][yu]
+            || self.flags[xu][yu] == power
         {
             return;
         }
 
-        let mut u = [[0; MAX_HEIGHT as usize]; MAX_WIDTH as usize];
-        self.flags[xu][yu] = true;
-        grid.spread(&mut u, &mut self.call, Pos(x, y), power, 1);
+        // Spreading is not linear in power, so the old
+        // power is taken back as a whole.
+        let old = core::mem::replace(&mut self.flags[xu][yu], power);
+        if old > 0 {
+            let mut u = [[0; MAX_HEIGHT as usize]; MAX_WIDTH as usize];
+            grid.spread(&mut u, &mut self.call, Pos(x, y), old, -1);
+        }
+        if power > 0 {
+            let mut u = [[0; MAX_HEIGHT as usize]; MAX_WIDTH as usize];
+            grid.spread(&mut u, &mut self.call, Pos(x, y), power, 1);
+        }
     }
 
-    /// Removes a flag on the given position with the given power.
-    pub fn remove(&mut self, grid: &Grid, Pos(x, y): Pos, power: i32) {
-        let (xu, yu) = (x as usize, y as usize);
-
-        if x < 0
-            || x >= self.width as i32
-            || y < 0
-            || y >= self.height as i32
-            || !grid.tiles[xu][yu].is_habitable()
-            || !self.flags[xu][yu]
-        {
-            return;
+    /// Adds a flag on the given position with the given power.
+    ///
+    /// Does nothing if the position is already flagged.
+    pub fn add(&mut self, grid: &Grid, pos: Pos, power: i32) {
+        if !self.is_flagged(pos) {
+            self.set_power(grid, pos, power.min(MAX_FLAG_POWER));
         }
+    }
 
-        let mut u = [[0; MAX_HEIGHT as usize]; MAX_WIDTH as usize];
-        self.flags[xu][yu] = false;
-        grid.spread(&mut u, &mut self.call, Pos(x, y), power, -1);
+    /// Stacks the given power onto the flag on the given position,
+    /// up to [`MAX_FLAG_POWER`].
+    ///
+    /// Adds a flag if the position is not flagged.
+    pub fn raise(&mut self, grid: &Grid, pos: Pos, power: i32) {
+        let power = (self.power(pos) + power).min(MAX_FLAG_POWER);
+        self.set_power(grid, pos, power);
+    }
+
+    /// Lowers the flag on the given position by the given power.
+    ///
+    /// The flag is removed once it has no power left.
+    pub fn remove(&mut self, grid: &Grid, pos: Pos, power: i32) {
+        if self.is_flagged(pos) {
+            let power = (self.power(pos) - power).max(0);
+            self.set_power(grid, pos, power);
+        }
     }
 
     /// Iterates over all tiles and removes flags
@@ -770,15 +1038,22 @@ impl FlagGrid {
     pub fn remove_with_prob(&mut self, grid: &Grid, prob: f32) {
         for i in 0..self.width as i32 {
             for j in 0..self.height as i32 {
-                if self.flags[i as usize][j as usize] && fastrand::f32() <= prob {
-                    self.remove(grid, Pos(i, j), FLAG_POWER);
+                if self.flags[i as usize][j as usize] > 0 && crate::rng::f32() <= prob {
+                    self.set_power(grid, Pos(i, j), 0);
                 }
             }
         }
     }
 
     #[inline]
-    pub fn is_flagged(&self, Pos(i, j): Pos) -> bool {
+    pub fn is_flagged(&self, pos: Pos) -> bool {
+        self.power(pos) > 0
+    }
+
+    /// Gets the power of the flag on the given position,
+    /// or `0` if there is none.
+    #[inline]
+    pub fn power(&self, Pos(i, j): Pos) -> i32 {
         self.flags
             .get(i as usize)
             .and_then(|a| a.get(j as usize))
@@ -786,6 +1061,12 @@ impl FlagGrid {
             .unwrap_or_default()
     }
 
+    /// Gets the number of flags stacked on the given position.
+    #[inline]
+    pub fn level(&self, pos: Pos) -> u32 {
+        (self.power(pos) as u32).div_ceil(FLAG_POWER as u32)
+    }
+
     #[inline]
     pub fn call(&self, Pos(i, j): Pos) -> Option<i32> {
         self.call
@@ -793,6 +1074,14 @@ impl FlagGrid {
             .and_then(|a| a.get(j as usize))
             .copied()
     }
+
+    /// Gets the call of the given position normalized to `0.0..=1.0`,
+    /// where `1.0` is the call at a single flag.
+    #[inline]
+    pub fn call_intensity(&self, pos: Pos) -> Option<f32> {
+        self.call(pos)
+            .map(|call| (call as f32 / FLAG_POWER as f32).clamp(0.0, 1.0))
+    }
 }
 
 impl Grid {
@@ -800,12 +1089,12 @@ impl Grid {
         &self,
         u: &mut [impl IndexMut<usize, Output = i32>],
         v: &mut [impl IndexMut<usize, Output = i32>],
-        Pos(x, y): Pos,
+        pos @ Pos(x, y): Pos,
         val: i32,
         factor: i32,
     ) {
         let (xu, yu) = (x as usize, y as usize);
-        if !self.tile(Pos(x, y)).map_or(false, Tile::is_habitable) {
+        if !self.tile(pos).map_or(false, Tile::is_habitable) {
             return;
         }
 
@@ -816,13 +1105,18 @@ impl Grid {
                 *vv = 0.max(*vv + d * factor);
                 u[xu][yu] += d;
             }
-            for Pos(xd, yd) in Pos::DIRS {
-                self.spread(u, v, Pos(x + xd, y + yd), val / 2, factor)
+            for dir in Pos::DIRS {
+                self.spread(u, v, pos.offset(dir), val / 2, factor)
             }
         }
     }
 
-    pub fn even(&self, v: &mut [impl IndexMut<usize, Output = i32>], Pos(x, y): Pos, val: i32) {
+    pub fn even(
+        &self,
+        v: &mut [impl IndexMut<usize, Output = i32>],
+        pos @ Pos(x, y): Pos,
+        val: i32,
+    ) {
         if x < 0
             || x >= self.width as i32
             || y < 0
@@ -833,8 +1127,48 @@ impl Grid {
         }
 
         v[x as usize][y as usize] = val;
-        for Pos(xd, yd) in Pos::DIRS {
-            self.even(v, Pos(x + xd, y + yd), val)
+        for dir in Pos::DIRS {
+            self.even(v, pos.offset(dir), val)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rings_hold_every_position_at_their_radius() {
+        let center = Pos(3, -2);
+        assert_eq!(center.ring(0).collect::<Vec<_>>(), [center]);
+        for r in 1..6 {
+            let ring: Vec<Pos> = center.ring(r).collect();
+            assert_eq!(ring.len(), 6 * r as usize);
+            assert!(ring.iter().all(|&pos| pos.hex_distance(center) == r));
+            assert!(ring
+                .iter()
+                .enumerate()
+                .all(|(i, pos)| !ring[..i].contains(pos)));
+        }
+    }
+
+    #[test]
+    fn offsets_round_trip() {
+        for row in -5..5 {
+            for column in -5..5 {
+                let pos = Pos::from_offset(column, row);
+                assert_eq!(pos.to_offset(), (column, row));
+            }
+        }
+        assert_eq!(Pos(0, -1).to_offset(), (-1, -1));
+        assert_eq!(Pos::from_offset(0, -3), Pos(2, -3));
+    }
+
+    #[test]
+    fn neighbors_are_one_step_away() {
+        let pos = Pos(4, 7);
+        for dir in Pos::DIRS {
+            assert_eq!(pos.offset(dir).hex_distance(pos), 1);
         }
     }
 }
@@ -1,6 +1,11 @@
+use alloc::{vec, vec::Vec};
+
+#[cfg(not(feature = "std"))]
+use crate::float::Float as _;
 use crate::{
     grid::{HabitLand, Tile},
-    Difficulty, Error, FlagGrid, Grid, Player, Pos, FLAG_POWER, MAX_POPULATION,
+    Difficulty, Error, FlagGrid, GameBalance, Grid, Player, Pos, FLAG_POWER, MAX_PLAYERS,
+    MAX_POPULATION,
 };
 
 /// Data about each country.
@@ -8,6 +13,14 @@ use crate::{
 pub struct Country {
     pub player: Player,
     pub gold: u64,
+    /// Whether the country is out of the game.
+    ///
+    /// See [`State::eliminate`](crate::state::State::eliminate).
+    pub eliminated: bool,
+    /// Tiles to upgrade once affordable, oldest first.
+    ///
+    /// See [`State::queue_build`](crate::state::State::queue_build).
+    pub build_queue: Vec<Pos>,
 }
 
 impl From<Player> for Country {
@@ -16,6 +29,8 @@ impl From<Player> for Country {
         Self {
             player: value,
             gold: 0,
+            eliminated: false,
+            build_queue: vec![],
         }
     }
 }
@@ -23,13 +38,31 @@ impl From<Player> for Country {
 pub const PRICE_VILLAGE: u64 = 160;
 pub const PRICE_TOWN: u64 = 240;
 pub const PRICE_FORTRESS: u64 = 320;
+pub const PRICE_ROAD: u64 = 40;
 
 impl Grid {
+    /// Gets the price the given player pays for the given type
+    /// of land, depending on the cities the player owns.
+    ///
+    /// See [`GameBalance::price`].
+    #[inline]
+    pub fn price(&self, player: Player, land: HabitLand, balance: &GameBalance) -> u64 {
+        balance.price(land, self.cities(player).count())
+    }
+
     /// Builds a village, upgrades a village to a town,
     /// or upgrades a town to a fortress.
     ///
+    /// Prices are scaled by the balance, see [`Grid::price`].
+    ///
     /// Returns whether the build was succeed.
-    pub fn build(&mut self, country: &mut Country, pos: Pos) -> crate::Result<()> {
+    pub fn build(
+        &mut self,
+        country: &mut Country,
+        pos: Pos,
+        balance: &GameBalance,
+    ) -> crate::Result<()> {
+        let cities = self.cities(country.player).count();
         let Tile::Habitable { land, .. } = self
             .tile_mut(pos)
             .ok_or(Error::PosOutOfBound(pos))
@@ -49,7 +82,8 @@ impl Grid {
         };
 
         let mut l = *land;
-        let price = l.upgrade().ok_or(Error::UpgradeTopLevelBuilding)?;
+        l.upgrade().ok_or(Error::UpgradeTopLevelBuilding)?;
+        let price = balance.price(l, cities);
         if country.gold >= price {
             *land = l;
             country.gold -= price;
@@ -62,30 +96,148 @@ impl Grid {
         }
     }
 
+    /// Builds a road on a habitable tile.
+    ///
+    /// Population moves faster between tiles with roads.
+    pub fn build_road(&mut self, country: &mut Country, pos: Pos) -> crate::Result<()> {
+        let tile = self.tile_mut(pos).ok_or(Error::PosOutOfBound(pos))?;
+        if tile.owner() != country.player {
+            return Err(Error::NotOwner {
+                operator: country.player,
+                owner: tile.owner(),
+                tile: pos,
+            });
+        }
+        let Tile::Habitable { road, .. } = tile else {
+            return Err(Error::TileNotHabitable(pos));
+        };
+        if *road {
+            return Err(Error::RoadExists(pos));
+        }
+
+        if country.gold >= PRICE_ROAD {
+            *road = true;
+            country.gold -= PRICE_ROAD;
+            Ok(())
+        } else {
+            Err(Error::InsufficientGold {
+                required: PRICE_ROAD,
+                owning: country.gold,
+            })
+        }
+    }
+
     /// Degrades a city.
     ///
     /// A fortress degrades to a town,
     /// a town degrades to a village,
     /// and a village is destroyed.
     pub fn degrade(&mut self, pos: Pos) -> crate::Result<()> {
-        let Tile::Habitable { land, .. } = self.tile_mut(pos).ok_or(Error::PosOutOfBound(pos))?
+        let Tile::Habitable { land, rally, .. } =
+            self.tile_mut(pos).ok_or(Error::PosOutOfBound(pos))?
         else {
             return Err(Error::TileNotHabitable(pos));
         };
         if land.degrade() {
+            if *land == HabitLand::Grassland {
+                *rally = None;
+            }
             Ok(())
         } else {
             Err(Error::DegradeGrassLand)
         }
     }
+
+    /// Sets or clears the rally point of a city of the given player.
+    ///
+    /// See [`Tile::Habitable`].
+    pub fn set_rally(
+        &mut self,
+        player: Player,
+        pos: Pos,
+        target: Option<Pos>,
+    ) -> crate::Result<()> {
+        if let Some(target) = target {
+            if !self
+                .tile(target)
+                .ok_or(Error::PosOutOfBound(target))?
+                .is_habitable()
+            {
+                return Err(Error::TileNotHabitable(target));
+            }
+        }
+        let tile = self.tile_mut(pos).ok_or(Error::PosOutOfBound(pos))?;
+        if tile.owner() != player {
+            return Err(Error::NotOwner {
+                operator: player,
+                owner: tile.owner(),
+                tile: pos,
+            });
+        }
+        match tile {
+            Tile::Habitable { land, rally, .. } if *land != HabitLand::Grassland => {
+                *rally = target.filter(|t| *t != pos);
+                Ok(())
+            }
+            _ => Err(Error::NotCity(pos)),
+        }
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct King {
     values: Vec<Vec<i32>>,
     player: Player,
 
     strategy: Strategy,
+    personality: Personality,
+    /// Players on the team of the king, see [`Self::set_allies`].
+    allies: Vec<Player>,
+}
+
+/// Per-game jitter of the parameters of a [`King`],
+/// so that the same lineup plays differently every game.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Personality {
+    /// Scale of flag thresholds. Lower is more eager to flag.
+    pub threshold: f32,
+    /// Scale of army exponents. Higher is bolder with big armies.
+    pub aggression: f32,
+}
+
+impl Default for Personality {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            threshold: 1.0,
+            aggression: 1.0,
+        }
+    }
+}
+
+impl Personality {
+    /// Rolls the personality of the given player from a seed,
+    /// within [`GameBalance::personality_jitter`].
+    pub fn roll(seed: u64, player: Player, balance: &GameBalance) -> Self {
+        let mut rng = fastrand::Rng::with_seed(seed ^ player.0 as u64);
+        let mut jitter = || 1.0 + (rng.f32() * 2.0 - 1.0) * balance.personality_jitter;
+        Self {
+            threshold: jitter(),
+            aggression: jitter(),
+        }
+    }
+
+    /// Applies the personality to the balance.
+    fn apply(self, balance: &GameBalance) -> GameBalance {
+        GameBalance {
+            flag_threshold: balance.flag_threshold * self.threshold,
+            persistent_flag_threshold: balance.persistent_flag_threshold * self.threshold,
+            persistent_unflag_threshold: balance.persistent_unflag_threshold * self.threshold,
+            army_exponent: balance.army_exponent * self.aggression,
+            persistent_army_exponent: balance.persistent_army_exponent * self.aggression,
+            ..*balance
+        }
+    }
 }
 
 /// Greedy strategy for a [`King`].
@@ -106,6 +258,14 @@ pub enum Strategy {
     /// Have more desire to control mines.
     /// Will never place flags.
     Midas,
+    /// Upgrades its cities before expanding,
+    /// keeps gold in reserve, and only places
+    /// flags around its cities when threatened.
+    Turtle,
+    /// Rushes the nearest enemy fortress from the start,
+    /// only founding villages, and retreats to its cities
+    /// once its population collapses.
+    Swarm,
 }
 
 impl Strategy {
@@ -118,30 +278,35 @@ impl Strategy {
     }
 
     #[inline]
-    const fn city_spread_val(self, city: HabitLand) -> i32 {
+    const fn city_spread_val(self, city: HabitLand, balance: &GameBalance) -> i32 {
         match (self, city) {
-            (Self::Noble, HabitLand::Fortress) => 32,
-            (_, HabitLand::Fortress) => 16,
-            (_, HabitLand::Town) => 8,
-            (Self::Noble, HabitLand::Village) => 2,
-            (_, HabitLand::Village) => 4,
+            (Self::Noble, HabitLand::Fortress) => balance.noble_fortress_spread,
+            (Self::Turtle, HabitLand::Fortress) => balance.turtle_fortress_spread,
+            (_, HabitLand::Fortress) => balance.fortress_spread,
+            (Self::Turtle, HabitLand::Town) => balance.turtle_town_spread,
+            (_, HabitLand::Town) => balance.town_spread,
+            (Self::Noble, HabitLand::Village) => balance.noble_village_spread,
+            (_, HabitLand::Village) => balance.village_spread,
             _ => 0,
         }
     }
 
     #[inline]
-    const fn mine_spread_val(self) -> i32 {
+    const fn mine_spread_val(self, balance: &GameBalance) -> i32 {
         match self {
-            Self::Midas => 8,
-            _ => 4,
+            Self::Midas => balance.midas_mine_spread,
+            _ => balance.mine_spread,
         }
     }
 
     #[inline]
-    #[allow(clippy::single_match)]
-    fn process_base(self, val: impl FnOnce() -> i32, base: &mut f32) {
-        match self {
-            Self::Midas => *base *= (val() + 10) as f32,
+    fn process_base(self, land: HabitLand, val: impl FnOnce() -> i32, base: &mut f32) {
+        match (self, land) {
+            (Self::Midas, _) => *base *= (val() + 10) as f32,
+            // Upgrades come before new villages.
+            (Self::Turtle, HabitLand::Grassland) => *base *= 0.1,
+            // Gold is not spent on upgrades.
+            (Self::Swarm, HabitLand::Village | HabitLand::Town) => *base = 0.0,
             _ => (),
         }
     }
@@ -155,55 +320,101 @@ impl King {
             values: vec![vec![0; height as usize]; width as usize],
             player,
             strategy,
+            personality: Personality::default(),
+            allies: vec![],
         }
     }
 
+    /// Sets the personality of the king.
+    #[inline]
+    pub fn set_personality(&mut self, personality: Personality) {
+        self.personality = personality;
+    }
+
+    /// Sets the players on the team of the king, whose units
+    /// it does not count as enemies.
+    #[inline]
+    pub fn set_allies(&mut self, allies: Vec<Player>) {
+        self.allies = allies;
+    }
+
+    /// Whether the given player is the king itself or one of its allies.
+    #[inline]
+    pub fn is_ally(&self, player: Player) -> bool {
+        player == self.player || self.allies.contains(&player)
+    }
+
+    /// Units on a tile fighting the king, of neither it nor its allies.
+    fn enemy(&self, units: &[u16; MAX_PLAYERS]) -> u16 {
+        units
+            .iter()
+            .enumerate()
+            .filter(|&(p, _)| !self.is_ally(Player(p as u32)))
+            .map(|(_, units)| units)
+            .sum()
+    }
+
+    /// Evaluation of the map shared by the given allied kings,
+    /// the mean of their own.
+    ///
+    /// See [`Self::set_map`].
+    pub fn shared_map(team: &[&King]) -> Vec<Vec<i32>> {
+        let mut values = team
+            .first()
+            .map_or_else(Vec::new, |king| king.values.clone());
+        for king in team.iter().skip(1) {
+            for (arr, arr_k) in values.iter_mut().zip(&king.values) {
+                arr.iter_mut().zip(arr_k).for_each(|(val, v)| *val += v);
+            }
+        }
+        let len = team.len().max(1) as i32;
+        values.iter_mut().flatten().for_each(|val| *val /= len);
+        values
+    }
+
+    /// Replaces the evaluation of the map, until it is
+    /// [evaluated](Self::evaluate_map) again.
+    #[inline]
+    pub fn set_map(&mut self, values: Vec<Vec<i32>>) {
+        self.values = values;
+    }
+
     /// Evaluates the grid.
     ///
     /// Difficulty determines the quality of evaluation.
-    pub fn evaluate_map(&mut self, grid: &Grid, difficulty: Difficulty) {
+    pub fn evaluate_map(&mut self, grid: &Grid, difficulty: Difficulty, balance: &GameBalance) {
         self.values.iter_mut().for_each(|a| a.fill(0));
         let mut u = self.values.clone();
 
-        enum Pt {
-            Land(HabitLand),
-            Mine,
-        }
-
-        for (i, arr) in grid.raw_tiles().iter().enumerate() {
-            for (j, pt) in arr.iter().enumerate().filter_map(|(j, t)| match t {
-                Tile::Habitable { land, .. } => Some((j, Pt::Land(*land))),
-                Tile::Mine(_) => Some((j, Pt::Mine)),
-                _ => None,
-            }) {
-                match pt {
-                    Pt::Land(l) => {
-                        self.values[i][j] += self.strategy.habitable_tile_val_addition();
-
-                        let pos = Pos(i as i32, j as i32);
+        for (pos @ Pos(i, j), tile) in grid.tiles() {
+            match tile {
+                Tile::Habitable { land, .. } => {
+                    self.values[i as usize][j as usize] +=
+                        self.strategy.habitable_tile_val_addition();
+
+                    grid.spread(
+                        &mut u,
+                        &mut self.values,
+                        pos,
+                        self.strategy.city_spread_val(*land, balance),
+                        1,
+                    );
+                    grid.even(&mut u, pos, 0);
+                }
+                Tile::Mine(_) => {
+                    for dir in Pos::DIRS {
+                        let pos = pos.offset(dir);
                         grid.spread(
                             &mut u,
                             &mut self.values,
                             pos,
-                            self.strategy.city_spread_val(l),
+                            self.strategy.mine_spread_val(balance),
                             1,
                         );
                         grid.even(&mut u, pos, 0);
                     }
-                    Pt::Mine => {
-                        for Pos(di, dj) in Pos::DIRS {
-                            let pos = Pos(di + i as i32, dj + j as i32);
-                            grid.spread(
-                                &mut u,
-                                &mut self.values,
-                                pos,
-                                self.strategy.mine_spread_val(),
-                                1,
-                            );
-                            grid.even(&mut u, pos, 0);
-                        }
-                    }
                 }
+                _ => {}
             }
         }
 
@@ -212,10 +423,10 @@ impl King {
             for val in arr.iter_mut() {
                 match difficulty {
                     Difficulty::Easiest => {
-                        *val = *val / 4 + fastrand::i32(..7) - 3;
+                        *val = *val / 4 + crate::rng::i32(..7) - 3;
                     }
                     Difficulty::Easy => {
-                        *val = *val / 2 + fastrand::i32(..3) - 1;
+                        *val = *val / 2 + crate::rng::i32(..3) - 1;
                     }
                     _ => (),
                 }
@@ -226,27 +437,52 @@ impl King {
     /// Build cities and returns whether something
     /// was built.
     ///
-    /// The strategy is same for all AIs.
-    pub fn build(&self, grid: &mut Grid, country: &mut Country) -> bool {
+    /// The strategy is same for all AIs, except that
+    /// the [`reserve`](Self::reserve) is never spent.
+    pub fn build(&self, grid: &mut Grid, country: &mut Country, balance: &GameBalance) -> bool {
         assert_eq!(self.player, country.player);
 
+        let reserve = self.reserve(balance);
+        self.best_build(grid).is_some_and(|pos| {
+            let affordable = match grid.tile(pos) {
+                Some(&Tile::Habitable { mut land, .. }) => land.upgrade().is_some_and(|_| {
+                    country.gold
+                        >= grid
+                            .price(self.player, land, balance)
+                            .saturating_add(reserve)
+                }),
+                _ => false,
+            };
+            affordable && grid.build(country, pos, balance).is_ok()
+        })
+    }
+
+    /// Gold the king keeps rather than build with.
+    #[inline]
+    pub fn reserve(&self, balance: &GameBalance) -> u64 {
+        match self.strategy {
+            Strategy::Turtle => balance.turtle_reserve,
+            _ => 0,
+        }
+    }
+
+    /// Finds the tile where building is most valuable,
+    /// regardless of its price.
+    ///
+    /// Tiles bordering habitable tiles of others than allies
+    /// are never chosen.
+    pub fn best_build(&self, grid: &Grid) -> Option<Pos> {
         let mut v_best = 0.0;
         let (mut i_best, mut j_best) = (0, 0);
 
         for (i, arr) in grid.raw_tiles().iter().enumerate() {
             for (j, tile) in arr.iter().enumerate() {
-                let mut ok = false;
-                if tile.owner() == self.player && tile.is_habitable() {
-                    ok = true;
-                    for Pos(di, dj) in Pos::DIRS {
-                        if let Some(tile) = grid
-                            .tile(Pos(i as i32 + di, j as i32 + dj))
-                            .filter(|t| t.is_habitable())
-                        {
-                            ok = ok && tile.owner() == self.player;
-                        }
-                    }
-                }
+                let ok = tile.owner() == self.player
+                    && tile.is_habitable()
+                    && grid
+                        .neighbors(Pos(i as i32, j as i32))
+                        .filter(|(_, t)| t.is_habitable())
+                        .all(|(_, t)| self.is_ally(t.owner()));
 
                 if let Tile::Habitable { units, land, .. } = tile {
                     let pl = self.player.0 as usize;
@@ -258,7 +494,8 @@ impl King {
                         HabitLand::Town => 32.0,
                         _ => 0.0,
                     };
-                    self.strategy.process_base(|| self.values[i][j], &mut base);
+                    self.strategy
+                        .process_base(*land, || self.values[i][j], &mut base);
                     let v = if ok {
                         base * (MAX_POPULATION - army) as f32
                     } else {
@@ -274,20 +511,16 @@ impl King {
             }
         }
 
-        if v_best > 0.0 {
-            grid.build(country, Pos(i_best as i32, j_best as i32))
-                .is_ok()
-        } else {
-            false
-        }
+        (v_best > 0.0).then_some(Pos(i_best as i32, j_best as i32))
     }
 
     /// Place flags based on the strategy.
     #[inline]
-    pub fn place_flags(&self, grid: &Grid, fg: &mut FlagGrid) {
+    pub fn place_flags(&self, grid: &Grid, fg: &mut FlagGrid, balance: &GameBalance) {
+        let balance = &self.personality.apply(balance);
         macro_rules! action {
             ($f:ident) => {
-                $f(self, grid, fg)
+                $f(self, grid, fg, balance)
             };
         }
         match self.strategy {
@@ -296,17 +529,62 @@ impl King {
             Strategy::PersistentGreedy => action!(action_persistent_greedy),
             Strategy::Opportunist => action!(action_opportunist),
             Strategy::Noble => action!(action_noble),
+            Strategy::Turtle => action!(action_turtle),
+            Strategy::Swarm => action!(action_swarm),
             Strategy::None | Strategy::Midas => (),
         }
     }
 
+    /// Places flags like [`Self::place_flags`], leaving the given
+    /// tiles, flagged by allied kings, to them.
+    ///
+    /// Claimed tiles are worth nothing to the king, so that it
+    /// turns to other fronts instead.
+    pub fn place_flags_with_claims(
+        &self,
+        grid: &Grid,
+        fg: &mut FlagGrid,
+        balance: &GameBalance,
+        claimed: &[Pos],
+    ) {
+        if claimed.is_empty() {
+            return self.place_flags(grid, fg, balance);
+        }
+        let mut king = self.clone();
+        for &Pos(i, j) in claimed {
+            if let Some(val) = king
+                .values
+                .get_mut(i as usize)
+                .and_then(|arr| arr.get_mut(j as usize))
+            {
+                *val = 0;
+            }
+        }
+        king.place_flags(grid, fg, balance);
+        for &pos in claimed {
+            fg.remove(grid, pos, FLAG_POWER);
+        }
+    }
+
     #[inline]
     pub fn player(&self) -> Player {
         self.player
     }
+
+    #[inline]
+    pub fn strategy(&self) -> Strategy {
+        self.strategy
+    }
+
+    /// Sets the strategy of the king, which takes effect once
+    /// the map is [evaluated](Self::evaluate_map) again.
+    #[inline]
+    pub fn set_strategy(&mut self, strategy: Strategy) {
+        self.strategy = strategy;
+    }
 }
 
-fn action_aggr_greedy(king: &King, grid: &Grid, fg: &mut FlagGrid) {
+fn action_aggr_greedy(king: &King, grid: &Grid, fg: &mut FlagGrid, balance: &GameBalance) {
     for (i, (arr_g, arr_k)) in grid.raw_tiles().iter().zip(&king.values).enumerate() {
         for (j, (tile, val)) in arr_g.iter().zip(arr_k.iter().copied()).enumerate() {
             if let Tile::Habitable { units, .. } = tile {
@@ -314,9 +592,10 @@ fn action_aggr_greedy(king: &King, grid: &Grid, fg: &mut FlagGrid) {
 
                 let pl = king.player.0 as usize;
                 let army = units[pl];
-                let enemy = units[..pl].iter().sum::<u16>() + units[pl + 1..].iter().sum::<u16>();
-                if (val * (2 * enemy as i32 - army as i32)) as f32 * (army as f32).powf(0.5)
-                    > 5000.0
+                let enemy = king.enemy(units);
+                if (val * (2 * enemy as i32 - army as i32)) as f32
+                    * (army as f32).powf(balance.army_exponent)
+                    > balance.flag_threshold
                 {
                     fg.add(grid, pos, FLAG_POWER);
                 } else {
@@ -327,7 +606,7 @@ fn action_aggr_greedy(king: &King, grid: &Grid, fg: &mut FlagGrid) {
     }
 }
 
-fn action_one_greedy(king: &King, grid: &Grid, fg: &mut FlagGrid) {
+fn action_one_greedy(king: &King, grid: &Grid, fg: &mut FlagGrid, balance: &GameBalance) {
     let mut v_best = -1.0;
     let mut best_pos = Pos(0, 0);
     for (i, (arr_g, arr_k)) in grid.raw_tiles().iter().zip(&king.values).enumerate() {
@@ -340,9 +619,10 @@ fn action_one_greedy(king: &King, grid: &Grid, fg: &mut FlagGrid) {
             if let Tile::Habitable { units, .. } = tile {
                 let pl = king.player.0 as usize;
                 let army = units[pl];
-                let enemy = units[..pl].iter().sum::<u16>() + units[pl + 1..].iter().sum::<u16>();
-                let v = (val * (5 * enemy as i32 - army as i32)) as f32 * (army as f32).powf(0.5);
-                if v > v_best && v > 5000.0 {
+                let enemy = king.enemy(units);
+                let v = (val * (5 * enemy as i32 - army as i32)) as f32
+                    * (army as f32).powf(balance.army_exponent);
+                if v > v_best && v > balance.flag_threshold {
                     v_best = v;
                     best_pos = pos;
                 }
@@ -355,26 +635,27 @@ fn action_one_greedy(king: &King, grid: &Grid, fg: &mut FlagGrid) {
     }
 }
 
-fn action_persistent_greedy(king: &King, grid: &Grid, fg: &mut FlagGrid) {
+fn action_persistent_greedy(king: &King, grid: &Grid, fg: &mut FlagGrid, balance: &GameBalance) {
     for (i, (arr_g, arr_k)) in grid.raw_tiles().iter().zip(&king.values).enumerate() {
         for (j, (tile, val)) in arr_g.iter().zip(arr_k.iter().copied()).enumerate() {
             let pos = Pos(i as i32, j as i32);
             if let Tile::Habitable { units, .. } = tile {
                 let pl = king.player.0 as usize;
                 let army = units[pl];
-                let enemy = units[..pl].iter().sum::<u16>() + units[pl + 1..].iter().sum::<u16>();
-                let v = (val as f32 * (2.5 * enemy as f32 - army as f32) * (army as f32).powf(0.7))
+                let enemy = king.enemy(units);
+                let exp = balance.persistent_army_exponent;
+                let v = (val as f32 * (2.5 * enemy as f32 - army as f32) * (army as f32).powf(exp))
                     .max(if enemy > army {
                         (val * (MAX_POPULATION as i32 - enemy as i32 + army as i32)) as f32
-                            * (army as f32).powf(0.7)
+                            * (army as f32).powf(exp)
                             * 0.5
                     } else {
                         -1000.0
                     });
 
-                if fg.is_flagged(pos) && v < 900.0 {
+                if fg.is_flagged(pos) && v < balance.persistent_unflag_threshold {
                     fg.remove(grid, pos, FLAG_POWER);
-                } else if v > 9000.0 {
+                } else if v > balance.persistent_flag_threshold {
                     fg.add(grid, pos, FLAG_POWER);
                 }
             }
@@ -382,7 +663,7 @@ fn action_persistent_greedy(king: &King, grid: &Grid, fg: &mut FlagGrid) {
     }
 }
 
-fn action_opportunist(king: &King, grid: &Grid, fg: &mut FlagGrid) {
+fn action_opportunist(king: &King, grid: &Grid, fg: &mut FlagGrid, balance: &GameBalance) {
     for (i, (arr_g, arr_k)) in grid.raw_tiles().iter().zip(&king.values).enumerate() {
         for (j, (tile, val)) in arr_g.iter().zip(arr_k.iter().copied()).enumerate() {
             if let Tile::Habitable { units, .. } = tile {
@@ -390,11 +671,11 @@ fn action_opportunist(king: &King, grid: &Grid, fg: &mut FlagGrid) {
 
                 let pl = king.player.0 as usize;
                 let army = units[pl];
-                let enemy = units[..pl].iter().sum::<u16>() + units[pl + 1..].iter().sum::<u16>();
+                let enemy = king.enemy(units);
                 if enemy > army
                     && (val * (MAX_POPULATION as i32 - enemy as i32 + army as i32)) as f32
-                        * (army as f32).powf(0.5)
-                        > 5000.0
+                        * (army as f32).powf(balance.army_exponent)
+                        > balance.flag_threshold
                 {
                     fg.add(grid, pos, FLAG_POWER);
                 } else {
@@ -405,7 +686,7 @@ fn action_opportunist(king: &King, grid: &Grid, fg: &mut FlagGrid) {
     }
 }
 
-fn action_noble(king: &King, grid: &Grid, fg: &mut FlagGrid) {
+fn action_noble(king: &King, grid: &Grid, fg: &mut FlagGrid, balance: &GameBalance) {
     const MAX_PRIORITY: usize = 32;
 
     struct PosVal<const N: usize> {
@@ -478,11 +759,11 @@ fn action_noble(king: &King, grid: &Grid, fg: &mut FlagGrid) {
 
                 let pl = king.player.0 as usize;
                 let army = units[pl];
-                let enemy = units.iter().sum::<u16>() - army;
+                let enemy = king.enemy(units);
                 let v = (val * (MAX_POPULATION as i32 - (enemy as i32 - army as i32))) as f32
-                    * (army as f32).sqrt();
+                    * (army as f32).powf(balance.army_exponent);
 
-                if enemy > army && v > 5000.0 {
+                if enemy > army && v > balance.flag_threshold {
                     pos_val.insert(pos, v as i32)
                 }
             }
@@ -497,3 +778,95 @@ fn action_noble(king: &King, grid: &Grid, fg: &mut FlagGrid) {
         .map(|(p, _)| p)
         .for_each(|p| fg.add(grid, p, FLAG_POWER));
 }
+
+fn action_turtle(king: &King, grid: &Grid, fg: &mut FlagGrid, balance: &GameBalance) {
+    let pl = king.player.0 as usize;
+    let enemy_at = |tile: &Tile| king.enemy(tile.units());
+    for (i, (arr_g, arr_k)) in grid.raw_tiles().iter().zip(&king.values).enumerate() {
+        for (j, (tile, val)) in arr_g.iter().zip(arr_k.iter().copied()).enumerate() {
+            if let Tile::Habitable { units, .. } = tile {
+                let pos = Pos(i as i32, j as i32);
+
+                // Only its own cities and their surroundings are defended.
+                let own_city = |t: &Tile| t.is_city() && t.owner() == king.player;
+                let near_city = own_city(tile) || grid.neighbors(pos).any(|(_, t)| own_city(t));
+                let army = units[pl];
+                let enemy =
+                    enemy_at(tile) + grid.neighbors(pos).map(|(_, t)| enemy_at(t)).sum::<u16>();
+                if near_city
+                    && (val * enemy as i32) as f32 * (army as f32).powf(balance.army_exponent)
+                        > balance.flag_threshold
+                {
+                    fg.add(grid, pos, FLAG_POWER);
+                } else {
+                    fg.remove(grid, pos, FLAG_POWER);
+                }
+            }
+        }
+    }
+}
+
+fn action_swarm(king: &King, grid: &Grid, fg: &mut FlagGrid, balance: &GameBalance) {
+    let mut pops = [0u32; MAX_PLAYERS];
+    for (_, tile) in grid.tiles() {
+        for (pop, units) in pops.iter_mut().zip(tile.units()) {
+            *pop += *units as u32;
+        }
+    }
+    let pl = king.player.0 as usize;
+    let strongest = pops
+        .iter()
+        .enumerate()
+        .filter(|&(p, _)| !king.is_ally(Player(p as u32)) && !Player(p as u32).is_neutral())
+        .map(|(_, pop)| *pop)
+        .max()
+        .unwrap_or(0);
+    let retreat = (pops[pl] as f32) < strongest as f32 * balance.swarm_retreat;
+
+    // The own city closest to the target leads the rush.
+    let cities: Vec<Pos> = grid.cities(king.player).map(|(pos, _)| pos).collect();
+    let closest = |pos: Pos| cities.iter().copied().min_by_key(|c| c.hex_distance(pos));
+    let rush = if retreat {
+        None
+    } else {
+        grid.tiles()
+            .filter_map(|(pos, tile)| match tile {
+                Tile::Habitable { land, .. }
+                    if tile.is_city()
+                        && !king.is_ally(tile.owner())
+                        && !tile.owner().is_neutral() =>
+                {
+                    let from = closest(pos)?;
+                    Some((
+                        *land != HabitLand::Fortress,
+                        from.hex_distance(pos),
+                        pos,
+                        from,
+                    ))
+                }
+                _ => None,
+            })
+            .min_by_key(|&(not_fortress, dist, _, _)| (not_fortress, dist))
+            .map(|(_, _, target, from)| (target, from))
+    };
+
+    for (pos, tile) in grid.tiles() {
+        if !tile.is_habitable() {
+            continue;
+        }
+        let flag = match rush {
+            // Tiles on the shortest ways from the city to the target.
+            Some((target, from)) => {
+                pos != from
+                    && from.hex_distance(pos) + pos.hex_distance(target)
+                        == from.hex_distance(target)
+            }
+            None => tile.is_city() && tile.owner() == king.player,
+        };
+        if flag {
+            fg.add(grid, pos, FLAG_POWER);
+        } else {
+            fg.remove(grid, pos, FLAG_POWER);
+        }
+    }
+}
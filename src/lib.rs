@@ -1,4 +1,11 @@
-use std::fmt::Display;
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(not(any(feature = "std", feature = "libm")))]
+compile_error!("building without the `std` feature requires the `libm` feature");
+
+extern crate alloc;
+
+use core::fmt::Display;
 
 macro_rules! in_segment {
     ($x:expr, $l:expr, $r:expr) => {
@@ -12,8 +19,25 @@ macro_rules! in_segment {
     };
 }
 
+macro_rules! rnd_round {
+    ($x:expr) => {{
+        let mut i = $x as i32;
+        if crate::rng::f32() < ($x - i as f32) {
+            i += 1;
+        }
+        i
+    }};
+}
+
+pub mod adaptive;
+pub mod balance;
+#[cfg(not(feature = "std"))]
+mod float;
 pub mod grid;
 pub mod king;
+mod rng;
+pub mod rules;
+pub mod snapshot;
 pub mod state;
 
 pub const MAX_WIDTH: u32 = 40;
@@ -22,7 +46,8 @@ pub const MAX_HEIGHT: u32 = 29;
 pub const MAX_PLAYERS: usize = 8;
 pub const MAX_POPULATION: u16 = 499;
 
-pub use grid::{FlagGrid, Grid, Pos, FLAG_POWER};
+pub use balance::{GameBalance, Preset};
+pub use grid::{FlagGrid, Grid, Pos, FLAG_POWER, MAX_FLAG_POWER};
 pub use king::{Country, King, Strategy};
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
@@ -35,10 +60,24 @@ impl Player {
     pub fn is_neutral(self) -> bool {
         self == Self::NEUTRAL
     }
+
+    /// Gets the index of the player into per-player arrays,
+    /// such as the [countries](state::State::countries) of a game.
+    ///
+    /// Fails if the id is out of range, as in malformed messages.
+    #[inline]
+    pub fn index(self) -> Result<usize> {
+        let i = self.0 as usize;
+        if i < MAX_PLAYERS {
+            Ok(i)
+        } else {
+            Err(Error::PlayerNotFound(self))
+        }
+    }
 }
 
 impl Display for Player {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         if self.is_neutral() {
             write!(f, "neutral")
         } else {
@@ -65,12 +104,16 @@ pub enum Error {
     },
     /// The target tile is not habitable.
     TileNotHabitable(Pos),
+    /// The target tile is not a city.
+    NotCity(Pos),
     /// Trying to upgrade a fortress, which
     /// cannot be upgraded anymore.
     UpgradeTopLevelBuilding,
     /// Trying to degrade grassland, which
     /// cannot be degraded anymore.
     DegradeGrassLand,
+    /// The target tile already has a road.
+    RoadExists(Pos),
     /// Money not enough.
     InsufficientGold {
         required: u64,
@@ -86,7 +129,7 @@ pub enum Error {
 }
 
 impl Display for Error {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match self {
             Error::ConflictDiffOutOfBound => write!(
                 f,
@@ -104,8 +147,10 @@ impl Display for Error {
                 "{operator} is not the owner of tile {tile:?} (owner: {owner})"
             ),
             Error::TileNotHabitable(pos) => write!(f, "tile {pos:?} is not habitable"),
+            Error::NotCity(pos) => write!(f, "tile {pos:?} is not a city"),
             Error::UpgradeTopLevelBuilding => write!(f, "upgrading a fortress is illegal"),
             Error::DegradeGrassLand => write!(f, "degrading grassland is illegal"),
+            Error::RoadExists(pos) => write!(f, "tile {pos:?} already has a road"),
             Error::InsufficientGold { required, owning } => write!(
                 f,
                 "gold not enough: required {required}, player owns {owning}"
@@ -118,9 +163,9 @@ impl Display for Error {
     }
 }
 
-impl std::error::Error for Error {}
+impl core::error::Error for Error {}
 
-pub type Result<T> = std::result::Result<T, Error>;
+pub type Result<T> = core::result::Result<T, Error>;
 
 /// Game speed.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
@@ -0,0 +1,73 @@
+//! The random number generator the simulation draws from.
+//!
+//! With `std`, this is the thread-local generator of `fastrand`, so
+//! that seeding it here seeds it for the frontends as well. Without
+//! `std` there are no thread locals, and a single generator is shared
+//! by the whole program instead.
+
+#[cfg(feature = "std")]
+pub(crate) use fastrand::{bool, choice, f32, get_seed, i32, seed, shuffle, u32, u64, u8, usize};
+
+#[cfg(not(feature = "std"))]
+pub(crate) use global::*;
+
+#[cfg(not(feature = "std"))]
+mod global {
+    use core::{
+        ops::RangeBounds,
+        sync::atomic::{AtomicU64, Ordering},
+    };
+
+    use fastrand::Rng;
+
+    /// Seed of the generator, until it is seeded by a game.
+    static SEED: AtomicU64 = AtomicU64::new(0x4d59_5df4_d0f3_3173);
+
+    /// Runs `f` with the generator, keeping the seed it leaves.
+    ///
+    /// The generator is not meant to be drawn from by several threads
+    /// at once: the draws are not lost, but may repeat.
+    fn with_rng<T>(f: impl FnOnce(&mut Rng) -> T) -> T {
+        let mut rng = Rng::with_seed(SEED.load(Ordering::Relaxed));
+        let t = f(&mut rng);
+        SEED.store(rng.get_seed(), Ordering::Relaxed);
+        t
+    }
+
+    pub(crate) fn seed(seed: u64) {
+        SEED.store(seed, Ordering::Relaxed);
+    }
+
+    pub(crate) fn get_seed() -> u64 {
+        SEED.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn bool() -> bool {
+        with_rng(Rng::bool)
+    }
+
+    pub(crate) fn f32() -> f32 {
+        with_rng(Rng::f32)
+    }
+
+    pub(crate) fn choice<I: IntoIterator>(iter: I) -> Option<I::Item>
+    where
+        I::IntoIter: ExactSizeIterator,
+    {
+        with_rng(|rng| rng.choice(iter))
+    }
+
+    pub(crate) fn shuffle<T>(slice: &mut [T]) {
+        with_rng(|rng| rng.shuffle(slice))
+    }
+
+    macro_rules! ranged {
+        ($($t:ident),*) => {$(
+            pub(crate) fn $t(range: impl RangeBounds<$t>) -> $t {
+                with_rng(|rng| rng.$t(range))
+            }
+        )*};
+    }
+
+    ranged!(i32, u8, u32, u64, usize);
+}
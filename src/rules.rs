@@ -0,0 +1,52 @@
+//! Hooks for variants of the game rules.
+
+use crate::{grid::HabitLand, state::State, Player, Pos, MAX_PLAYERS, MAX_POPULATION};
+
+/// Rules consulted by [`State::simulate`].
+///
+/// Every method defaults to the behavior of the original game,
+/// so variants only override what they change.
+pub trait Rules: Send + Sync {
+    /// Gets the damage `pop` units of `player` at `pos` take
+    /// from `enemy_pop` units of other players in one step.
+    fn combat_damage(&self, pos: Pos, player: Player, pop: u16, enemy_pop: u16) -> i32 {
+        let _ = (pos, player);
+        let total_pop = pop as u32 + enemy_pop as u32;
+        if total_pop == 0 {
+            return 0;
+        }
+        rnd_round!(enemy_pop as f32 * pop as f32 / total_pop as f32)
+    }
+
+    /// Gets the population of the owner of a city
+    /// after one step of growth.
+    fn growth(&self, pos: Pos, land: HabitLand, pop: u16) -> u16 {
+        let _ = pos;
+        (rnd_round!(pop as f32 * land.growth()) as u16).min(MAX_POPULATION)
+    }
+
+    /// Gets the gold the owner of the mine at `pos` earns in one step.
+    fn mine_income(&self, pos: Pos, owner: Player) -> u64 {
+        let _ = (pos, owner);
+        1
+    }
+
+    /// Checks whether the game is over after a step,
+    /// and returns the winner if so.
+    ///
+    /// Defaults to the only player with population left, or with
+    /// [teams](State::teams), to the first of the only team left.
+    fn winner(&self, state: &State) -> Option<Player> {
+        let mut alive = (1..MAX_PLAYERS)
+            .filter(|&p| state.grid.tiles().any(|(_, t)| t.units()[p] > 0))
+            .map(|p| Player(p as u32));
+        let first = alive.next()?;
+        alive.all(|p| state.allied(first, p)).then_some(first)
+    }
+}
+
+/// Rules of the original game.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefaultRules;
+
+impl Rules for DefaultRules {}
@@ -0,0 +1,337 @@
+//! Compact snapshots of render-relevant game data.
+//!
+//! Frontends can compare two snapshots to redraw
+//! only the tiles that changed.
+
+use alloc::{vec, vec::Vec};
+
+use crate::{
+    grid::{HabitLand, Landscape, Tile},
+    state::State,
+    Player, Pos, MAX_PLAYERS,
+};
+
+/// Terrain of a tile in a [`Snapshot`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Terrain {
+    #[default]
+    Void,
+    Mountain,
+    Mine,
+    Habitable(HabitLand),
+}
+
+/// Render-relevant data of a single tile.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TileSnapshot {
+    pub terrain: Terrain,
+    pub landscape: Landscape,
+    pub road: bool,
+    pub owner: Player,
+    /// Population information of this tile.
+    pub units: [u16; MAX_PLAYERS],
+    /// Bit `p` is set if player `p` has a flag on this tile.
+    pub flags: u8,
+}
+
+impl TileSnapshot {
+    /// Gets population of the owner.
+    #[inline]
+    pub fn pop(&self) -> u16 {
+        self.units[self.owner.0 as usize]
+    }
+
+    /// Whether the given player has a flag on this tile.
+    #[inline]
+    pub fn is_flagged(&self, Player(p): Player) -> bool {
+        p < u8::BITS && self.flags & (1 << p) != 0
+    }
+}
+
+/// Magic bytes leading a [packed](Snapshot::pack_into) snapshot.
+pub const PACKED_MAGIC: [u8; 4] = *b"CORS";
+/// Size of the header of a packed snapshot.
+pub const PACKED_HEADER_SIZE: usize = 20;
+/// Size of each tile of a packed snapshot.
+pub const PACKED_TILE_SIZE: usize = 4;
+
+/// A tile that differs between two snapshots.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TileChange {
+    pub pos: Pos,
+    /// The new data of the tile.
+    pub tile: TileSnapshot,
+}
+
+/// A noteworthy change between two snapshots.
+///
+/// See [`Snapshot::events`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum GameEvent {
+    /// A city changed owner.
+    CityCaptured {
+        pos: Pos,
+        land: HabitLand,
+        from: Player,
+        to: Player,
+    },
+    /// A mine was taken from its owner.
+    MineLost { pos: Pos, from: Player, to: Player },
+    /// A player lost its last population.
+    PlayerEliminated(Player),
+    /// The conqueror of a city took gold from its old owner.
+    ///
+    /// Snapshots cannot tell this, see [`State::take_events`].
+    CityPlundered {
+        pos: Pos,
+        from: Player,
+        to: Player,
+        gold: u64,
+    },
+}
+
+/// Compact copy of render-relevant data of a [`State`].
+///
+/// See [`State::snapshot`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    width: u32,
+    height: u32,
+    time: u64,
+
+    /// Tiles, as `[x * height + y]`.
+    tiles: Vec<TileSnapshot>,
+}
+
+impl Snapshot {
+    /// Gets width of the snapshotted grid.
+    #[inline]
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Gets height of the snapshotted grid.
+    #[inline]
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Gets the time the snapshot was taken at.
+    #[inline]
+    pub fn time(&self) -> u64 {
+        self.time
+    }
+
+    #[inline]
+    fn index(&self, Pos(x, y): Pos) -> Option<usize> {
+        (x >= 0 && y >= 0 && (x as u32) < self.width && (y as u32) < self.height)
+            .then(|| x as usize * self.height as usize + y as usize)
+    }
+
+    /// Gets the tile from given position.
+    #[inline]
+    pub fn tile(&self, pos: Pos) -> Option<&TileSnapshot> {
+        self.index(pos).map(|i| &self.tiles[i])
+    }
+
+    /// Returns the changes turning this snapshot into `other`.
+    ///
+    /// If sizes of the snapshots differ, every tile
+    /// of `other` is reported.
+    pub fn diff(&self, other: &Self) -> Vec<TileChange> {
+        let same_size = self.width == other.width && self.height == other.height;
+        other
+            .tiles
+            .iter()
+            .enumerate()
+            .filter(|&(i, tile)| !same_size || self.tiles[i] != *tile)
+            .map(|(i, tile)| TileChange {
+                pos: Pos(
+                    (i / other.height as usize) as i32,
+                    (i % other.height as usize) as i32,
+                ),
+                tile: *tile,
+            })
+            .collect()
+    }
+
+    /// Returns the events turning this snapshot into `other`.
+    ///
+    /// If sizes of the snapshots differ, there are no events.
+    pub fn events(&self, other: &Self) -> Vec<GameEvent> {
+        if self.width != other.width || self.height != other.height {
+            return vec![];
+        }
+        let mut events: Vec<_> = self
+            .diff(other)
+            .into_iter()
+            .filter_map(|TileChange { pos, tile }| {
+                let old = self.tile(pos)?;
+                if old.owner == tile.owner {
+                    return None;
+                }
+                match (old.terrain, tile.terrain) {
+                    (_, Terrain::Habitable(land)) if land != HabitLand::Grassland => {
+                        Some(GameEvent::CityCaptured {
+                            pos,
+                            land,
+                            from: old.owner,
+                            to: tile.owner,
+                        })
+                    }
+                    (Terrain::Mine, Terrain::Mine) if !old.owner.is_neutral() => {
+                        Some(GameEvent::MineLost {
+                            pos,
+                            from: old.owner,
+                            to: tile.owner,
+                        })
+                    }
+                    _ => None,
+                }
+            })
+            .collect();
+
+        let alive = |snapshot: &Self, p: usize| snapshot.tiles.iter().any(|t| t.units[p] > 0);
+        events.extend(
+            (1..MAX_PLAYERS)
+                .filter(|&p| alive(self, p) && !alive(other, p))
+                .map(|p| GameEvent::PlayerEliminated(Player(p as u32))),
+        );
+        events
+    }
+
+    /// Gets the size of this snapshot when packed.
+    #[inline]
+    pub fn packed_len(&self) -> usize {
+        PACKED_HEADER_SIZE + self.tiles.len() * PACKED_TILE_SIZE
+    }
+
+    /// Packs this snapshot into a flat buffer for external readers.
+    ///
+    /// The header holds [`PACKED_MAGIC`], then the width, height
+    /// and time, followed by the tiles row by row, so the tile at
+    /// `(x, y)` comes `y * width + x` tiles after the header.
+    /// Each tile is its kind, owner and the population of its owner,
+    /// where kinds count from void, mountain, mine, grassland,
+    /// village and town up to fortress.
+    /// Integers are little-endian.
+    ///
+    /// The time is written last, so readers can tell a torn read
+    /// by reading it before and after the tiles.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `buf` is shorter than [`Self::packed_len`].
+    pub fn pack_into(&self, buf: &mut [u8]) {
+        let (header, tiles) = buf[..self.packed_len()].split_at_mut(PACKED_HEADER_SIZE);
+        header[..4].copy_from_slice(&PACKED_MAGIC);
+        header[4..8].copy_from_slice(&self.width.to_le_bytes());
+        header[8..12].copy_from_slice(&self.height.to_le_bytes());
+        for (i, tile) in self.tiles.iter().enumerate() {
+            let (x, y) = (i / self.height as usize, i % self.height as usize);
+            let at = (y * self.width as usize + x) * PACKED_TILE_SIZE;
+            tiles[at] = match tile.terrain {
+                Terrain::Void => 0,
+                Terrain::Mountain => 1,
+                Terrain::Mine => 2,
+                Terrain::Habitable(land) => 3 + land as u8,
+            };
+            tiles[at + 1] = tile.owner.0 as u8;
+            tiles[at + 2..at + PACKED_TILE_SIZE].copy_from_slice(&tile.pop().to_le_bytes());
+        }
+        header[12..20].copy_from_slice(&self.time.to_le_bytes());
+    }
+
+    /// Applies the given changes to this snapshot.
+    ///
+    /// Changes out of bounds are ignored.
+    pub fn patch(&mut self, changes: &[TileChange]) {
+        for change in changes {
+            if let Some(i) = self.index(change.pos) {
+                self.tiles[i] = change.tile;
+            }
+        }
+    }
+}
+
+impl State {
+    /// Takes a [`Snapshot`] of this state.
+    pub fn snapshot(&self) -> Snapshot {
+        let mut tiles =
+            Vec::with_capacity(self.grid.width() as usize * self.grid.height() as usize);
+        for (x, arr) in self.grid.raw_tiles().iter().enumerate() {
+            for (y, tile) in arr.iter().enumerate() {
+                let pos = Pos(x as i32, y as i32);
+                tiles.push(TileSnapshot {
+                    terrain: match tile {
+                        Tile::Void => Terrain::Void,
+                        Tile::Mountain => Terrain::Mountain,
+                        Tile::Mine(_) => Terrain::Mine,
+                        Tile::Habitable { land, .. } => Terrain::Habitable(*land),
+                    },
+                    landscape: tile.landscape(),
+                    road: tile.road(),
+                    owner: tile.owner(),
+                    units: *tile.units(),
+                    flags: self
+                        .fgs
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, fg)| fg.is_flagged(pos))
+                        .fold(0, |flags, (p, _)| flags | 1 << p),
+                });
+            }
+        }
+
+        Snapshot {
+            width: self.grid.width(),
+            height: self.grid.height(),
+            time: self.time,
+            tiles,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::BasicOpts;
+
+    #[test]
+    fn diff_reports_changed_tiles_only() {
+        let mut st = State::new(BasicOpts {
+            seed: 7,
+            ..Default::default()
+        })
+        .unwrap();
+        // Kings place flags but never build, so terrain stays.
+        st.balance.build_cooldown = [u32::MAX; 5];
+        let before = st.snapshot();
+        assert!(before.diff(&before.clone()).is_empty());
+
+        st.step_n(20);
+        let after = st.snapshot();
+        let changes = before.diff(&after);
+        assert!(!changes.is_empty());
+
+        let mut expected = vec![];
+        for x in 0..after.width() as i32 {
+            for y in 0..after.height() as i32 {
+                let (old, new) = (
+                    before.tile(Pos(x, y)).unwrap(),
+                    after.tile(Pos(x, y)).unwrap(),
+                );
+                assert_eq!(old.terrain, new.terrain);
+                if old.owner != new.owner || old.units != new.units || old.flags != new.flags {
+                    expected.push(TileChange {
+                        pos: Pos(x, y),
+                        tile: *new,
+                    });
+                }
+            }
+        }
+        assert_eq!(changes, expected);
+        assert!(after.diff(&st.snapshot()).is_empty());
+    }
+}
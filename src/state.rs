@@ -1,9 +1,16 @@
-use std::net::SocketAddr;
+use alloc::{boxed::Box, vec, vec::Vec};
+use core::{fmt::Display, time::Duration};
 
+#[cfg(not(feature = "std"))]
+use crate::float::Float as _;
 use crate::{
+    adaptive::Adaptive,
     grid::{HabitLand, Stencil, Tile, MAX_AVLBL_LOCS},
-    Country, Difficulty, FlagGrid, Grid, King, Player, Pos, Speed, Strategy, MAX_HEIGHT,
-    MAX_PLAYERS, MAX_POPULATION, MAX_WIDTH,
+    king::Personality,
+    rules::{DefaultRules, Rules},
+    snapshot::GameEvent,
+    Country, Difficulty, Error, FlagGrid, GameBalance, Grid, King, Player, Pos, Preset, Speed,
+    Strategy, FLAG_POWER, MAX_HEIGHT, MAX_PLAYERS, MAX_POPULATION, MAX_WIDTH,
 };
 
 #[derive(Debug)]
@@ -14,52 +21,68 @@ pub struct UI {
     pub xskip: u16,
     /// Total max number of tiles in horizontal direction.
     pub xlen: u16,
+    /// City whose rally point is being chosen.
+    pub rally_from: Option<Pos>,
 }
 
+/// Population of each player over time.
 pub struct Timeline {
     data: [[f32; Self::MAX_MARKS]; MAX_PLAYERS],
     /// Time when data was recorded.
     time: [u64; Self::MAX_MARKS],
 
-    /// The most recently updated time mark.
-    /// It can be used as index in two other fields.
+    /// Number of recorded time marks, oldest first.
+    /// Marks beyond it in two other fields are unused.
     ///
-    /// `0 <= mark < MAX_MAKRS`.
-    mark: usize,
+    /// `0 <= len <= MAX_MARKS`.
+    len: usize,
 }
 
 impl Timeline {
     pub const MAX_MARKS: usize = 72;
 
+    /// Records the population of each player as a new mark,
+    /// dropping the oldest one if all marks are used.
     pub fn update(&mut self, time: u64, grid: &Grid) {
-        if self.mark + 1 < Self::MAX_MARKS {
-            self.mark += 1;
+        if self.len < Self::MAX_MARKS {
+            self.len += 1;
         } else {
-            for i in 0..Self::MAX_MARKS {
-                self.time[i] = self.time[i + 1];
-                for p in 0..MAX_PLAYERS {
-                    self.data[p][i] = self.data[p][i + 1];
-                }
+            self.time.copy_within(1.., 0);
+            for data in &mut self.data {
+                data.copy_within(1.., 0);
             }
         }
 
-        self.time[self.mark] = time;
+        let mark = self.len - 1;
+        self.time[mark] = time;
         for p in 0..MAX_PLAYERS {
-            self.data[p][self.mark] = grid
-                .raw_tiles()
-                .iter()
-                .map(|a| a.iter().map(|t| t.units()[p]).sum::<u16>() as u32)
-                .sum::<u32>() as f32;
+            self.data[p][mark] = grid.population(Player(p as u32)) as f32;
         }
     }
+
+    /// Times of the recorded marks, oldest first.
+    #[inline]
+    pub fn times(&self) -> &[u64] {
+        &self.time[..self.len]
+    }
+
+    /// Population of the given player at the recorded marks,
+    /// oldest first.
+    #[inline]
+    pub fn population(&self, Player(p): Player) -> &[f32] {
+        self.data.get(p as usize).map_or(&[], |d| &d[..self.len])
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 #[non_exhaustive]
 pub struct BasicOpts {
     pub keep_random: bool,
     pub difficulty: Difficulty,
     pub speed: Speed,
+    /// Real time of a simulation tick in milliseconds.
+    /// Speeds step the game every few ticks.
+    pub tick_ms: u64,
 
     pub width: u32,
     pub height: u32,
@@ -72,6 +95,26 @@ pub struct BasicOpts {
     pub shape: Stencil,
 
     pub clients: usize,
+    pub balance: GameBalance,
+    /// Whether human players start with the [`Assist`]
+    /// of the difficulty.
+    pub assist: bool,
+    /// Seed of the [`Personality`] of kings, which
+    /// play without one if `None`.
+    pub ai_personality_seed: Option<u64>,
+    /// Preset the balance and speed were taken from, if any.
+    pub preset: Option<Preset>,
+    /// Whether kings shift their strategies with the aggression
+    /// of humans, see [`Adaptive`].
+    pub adaptive_ai: bool,
+    /// Whether the strategies dealt to kings rotate with the seed.
+    /// Otherwise every seed deals the lineup of the original game.
+    pub rotate_ai: bool,
+    /// Whether some grassland is generated as forest or hill.
+    pub landscapes: bool,
+    /// Number of teams players are dealt into in turn, if more
+    /// than one. Allies do not fight each other.
+    pub teams: Option<u32>,
 }
 
 impl Default for BasicOpts {
@@ -80,30 +123,203 @@ impl Default for BasicOpts {
             keep_random: false,
             difficulty: Default::default(),
             speed: Default::default(),
+            tick_ms: 10,
             width: 21,
             height: 21,
             locations: Stencil::default().max_locs(),
-            seed: fastrand::u64(..),
+            seed: crate::rng::u64(..),
             conditions: Default::default(),
             timeline: false,
             inequality: Default::default(),
             shape: Default::default(),
             clients: 1,
+            balance: Default::default(),
+            assist: false,
+            ai_personality_seed: None,
+            preset: None,
+            adaptive_ai: false,
+            rotate_ai: false,
+            landscapes: false,
+            teams: None,
         }
     }
 }
 
-#[derive(Default, Debug)]
-pub enum MultiplayerOpts {
-    Server {
-        port: u16,
-    },
-    Client {
-        server: SocketAddr,
-        port: u16,
-    },
-    #[default]
-    None,
+/// Extra starting resources of human players,
+/// helping new players against the AI.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Assist {
+    pub gold: u64,
+    /// Population added to the starting city.
+    pub pop: u16,
+}
+
+impl Assist {
+    /// Gets the assist granted on the given difficulty.
+    ///
+    /// Only the easy difficulties grant one.
+    pub const fn of(difficulty: Difficulty) -> Self {
+        match difficulty {
+            Difficulty::Easiest => Self { gold: 320, pop: 20 },
+            Difficulty::Easy => Self { gold: 160, pop: 10 },
+            _ => Self { gold: 0, pop: 0 },
+        }
+    }
+
+    /// Whether anything is granted.
+    #[inline]
+    pub const fn is_active(self) -> bool {
+        self.gold > 0 || self.pop > 0
+    }
+}
+
+/// Aggregate statistics of a player.
+///
+/// See [`State::stats`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PlayerStats {
+    /// Total population on all tiles.
+    pub population: u32,
+    /// Numbers of villages, towns and fortresses owned.
+    pub cities: [usize; 3],
+    /// Number of mines held.
+    pub mines: usize,
+    pub gold: u64,
+}
+
+/// Reference to a moment of a game, given in bug reports so
+/// that crashes and desyncs can be reproduced.
+///
+/// Displayed as `seed-time-hash`. See [`State::fingerprint`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Fingerprint {
+    /// The map seed.
+    pub seed: u64,
+    /// Simulation time.
+    pub time: u64,
+    /// Hash of the options, the grid, the flags and the countries.
+    pub hash: u64,
+}
+
+impl Display for Fingerprint {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}-{}-{:016x}", self.seed, self.time, self.hash)
+    }
+}
+
+/// 64-bit FNV-1a hash, which unlike the hasher of the
+/// standard library is the same across platforms and builds.
+struct Fnv(u64);
+
+impl Fnv {
+    #[inline]
+    fn new() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn bytes(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 ^= b as u64;
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    #[inline]
+    fn u64(&mut self, value: u64) {
+        self.bytes(&value.to_le_bytes())
+    }
+
+    #[inline]
+    fn f32(&mut self, value: f32) {
+        self.u64(value.to_bits() as u64)
+    }
+
+    /// Hashes every field of the balance, so that adding one
+    /// without hashing it does not compile.
+    fn balance(&mut self, balance: &GameBalance) {
+        let GameBalance {
+            flag_threshold,
+            persistent_flag_threshold,
+            persistent_unflag_threshold,
+            army_exponent,
+            persistent_army_exponent,
+            flag_cooldown,
+            build_cooldown,
+            fortress_spread,
+            noble_fortress_spread,
+            town_spread,
+            village_spread,
+            noble_village_spread,
+            mine_spread,
+            midas_mine_spread,
+            turtle_fortress_spread,
+            turtle_town_spread,
+            turtle_reserve,
+            swarm_retreat,
+            personality_jitter,
+            attack,
+            movement,
+            call_move,
+            road_movement,
+            forest_defense,
+            hill_defense,
+            hill_growth,
+            growth,
+            price_scale,
+            city_price_multiplier,
+            king_income,
+            gold_score,
+            elimination_pop,
+            plunder,
+        } = *balance;
+        for value in [
+            flag_threshold,
+            persistent_flag_threshold,
+            persistent_unflag_threshold,
+            army_exponent,
+            persistent_army_exponent,
+            swarm_retreat,
+            personality_jitter,
+            attack,
+            movement,
+            call_move,
+            road_movement,
+            forest_defense,
+            hill_defense,
+            hill_growth,
+            growth,
+            price_scale,
+            city_price_multiplier,
+            gold_score,
+            plunder,
+        ] {
+            self.f32(value);
+        }
+        for cooldown in flag_cooldown.into_iter().chain(build_cooldown) {
+            self.u64(cooldown as u64);
+        }
+        for spread in [
+            fortress_spread,
+            noble_fortress_spread,
+            town_spread,
+            village_spread,
+            noble_village_spread,
+            mine_spread,
+            midas_mine_spread,
+            turtle_fortress_spread,
+            turtle_town_spread,
+        ] {
+            self.u64(spread as u64);
+        }
+        self.u64(turtle_reserve);
+        self.u64(king_income);
+        self.u64(elimination_pop as u64);
+    }
+
+    fn pos(&mut self, Pos(x, y): Pos) {
+        self.u64(x as u64);
+        self.u64(y as u64);
+    }
 }
 
 /// Game state.
@@ -125,26 +341,46 @@ pub struct State {
     pub seed: u64,
     /// Player id of the human controlled player.
     pub controlled: Player,
+    /// Players controlled by humans, between which
+    /// [`controlled`](Self::controlled) can be switched.
+    ///
+    /// See [`Self::switch_controlled`].
+    pub humans: Vec<Player>,
 
     pub conditions: Option<u32>,
     pub inequality: Option<u32>,
 
     pub speed: Speed,
     pub prev_speed: Speed,
+    /// Real time of a simulation tick.
+    pub tick: Duration,
     pub difficulty: Difficulty,
-}
+    pub balance: GameBalance,
+    /// Starting resources granted to human players.
+    pub assist: Assist,
+    /// Seed of the [`Personality`] of kings, if any.
+    pub ai_personality_seed: Option<u64>,
+    /// Preset of the balance and speed, if any.
+    pub preset: Option<Preset>,
+    /// Rules of the game, [`DefaultRules`] by default.
+    pub rules: Box<dyn Rules>,
+    /// Shifter of the strategies of kings, if adaptive.
+    pub adaptive: Option<Adaptive>,
 
-macro_rules! rnd_round {
-    ($x:expr) => {{
-        let mut i = $x as i32;
-        if fastrand::f32() < ($x - i as f32) {
-            i += 1;
-        }
-        i
-    }};
+    teams: Option<u32>,
+    winner: Option<Player>,
+    /// Events recorded by the simulation, oldest first.
+    ///
+    /// See [`Self::take_events`].
+    events: Vec<GameEvent>,
 }
 
 impl State {
+    /// Farthest distance enemies count toward [`Self::threat_at`].
+    pub const THREAT_RADIUS: u32 = 3;
+    /// Oldest events are dropped beyond this many.
+    const MAX_EVENTS: usize = 64;
+
     pub fn new(b_opt: BasicOpts) -> crate::Result<Self> {
         let width = b_opt.width.min(match b_opt.shape {
             Stencil::Rect => MAX_WIDTH + 10,
@@ -153,7 +389,7 @@ impl State {
         let height = b_opt.height.min(MAX_HEIGHT);
 
         const PLAYERS: usize = 7;
-        let time = (1850 + fastrand::u64(..100)) * 360 + fastrand::u64(..360);
+        let time = (1850 + crate::rng::u64(..100)) * 360 + crate::rng::u64(..360);
 
         let mut all_players = [Player::default(); PLAYERS];
         all_players
@@ -163,26 +399,39 @@ impl State {
         let comp_players = all_players[b_opt.clients..].to_vec();
         let ui_players = all_players[..b_opt.clients].to_vec();
 
+        // There are more strategies than computer players,
+        // so the lineup can rotate with the seed.
+        const ROTATION: [Strategy; 7] = [
+            Strategy::Opportunist,
+            Strategy::OneGreedy,
+            Strategy::Midas,
+            Strategy::AggrGreedy,
+            Strategy::Noble,
+            Strategy::PersistentGreedy,
+            Strategy::Turtle,
+        ];
+        let shift = match b_opt.rotate_ai {
+            true => (b_opt.seed % ROTATION.len() as u64) as usize,
+            false => 0,
+        };
         let mut kings: Vec<King> = (b_opt.clients..7)
             .map(|i| {
                 King::new(
                     Player(i as u32 + 1),
-                    match i as isize - b_opt.clients as isize {
-                        0 => Strategy::Opportunist,
-                        1 => Strategy::OneGreedy,
-                        2 => Strategy::Midas,
-                        3 => Strategy::AggrGreedy,
-                        4 => Strategy::Noble,
-                        5 => Strategy::PersistentGreedy,
-                        _ => unreachable!(),
-                    },
+                    ROTATION[(i - b_opt.clients + shift) % ROTATION.len()],
                     width,
                     height,
                 )
             })
             .collect();
 
-        fastrand::seed(b_opt.seed);
+        let assist = if b_opt.assist {
+            Assist::of(b_opt.difficulty)
+        } else {
+            Assist::default()
+        };
+
+        crate::rng::seed(b_opt.seed);
         let mut grid = Grid::new(b_opt.width, b_opt.height);
 
         // Map generation
@@ -204,29 +453,39 @@ impl State {
                     ui_players: &ui_players,
                     conditions: b_opt.conditions,
                     ineq: b_opt.inequality,
+                    ui_pop: assist.pop,
                 })
                 .is_ok_and(|_| grid.is_connected())
             {
                 break;
             }
         }
+        if b_opt.landscapes {
+            grid.scatter_landscapes(&mut fastrand::Rng::with_seed(b_opt.seed));
+        }
 
         let fgs = [0; MAX_PLAYERS].map(|_| FlagGrid::new(width, height));
         let mut countries = [0; MAX_PLAYERS];
         countries.iter_mut().enumerate().for_each(|(i, c)| *c = i);
-        let countries = countries.map(|c| Country::from(Player(c as u32)));
+        let mut countries = countries.map(|c| Country::from(Player(c as u32)));
+        for &Player(p) in &ui_players {
+            countries[p as usize].gold += assist.gold;
+        }
 
-        kings
-            .iter_mut()
-            .for_each(|k| k.evaluate_map(&grid, b_opt.difficulty));
+        for king in &mut kings {
+            if let Some(seed) = b_opt.ai_personality_seed {
+                king.set_personality(Personality::roll(seed, king.player(), &b_opt.balance));
+            }
+            king.evaluate_map(&grid, b_opt.difficulty, &b_opt.balance);
+        }
 
         let timeline = Timeline {
             data: [[0.0; Timeline::MAX_MARKS]; MAX_PLAYERS],
             time: [time; Timeline::MAX_MARKS],
-            mark: 0,
+            len: 0,
         };
 
-        Ok(Self {
+        let mut state = Self {
             grid,
             fgs,
             kings,
@@ -234,36 +493,616 @@ impl State {
             show_timeline: b_opt.timeline,
             countries,
             time,
-            seed: fastrand::get_seed(),
+            seed: crate::rng::get_seed(),
             controlled: Player(1),
+            humans: ui_players,
             conditions: b_opt.conditions,
             inequality: b_opt.inequality,
             speed: b_opt.speed,
             prev_speed: b_opt.speed,
+            tick: Duration::from_millis(b_opt.tick_ms),
             difficulty: b_opt.difficulty,
-        })
+            balance: b_opt.balance,
+            assist,
+            ai_personality_seed: b_opt.ai_personality_seed,
+            preset: b_opt.preset,
+            rules: Box::new(DefaultRules),
+            adaptive: b_opt.adaptive_ai.then(Adaptive::default),
+            teams: b_opt.teams.filter(|&teams| teams > 1),
+            winner: None,
+            events: vec![],
+        };
+        for i in 0..state.kings.len() {
+            let allies = state.allies(state.kings[i].player());
+            state.kings[i].set_allies(allies);
+        }
+        state.share_maps();
+        // Players left without a location are out from the start.
+        state.check_eliminations();
+        Ok(state)
     }
 
-    /// Kings build cities and place flags.
+    /// Kings build cities and place flags, as often as the
+    /// cooldowns of the balance allow at the difficulty.
     pub fn kings_move(&mut self) {
         let mut ev = false;
+        let flag_cooldown = self.balance.flag_cooldown[self.difficulty as usize].max(1) as u64;
+        let build_cooldown = self.balance.build_cooldown[self.difficulty as usize].max(1) as u64;
+        if let Some(adaptive) = &mut self.adaptive {
+            ev = adaptive.step(&self.grid, &mut self.kings, &self.humans);
+        }
         for king in &self.kings {
-            let Player(pl) = king.player();
-            king.place_flags(&self.grid, &mut self.fgs[pl as usize]);
-            let res = king.build(&mut self.grid, &mut self.countries[pl as usize]);
-            ev = ev || res;
+            let Ok(i) = king.player().index() else {
+                continue;
+            };
+            // Staggered by player, so kings do not all act on the same step.
+            let turn = self.time + i as u64;
+            if turn.is_multiple_of(flag_cooldown) {
+                // Tiles flagged by allied kings are left to them.
+                let claimed: Vec<Pos> = match self.teams {
+                    Some(_) => self
+                        .grid
+                        .tiles()
+                        .map(|(pos, _)| pos)
+                        .filter(|&pos| {
+                            self.kings.iter().any(|k| {
+                                k.player() != king.player()
+                                    && king.is_ally(k.player())
+                                    && self
+                                        .flag_grid(k.player())
+                                        .is_ok_and(|fg| fg.is_flagged(pos))
+                            })
+                        })
+                        .collect(),
+                    None => vec![],
+                };
+                king.place_flags_with_claims(&self.grid, &mut self.fgs[i], &self.balance, &claimed);
+            }
+            if turn.is_multiple_of(build_cooldown) {
+                let res = king.build(&mut self.grid, &mut self.countries[i], &self.balance);
+                ev = ev || res;
+            }
         }
         if ev {
-            for king in &mut self.kings {
-                king.evaluate_map(&self.grid, self.difficulty);
+            self.evaluate_kings();
+        }
+    }
+
+    /// Evaluates the map for every king.
+    fn evaluate_kings(&mut self) {
+        for king in &mut self.kings {
+            king.evaluate_map(&self.grid, self.difficulty, &self.balance);
+        }
+        self.share_maps();
+    }
+
+    /// Has allied kings share the mean of their evaluations
+    /// of the map, so that they weigh fronts alike.
+    fn share_maps(&mut self) {
+        if self.teams.is_none() {
+            return;
+        }
+        let maps: Vec<_> = self
+            .kings
+            .iter()
+            .map(|king| {
+                let team: Vec<&King> = self
+                    .kings
+                    .iter()
+                    .filter(|k| king.is_ally(k.player()))
+                    .collect();
+                King::shared_map(&team)
+            })
+            .collect();
+        for (king, map) in self.kings.iter_mut().zip(maps) {
+            king.set_map(map);
+        }
+    }
+
+    /// Advances the game by the given number of steps,
+    /// kings moving before each step.
+    ///
+    /// Turn-based games advance a whole turn with this.
+    pub fn step_n(&mut self, ticks: u32) {
+        for _ in 0..ticks {
+            self.kings_move();
+            self.simulate();
+        }
+    }
+
+    /// Hands control of the given player over to a new [`King`].
+    ///
+    /// Does nothing if the player is already controlled by a king,
+    /// or is eliminated.
+    pub fn hand_to_king(&mut self, player: Player, strategy: Strategy) {
+        if self.is_eliminated(player) || self.kings.iter().any(|k| k.player() == player) {
+            return;
+        }
+        let mut king = King::new(player, strategy, self.grid.width(), self.grid.height());
+        if let Some(seed) = self.ai_personality_seed {
+            king.set_personality(Personality::roll(seed, player, &self.balance));
+        }
+        king.set_allies(self.allies(player));
+        king.evaluate_map(&self.grid, self.difficulty, &self.balance);
+        self.kings.push(king);
+        // Shared maps are evaluated anew to take the king in.
+        if self.teams.is_some() {
+            self.evaluate_kings();
+        }
+    }
+
+    /// Switches [`controlled`](Self::controlled) to the next of
+    /// [`humans`](Self::humans) still playing, neither eliminated
+    /// nor controlled by a king, and returns it.
+    ///
+    /// Keeps the controlled player if no other one is playing.
+    pub fn switch_controlled(&mut self) -> Player {
+        if let Some(next) = self.next_playing(&self.humans, self.controlled) {
+            self.controlled = next;
+        }
+        self.controlled
+    }
+
+    /// Gets the next of the given players after `current` still
+    /// playing, neither eliminated nor controlled by a king, going
+    /// around to `current` itself last.
+    pub fn next_playing(&self, players: &[Player], current: Player) -> Option<Player> {
+        let len = players.len();
+        let at = players
+            .iter()
+            .position(|&p| p == current)
+            .unwrap_or_default();
+        (1..=len)
+            .map(|i| players[(at + i) % len])
+            .find(|&p| !self.is_eliminated(p) && !self.kings.iter().any(|k| k.player() == p))
+    }
+
+    /// Takes control of the given player back from its [`King`].
+    ///
+    /// Returns whether the player was controlled by a king.
+    pub fn take_from_king(&mut self, player: Player) -> bool {
+        let len = self.kings.len();
+        self.kings.retain(|k| k.player() != player);
+        self.kings.len() != len
+    }
+
+    /// Gets the country of the given player.
+    #[inline]
+    pub fn country(&self, player: Player) -> crate::Result<&Country> {
+        Ok(&self.countries[player.index()?])
+    }
+
+    /// Gets the country of the given player mutably.
+    #[inline]
+    pub fn country_mut(&mut self, player: Player) -> crate::Result<&mut Country> {
+        Ok(&mut self.countries[player.index()?])
+    }
+
+    /// Gets the flag grid of the given player.
+    #[inline]
+    pub fn flag_grid(&self, player: Player) -> crate::Result<&FlagGrid> {
+        Ok(&self.fgs[player.index()?])
+    }
+
+    /// Gets the flag grid of the given player mutably.
+    #[inline]
+    pub fn flag_grid_mut(&mut self, player: Player) -> crate::Result<&mut FlagGrid> {
+        Ok(&mut self.fgs[player.index()?])
+    }
+
+    /// Gets the king playing the given player, if any.
+    #[inline]
+    pub fn king(&self, player: Player) -> Option<&King> {
+        self.kings.iter().find(|k| k.player() == player)
+    }
+
+    /// Whether the given player is out of the game.
+    #[inline]
+    pub fn is_eliminated(&self, player: Player) -> bool {
+        self.country(player).is_ok_and(|c| c.eliminated)
+    }
+
+    /// Eliminates the given player: its king stops acting,
+    /// its flags are cleared, and its remaining units disband,
+    /// reverting its tiles and mines to neutral.
+    pub fn eliminate(&mut self, player: Player) {
+        let p = player.0 as usize;
+        if player.is_neutral() || p >= MAX_PLAYERS {
+            return;
+        }
+        self.countries[p].eliminated = true;
+        self.countries[p].build_queue.clear();
+        self.take_from_king(player);
+        self.fgs[p].remove_with_prob(&self.grid, 1.);
+        for arr in self.grid.raw_tiles_mut() {
+            for tile in arr {
+                match tile {
+                    Tile::Mine(owner) if *owner == player => *owner = Player::NEUTRAL,
+                    Tile::Habitable {
+                        units,
+                        owner,
+                        rally,
+                        ..
+                    } => {
+                        units[p] = 0;
+                        if *owner == player {
+                            *owner = Player::NEUTRAL;
+                            *rally = None;
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    /// Eliminates players without cities whose population
+    /// fell below [`GameBalance::elimination_pop`].
+    fn check_eliminations(&mut self) {
+        for p in 1..MAX_PLAYERS {
+            let player = Player(p as u32);
+            if self.countries[p].eliminated || self.grid.cities(player).next().is_some() {
+                continue;
+            }
+            let pop: u32 = self.grid.tiles().map(|(_, t)| t.units()[p] as u32).sum();
+            if pop < self.balance.elimination_pop {
+                self.eliminate(player);
+            }
+        }
+    }
+
+    /// Takes the events recorded by the simulation since the last call,
+    /// which cannot be told from [snapshots](Self::snapshot).
+    pub fn take_events(&mut self) -> Vec<GameEvent> {
+        core::mem::take(&mut self.events)
+    }
+
+    /// Moves [`GameBalance::plunder`] of the gold of the old owners
+    /// of cities captured since `owners` were taken to their conquerors.
+    fn plunder(&mut self, owners: &[(Pos, Player)]) {
+        for &(pos, from) in owners {
+            let Some(to) = self.grid.tile(pos).filter(|t| t.is_city()).map(Tile::owner) else {
+                continue;
+            };
+            if to == from || to.is_neutral() {
+                continue;
+            }
+            // Balances built in code are not checked, and no more than
+            // all of the gold can be taken, rounding aside.
+            let held = self.countries[from.0 as usize].gold;
+            let share = self.balance.plunder.clamp(0.0, 1.0);
+            let gold = ((held as f32 * share) as u64).min(held);
+            if gold == 0 {
+                continue;
             }
+            self.countries[from.0 as usize].gold -= gold;
+            self.countries[to.0 as usize].gold += gold;
+            if self.events.len() >= Self::MAX_EVENTS {
+                self.events.remove(0);
+            }
+            self.events.push(GameEvent::CityPlundered {
+                pos,
+                from,
+                to,
+                gold,
+            });
         }
     }
 
+    /// Number of teams players are dealt into, if any.
+    ///
+    /// See [`BasicOpts::teams`].
+    #[inline]
+    pub fn teams(&self) -> Option<u32> {
+        self.teams
+    }
+
+    /// Gets the team of the given player, players being dealt
+    /// into the [teams](Self::teams) in turn.
+    pub fn team(&self, Player(p): Player) -> Option<u32> {
+        let teams = self.teams?;
+        (p != 0).then(|| (p - 1) % teams)
+    }
+
+    /// Whether the given players fight alongside each other,
+    /// being the same player or on the same team.
+    pub fn allied(&self, a: Player, b: Player) -> bool {
+        a == b || self.team(a).is_some_and(|team| self.team(b) == Some(team))
+    }
+
+    /// Gets the allies of the given player, on its team.
+    pub fn allies(&self, player: Player) -> Vec<Player> {
+        (1..MAX_PLAYERS as u32)
+            .map(Player)
+            .filter(|&p| p != player && self.allied(player, p))
+            .collect()
+    }
+
+    /// Gets the winner of the game, if it is over.
+    ///
+    /// See [`Rules::winner`].
+    #[inline]
+    pub fn winner(&self) -> Option<Player> {
+        self.winner
+    }
+
+    /// Gets the score of the given player: its population
+    /// plus its gold weighted by [`GameBalance::gold_score`].
+    pub fn score(&self, player: Player) -> u64 {
+        let pop = self.grid.population(player) as u64;
+        let gold = self.country(player).map_or(0, |c| c.gold);
+        pop + (gold as f32 * self.balance.gold_score).round() as u64
+    }
+
+    /// Gets the [`Fingerprint`] of the game at the current time.
+    ///
+    /// Its hash covers the options affecting the simulation,
+    /// every tile of the grid, the flags of every player, and the
+    /// gold and queued upgrades of the countries.
+    pub fn fingerprint(&self) -> Fingerprint {
+        let mut h = Fnv::new();
+        h.u64(self.grid.width() as u64);
+        h.u64(self.grid.height() as u64);
+        h.u64(self.difficulty as u64);
+        for opt in [self.conditions, self.inequality] {
+            h.u64(opt.map_or(u64::MAX, u64::from));
+        }
+        h.u64(self.ai_personality_seed.unwrap_or(u64::MAX));
+        h.u64(self.kings.len() as u64);
+        // Games without teams keep the hashes they had before teams.
+        if let Some(teams) = self.teams {
+            h.u64(teams as u64);
+        }
+        h.balance(&self.balance);
+
+        for (_, tile) in self.grid.tiles() {
+            match *tile {
+                Tile::Void => h.u64(0),
+                Tile::Mountain => h.u64(1),
+                Tile::Mine(owner) => {
+                    h.u64(2);
+                    h.u64(owner.0 as u64);
+                }
+                Tile::Habitable {
+                    land,
+                    units,
+                    owner,
+                    rally,
+                    landscape,
+                    road,
+                } => {
+                    h.u64(3 + land as u64);
+                    h.u64(owner.0 as u64);
+                    for pop in units {
+                        h.u64(pop as u64);
+                    }
+                    match rally {
+                        Some(pos) => h.pos(pos),
+                        None => h.u64(u64::MAX),
+                    }
+                    h.u64(landscape as u64);
+                    h.u64(road as u64);
+                }
+            }
+        }
+        for fg in &self.fgs {
+            for &power in fg.flags.iter().flatten() {
+                h.u64(power as u64);
+            }
+        }
+        for c in &self.countries {
+            h.u64(c.gold);
+            h.u64(c.eliminated as u64);
+            h.u64(c.build_queue.len() as u64);
+            for &pos in &c.build_queue {
+                h.pos(pos);
+            }
+        }
+
+        Fingerprint {
+            seed: self.seed,
+            time: self.time,
+            hash: h.0,
+        }
+    }
+
+    /// Gets the aggregate statistics of the given player.
+    pub fn stats(&self, player: Player) -> PlayerStats {
+        let mut cities = [0; 3];
+        for (_, land) in self.grid.cities(player) {
+            cities[land as usize - HabitLand::Village as usize] += 1;
+        }
+        PlayerStats {
+            population: self.grid.population(player),
+            cities,
+            mines: self.grid.mines().filter(|&(_, o)| o == player).count(),
+            gold: self.country(player).map_or(0, |c| c.gold),
+        }
+    }
+
+    /// Ends the game as its time limit is up, with the player
+    /// of the highest [score](Self::score) winning.
+    ///
+    /// Keeps the winner if the game is already over.
+    pub fn time_up(&mut self) -> Option<Player> {
+        if self.winner.is_none() {
+            self.winner = (1..MAX_PLAYERS)
+                .map(|p| Player(p as u32))
+                .filter(|&p| !self.is_eliminated(p) && self.score(p) > 0)
+                .max_by_key(|&p| self.score(p));
+        }
+        self.winner
+    }
+
+    /// Estimates the enemy population pressure on the given
+    /// position from the point of view of `player`.
+    ///
+    /// Each enemy unit within [`Self::THREAT_RADIUS`] steps
+    /// counts `1 / (1 + distance)`.
+    pub fn threat_at(&self, pos: Pos, player: Player) -> f32 {
+        (0..=Self::THREAT_RADIUS)
+            .flat_map(|r| pos.ring(r).map(move |p| (p, r)))
+            .filter_map(|(p, r)| self.grid.tile(p).map(|t| (t, r)))
+            .map(|(t, r)| {
+                let enemies = t
+                    .units()
+                    .iter()
+                    .enumerate()
+                    .filter(|&(p, _)| p != player.0 as usize && !Player(p as u32).is_neutral())
+                    .map(|(_, pop)| *pop as f32)
+                    .sum::<f32>();
+                enemies / (1 + r) as f32
+            })
+            .sum()
+    }
+
+    /// Whether the city at the given position faces more threat
+    /// than the population of its owner there.
+    ///
+    /// See [`Self::threat_at`].
+    pub fn is_endangered(&self, pos: Pos) -> bool {
+        self.grid.tile(pos).is_some_and(|t| {
+            let owner = t.owner();
+            t.is_city()
+                && !owner.is_neutral()
+                && self.threat_at(pos, owner) > t.units()[owner.0 as usize] as f32
+        })
+    }
+
+    /// Iterates over endangered fortresses of the given player.
+    ///
+    /// See [`Self::is_endangered`].
+    pub fn besieged_fortresses(&self, player: Player) -> impl Iterator<Item = Pos> + '_ {
+        self.grid
+            .cities(player)
+            .filter(|&(pos, land)| land == HabitLand::Fortress && self.is_endangered(pos))
+            .map(|(pos, _)| pos)
+    }
+
+    /// Flags every city of the given player not flagged yet.
+    pub fn flag_cities(&mut self, player: Player) {
+        let Some(fg) = self.fgs.get_mut(player.0 as usize) else {
+            return;
+        };
+        for (pos, _) in self.grid.cities(player) {
+            fg.add(&self.grid, pos, FLAG_POWER);
+        }
+    }
+
+    /// Flags every tile on the [frontline](Grid::frontline)
+    /// of the given player not flagged yet.
+    pub fn flag_frontline(&mut self, player: Player) {
+        let Some(fg) = self.fgs.get_mut(player.0 as usize) else {
+            return;
+        };
+        for pos in self.grid.frontline(player) {
+            fg.add(&self.grid, pos, FLAG_POWER);
+        }
+    }
+
+    /// Queues an upgrade of the tile at `pos` of the given player,
+    /// built by [`simulate`](Self::simulate) once the player can
+    /// afford it.
+    ///
+    /// Does nothing if the upgrade is queued already.
+    pub fn queue_build(&mut self, player: Player, pos: Pos) -> crate::Result<()> {
+        let country = &mut self.countries[player.index()?];
+        let tile = self.grid.tile(pos).ok_or(Error::PosOutOfBound(pos))?;
+        if tile.owner() != player {
+            return Err(Error::NotOwner {
+                operator: player,
+                owner: tile.owner(),
+                tile: pos,
+            });
+        }
+        let &Tile::Habitable { mut land, .. } = tile else {
+            return Err(Error::TileNotHabitable(pos));
+        };
+        land.upgrade().ok_or(Error::UpgradeTopLevelBuilding)?;
+        if !country.build_queue.contains(&pos) {
+            country.build_queue.push(pos);
+        }
+        Ok(())
+    }
+
+    /// Removes the queued upgrade of the tile at `pos`
+    /// of the given player, if any.
+    pub fn unqueue_build(&mut self, player: Player, pos: Pos) {
+        if let Ok(country) = self.country_mut(player) {
+            country.build_queue.retain(|p| *p != pos);
+        }
+    }
+
+    /// Whether an upgrade of the tile at `pos` is queued
+    /// by the given player.
+    #[inline]
+    pub fn is_build_queued(&self, player: Player, pos: Pos) -> bool {
+        self.country(player)
+            .is_ok_and(|c| c.build_queue.contains(&pos))
+    }
+
+    /// Builds queued upgrades the players can afford, in order.
+    ///
+    /// Upgrades that are no longer possible, as the tile was lost
+    /// or is a fortress already, are dropped.
+    fn build_queued(&mut self) {
+        for country in &mut self.countries {
+            while let Some(&pos) = country.build_queue.first() {
+                if let Err(Error::InsufficientGold { .. }) =
+                    self.grid.build(country, pos, &self.balance)
+                {
+                    break;
+                }
+                country.build_queue.remove(0);
+            }
+        }
+    }
+
+    /// Suggests where the given player should build next,
+    /// evaluating the grid the same way kings do.
+    ///
+    /// The suggestion ignores whether the player can afford it.
+    pub fn suggest_build(&self, player: Player) -> Option<Pos> {
+        King::new(
+            player,
+            Strategy::Opportunist,
+            self.grid.width(),
+            self.grid.height(),
+        )
+        .best_build(&self.grid)
+    }
+
+    /// The habitable neighbour of `pos` closest to `target`,
+    /// if it is closer than `pos` itself.
+    fn rally_step(&self, pos: Pos, target: Pos) -> Option<Pos> {
+        self.grid
+            .neighbors(pos)
+            .filter(|(_, t)| t.is_habitable())
+            .map(|(n, _)| n)
+            .min_by_key(|n| n.hex_distance(target))
+            .filter(|n| n.hex_distance(target) < pos.hex_distance(target))
+    }
+
     /// Performs one step of the game simulation.
     pub fn simulate(&mut self) {
         self.time += 1;
         let mut need_to_reeval = false;
+        // Units fight those of every player but their allies.
+        let allied: [[bool; MAX_PLAYERS]; MAX_PLAYERS] = core::array::from_fn(|p| {
+            core::array::from_fn(|q| self.allied(Player(p as u32), Player(q as u32)))
+        });
+        let owners: Vec<(Pos, Player)> = if self.balance.plunder > 0.0 {
+            self.grid
+                .tiles()
+                .filter(|(_, t)| t.is_city() && !t.owner().is_neutral())
+                .map(|(pos, t)| (pos, t.owner()))
+                .collect()
+        } else {
+            vec![]
+        };
+        // Cities with a rally point, their owner, the neighbour
+        // on the way to it and the population grown this step.
+        let mut rallied: Vec<(Pos, usize, Pos, u16)> = vec![];
 
         for i in 0..self.grid.width() {
             for j in 0..self.grid.height() {
@@ -274,30 +1113,27 @@ impl State {
                     .map_or(false, |t| matches!(t, Tile::Mine(_)))
                 {
                     let mut owner = Some(Player::NEUTRAL);
-                    for dir in Pos::DIRS {
-                        if let Some(pl) = self
-                            .grid
-                            .tile(Pos(i as i32 + dir.0, j as i32 + dir.1))
-                            .and_then(|t| {
-                                if t.is_habitable() {
-                                    Some(t.owner())
-                                } else {
-                                    None
-                                }
-                            })
-                        {
-                            if owner == Some(Player::NEUTRAL) {
-                                owner = Some(pl);
-                            } else if owner != Some(pl) && !pl.is_neutral() {
-                                owner = None;
-                            }
+                    for (_, t) in self
+                        .grid
+                        .neighbors(Pos(i as i32, j as i32))
+                        .filter(|(_, t)| t.is_habitable())
+                    {
+                        let pl = t.owner();
+                        if owner == Some(Player::NEUTRAL) {
+                            owner = Some(pl);
+                        } else if owner != Some(pl) && !pl.is_neutral() {
+                            owner = None;
                         }
                     }
                     let t = self.grid.tile_mut(Pos(i as i32, j as i32)).unwrap();
                     if let Some(owner) = owner {
                         t.set_owner(owner);
                         if !owner.is_neutral() {
-                            self.countries[owner.0 as usize].gold += 1;
+                            let mut income = self.rules.mine_income(Pos(i as i32, j as i32), owner);
+                            if self.kings.iter().any(|k| k.player() == owner) {
+                                income += self.balance.king_income;
+                            }
+                            self.countries[owner.0 as usize].gold += income;
                         }
                     } else {
                         t.set_owner(Player::NEUTRAL);
@@ -308,31 +1144,40 @@ impl State {
                     ref mut units,
                     owner,
                     land,
+                    landscape,
+                    ..
                 } = self.grid.raw_tiles_mut()[i as usize][j as usize]
                 {
                     let my_pops = *units;
                     let total_pop = my_pops.into_iter().sum::<u16>();
-                    let enemy_pops = my_pops.map(|p| total_pop - p);
+                    let enemy_pops: [u16; MAX_PLAYERS] = core::array::from_fn(|p| {
+                        let team_pop = (0..MAX_PLAYERS)
+                            .filter(|&q| allied[p][q])
+                            .map(|q| my_pops[q])
+                            .sum::<u16>();
+                        total_pop - team_pop
+                    });
 
                     let mut defender_dmg = 0;
                     for (p, (my_pop, enemy_pop)) in my_pops.into_iter().zip(enemy_pops).enumerate()
                     {
-                        let mut dmg = 0;
-                        if total_pop != 0 {
-                            dmg = rnd_round!(enemy_pop as f32 * my_pop as f32 / total_pop as f32);
-                        }
-                        units[p] = (my_pop as i32 - dmg).max(0) as u16;
+                        let mut dmg = self.rules.combat_damage(
+                            Pos(i as i32, j as i32),
+                            Player(p as u32),
+                            my_pop,
+                            enemy_pop,
+                        );
                         if owner == Player(p as u32) {
+                            dmg = rnd_round!(dmg as f32 * landscape.defense(&self.balance));
                             defender_dmg = dmg;
                         }
+                        units[p] = (my_pop as i32 - dmg).max(0) as u16;
                     }
 
-                    const ATTACK: f32 = 0.1;
-
                     // Burning cities
-                    if defender_dmg as f32 > 2.0 * MAX_POPULATION as f32 * ATTACK
+                    if defender_dmg as f32 > 2.0 * MAX_POPULATION as f32 * self.balance.attack
                         && land != HabitLand::Grassland
-                        && fastrand::bool()
+                        && crate::rng::bool()
                     {
                         need_to_reeval = true;
                         let _ = self.grid.degrade(Pos(i as i32, j as i32));
@@ -342,25 +1187,45 @@ impl State {
                         ref mut units,
                         ref mut owner,
                         land,
+                        ref mut rally,
+                        landscape,
+                        ..
                     } = self.grid.raw_tiles_mut()[i as usize][j as usize]
                     else {
                         unreachable!()
                     };
 
                     // Determine ownership
+                    let old_owner = *owner;
                     *owner = Player::NEUTRAL;
                     for p in 0..MAX_PLAYERS {
                         if units[p] > units[owner.0 as usize] {
                             *owner = Player(p as u32)
                         }
                     }
+                    if *owner != old_owner {
+                        *rally = None;
+                    }
 
                     // Population growth
                     if land != HabitLand::Grassland {
                         let pop = units[owner.0 as usize];
-                        let fnpop = pop as f32 * land.growth();
-                        let npop = (rnd_round!(fnpop) as u16).min(MAX_POPULATION);
-                        units[owner.0 as usize] = npop;
+                        let mut grown = self.rules.growth(Pos(i as i32, j as i32), land, pop);
+                        // Hills slow growth, but not decline.
+                        if grown > pop {
+                            let growth = landscape.growth(&self.balance) * self.balance.growth;
+                            grown = (pop + rnd_round!((grown - pop) as f32 * growth) as u16)
+                                .min(MAX_POPULATION);
+                        }
+                        units[owner.0 as usize] = grown;
+
+                        if let Some(target) = *rally {
+                            let grown = units[owner.0 as usize].saturating_sub(pop);
+                            let (pos, owner) = (Pos(i as i32, j as i32), owner.0 as usize);
+                            if let Some(next) = self.rally_step(pos, target) {
+                                rallied.push((pos, owner, next, grown));
+                            }
+                        }
                     }
                 }
             }
@@ -373,7 +1238,7 @@ impl State {
         let i_inc;
         let j_inc;
 
-        if fastrand::u8(..2) == 0 {
+        if crate::rng::u8(..2) == 0 {
             i_start = 0;
             i_end = self.grid.width() as i32;
             i_inc = 1;
@@ -383,7 +1248,7 @@ impl State {
             i_inc = -1;
         }
 
-        if fastrand::u8(..2) == 0 {
+        if crate::rng::u8(..2) == 0 {
             j_start = 0;
             j_end = self.grid.height() as i32;
             j_inc = 1;
@@ -402,27 +1267,39 @@ impl State {
                         continue;
                     };
                     let initial_pop = tile.units()[p];
-                    let k_shift = fastrand::usize(..6);
+                    let k_shift = crate::rng::usize(..6);
                     let fg = &self.fgs[p];
+                    // Grown population is called toward the rally point.
+                    let rally = rallied
+                        .iter()
+                        .find(|r| r.0 == Pos(i, j) && r.1 == p)
+                        .map(|r| (r.2, r.3));
 
                     for k in 0..6 {
                         let tile = self.grid.tile(Pos(i, j)).unwrap();
                         let dir = Pos::DIRS[(k + k_shift) % 6];
-                        let pos = Pos(i + dir.0 as i32, j + dir.1 as i32);
+                        let pos = Pos(i, j).offset(dir);
                         if let Some(Tile::Habitable { units, .. }) = self.grid.tile(pos) {
                             let pop = tile.units()[p];
                             let dcall = (fg.call(pos).unwrap_or_default()
                                 - fg.call(Pos(i, j)).unwrap_or_default())
                             .max(0);
 
-                            const MOVE: f32 = 0.05;
-                            const CALL_MOVE: f32 = 0.10;
-                            let dpop = rnd_round!(
-                                MOVE * initial_pop as f32
-                                    + CALL_MOVE * dcall as f32 * initial_pop as f32
-                            )
-                            .min(pop as i32)
-                            .min((MAX_POPULATION - units[p]) as i32);
+                            let movement = if tile.road() && self.grid.tile(pos).unwrap().road() {
+                                self.balance.road_movement
+                            } else {
+                                self.balance.movement
+                            };
+                            let bias = match rally {
+                                Some((next, grown)) if next == pos => grown as i32,
+                                _ => 0,
+                            };
+                            let dpop = (rnd_round!(
+                                movement * initial_pop as f32
+                                    + self.balance.call_move * dcall as f32 * initial_pop as f32
+                            ) + bias)
+                                .min(pop as i32)
+                                .min((MAX_POPULATION - units[p]) as i32);
 
                             let Some(Tile::Habitable { units, .. }) = self.grid.tile_mut(pos)
                             else {
@@ -457,11 +1334,11 @@ impl State {
             }
         }
 
+        self.plunder(&owners);
+
         // Kings re-evaluate the map
         if need_to_reeval {
-            self.kings
-                .iter_mut()
-                .for_each(|t| t.evaluate_map(&self.grid, self.difficulty));
+            self.evaluate_kings();
         }
 
         // Give gold to AI on hard difficulties
@@ -479,6 +1356,13 @@ impl State {
                 }
             }
         }
+
+        self.build_queued();
+        self.check_eliminations();
+
+        if self.winner.is_none() {
+            self.winner = self.rules.winner(self);
+        }
     }
 
     #[inline]
@@ -535,6 +1419,7 @@ impl UI {
             cursor,
             xskip: (xskip_x2 as u16 + 1) / 2,
             xlen: (xrightmost_x2 as u16 + 1) / 2 - xskip_x2 as u16 / 2,
+            rally_from: None,
         }
     }
 
@@ -575,3 +1460,261 @@ impl UI {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Row of grassland with a city of player 1 at the left end,
+    /// rallying to the right end.
+    fn state(teams: Option<u32>) -> State {
+        let mut st = State::new(BasicOpts {
+            teams,
+            ..Default::default()
+        })
+        .unwrap();
+        let mut grid = Grid::new(8, 1);
+        for x in 0..8 {
+            *grid.tile_mut(Pos(x, 0)).unwrap() = Tile::Habitable {
+                land: match x {
+                    0 => HabitLand::Fortress,
+                    _ => HabitLand::Grassland,
+                },
+                units: [0; MAX_PLAYERS],
+                owner: Player::NEUTRAL,
+                rally: None,
+                landscape: Default::default(),
+                road: false,
+            };
+        }
+        let Some(Tile::Habitable {
+            units,
+            owner,
+            rally,
+            ..
+        }) = grid.tile_mut(Pos(0, 0))
+        else {
+            unreachable!()
+        };
+        units[1] = 100;
+        *owner = Player(1);
+        *rally = Some(Pos(7, 0));
+        st.grid = grid;
+        st.kings.clear();
+        st.balance.movement = 0.0;
+        st.balance.road_movement = 0.0;
+        st
+    }
+
+    #[test]
+    fn fingerprints_cover_flags_queues_and_balance() {
+        let mut st = state(None);
+        if let Some(Tile::Habitable { owner, .. }) = st.grid.tile_mut(Pos(1, 0)) {
+            *owner = Player(1);
+        }
+        let mut last = st.fingerprint();
+        assert_eq!(st.fingerprint(), last);
+        let mut changed = |st: &State| {
+            let next = st.fingerprint();
+            core::mem::replace(&mut last, next) != next
+        };
+
+        st.fgs[2].add(&st.grid, Pos(3, 0), FLAG_POWER);
+        assert!(changed(&st));
+        st.queue_build(Player(1), Pos(1, 0)).unwrap();
+        assert!(changed(&st));
+        st.balance.growth = f32::from_bits(st.balance.growth.to_bits() + 1);
+        assert!(changed(&st));
+    }
+
+    #[test]
+    fn players_without_cities_are_eliminated() {
+        let mut st = state(None);
+        let p2 = Player(2);
+        // The map replaced may have left the player out.
+        st.countries[2].eliminated = false;
+        *st.grid.tile_mut(Pos(6, 0)).unwrap() = Tile::Mine(p2);
+        if let Some(Tile::Habitable { units, owner, .. }) = st.grid.tile_mut(Pos(5, 0)) {
+            units[2] = 3;
+            *owner = p2;
+        }
+        st.queue_build(p2, Pos(5, 0)).unwrap();
+        st.fgs[2].add(&st.grid, Pos(4, 0), FLAG_POWER);
+        st.hand_to_king(p2, Strategy::AggrGreedy);
+        assert!(st.king(p2).is_some());
+
+        let before = st.snapshot();
+        st.simulate();
+        let after = st.snapshot();
+
+        assert!(st.is_eliminated(p2));
+        assert!(!st.is_eliminated(Player(1)));
+        assert!(matches!(
+            st.grid.tile(Pos(6, 0)),
+            Some(Tile::Mine(Player::NEUTRAL))
+        ));
+        let tile = st.grid.tile(Pos(5, 0)).unwrap();
+        assert_eq!(tile.owner(), Player::NEUTRAL);
+        assert_eq!(tile.units()[2], 0);
+        assert!(!st.fgs[2].is_flagged(Pos(4, 0)));
+        assert!(st.countries[2].build_queue.is_empty());
+        assert!(st.king(p2).is_none());
+        assert!(before
+            .events(&after)
+            .contains(&GameEvent::PlayerEliminated(p2)));
+    }
+
+    #[test]
+    fn conquerors_plunder_cities() {
+        // Player 1 takes the village of player 2 at the right end.
+        let plundered = |share| {
+            let mut st = state(None);
+            *st.grid.tile_mut(Pos(7, 0)).unwrap() = Tile::Habitable {
+                land: HabitLand::Village,
+                units: core::array::from_fn(|q| if q == 1 { 50 } else { 0 }),
+                owner: Player(2),
+                rally: None,
+                landscape: Default::default(),
+                road: false,
+            };
+            st.balance.plunder = share;
+            st.countries[1].gold = 0;
+            st.countries[2].gold = 101;
+            st.simulate();
+            assert_eq!(st.grid.tile(Pos(7, 0)).unwrap().owner(), Player(1));
+            (st.countries[1].gold, st.countries[2].gold, st.take_events())
+        };
+
+        let (won, left, events) = plundered(0.5);
+        assert_eq!((won, left), (50, 51));
+        assert_eq!(
+            events,
+            [GameEvent::CityPlundered {
+                pos: Pos(7, 0),
+                from: Player(2),
+                to: Player(1),
+                gold: 50,
+            }]
+        );
+        // Shares out of range are clamped.
+        let (won, left, events) = plundered(2.0);
+        assert_eq!((won, left), (101, 0));
+        assert_eq!(events.len(), 1);
+        for share in [0.0, -1.0] {
+            let (won, left, events) = plundered(share);
+            assert_eq!((won, left), (0, 101));
+            assert!(events.is_empty());
+        }
+    }
+
+    #[test]
+    fn queued_upgrades_wait_for_gold() {
+        let mut st = state(None);
+        for x in 1..4 {
+            if let Some(Tile::Habitable { units, owner, .. }) = st.grid.tile_mut(Pos(x, 0)) {
+                units[1] = 20;
+                *owner = Player(1);
+            }
+        }
+        let land = |st: &State, x| match st.grid.tile(Pos(x, 0)) {
+            Some(&Tile::Habitable { land, .. }) => land,
+            _ => unreachable!(),
+        };
+        st.countries[1].gold = 100;
+        st.queue_build(Player(1), Pos(2, 0)).unwrap();
+        st.queue_build(Player(1), Pos(1, 0)).unwrap();
+        st.simulate();
+        assert_eq!(land(&st, 2), HabitLand::Grassland);
+        assert_eq!(st.countries[1].build_queue, [Pos(2, 0), Pos(1, 0)]);
+
+        // Built on the step the gold suffices, in order.
+        st.countries[1].gold = 170;
+        st.simulate();
+        assert_eq!(land(&st, 2), HabitLand::Village);
+        assert_eq!(land(&st, 1), HabitLand::Grassland);
+        assert_eq!(st.countries[1].gold, 10);
+        assert_eq!(st.countries[1].build_queue, [Pos(1, 0)]);
+
+        // Lost tiles are dropped, and the next upgrade built.
+        st.countries[1].build_queue.clear();
+        st.queue_build(Player(1), Pos(3, 0)).unwrap();
+        st.queue_build(Player(1), Pos(1, 0)).unwrap();
+        st.grid.tile_mut(Pos(3, 0)).unwrap().units_mut().unwrap()[2] = 100;
+        st.countries[1].gold = 1000;
+        st.simulate();
+        assert_eq!(st.grid.tile(Pos(3, 0)).unwrap().owner(), Player(2));
+        assert_eq!(land(&st, 3), HabitLand::Grassland);
+        assert_eq!(land(&st, 1), HabitLand::Village);
+        assert!(st.countries[1].build_queue.is_empty());
+    }
+
+    #[test]
+    fn rally_one_tile_per_step() {
+        let mut st = state(None);
+        let units = |st: &State, x| st.grid.tile(Pos(x, 0)).unwrap().units()[1];
+        for _ in 0..3 {
+            let before = units(&st, 0) + units(&st, 1);
+            st.simulate();
+            assert!(units(&st, 0) + units(&st, 1) > before);
+            assert_eq!(units(&st, 0), 100);
+            assert!((2..8).all(|x| units(&st, x) == 0));
+        }
+    }
+
+    #[test]
+    fn allies_do_not_fight() {
+        // Players 1 and 3 are on the first team, 2 on the second.
+        let mut st = state(Some(2));
+        let units = |st: &State, x| *st.grid.tile(Pos(x, 0)).unwrap().units();
+        for (x, p) in [(3, 3), (5, 2)] {
+            let tile = st.grid.tile_mut(Pos(x, 0)).unwrap();
+            let units = tile.units_mut().unwrap();
+            units[1] = 50;
+            units[p] = 50;
+        }
+        st.simulate();
+        assert_eq!((units(&st, 3)[1], units(&st, 3)[3]), (50, 50));
+        assert!(units(&st, 5)[1] < 50 && units(&st, 5)[2] < 50);
+
+        for x in 0..8 {
+            st.grid.tile_mut(Pos(x, 0)).unwrap().units_mut().unwrap()[2] = 0;
+        }
+        assert_eq!(st.rules.winner(&st), Some(Player(1)));
+        st.grid.tile_mut(Pos(5, 0)).unwrap().units_mut().unwrap()[2] = 1;
+        assert_eq!(st.rules.winner(&st), None);
+    }
+
+    #[test]
+    fn allied_kings_leave_flagged_tiles() {
+        let flagged = |teams| {
+            // Player 4 swarms toward the city of player 1, past the
+            // tile player 2 flagged, on its team if there are two.
+            let mut st = state(teams);
+            for (x, land, p, pop) in [
+                (0, HabitLand::Fortress, 4, 100),
+                (7, HabitLand::Village, 1, 10),
+            ] {
+                *st.grid.tile_mut(Pos(x, 0)).unwrap() = Tile::Habitable {
+                    land,
+                    units: core::array::from_fn(|q| if q == p { pop } else { 0 }),
+                    owner: Player(p as u32),
+                    rally: None,
+                    landscape: Default::default(),
+                    road: false,
+                };
+            }
+            st.balance.flag_cooldown = [1; 5];
+            // Players without a location on the generated map are out.
+            st.countries.iter_mut().for_each(|c| c.eliminated = false);
+            st.hand_to_king(Player(2), Strategy::None);
+            st.hand_to_king(Player(4), Strategy::Swarm);
+            st.fgs[2].add(&st.grid, Pos(3, 0), FLAG_POWER);
+            st.kings_move();
+            (1..8)
+                .filter(|&x| st.fgs[4].is_flagged(Pos(x, 0)))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(flagged(None), [1, 2, 3, 4, 5, 6, 7]);
+        assert_eq!(flagged(Some(2)), [1, 2, 4, 5, 6, 7]);
+    }
+}